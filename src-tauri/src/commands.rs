@@ -1,22 +1,168 @@
-use tauri::State;
+use tauri::{AppHandle, Manager, State};
 use uuid::Uuid;
 use crate::database::ConnectionManager;
+use crate::history::HistoryStore;
 use crate::models::*;
+use crate::profiles::ProfileStore;
+use crate::secrets::SecretStore;
+use crate::tasks::{ProgressReporter, TaskManager};
 
 #[tauri::command]
-pub async fn test_connection(config: ConnectionConfig) -> Result<TestConnectionResult, String> {
+pub async fn test_connection(
+    config: ConnectionConfig,
+    secrets: State<'_, SecretStore>,
+) -> Result<TestConnectionResult, String> {
+    let config = secrets.fill(config).await?;
     Ok(crate::database::test_database_connection(&config).await)
 }
 
+#[tauri::command]
+pub async fn parse_connection_url(url: String) -> Result<ConnectionConfig, String> {
+    crate::database::dsn::parse(&url)
+}
+
+#[tauri::command]
+pub async fn format_connection_url(config: ConnectionConfig) -> Result<String, String> {
+    let config = crate::database::dsn::resolve(&config)?;
+    Ok(crate::database::dsn::format(&config))
+}
+
+#[tauri::command]
+pub async fn save_credentials(
+    config: ConnectionConfig,
+    secrets: State<'_, SecretStore>,
+) -> Result<ConnectionConfig, String> {
+    secrets.store(config).await
+}
+
+#[tauri::command]
+pub async fn delete_credentials(
+    credential_id: String,
+    secrets: State<'_, SecretStore>,
+) -> Result<(), String> {
+    secrets.delete(&credential_id).await
+}
+
+#[tauri::command]
+pub async fn get_secret_store_status(secrets: State<'_, SecretStore>) -> Result<SecretStoreStatus, String> {
+    Ok(secrets.status().await)
+}
+
+#[tauri::command]
+pub async fn unlock_secret_store(
+    master_password: String,
+    secrets: State<'_, SecretStore>,
+) -> Result<SecretStoreStatus, String> {
+    secrets.unlock(master_password).await
+}
+
+#[tauri::command]
+pub async fn lock_secret_store(secrets: State<'_, SecretStore>) -> Result<SecretStoreStatus, String> {
+    secrets.lock().await;
+    Ok(secrets.status().await)
+}
+
+#[tauri::command]
+pub async fn list_profiles(profiles: State<'_, ProfileStore>) -> Result<Vec<ConnectionProfile>, String> {
+    Ok(profiles.list().await)
+}
+
+#[tauri::command]
+pub async fn profile_load_error(profiles: State<'_, ProfileStore>) -> Result<Option<String>, String> {
+    Ok(profiles.load_error())
+}
+
+#[tauri::command]
+pub async fn save_profile(
+    profile: ConnectionProfile,
+    profiles: State<'_, ProfileStore>,
+    secrets: State<'_, SecretStore>,
+) -> Result<ConnectionProfile, String> {
+    profiles.save(profile, &secrets).await
+}
+
+#[tauri::command]
+pub async fn delete_profile(
+    id: String,
+    profiles: State<'_, ProfileStore>,
+    secrets: State<'_, SecretStore>,
+) -> Result<bool, String> {
+    profiles.delete(&id, &secrets).await
+}
+
+#[tauri::command]
+pub async fn export_profiles(
+    ids: Option<Vec<String>>,
+    format: ProfileFormat,
+    path: String,
+    profiles: State<'_, ProfileStore>,
+) -> Result<usize, String> {
+    log::info!("[export_profiles] Exporting as {:?} to {}", format, path);
+    
+    let selected: Vec<ConnectionProfile> = profiles
+        .list()
+        .await
+        .into_iter()
+        .filter(|p| ids.as_ref().is_none_or(|ids| ids.contains(&p.id)))
+        .collect();
+    let text = crate::profiles::export(&selected, format)?;
+    tokio::fs::write(&path, text)
+        .await
+        .map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    Ok(selected.len())
+}
+
+#[tauri::command]
+pub async fn import_profiles(
+    path: String,
+    format: ProfileFormat,
+    profiles: State<'_, ProfileStore>,
+    secrets: State<'_, SecretStore>,
+) -> Result<ProfileImportResult, String> {
+    log::info!("[import_profiles] Importing {:?} from {}", format, path);
+    
+    let text = tokio::fs::read_to_string(&path)
+        .await
+        .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let imported = crate::profiles::parse(&text, format)?;
+    Ok(profiles.import(imported, &secrets).await)
+}
+
+#[tauri::command]
+pub async fn import_client_profiles(
+    app: AppHandle,
+    profiles: State<'_, ProfileStore>,
+    secrets: State<'_, SecretStore>,
+) -> Result<ProfileImportResult, String> {
+    let home = app.path().home_dir().map_err(|e| format!("Failed to find the home directory: {}", e))?;
+    let (found, unreadable) = crate::profiles::client::discover(&home);
+    let mut result = profiles.import(found, &secrets).await;
+    result.skipped.extend(unreadable);
+    Ok(result)
+}
+
 #[tauri::command]
 pub async fn connect_database(
     config: ConnectionConfig,
     state: State<'_, ConnectionManager>,
+    secrets: State<'_, SecretStore>,
 ) -> Result<Connection, String> {
-    let conn = crate::database::connect_to_database(&config).await?;
+    // The manager keeps the settings a URL stands for and the stored
+    // secrets; the caller gets back what it sent
+    let resolved = crate::database::dsn::resolve(&secrets.fill(config.clone()).await?)?;
+    let (conn, tunnel) = crate::database::connect_to_database(&resolved).await?;
+    let temporal = match crate::database::resolve_temporal_format(&conn, &resolved).await {
+        Ok(temporal) => temporal,
+        Err(e) => {
+            if let Some(tunnel) = tunnel {
+                tunnel.close().await;
+            }
+            return Err(e);
+        }
+    };
     let id = Uuid::new_v4().to_string();
     
-    state.add_connection(id.clone(), conn).await;
+    state.add_connection(id.clone(), conn, resolved, temporal, tunnel).await;
     
     Ok(Connection {
         id,
@@ -37,7 +183,9 @@ pub async fn disconnect_database(
 pub async fn execute_query(
     id: String,
     sql: String,
+    confirmation_token: Option<String>,
     state: State<'_, ConnectionManager>,
+    history: State<'_, HistoryStore>,
 ) -> Result<QueryResult, String> {
     // Log all queries, especially UPDATEs
     let sql_upper = sql.trim().to_uppercase();
@@ -59,7 +207,63 @@ pub async fn execute_query(
             "Connection not found".to_string()
         })?;
     
-    let result = crate::database::execute_sql_query(&conn, &sql).await;
+    let config = state.get_config(&id).await;
+    if config.as_ref().is_some_and(|c| c.read_only) {
+        crate::database::guard::check_read_only(&conn.db_type(), &sql)?;
+    }
+    
+    if config.as_ref().is_some_and(|c| c.protected) {
+        match confirmation_token {
+            Some(token) => {
+                if !state.take_confirmation(&token, &id, &sql).await {
+                    return Err("The confirmation has expired or was for a different query; run it again to confirm".to_string());
+                }
+                log::info!("[execute_query] Destructive query confirmed on {}", id);
+            }
+            None => {
+                let impacts = crate::database::guard::assess(&conn, &sql).await;
+                if !impacts.is_empty() {
+                    let token = state.request_confirmation(&id, &sql).await;
+                    return Ok(QueryResult {
+                        columns: Vec::new(),
+                        rows: Vec::new(),
+                        row_count: 0,
+                        execution_time_ms: 0,
+                        truncated: false,
+                        confirmation_required: Some(ConfirmationRequired { token, impacts }),
+                    });
+                }
+            }
+        }
+    }
+    
+    let temporal = state.get_temporal_format(&id).await;
+    let started = std::time::Instant::now();
+    // Reads are run again on a reopened connection; anything else only
+    // reopens it for the next statement
+    let result = if crate::database::guard::is_repeatable_read(&conn.db_type(), &sql) {
+        let (sql, temporal) = (&sql, &temporal);
+        state.retry_read(&id, move |conn| async move {
+            crate::database::execute_sql_query(&conn, sql, temporal).await
+        }).await
+    } else {
+        let result = crate::database::execute_sql_query(&conn, &sql, &temporal).await;
+        if let Err(e) = &result {
+            state.recover(&id, e).await;
+        }
+        result
+    };
+    
+    if let Some(config) = state.get_config(&id).await {
+        history.record(NewHistoryEntry {
+            connection_name: config.name,
+            database_name: config.database,
+            sql: sql.clone(),
+            duration_ms: started.elapsed().as_millis() as u64,
+            row_count: result.as_ref().ok().map(|r| r.row_count as u64),
+            error: result.as_ref().err().cloned(),
+        }).await;
+    }
     
     match &result {
         Ok(r) => {
@@ -76,23 +280,48 @@ pub async fn execute_query(
 }
 
 #[tauri::command]
-pub async fn get_tables(
+pub async fn explain_query(
     id: String,
+    sql: String,
+    analyze: bool,
     state: State<'_, ConnectionManager>,
-) -> Result<Vec<TableInfo>, String> {
-    log::info!("[get_tables] Called for connection id: {}", id);
+) -> Result<QueryPlan, String> {
+    log::info!("[explain_query] Explaining query (analyze: {})", analyze);
     
     let conn = state
         .get_connection(&id)
         .await
-        .ok_or_else(|| {
-            log::error!("[get_tables] Connection not found: {}", id);
-            "Connection not found".to_string()
-        })?;
+        .ok_or_else(|| "Connection not found".to_string())?;
+    // EXPLAIN ANALYZE runs the statement
+    let config = state.get_config(&id).await;
+    if analyze && config.as_ref().is_some_and(|c| c.read_only) {
+        crate::database::guard::check_read_only(&conn.db_type(), &sql)?;
+    }
+    // The rollback around it does not undo DDL everywhere, and there is no
+    // confirmation step for plans
+    if analyze && config.as_ref().is_some_and(|c| c.protected) {
+        crate::database::guard::check_protected(&conn.db_type(), &sql)?;
+    }
     
-    log::info!("[get_tables] Connection found, fetching tables...");
+    if analyze {
+        return crate::database::explain_sql_query(&conn, &sql, analyze).await;
+    }
+    let sql = &sql;
+    state.retry_read(&id, move |conn| async move {
+        crate::database::explain_sql_query(&conn, sql, false).await
+    }).await
+}
+
+#[tauri::command]
+pub async fn get_tables(
+    id: String,
+    state: State<'_, ConnectionManager>,
+) -> Result<Vec<TableInfo>, String> {
+    log::info!("[get_tables] Called for connection id: {}", id);
     
-    let result = crate::database::get_tables_list(&conn).await;
+    let result = state.retry_read(&id, |conn| async move {
+        crate::database::get_tables_list(&conn).await
+    }).await;
     
     match &result {
         Ok(tables) => log::info!("[get_tables] Success: {} tables found", tables.len()),
@@ -108,46 +337,560 @@ pub async fn get_table_structure(
     table: String,
     state: State<'_, ConnectionManager>,
 ) -> Result<TableStructure, String> {
+    let table = &table;
+    state.retry_read(&id, move |conn| async move {
+        crate::database::get_table_structure_info(&conn, table).await
+    }).await
+}
+
+#[tauri::command]
+pub async fn get_table_data(
+    id: String,
+    table: String,
+    limit: u32,
+    state: State<'_, ConnectionManager>,
+) -> Result<QueryResult, String> {
+    println!("[DEBUG] get_table_data called: table={}, limit={}", table, limit);
+    
+    let temporal = state.get_temporal_format(&id).await;
+    let (table, temporal) = (&table, &temporal);
+    let result = state.retry_read(&id, move |conn| async move {
+        crate::database::get_table_data_rows(&conn, table, limit, temporal).await
+    }).await;
+    
+    println!("[DEBUG] Query finished: {:?}", result.is_ok());
+    
+    result
+}
+
+#[tauri::command]
+pub async fn get_connection_stats(
+    id: String,
+    state: State<'_, ConnectionManager>,
+) -> Result<ConnectionStats, String> {
+    let conn = state
+        .get_connection(&id)
+        .await
+        .ok_or_else(|| "Connection not found".to_string())?;
+    let config = state
+        .get_config(&id)
+        .await
+        .ok_or_else(|| "Connection not found".to_string())?;
+    
+    let pool = crate::database::stats::pool_stats(&conn, &config).await?;
+    // Asking the server would only wait in line with the queries
+    let session = match pool.acquire_ms {
+        Some(_) => match crate::database::stats::session_info(&conn).await {
+            Ok(session) => Some(session),
+            Err(e) => {
+                log::warn!("[get_connection_stats] {}", e);
+                None
+            }
+        },
+        None => None,
+    };
+    Ok(ConnectionStats {
+        state: state.get_state(&id).await.unwrap_or(ConnectionState::Connected),
+        pool,
+        session,
+    })
+}
+
+#[tauri::command]
+pub async fn list_databases(
+    id: String,
+    state: State<'_, ConnectionManager>,
+) -> Result<Vec<String>, String> {
+    state.retry_read(&id, |conn| async move {
+        crate::database::list_databases(&conn).await
+    }).await
+}
+
+#[tauri::command]
+pub async fn download_cell(
+    id: String,
+    table: String,
+    pk: serde_json::Map<String, serde_json::Value>,
+    column: String,
+    path: String,
+    state: State<'_, ConnectionManager>,
+) -> Result<u64, String> {
     let conn = state
         .get_connection(&id)
         .await
         .ok_or_else(|| "Connection not found".to_string())?;
     
-    crate::database::get_table_structure_info(&conn, &table).await
+    crate::database::download_cell(&conn, &table, &pk, &column, &path).await
 }
 
 #[tauri::command]
-pub async fn get_table_data(
+pub async fn upload_cell(
     id: String,
     table: String,
-    limit: u32,
+    pk: serde_json::Map<String, serde_json::Value>,
+    column: String,
+    path: String,
     state: State<'_, ConnectionManager>,
-) -> Result<QueryResult, String> {
-    println!("[DEBUG] get_table_data called: table={}, limit={}", table, limit);
+) -> Result<u64, String> {
+    let conn = state
+        .get_connection(&id)
+        .await
+        .ok_or_else(|| "Connection not found".to_string())?;
+    state.ensure_writable(&id).await?;
+    
+    crate::database::upload_cell(&conn, &table, &pk, &column, &path).await
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn export_query(
+    id: String,
+    sql: String,
+    format: ExportFormat,
+    path: String,
+    options: Option<ExportOptions>,
+    task_id: String,
+    app: AppHandle,
+    state: State<'_, ConnectionManager>,
+    tasks: State<'_, TaskManager>,
+) -> Result<ExportSummary, String> {
+    log::info!("[export_query] Exporting as {:?} to {}", format, path);
     
     let conn = state
         .get_connection(&id)
         .await
         .ok_or_else(|| "Connection not found".to_string())?;
+    // Not every engine refuses writes at the session level, and
+    // `DELETE ... OUTPUT` or `RETURNING` stream rows like a query
+    let config = state.get_config(&id).await;
+    if config.as_ref().is_some_and(|c| c.read_only) {
+        crate::database::guard::check_read_only(&conn.db_type(), &sql)?;
+    }
+    if config.as_ref().is_some_and(|c| c.protected) {
+        crate::database::guard::check_protected(&conn.db_type(), &sql)?;
+    }
     
-    println!("[DEBUG] Connection found, executing query...");
+    let temporal = state.get_temporal_format(&id).await;
+    let options = options.unwrap_or_default();
+    let cancel = tasks.register(&task_id).await;
+    let mut reporter = ProgressReporter::new(app, &task_id);
     
-    let result = crate::database::get_table_data_rows(&conn, &table, limit).await;
+    let result = crate::export::export_query(
+        &conn,
+        &sql,
+        &temporal,
+        format,
+        &path,
+        &options,
+        cancel,
+        &mut |rows| reporter.report(rows, None),
+    ).await;
     
-    println!("[DEBUG] Query finished: {:?}", result.is_ok());
+    tasks.finish(&task_id).await;
+    if let Ok(summary) = &result {
+        reporter.emit(summary.rows, Some(summary.rows));
+    }
     
     result
 }
 
 #[tauri::command]
-pub async fn list_databases(
+#[allow(clippy::too_many_arguments)]
+pub async fn export_table(
     id: String,
+    table: String,
+    format: ExportFormat,
+    path: String,
+    options: Option<ExportOptions>,
+    task_id: String,
+    app: AppHandle,
     state: State<'_, ConnectionManager>,
-) -> Result<Vec<String>, String> {
+    tasks: State<'_, TaskManager>,
+) -> Result<ExportSummary, String> {
+    let conn = state
+        .get_connection(&id)
+        .await
+        .ok_or_else(|| "Connection not found".to_string())?;
+    
+    let (sql, options) = crate::export::table_export(&conn.db_type(), &table, &options.unwrap_or_default());
+    export_query(id, sql, format, path, Some(options), task_id, app, state, tasks).await
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn dump_database(
+    id: String,
+    path: String,
+    options: Option<DumpOptions>,
+    task_id: String,
+    app: AppHandle,
+    state: State<'_, ConnectionManager>,
+    tasks: State<'_, TaskManager>,
+) -> Result<DumpSummary, String> {
+    log::info!("[dump_database] Dumping connection {} to {}", id, path);
+    
+    let conn = state
+        .get_connection(&id)
+        .await
+        .ok_or_else(|| "Connection not found".to_string())?;
+    
+    let options = options.unwrap_or_default();
+    let cancel = tasks.register(&task_id).await;
+    let mut reporter = ProgressReporter::new(app, &task_id);
+    
+    let result = crate::export::dump::dump_database(
+        &conn,
+        &path,
+        &options,
+        cancel,
+        &mut |rows| reporter.report(rows, None),
+    ).await;
+    
+    tasks.finish(&task_id).await;
+    if let Ok(summary) = &result {
+        reporter.emit(summary.rows, Some(summary.rows));
+    }
+    
+    result
+}
+
+#[tauri::command]
+pub async fn preview_csv(
+    path: String,
+    options: Option<CsvReadOptions>,
+) -> Result<CsvPreview, String> {
+    crate::import::delimited::preview(&path, &options.unwrap_or_default())
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn import_csv(
+    id: String,
+    path: String,
+    table: String,
+    options: Option<ImportOptions>,
+    task_id: String,
+    app: AppHandle,
+    state: State<'_, ConnectionManager>,
+    tasks: State<'_, TaskManager>,
+) -> Result<ImportSummary, String> {
+    log::info!("[import_csv] Importing {} into {}", path, table);
+    
+    let conn = state
+        .get_connection(&id)
+        .await
+        .ok_or_else(|| "Connection not found".to_string())?;
+    state.ensure_writable(&id).await?;
+    
+    let options = options.unwrap_or_default();
+    let format = crate::import::delimited::detect_format(&path, &options.csv)?;
+    let source = crate::import::delimited::CsvSource::open(&path, &format)?;
+    let null_token = options.csv.null_token.clone();
+    
+    let target = crate::import::resolve_target(&conn, &table, source.columns(), &options, || {
+        crate::import::delimited::infer_columns(&path, &format, null_token.as_deref())
+    }).await?;
+    let mut rows = source.into_rows(&target, null_token.clone());
+    
+    let cancel = tasks.register(&task_id).await;
+    let mut reporter = ProgressReporter::new(app, &task_id);
+    
+    let result = crate::import::run_import(
+        &conn,
+        &target,
+        &mut rows,
+        &options,
+        cancel,
+        &mut |rows| reporter.report(rows, None),
+    ).await;
+    
+    tasks.finish(&task_id).await;
+    if let Ok(summary) = &result {
+        reporter.emit(summary.rows_read, Some(summary.rows_read));
+    }
+    
+    result
+}
+
+#[tauri::command]
+pub async fn preview_json(
+    path: String,
+    options: Option<JsonReadOptions>,
+) -> Result<JsonPreview, String> {
+    crate::import::json::preview(&path, &options.unwrap_or_default())
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn import_json(
+    id: String,
+    path: String,
+    table: String,
+    options: Option<ImportOptions>,
+    task_id: String,
+    app: AppHandle,
+    state: State<'_, ConnectionManager>,
+    tasks: State<'_, TaskManager>,
+) -> Result<ImportSummary, String> {
+    log::info!("[import_json] Importing {} into {}", path, table);
+    
+    let conn = state
+        .get_connection(&id)
+        .await
+        .ok_or_else(|| "Connection not found".to_string())?;
+    state.ensure_writable(&id).await?;
+    
+    let options = options.unwrap_or_default();
+    let layout = match options.json.layout {
+        Some(layout) => layout,
+        None => crate::import::json::detect_layout(&path)?,
+    };
+    // Every record is read up front to collect the columns and their types
+    let columns = crate::import::json::scan_columns(&path, layout, &options.json)?;
+    let names: Vec<String> = columns.iter().map(|c| c.name.clone()).collect();
+    
+    let target = crate::import::resolve_target(&conn, &table, &names, &options, || Ok(columns)).await?;
+    let mut rows = crate::import::json::JsonRows::open(&path, layout, &names, &target, &options.json)?;
+    
+    let cancel = tasks.register(&task_id).await;
+    let mut reporter = ProgressReporter::new(app, &task_id);
+    
+    let result = crate::import::run_import(
+        &conn,
+        &target,
+        &mut rows,
+        &options,
+        cancel,
+        &mut |rows| reporter.report(rows, None),
+    ).await;
+    
+    tasks.finish(&task_id).await;
+    if let Ok(summary) = &result {
+        reporter.emit(summary.rows_read, Some(summary.rows_read));
+    }
+    
+    result
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn execute_sql_file(
+    id: String,
+    path: String,
+    options: Option<ScriptOptions>,
+    task_id: String,
+    app: AppHandle,
+    state: State<'_, ConnectionManager>,
+    tasks: State<'_, TaskManager>,
+) -> Result<ScriptSummary, String> {
+    log::info!("[execute_sql_file] Running {} on connection {}", path, id);
+    
     let conn = state
         .get_connection(&id)
         .await
         .ok_or_else(|| "Connection not found".to_string())?;
+    state.ensure_writable(&id).await?;
+    
+    let options = options.unwrap_or_default();
+    let protected = state.get_config(&id).await.is_some_and(|c| c.protected);
+    let size = std::fs::metadata(&path).map(|m| m.len()).ok();
+    let cancel = tasks.register(&task_id).await;
+    let mut reporter = ProgressReporter::new(app, &task_id);
+    
+    let result = crate::script::run_script(
+        &conn,
+        &path,
+        &options,
+        protected,
+        cancel,
+        &mut |statements, bytes| reporter.report_bytes(statements, bytes, size),
+    ).await;
+    
+    tasks.finish(&task_id).await;
+    if let Ok(summary) = &result {
+        let statements = summary.statements_executed + summary.statements_failed;
+        reporter.emit_bytes(statements, summary.bytes_read, size);
+    }
+    
+    result
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn copy_table(
+    source_id: String,
+    source_table: String,
+    target_id: String,
+    target_table: String,
+    options: Option<CopyTableOptions>,
+    task_id: String,
+    app: AppHandle,
+    state: State<'_, ConnectionManager>,
+    tasks: State<'_, TaskManager>,
+) -> Result<CopyTableSummary, String> {
+    log::info!(
+        "[copy_table] Copying {} on {} to {} on {}",
+        source_table, source_id, target_table, target_id
+    );
+    
+    let source = state
+        .get_connection(&source_id)
+        .await
+        .ok_or_else(|| "Source connection not found".to_string())?;
+    let target = state
+        .get_connection(&target_id)
+        .await
+        .ok_or_else(|| "Target connection not found".to_string())?;
+    state.ensure_writable(&target_id).await?;
+    
+    let options = options.unwrap_or_default();
+    if options.truncate && state.get_config(&target_id).await.is_some_and(|c| c.protected) {
+        return Err(format!(
+            "This connection is protected: empty {} with a query to confirm it, then copy without truncating",
+            target_table
+        ));
+    }
+    let cancel = tasks.register(&task_id).await;
+    let mut reporter = ProgressReporter::new(app, &task_id);
+    
+    let result = crate::transfer::copy_table(
+        source,
+        &source_table,
+        &target,
+        &target_table,
+        &options,
+        cancel,
+        &mut |rows| reporter.report(rows, None),
+    ).await;
+    
+    tasks.finish(&task_id).await;
+    if let Ok(summary) = &result {
+        reporter.emit(summary.rows_read, Some(summary.rows_read));
+    }
+    
+    result
+}
+
+#[tauri::command]
+pub async fn compare_schemas(
+    source_id: String,
+    target_id: String,
+    options: Option<SchemaCompareOptions>,
+    state: State<'_, ConnectionManager>,
+) -> Result<SchemaComparison, String> {
+    log::info!("[compare_schemas] Comparing {} with {}", source_id, target_id);
+    
+    let source = state
+        .get_connection(&source_id)
+        .await
+        .ok_or_else(|| "Source connection not found".to_string())?;
+    let target = state
+        .get_connection(&target_id)
+        .await
+        .ok_or_else(|| "Target connection not found".to_string())?;
+    
+    crate::compare::schema::compare_schemas(&source, &target, &options.unwrap_or_default()).await
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn compare_table_data(
+    source_id: String,
+    source_table: String,
+    target_id: String,
+    target_table: String,
+    key_columns: Vec<String>,
+    options: Option<DataCompareOptions>,
+    task_id: String,
+    app: AppHandle,
+    state: State<'_, ConnectionManager>,
+    tasks: State<'_, TaskManager>,
+) -> Result<DataComparison, String> {
+    log::info!(
+        "[compare_table_data] Comparing {} on {} with {} on {}",
+        source_table, source_id, target_table, target_id
+    );
+    
+    let source = state
+        .get_connection(&source_id)
+        .await
+        .ok_or_else(|| "Source connection not found".to_string())?;
+    let target = state
+        .get_connection(&target_id)
+        .await
+        .ok_or_else(|| "Target connection not found".to_string())?;
     
-    crate::database::list_databases(&conn).await
+    let options = options.unwrap_or_default();
+    let cancel = tasks.register(&task_id).await;
+    let mut reporter = ProgressReporter::new(app, &task_id);
+    
+    let result = crate::compare::data::compare_table_data(
+        source,
+        &source_table,
+        target,
+        &target_table,
+        &key_columns,
+        &options,
+        cancel,
+        &mut |rows| reporter.report(rows, None),
+    ).await;
+    
+    tasks.finish(&task_id).await;
+    if let Ok(comparison) = &result {
+        reporter.emit(comparison.source_rows, Some(comparison.source_rows));
+    }
+    
+    result
+}
+
+#[tauri::command]
+pub async fn cancel_task(
+    task_id: String,
+    tasks: State<'_, TaskManager>,
+) -> Result<bool, String> {
+    Ok(tasks.cancel(&task_id).await)
+}
+
+#[tauri::command]
+pub async fn search_history(
+    filter: HistoryFilter,
+    history: State<'_, HistoryStore>,
+) -> Result<Vec<HistoryEntry>, String> {
+    history.search(&filter).await
+}
+
+#[tauri::command]
+pub async fn save_query(
+    connection_name: String,
+    title: String,
+    sql: String,
+    history: State<'_, HistoryStore>,
+) -> Result<HistoryEntry, String> {
+    history.save_query(&connection_name, &title, &sql).await
+}
+
+#[tauri::command]
+pub async fn set_history_pinned(
+    entry_id: i64,
+    pinned: bool,
+    title: Option<String>,
+    history: State<'_, HistoryStore>,
+) -> Result<HistoryEntry, String> {
+    history.set_pinned(entry_id, pinned, title).await
+}
+
+#[tauri::command]
+pub async fn delete_history_entry(
+    entry_id: i64,
+    history: State<'_, HistoryStore>,
+) -> Result<bool, String> {
+    history.delete(entry_id).await
+}
+
+#[tauri::command]
+pub async fn clear_history(
+    connection_name: Option<String>,
+    history: State<'_, HistoryStore>,
+) -> Result<u64, String> {
+    history.clear(connection_name.as_deref()).await
 }
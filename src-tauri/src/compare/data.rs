@@ -0,0 +1,1108 @@
+use std::cmp::Ordering;
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::time::Instant;
+use tokio_util::sync::CancellationToken;
+use crate::database::dialect::{self, ColumnKind, SqlValue};
+use crate::database::temporal::TemporalFormat;
+use crate::database::{self, DatabaseConnection, RowSink};
+use crate::import::infer;
+use crate::models::*;
+
+/// Rows sent from a reader thread at a time.
+const BATCH_ROWS: usize = 1000;
+/// Batches buffered between each reader and the merge.
+const CHANNEL_BATCHES: usize = 8;
+
+type Row = Vec<serde_json::Value>;
+type BatchMessage = Result<Option<Vec<Row>>, String>;
+
+/// A compared column on one side.
+#[derive(Debug, Clone)]
+struct Column {
+    name: String,
+    data_type: String,
+    kind: ColumnKind,
+}
+
+/// One of the two tables. `keys` and `columns` line up with the other side's.
+struct Side {
+    conn: DatabaseConnection,
+    db_type: DatabaseType,
+    table: String,
+    keys: Vec<Column>,
+    columns: Vec<Column>,
+}
+
+/// Rows between two source key values: from `lower` (inclusive) up to
+/// `upper` (exclusive). `None` leaves that end open.
+struct KeyRange {
+    lower: Option<Row>,
+    upper: Option<Row>,
+}
+
+/// Compare the rows of two tables by key. Both sides are read ordered by the
+/// key and merged; on the same engine, key ranges whose row count and hash
+/// match are skipped without reading their rows.
+#[allow(clippy::too_many_arguments)]
+pub async fn compare_table_data(
+    source: DatabaseConnection,
+    source_table: &str,
+    target: DatabaseConnection,
+    target_table: &str,
+    key_columns: &[String],
+    options: &DataCompareOptions,
+    cancel: CancellationToken,
+    progress: &mut (dyn FnMut(u64) + Send),
+) -> Result<DataComparison, String> {
+    let start = Instant::now();
+
+    let source_columns = database::get_table_structure_info(&source, source_table).await?.columns;
+    if source_columns.is_empty() {
+        return Err(format!("Table {} does not exist", source_table));
+    }
+    let target_columns = database::get_table_structure_info(&target, target_table).await?.columns;
+    if target_columns.is_empty() {
+        return Err(format!("Table {} does not exist", target_table));
+    }
+
+    let key_names: Vec<String> = match key_columns.is_empty() {
+        true => source_columns.iter().filter(|c| c.is_primary_key).map(|c| c.name.clone()).collect(),
+        false => key_columns.to_vec(),
+    };
+    if key_names.is_empty() {
+        return Err(format!(
+            "Table {} has no primary key; pass the key columns to compare by",
+            source_table
+        ));
+    }
+
+    let find = |columns: &[ColumnInfo], name: &str| {
+        columns.iter().find(|c| c.name.eq_ignore_ascii_case(name)).map(|c| Column {
+            name: c.name.clone(),
+            data_type: c.data_type.clone(),
+            kind: dialect::column_kind(&c.data_type),
+        })
+    };
+    let pair = |name: &str| find(&source_columns, name).zip(find(&target_columns, name));
+    let is_key = |name: &str| key_names.iter().any(|k| k.eq_ignore_ascii_case(name));
+    let ignored = |name: &str| options.ignore_columns.iter().any(|c| c.eq_ignore_ascii_case(name));
+
+    let mut source_keys = Vec::new();
+    let mut target_keys = Vec::new();
+    for name in &key_names {
+        let (source_key, target_key) =
+            pair(name).ok_or_else(|| format!("Key column {} is not in both tables", name))?;
+        source_keys.push(source_key);
+        target_keys.push(target_key);
+    }
+
+    let mut warnings = Vec::new();
+    let wanted: Vec<String> = match &options.columns {
+        Some(columns) => columns.clone(),
+        None => {
+            for column in &target_columns {
+                if find(&source_columns, &column.name).is_none() && !ignored(&column.name) {
+                    warnings.push(format!("Column {} is only in the target table; skipped", column.name));
+                }
+            }
+            source_columns.iter().map(|c| c.name.clone()).collect()
+        }
+    };
+    let mut source_compared = Vec::new();
+    let mut target_compared = Vec::new();
+    for name in wanted.iter().filter(|n| !is_key(n) && !ignored(n)) {
+        match pair(name) {
+            Some((source_column, target_column)) => {
+                source_compared.push(source_column);
+                target_compared.push(target_column);
+            }
+            None if find(&source_columns, name).is_some() => {
+                warnings.push(format!("Column {} is only in the source table; skipped", name));
+            }
+            None => warnings.push(format!("Column {} is not in the source table; skipped", name)),
+        }
+    }
+
+    let source = Side {
+        db_type: source.db_type(),
+        conn: source,
+        table: source_table.to_string(),
+        keys: source_keys,
+        columns: source_compared,
+    };
+    let target = Side {
+        db_type: target.db_type(),
+        conn: target,
+        table: target_table.to_string(),
+        keys: target_keys,
+        columns: target_compared,
+    };
+
+    let chunked = options.chunk_size > 0 && source.db_type == target.db_type && source.hash_sql(None).is_some();
+    if options.chunk_size > 0 && !chunked {
+        warnings.push(match source.db_type == target.db_type {
+            true => format!("{:?} cannot hash rows; every row was compared", source.db_type),
+            false => "Hash chunks need both tables on the same engine; every row was compared".to_string(),
+        });
+    }
+    let ranges = match chunked {
+        true => chunk_ranges(&source, options.chunk_size as u64, &cancel).await?,
+        false => vec![KeyRange { lower: None, upper: None }],
+    };
+
+    let mut comparer = Comparer {
+        source: &source,
+        target: &target,
+        options,
+        result: DataComparison {
+            key_columns: source.keys.iter().map(|c| c.name.clone()).collect(),
+            columns: source.columns.iter().map(|c| c.name.clone()).collect(),
+            source_rows: 0,
+            target_rows: 0,
+            rows_missing: 0,
+            rows_extra: 0,
+            rows_changed: 0,
+            rows_identical: 0,
+            chunks: 0,
+            chunks_skipped: 0,
+            differences: Vec::new(),
+            warnings,
+            script: None,
+            duration_ms: 0,
+        },
+        deletes: Vec::new(),
+        updates: Vec::new(),
+        inserts: Vec::new(),
+    };
+
+    for range in &ranges {
+        if cancel.is_cancelled() {
+            return Err("Comparison cancelled".to_string());
+        }
+        if chunked {
+            comparer.result.chunks += 1;
+            let (source_hash, target_hash) = tokio::try_join!(source.range_hash(range), target.range_hash(range))?;
+            if source_hash == target_hash {
+                comparer.result.chunks_skipped += 1;
+                comparer.result.source_rows += source_hash.0;
+                comparer.result.target_rows += target_hash.0;
+                comparer.result.rows_identical += source_hash.0;
+                progress(comparer.result.source_rows);
+                continue;
+            }
+        }
+        comparer.compare_range(range, &cancel, progress).await?;
+    }
+
+    let mut result = comparer.finish();
+    result.duration_ms = start.elapsed().as_millis() as u64;
+    log::info!(
+        "[compare_table_data] {} vs {}: {} missing, {} extra, {} changed, {} of {} chunks skipped",
+        source_table,
+        target_table,
+        result.rows_missing,
+        result.rows_extra,
+        result.rows_changed,
+        result.chunks_skipped,
+        result.chunks
+    );
+    Ok(result)
+}
+
+/// Split the source into ranges of `chunk_size` rows by reading its keys.
+async fn chunk_ranges(source: &Side, chunk_size: u64, cancel: &CancellationToken) -> Result<Vec<KeyRange>, String> {
+    let sql = source.select_sql(&KeyRange { lower: None, upper: None }, false);
+    let mut sink = BoundarySink {
+        every: chunk_size,
+        rows: 0,
+        boundaries: Vec::new(),
+        cancel: cancel.clone(),
+    };
+    database::stream_sql_query(&source.conn, &sql, &TemporalFormat::default(), &mut sink).await?;
+
+    let mut ranges = Vec::with_capacity(sink.boundaries.len() + 1);
+    let mut lower = None;
+    for boundary in sink.boundaries {
+        ranges.push(KeyRange { lower, upper: Some(boundary.clone()) });
+        lower = Some(boundary);
+    }
+    ranges.push(KeyRange { lower, upper: None });
+    Ok(ranges)
+}
+
+impl Side {
+    /// Key expression that orders the same way as `compare_values`: text
+    /// keys sort by their bytes, whatever the column collation.
+    fn key_expr(&self, column: &Column) -> String {
+        let quoted = dialect::quote_ident(&self.db_type, &column.name);
+        if column.kind != ColumnKind::Text {
+            return quoted;
+        }
+        let character = dialect::is_character_type(&column.data_type);
+        match (&self.db_type, character) {
+            (DatabaseType::PostgreSQL, true) => format!("{} COLLATE \"C\"", quoted),
+            (DatabaseType::PostgreSQL, false) => format!("{}::text COLLATE \"C\"", quoted),
+            (DatabaseType::MySQL, _) => format!("BINARY {}", quoted),
+            (DatabaseType::SQLServer, true) => format!("{} COLLATE Latin1_General_BIN2", quoted),
+            (DatabaseType::SQLServer, false) => {
+                format!("CAST({} AS NVARCHAR(MAX)) COLLATE Latin1_General_BIN2", quoted)
+            }
+            (DatabaseType::SQLite, _) => quoted,
+        }
+    }
+
+    /// SQL literal for a value read from either side, as this side's column.
+    fn literal(&self, column: &Column, value: &serde_json::Value) -> String {
+        match parse_value(column.kind, value) {
+            Ok(parsed) => parsed.to_column_literal(&self.db_type, &column.data_type),
+            Err(_) => dialect::quote_column_literal(&self.db_type, &column.data_type, value),
+        }
+    }
+
+    /// `WHERE` condition for the rows of a range. Bounds are source key
+    /// values, so a composite key is compared column by column.
+    fn range_predicate(&self, range: &KeyRange) -> Option<String> {
+        let exprs: Vec<String> = self.keys.iter().map(|k| self.key_expr(k)).collect();
+        let literals = |bound: &Row| -> Vec<Option<String>> {
+            self.keys
+                .iter()
+                .zip(bound)
+                .map(|(key, value)| match value.is_null() {
+                    true => None,
+                    false => Some(self.literal(key, value)),
+                })
+                .collect()
+        };
+        let mut parts = Vec::new();
+        if let Some(lower) = &range.lower {
+            parts.extend(tuple_predicate(&exprs, &literals(lower), ">", true));
+        }
+        if let Some(upper) = &range.upper {
+            parts.extend(tuple_predicate(&exprs, &literals(upper), "<", false));
+        }
+        match parts.len() {
+            0 => None,
+            1 => parts.pop(),
+            _ => Some(parts.iter().map(|p| format!("({})", p)).collect::<Vec<_>>().join(" AND ")),
+        }
+    }
+
+    /// Rows of a range ordered by key: the key columns, then with
+    /// `with_columns` the compared columns. Outputs are renamed so `ORDER BY`
+    /// sorts by the columns rather than by their text casts.
+    fn select_sql(&self, range: &KeyRange, with_columns: bool) -> String {
+        let db_type = &self.db_type;
+        let mut list = Vec::new();
+        let columns = match with_columns {
+            true => self.keys.iter().chain(&self.columns).collect::<Vec<_>>(),
+            false => self.keys.iter().collect(),
+        };
+        for (idx, column) in columns.into_iter().enumerate() {
+            let quoted = dialect::quote_ident(db_type, &column.name);
+            let alias = dialect::quote_ident(db_type, &format!("c{}", idx));
+            // PostgreSQL columns are read as text, as in `copy_table`
+            list.push(match db_type {
+                DatabaseType::PostgreSQL => format!("{}::text AS {}", quoted, alias),
+                _ => format!("{} AS {}", quoted, alias),
+            });
+        }
+
+        let mut sql = format!("SELECT {} FROM {}", list.join(", "), dialect::quote_table(db_type, &self.table));
+        if let Some(predicate) = self.range_predicate(range) {
+            sql.push_str(&format!(" WHERE {}", predicate));
+        }
+        // NULL keys come first, as in `compare_values`; PostgreSQL sorts them last by default
+        let order: Vec<String> = self
+            .keys
+            .iter()
+            .map(|k| match db_type {
+                DatabaseType::PostgreSQL => format!("{} NULLS FIRST", self.key_expr(k)),
+                _ => self.key_expr(k),
+            })
+            .collect();
+        sql.push_str(&format!(" ORDER BY {}", order.join(", ")));
+        sql
+    }
+
+    /// Row count and the sum of per-row hash prefixes for a range, both as
+    /// text. `None` on engines without a hash function.
+    fn hash_sql(&self, range: Option<&KeyRange>) -> Option<String> {
+        let db_type = &self.db_type;
+        let columns: Vec<&Column> = self.keys.iter().chain(&self.columns).collect();
+        let quoted = |c: &Column| dialect::quote_ident(db_type, &c.name);
+
+        let select = match db_type {
+            DatabaseType::PostgreSQL => {
+                let row: Vec<String> = columns.iter().map(|c| quoted(c)).collect();
+                format!(
+                    "SELECT COUNT(*)::text, COALESCE(SUM(('x' || SUBSTR(MD5(ROW({})::text), 1, 15))::BIT(60)::BIGINT), 0)::text",
+                    row.join(", ")
+                )
+            }
+            DatabaseType::MySQL => {
+                let row: Vec<String> = columns
+                    .iter()
+                    .map(|c| format!("COALESCE(CAST({} AS CHAR), CHAR(0))", quoted(c)))
+                    .collect();
+                format!(
+                    "SELECT CAST(COUNT(*) AS CHAR), CAST(COALESCE(SUM(CAST(CONV(SUBSTRING(MD5(CONCAT_WS(CHAR(1), {})), 1, 15), 16, 10) AS UNSIGNED)), 0) AS CHAR)",
+                    row.join(", ")
+                )
+            }
+            DatabaseType::SQLServer => {
+                let row: Vec<String> = columns
+                    .iter()
+                    .map(|c| {
+                        let text = match c.kind {
+                            ColumnKind::Date | ColumnKind::Time | ColumnKind::DateTime | ColumnKind::Timestamp => {
+                                format!("CONVERT(NVARCHAR(MAX), {}, 126)", quoted(c))
+                            }
+                            ColumnKind::Binary => format!("CONVERT(NVARCHAR(MAX), {}, 1)", quoted(c)),
+                            ColumnKind::Float => format!("CONVERT(NVARCHAR(MAX), {}, 3)", quoted(c)),
+                            _ => format!("CAST({} AS NVARCHAR(MAX))", quoted(c)),
+                        };
+                        format!("ISNULL({}, NCHAR(0))", text)
+                    })
+                    .collect();
+                format!(
+                    "SELECT CAST(COUNT_BIG(*) AS NVARCHAR(20)), CAST(COALESCE(SUM(CAST(CAST(SUBSTRING(HASHBYTES('MD5', CONCAT_WS(NCHAR(1), {})), 1, 7) AS BIGINT) AS DECIMAL(38, 0))), 0) AS NVARCHAR(40))",
+                    row.join(", ")
+                )
+            }
+            DatabaseType::SQLite => return None,
+        };
+
+        let mut sql = format!("{} FROM {}", select, dialect::quote_table(db_type, &self.table));
+        if let Some(predicate) = range.and_then(|r| self.range_predicate(r)) {
+            sql.push_str(&format!(" WHERE {}", predicate));
+        }
+        Some(sql)
+    }
+
+    async fn range_hash(&self, range: &KeyRange) -> Result<(u64, String), String> {
+        let sql = self.hash_sql(Some(range)).unwrap_or_default();
+        let mut sink = FirstRowSink { row: None };
+        database::stream_sql_query(&self.conn, &sql, &TemporalFormat::default(), &mut sink).await?;
+
+        let row = sink.row.unwrap_or_default();
+        let text = |idx: usize| match row.get(idx) {
+            Some(serde_json::Value::String(s)) => s.trim().to_string(),
+            Some(other) => other.to_string(),
+            None => String::new(),
+        };
+        let count = text(0)
+            .parse()
+            .map_err(|_| format!("Failed to count the rows of {}", self.table))?;
+        Ok((count, text(1)))
+    }
+
+    /// `WHERE` condition matching one row by the key values read from this side.
+    fn row_predicate(&self, row: &[serde_json::Value]) -> String {
+        self.keys
+            .iter()
+            .zip(row)
+            .map(|(key, value)| {
+                let quoted = dialect::quote_ident(&self.db_type, &key.name);
+                match value.is_null() {
+                    true => format!("{} IS NULL", quoted),
+                    false => format!("{} = {}", quoted, self.literal(key, value)),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" AND ")
+    }
+}
+
+/// `key > bound` (or `<`, optionally inclusive) over a composite key, spelled
+/// out as `k1 > v1 OR (k1 = v1 AND k2 > v2) ...` so every engine accepts it.
+/// A `None` literal is a NULL bound; NULL keys sort first, as in
+/// `compare_values`. `None` when every row matches.
+fn tuple_predicate(exprs: &[String], literals: &[Option<String>], op: &str, inclusive: bool) -> Option<String> {
+    let mut alternatives = Vec::new();
+    for i in 0..exprs.len() {
+        let op = match inclusive && i + 1 == exprs.len() {
+            true => format!("{}=", op),
+            false => op.to_string(),
+        };
+        let conditions = (0..i)
+            .map(|j| key_condition(&exprs[j], "=", literals[j].as_deref()))
+            .chain(std::iter::once(key_condition(&exprs[i], &op, literals[i].as_deref())));
+        let mut parts = Vec::new();
+        let mut never = false;
+        for condition in conditions {
+            match condition {
+                KeyCondition::Always => {}
+                KeyCondition::Never => never = true,
+                KeyCondition::Sql(sql) => parts.push(sql),
+            }
+        }
+        match (never, parts.is_empty()) {
+            (true, _) => {}
+            (false, true) => return None,
+            (false, false) => alternatives.push(parts.join(" AND ")),
+        }
+    }
+    match alternatives.len() {
+        0 => Some("1 = 0".to_string()),
+        1 => alternatives.pop(),
+        _ => Some(alternatives.iter().map(|a| format!("({})", a)).collect::<Vec<_>>().join(" OR ")),
+    }
+}
+
+enum KeyCondition {
+    Always,
+    Never,
+    Sql(String),
+}
+
+/// `expr op literal` with NULL sorting before every value.
+fn key_condition(expr: &str, op: &str, literal: Option<&str>) -> KeyCondition {
+    match (op, literal) {
+        ("=" | "<=", None) => KeyCondition::Sql(format!("{} IS NULL", expr)),
+        (">", None) => KeyCondition::Sql(format!("{} IS NOT NULL", expr)),
+        (">=", None) => KeyCondition::Always,
+        (_, None) => KeyCondition::Never,
+        ("<" | "<=", Some(literal)) => KeyCondition::Sql(format!("({} IS NULL OR {} {} {})", expr, expr, op, literal)),
+        (_, Some(literal)) => KeyCondition::Sql(format!("{} {} {}", expr, op, literal)),
+    }
+}
+
+/// Tallies the merged rows and collects the differences and sync statements.
+struct Comparer<'a> {
+    source: &'a Side,
+    target: &'a Side,
+    options: &'a DataCompareOptions,
+    result: DataComparison,
+    deletes: Vec<String>,
+    updates: Vec<String>,
+    inserts: Vec<String>,
+}
+
+impl Comparer<'_> {
+    /// Read both sides of a range and merge them by key.
+    async fn compare_range(
+        &mut self,
+        range: &KeyRange,
+        cancel: &CancellationToken,
+        progress: &mut (dyn FnMut(u64) + Send),
+    ) -> Result<(), String> {
+        let mut source_rows = OrderedRows::open(self.source, self.source.select_sql(range, true));
+        let mut target_rows = OrderedRows::open(self.target, self.target.select_sql(range, true));
+
+        let result = self.merge(&mut source_rows, &mut target_rows, cancel, progress);
+        // Unblocks the readers if the merge stopped early
+        source_rows.close().await;
+        target_rows.close().await;
+        result
+    }
+
+    fn merge(
+        &mut self,
+        source_rows: &mut OrderedRows,
+        target_rows: &mut OrderedRows,
+        cancel: &CancellationToken,
+        progress: &mut (dyn FnMut(u64) + Send),
+    ) -> Result<(), String> {
+        let mut source_row = source_rows.next()?;
+        let mut target_row = target_rows.next()?;
+        loop {
+            if cancel.is_cancelled() {
+                return Err("Comparison cancelled".to_string());
+            }
+            let order = match (&source_row, &target_row) {
+                (None, None) => break,
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (Some(s), Some(t)) => compare_keys(&s.0, &t.0),
+            };
+            match order {
+                Ordering::Less => {
+                    if let Some((_, row)) = &source_row {
+                        self.missing(row);
+                    }
+                    source_row = source_rows.next()?;
+                }
+                Ordering::Greater => {
+                    if let Some((_, row)) = &target_row {
+                        self.extra(row);
+                    }
+                    target_row = target_rows.next()?;
+                }
+                Ordering::Equal => {
+                    if let (Some((_, s)), Some((_, t))) = (&source_row, &target_row) {
+                        self.matched(s, t);
+                    }
+                    source_row = source_rows.next()?;
+                    target_row = target_rows.next()?;
+                }
+            }
+            progress(self.result.source_rows);
+        }
+        Ok(())
+    }
+
+    fn missing(&mut self, row: &[serde_json::Value]) {
+        self.result.source_rows += 1;
+        self.result.rows_missing += 1;
+        self.record(RowChange::Missing, self.key_map(self.source, row), Vec::new());
+
+        if self.options.generate_script {
+            let target = self.target;
+            let columns: Vec<&Column> = target.keys.iter().chain(&target.columns).collect();
+            let names: Vec<String> = columns
+                .iter()
+                .map(|c| dialect::quote_ident(&target.db_type, &c.name))
+                .collect();
+            let values: Vec<String> = columns.iter().zip(row).map(|(c, v)| target.literal(c, v)).collect();
+            self.inserts.push(format!(
+                "INSERT INTO {} ({}) VALUES ({})",
+                dialect::quote_table(&target.db_type, &target.table),
+                names.join(", "),
+                values.join(", ")
+            ));
+        }
+    }
+
+    fn extra(&mut self, row: &[serde_json::Value]) {
+        self.result.target_rows += 1;
+        self.result.rows_extra += 1;
+        self.record(RowChange::Extra, self.key_map(self.target, row), Vec::new());
+
+        if self.options.generate_script {
+            let target = self.target;
+            self.deletes.push(format!(
+                "DELETE FROM {} WHERE {}",
+                dialect::quote_table(&target.db_type, &target.table),
+                target.row_predicate(row)
+            ));
+        }
+    }
+
+    fn matched(&mut self, source_row: &[serde_json::Value], target_row: &[serde_json::Value]) {
+        self.result.source_rows += 1;
+        self.result.target_rows += 1;
+
+        let offset = self.source.keys.len();
+        let mut changed = Vec::new();
+        let mut assignments = Vec::new();
+        for (idx, (from, to)) in self.source.columns.iter().zip(&self.target.columns).enumerate() {
+            let null = serde_json::Value::Null;
+            let source_value = source_row.get(offset + idx).unwrap_or(&null);
+            let target_value = target_row.get(offset + idx).unwrap_or(&null);
+            let source_parsed = canonical(from.kind, source_value);
+            // Read the target as the source kind where it parses, so `1` and `1.0` match
+            let target_parsed = parse_value(from.kind, target_value)
+                .map(normalize)
+                .unwrap_or_else(|_| canonical(to.kind, target_value));
+            if compare_values(&source_parsed, &target_parsed) == Ordering::Equal {
+                continue;
+            }
+
+            changed.push(ColumnValueDifference {
+                column: from.name.clone(),
+                source: to_json(&source_parsed),
+                target: to_json(&target_parsed),
+            });
+            assignments.push(format!(
+                "{} = {}",
+                dialect::quote_ident(&self.target.db_type, &to.name),
+                self.target.literal(to, source_value)
+            ));
+        }
+
+        if changed.is_empty() {
+            self.result.rows_identical += 1;
+            return;
+        }
+        self.result.rows_changed += 1;
+        self.record(RowChange::Changed, self.key_map(self.source, source_row), changed);
+
+        if self.options.generate_script {
+            let target = self.target;
+            self.updates.push(format!(
+                "UPDATE {} SET {} WHERE {}",
+                dialect::quote_table(&target.db_type, &target.table),
+                assignments.join(", "),
+                target.row_predicate(target_row)
+            ));
+        }
+    }
+
+    fn key_map(&self, side: &Side, row: &[serde_json::Value]) -> serde_json::Map<String, serde_json::Value> {
+        self.source
+            .keys
+            .iter()
+            .zip(&side.keys)
+            .zip(row)
+            .map(|((source_key, key), value)| (source_key.name.clone(), to_json(&canonical(key.kind, value))))
+            .collect()
+    }
+
+    fn record(
+        &mut self,
+        change: RowChange,
+        key: serde_json::Map<String, serde_json::Value>,
+        columns: Vec<ColumnValueDifference>,
+    ) {
+        if self.result.differences.len() < self.options.max_differences {
+            self.result.differences.push(RowDifference { change, key, columns });
+        }
+    }
+
+    /// The result, with the sync script: deletes first so inserted keys are
+    /// free, then updates and inserts.
+    fn finish(mut self) -> DataComparison {
+        if !self.options.generate_script {
+            return self.result;
+        }
+
+        let db_type = &self.target.db_type;
+        let terminate = |sql: &str| match db_type {
+            DatabaseType::SQLServer => format!("{}\nGO\n", sql),
+            _ => format!("{};\n", sql),
+        };
+        let mut script = format!(
+            "-- Datablaze data sync\n-- Source: {}\n-- Target: {}\n-- Created: {}\n",
+            self.source.table,
+            self.target.table,
+            chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        );
+        let sections = [
+            ("Rows only in the target", &self.deletes),
+            ("Changed rows", &self.updates),
+            ("Rows only in the source", &self.inserts),
+        ];
+        if sections.iter().all(|(_, statements)| statements.is_empty()) {
+            script.push_str("\n-- No changes\n");
+        }
+        for (title, statements) in sections {
+            if statements.is_empty() {
+                continue;
+            }
+            script.push_str(&format!("\n-- {}\n", title));
+            for sql in statements {
+                script.push_str(&terminate(sql));
+            }
+        }
+        self.result.script = Some(script);
+        self.result
+    }
+}
+
+/// Rows of one side with their parsed keys, checked to arrive in key order.
+struct OrderedRows {
+    receiver: Receiver<BatchMessage>,
+    reader: tokio::task::JoinHandle<()>,
+    buffered: std::vec::IntoIter<Row>,
+    done: bool,
+    kinds: Vec<ColumnKind>,
+    table: String,
+    last: Option<Vec<SqlValue>>,
+}
+
+impl OrderedRows {
+    /// Start reading on a blocking thread: `RowSink` is synchronous and
+    /// blocks while the merge catches up.
+    fn open(side: &Side, sql: String) -> Self {
+        let (sender, receiver) = sync_channel::<BatchMessage>(CHANNEL_BATCHES);
+        let conn = side.conn.clone();
+        let runtime = tokio::runtime::Handle::current();
+        let reader = tokio::task::spawn_blocking(move || {
+            let mut sink = BatchSink { sender, batch: Vec::new() };
+            let result = runtime.block_on(database::stream_sql_query(&conn, &sql, &TemporalFormat::default(), &mut sink));
+            if result.is_ok() && !sink.batch.is_empty() {
+                let batch = std::mem::take(&mut sink.batch);
+                if sink.sender.send(Ok(Some(batch))).is_err() {
+                    return;
+                }
+            }
+            let _ = sink.sender.send(result.map(|_| None));
+        });
+
+        Self {
+            receiver,
+            reader,
+            buffered: Vec::new().into_iter(),
+            done: false,
+            kinds: side.keys.iter().map(|k| k.kind).collect(),
+            table: side.table.clone(),
+            last: None,
+        }
+    }
+
+    fn next(&mut self) -> Result<Option<(Vec<SqlValue>, Row)>, String> {
+        let row = loop {
+            if let Some(row) = self.buffered.next() {
+                break row;
+            }
+            if self.done {
+                return Ok(None);
+            }
+            match tokio::task::block_in_place(|| self.receiver.recv()) {
+                Ok(Ok(Some(batch))) => self.buffered = batch.into_iter(),
+                Ok(Ok(None)) => self.done = true,
+                Ok(Err(e)) => return Err(e),
+                Err(_) => return Err("Row reader stopped unexpectedly".to_string()),
+            }
+        };
+
+        let key: Vec<SqlValue> = self.kinds.iter().zip(&row).map(|(&kind, v)| canonical(kind, v)).collect();
+        if let Some(last) = &self.last {
+            match compare_keys(last, &key) {
+                Ordering::Less => {}
+                Ordering::Equal => {
+                    return Err(format!("Key {} is not unique in {}", key_text(&key), self.table));
+                }
+                Ordering::Greater => {
+                    return Err(format!(
+                        "Rows of {} did not arrive in key order; the key columns must have the same types in both tables",
+                        self.table
+                    ));
+                }
+            }
+        }
+        self.last = Some(key.clone());
+        Ok(Some((key, row)))
+    }
+
+    async fn close(self) {
+        drop(self.receiver);
+        if let Err(e) = self.reader.await {
+            log::error!("[compare_table_data] Row reader failed: {}", e);
+        }
+    }
+}
+
+/// Sends streamed rows to the merge in batches.
+struct BatchSink {
+    sender: SyncSender<BatchMessage>,
+    batch: Vec<Row>,
+}
+
+impl RowSink for BatchSink {
+    fn begin(&mut self, _columns: &[ResultColumn]) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn write_row(&mut self, values: &[serde_json::Value]) -> Result<(), String> {
+        self.batch.push(values.to_vec());
+        if self.batch.len() >= BATCH_ROWS {
+            self.sender
+                .send(Ok(Some(std::mem::take(&mut self.batch))))
+                .map_err(|_| "Comparison stopped".to_string())?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// Keeps every `every`-th key as a chunk boundary.
+struct BoundarySink {
+    every: u64,
+    rows: u64,
+    boundaries: Vec<Row>,
+    cancel: CancellationToken,
+}
+
+impl RowSink for BoundarySink {
+    fn begin(&mut self, _columns: &[ResultColumn]) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn write_row(&mut self, values: &[serde_json::Value]) -> Result<(), String> {
+        if self.cancel.is_cancelled() {
+            return Err("Comparison cancelled".to_string());
+        }
+        if self.rows > 0 && self.rows.is_multiple_of(self.every) {
+            self.boundaries.push(values.to_vec());
+        }
+        self.rows += 1;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+struct FirstRowSink {
+    row: Option<Row>,
+}
+
+impl RowSink for FirstRowSink {
+    fn begin(&mut self, _columns: &[ResultColumn]) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn write_row(&mut self, values: &[serde_json::Value]) -> Result<(), String> {
+        if self.row.is_none() {
+            self.row = Some(values.to_vec());
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+fn parse_value(kind: ColumnKind, value: &serde_json::Value) -> Result<SqlValue, String> {
+    // JSON documents arrive as text on some engines
+    let parsed = match (kind, value) {
+        (ColumnKind::Json, serde_json::Value::String(text)) => serde_json::from_str(text).ok(),
+        _ => None,
+    };
+    infer::parse_json_value(kind, parsed.as_ref().unwrap_or(value))
+}
+
+/// A value in the form it is compared in; values that do not parse compare as text.
+fn canonical(kind: ColumnKind, value: &serde_json::Value) -> SqlValue {
+    parse_value(kind, value).map(normalize).unwrap_or_else(|_| match value {
+        serde_json::Value::String(s) => SqlValue::Text(s.clone()),
+        other => SqlValue::Text(other.to_string()),
+    })
+}
+
+/// Drop the trailing zeros engines add for the column scale.
+fn normalize(value: SqlValue) -> SqlValue {
+    match value {
+        SqlValue::Decimal(text) if !text.contains(['e', 'E']) => {
+            let text = match text.contains('.') {
+                true => text.trim_end_matches('0').trim_end_matches('.'),
+                false => text.as_str(),
+            };
+            match text {
+                "-0" | "" => SqlValue::Decimal("0".to_string()),
+                text => SqlValue::Decimal(text.to_string()),
+            }
+        }
+        other => other,
+    }
+}
+
+fn compare_keys(a: &[SqlValue], b: &[SqlValue]) -> Ordering {
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| compare_values(a, b))
+        .find(|o| o.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+/// Order of two values as the key expressions sort them: numbers by value,
+/// text and binary by bytes, temporal values in time.
+fn compare_values(a: &SqlValue, b: &SqlValue) -> Ordering {
+    use SqlValue::*;
+
+    match (a, b) {
+        (Null, Null) => Ordering::Equal,
+        (Null, _) => Ordering::Less,
+        (_, Null) => Ordering::Greater,
+        (Bool(a), Bool(b)) => a.cmp(b),
+        (Int(a), Int(b)) => a.cmp(b),
+        (Decimal(a), Decimal(b)) => compare_decimals(a, b),
+        (Int(_) | Float(_) | Decimal(_), Int(_) | Float(_) | Decimal(_)) => {
+            let number = |v: &SqlValue| v.to_text().parse::<f64>().unwrap_or(f64::NAN);
+            number(a).total_cmp(&number(b))
+        }
+        (Date(a), Date(b)) => a.cmp(b),
+        (Time(a), Time(b)) => a.cmp(b),
+        (DateTime(a), DateTime(b)) => a.cmp(b),
+        (Timestamp(a), Timestamp(b)) => a.cmp(b),
+        (Bytes(a), Bytes(b)) => a.cmp(b),
+        (Json(a), Json(b)) if a == b => Ordering::Equal,
+        _ => a.to_text().cmp(&b.to_text()),
+    }
+}
+
+/// Compare decimals in plain notation without going through floats.
+fn compare_decimals(a: &str, b: &str) -> Ordering {
+    let split = |text: &str| {
+        let (negative, digits) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text),
+        };
+        let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+        let whole = whole.trim_start_matches('0').to_string();
+        let fraction = fraction.trim_end_matches('0').to_string();
+        let zero = whole.is_empty() && fraction.is_empty();
+        (negative && !zero, whole, fraction)
+    };
+    let (a_negative, a_whole, a_fraction) = split(a);
+    let (b_negative, b_whole, b_fraction) = split(b);
+
+    let magnitude = a_whole
+        .len()
+        .cmp(&b_whole.len())
+        .then_with(|| a_whole.cmp(&b_whole))
+        .then_with(|| a_fraction.cmp(&b_fraction));
+    match (a_negative, b_negative) {
+        (false, true) => Ordering::Greater,
+        (true, false) => Ordering::Less,
+        (false, false) => magnitude,
+        (true, true) => magnitude.reverse(),
+    }
+}
+
+fn to_json(value: &SqlValue) -> serde_json::Value {
+    match value {
+        SqlValue::Null => serde_json::Value::Null,
+        SqlValue::Bool(b) => serde_json::Value::Bool(*b),
+        SqlValue::Int(i) => serde_json::Value::from(*i),
+        SqlValue::Float(f) => serde_json::Number::from_f64(*f)
+            .map(serde_json::Value::Number)
+            .unwrap_or_else(|| serde_json::Value::String(f.to_string())),
+        SqlValue::Json(value) => value.clone(),
+        other => serde_json::Value::String(other.to_text()),
+    }
+}
+
+fn key_text(key: &[SqlValue]) -> String {
+    let parts: Vec<String> = key.iter().map(SqlValue::to_text).collect();
+    format!("({})", parts.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decimal(text: &str) -> SqlValue {
+        SqlValue::Decimal(text.to_string())
+    }
+
+    fn exprs(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    fn bound(values: &[Option<&str>]) -> Vec<Option<String>> {
+        values.iter().map(|v| v.map(str::to_string)).collect()
+    }
+
+    #[test]
+    fn nulls_sort_first() {
+        assert_eq!(compare_values(&SqlValue::Null, &SqlValue::Null), Ordering::Equal);
+        assert_eq!(compare_values(&SqlValue::Null, &SqlValue::Int(i64::MIN)), Ordering::Less);
+        assert_eq!(compare_values(&SqlValue::Text(String::new()), &SqlValue::Null), Ordering::Greater);
+        assert_eq!(
+            compare_keys(&[SqlValue::Int(1), SqlValue::Null], &[SqlValue::Int(1), SqlValue::Int(0)]),
+            Ordering::Less
+        );
+        assert_eq!(
+            compare_keys(&[SqlValue::Null, SqlValue::Int(5)], &[SqlValue::Int(0), SqlValue::Int(0)]),
+            Ordering::Less
+        );
+    }
+
+    #[test]
+    fn values_compare_by_kind() {
+        assert_eq!(compare_values(&SqlValue::Int(2), &SqlValue::Int(10)), Ordering::Less);
+        assert_eq!(compare_values(&SqlValue::Int(2), &SqlValue::Float(1.5)), Ordering::Greater);
+        assert_eq!(compare_values(&SqlValue::Int(1), &decimal("1.0")), Ordering::Equal);
+        // Text sorts by bytes, so upper case comes before lower case
+        assert_eq!(
+            compare_values(&SqlValue::Text("Z".to_string()), &SqlValue::Text("a".to_string())),
+            Ordering::Less
+        );
+        assert_eq!(compare_values(&SqlValue::Bytes(vec![0x01]), &SqlValue::Bytes(vec![0x01, 0x00])), Ordering::Less);
+        assert_eq!(
+            compare_values(&SqlValue::Json(serde_json::json!({"a": 1})), &SqlValue::Json(serde_json::json!({"a": 1}))),
+            Ordering::Equal
+        );
+    }
+
+    #[test]
+    fn decimals_compare_without_floats() {
+        let cases = [
+            ("10", "9.5", Ordering::Greater),
+            ("001.10", "1.1", Ordering::Equal),
+            ("0.0", "-0", Ordering::Equal),
+            ("-1.5", "-1.25", Ordering::Less),
+            ("-2", "1", Ordering::Less),
+            ("0.1", "0.09", Ordering::Greater),
+            ("12345678901234567890.1", "12345678901234567890.01", Ordering::Greater),
+        ];
+        for (a, b, expected) in cases {
+            assert_eq!(compare_decimals(a, b), expected, "{} vs {}", a, b);
+            assert_eq!(compare_decimals(b, a), expected.reverse(), "{} vs {}", b, a);
+        }
+    }
+
+    #[test]
+    fn normalize_drops_scale_zeros() {
+        let cases = [
+            ("1.500", "1.5"),
+            ("2.000", "2"),
+            ("-0.00", "0"),
+            ("100", "100"),
+            (".0", "0"),
+            ("1e10", "1e10"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(normalize(decimal(input)), decimal(expected), "{}", input);
+        }
+        assert_eq!(normalize(SqlValue::Text("1.50".to_string())), SqlValue::Text("1.50".to_string()));
+    }
+
+    #[test]
+    fn canonical_values_match_across_representations() {
+        let source = canonical(ColumnKind::Decimal, &serde_json::json!("1.50"));
+        let target = canonical(ColumnKind::Decimal, &serde_json::json!(1.5));
+        assert_eq!(compare_values(&source, &target), Ordering::Equal);
+        // Values that do not parse compare as text
+        assert_eq!(
+            canonical(ColumnKind::Integer, &serde_json::json!("abc")),
+            SqlValue::Text("abc".to_string())
+        );
+        assert_eq!(canonical(ColumnKind::Integer, &serde_json::Value::Null), SqlValue::Null);
+    }
+
+    #[test]
+    fn tuple_predicate_spells_out_composite_keys() {
+        assert_eq!(
+            tuple_predicate(&exprs(&["a"]), &bound(&[Some("1")]), ">", true).as_deref(),
+            Some("a >= 1")
+        );
+        assert_eq!(
+            tuple_predicate(&exprs(&["a", "b"]), &bound(&[Some("1"), Some("'x'")]), ">", true).as_deref(),
+            Some("(a > 1) OR (a = 1 AND b >= 'x')")
+        );
+    }
+
+    #[test]
+    fn upper_bounds_include_null_keys() {
+        // The open first range must hold the rows whose key is NULL
+        assert_eq!(
+            tuple_predicate(&exprs(&["a"]), &bound(&[Some("1")]), "<", false).as_deref(),
+            Some("(a IS NULL OR a < 1)")
+        );
+        assert_eq!(
+            tuple_predicate(&exprs(&["a", "b"]), &bound(&[Some("1"), Some("2")]), "<", false).as_deref(),
+            Some("((a IS NULL OR a < 1)) OR (a = 1 AND (b IS NULL OR b < 2))")
+        );
+    }
+
+    #[test]
+    fn null_bounds_use_is_null() {
+        // Nothing sorts before a NULL key
+        assert_eq!(
+            tuple_predicate(&exprs(&["a"]), &bound(&[None]), "<", false).as_deref(),
+            Some("1 = 0")
+        );
+        // Every row is at or after a NULL key
+        assert_eq!(tuple_predicate(&exprs(&["a"]), &bound(&[None]), ">", true), None);
+        assert_eq!(
+            tuple_predicate(&exprs(&["a", "b"]), &bound(&[None, Some("5")]), ">", true).as_deref(),
+            Some("(a IS NOT NULL) OR (a IS NULL AND b >= 5)")
+        );
+        assert_eq!(
+            tuple_predicate(&exprs(&["a", "b"]), &bound(&[Some("1"), None]), "<", false).as_deref(),
+            Some("(a IS NULL OR a < 1)")
+        );
+        assert_eq!(
+            tuple_predicate(&exprs(&["a", "b"]), &bound(&[Some("1"), None]), ">", true).as_deref(),
+            Some("(a > 1) OR (a = 1)")
+        );
+    }
+}
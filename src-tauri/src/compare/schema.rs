@@ -0,0 +1,902 @@
+use std::collections::{HashMap, HashSet};
+use crate::database::{self, ddl, dialect, DatabaseConnection};
+use crate::export::dump::{dependency_order, matches_filter};
+use crate::models::*;
+
+/// Sections of the migration script, in the order they run. Objects that
+/// depend on what changes are dropped first and created again at the end.
+#[derive(Debug, Clone, Copy)]
+enum Phase {
+    DropViews,
+    DropForeignKeys,
+    DropConstraints,
+    Sequences,
+    CreateTables,
+    Columns,
+    AddConstraints,
+    Indexes,
+    DropTables,
+    AddForeignKeys,
+    CreateViews,
+    DropSequences,
+}
+
+const PHASE_TITLES: [&str; 12] = [
+    "Drop views",
+    "Drop foreign keys",
+    "Drop constraints and indexes",
+    "Sequences",
+    "Create tables",
+    "Columns",
+    "Add constraints",
+    "Indexes",
+    "Drop tables",
+    "Add foreign keys",
+    "Create views",
+    "Drop sequences",
+];
+
+/// Compare the schemas of two connections. Differences are reported from the
+/// target's point of view: `Missing` objects exist only in the source, and the
+/// script makes the target match the source.
+pub async fn compare_schemas(
+    source: &DatabaseConnection,
+    target: &DatabaseConnection,
+    options: &SchemaCompareOptions,
+) -> Result<SchemaComparison, String> {
+    let source_snapshot = database::get_schema_snapshot(source).await?;
+    let target_snapshot = database::get_schema_snapshot(target).await?;
+    let comparison = compare_snapshots(source.db_type(), &source_snapshot, target.db_type(), &target_snapshot, options);
+    log::info!(
+        "[compare_schemas] Compared {} tables, {} differences",
+        comparison.tables_compared,
+        comparison.differences.len()
+    );
+    Ok(comparison)
+}
+
+pub fn compare_snapshots(
+    source_type: DatabaseType,
+    source: &SchemaSnapshot,
+    target_type: DatabaseType,
+    target: &SchemaSnapshot,
+    options: &SchemaCompareOptions,
+) -> SchemaComparison {
+    let has_schemas = |db_type: &DatabaseType| matches!(db_type, DatabaseType::PostgreSQL | DatabaseType::SQLServer);
+    let mut diff = Differ {
+        same_engine: source_type == target_type,
+        by_name: options.ignore_schema || !has_schemas(&source_type) || !has_schemas(&target_type),
+        options,
+        differences: Vec::new(),
+        warnings: Vec::new(),
+        phases: Default::default(),
+        target_names: HashMap::new(),
+        altered_tables: HashSet::new(),
+        source_type,
+        target_type,
+    };
+    if !diff.same_engine {
+        diff.warnings.push(format!(
+            "Comparing {:?} with {:?}: column types are compared by kind; defaults, check constraints and view definitions are not compared",
+            diff.source_type, diff.target_type
+        ));
+    }
+
+    let selected = |table: &&TableDefinition| {
+        (options.tables.is_empty() || options.tables.iter().any(|f| matches_filter(f, table)))
+            && !options.exclude_tables.iter().any(|f| matches_filter(f, table))
+    };
+    let source_tables: Vec<&TableDefinition> = source.tables.iter().filter(selected).collect();
+    let target_tables: Vec<&TableDefinition> = target.tables.iter().filter(selected).collect();
+
+    // Names foreign keys and views refer to on the target, including tables
+    // the script creates
+    for table in &target.tables {
+        diff.target_names.insert(diff.table_key(table.schema.as_deref(), &table.name), table.qualified_name());
+    }
+    for table in &source_tables {
+        let key = diff.table_key(table.schema.as_deref(), &table.name);
+        let name = qualify(diff.created_schema(table.schema.as_deref()).as_deref(), &table.name);
+        diff.target_names.entry(key).or_insert(name);
+    }
+
+    let target_by_key: HashMap<String, &TableDefinition> = target_tables
+        .iter()
+        .map(|t| (diff.table_key(t.schema.as_deref(), &t.name), *t))
+        .collect();
+    let source_keys: HashSet<String> = source_tables.iter().map(|t| diff.table_key(t.schema.as_deref(), &t.name)).collect();
+
+    for table in &source_tables {
+        match target_by_key.get(&diff.table_key(table.schema.as_deref(), &table.name)) {
+            Some(existing) => diff.compare_table(table, existing),
+            None => diff.create_table(table),
+        }
+    }
+    let extra: Vec<&TableDefinition> = target_tables
+        .iter()
+        .copied()
+        .filter(|t| !source_keys.contains(&diff.table_key(t.schema.as_deref(), &t.name)))
+        .collect();
+    for table in &extra {
+        diff.drop_table(table);
+    }
+
+    // Views and sequences are not tied to tables; they are left out when only some tables are compared
+    let whole_schema = options.tables.is_empty();
+    if whole_schema {
+        diff.compare_views(&source.views, &target.views);
+        diff.compare_sequences(&source.sequences, &target.sequences);
+    }
+
+    let script = options.generate_script.then(|| diff.render_script());
+    SchemaComparison {
+        tables_compared: source_tables.len() + extra.len(),
+        views_compared: if whole_schema { source.views.len().max(target.views.len()) } else { 0 },
+        sequences_compared: if whole_schema { source.sequences.len().max(target.sequences.len()) } else { 0 },
+        source_type: diff.source_type,
+        target_type: diff.target_type,
+        differences: diff.differences,
+        warnings: diff.warnings,
+        script,
+    }
+}
+
+struct Differ<'a> {
+    source_type: DatabaseType,
+    target_type: DatabaseType,
+    same_engine: bool,
+    /// Match tables, views and sequences on their name without the schema
+    by_name: bool,
+    options: &'a SchemaCompareOptions,
+    differences: Vec<SchemaDifference>,
+    warnings: Vec<String>,
+    /// Statements per phase, with whether each is a drop left commented out
+    phases: [Vec<(String, bool)>; 12],
+    /// Target table name by comparison key
+    target_names: HashMap<String, String>,
+    /// Tables whose columns the script changes, by key
+    altered_tables: HashSet<String>,
+}
+
+impl Differ<'_> {
+    fn table_key(&self, schema: Option<&str>, name: &str) -> String {
+        match schema.filter(|_| !self.by_name) {
+            Some(schema) => format!("{}.{}", schema, name).to_lowercase(),
+            None => name.to_lowercase(),
+        }
+    }
+
+    fn qualified_key(&self, qualified: &str) -> String {
+        match qualified.split_once('.') {
+            Some((schema, name)) => self.table_key(Some(schema), name),
+            None => self.table_key(None, qualified),
+        }
+    }
+
+    /// Schema for objects created on the target: the source's when schemas
+    /// are compared, otherwise the target's default.
+    fn created_schema(&self, schema: Option<&str>) -> Option<String> {
+        schema.filter(|_| !self.by_name).map(str::to_string)
+    }
+
+    fn target_table_name(&self, source_name: &str) -> String {
+        self.target_names
+            .get(&self.qualified_key(source_name))
+            .cloned()
+            .unwrap_or_else(|| match source_name.split_once('.') {
+                Some((schema, name)) => qualify(self.created_schema(Some(schema)).as_deref(), name),
+                None => source_name.to_string(),
+            })
+    }
+
+    fn report(&mut self, object_type: SchemaObjectType, change: SchemaChange, table: Option<&str>, name: &str, details: Vec<String>) {
+        self.differences.push(SchemaDifference {
+            object_type,
+            change,
+            table: table.map(str::to_string),
+            name: name.to_string(),
+            details,
+        });
+    }
+
+    fn push(&mut self, phase: Phase, sql: String) {
+        self.phases[phase as usize].push((sql, false));
+    }
+
+    /// Drop of an object that only exists in the target, kept only when asked for.
+    fn push_drop(&mut self, phase: Phase, sql: String) {
+        let commented = !self.options.include_drops;
+        self.phases[phase as usize].push((sql, commented));
+    }
+
+    /// A source column as it would be declared on the target engine.
+    fn translate_column(&self, column: &ColumnDefinition) -> ColumnDefinition {
+        if self.same_engine {
+            return column.clone();
+        }
+        let (first, second) = dialect::type_modifiers(&column.data_type);
+        let is_decimal = dialect::column_kind(&column.data_type) == dialect::ColumnKind::Decimal;
+        let data_type = if is_decimal {
+            dialect::translate_type(&self.target_type, &column.data_type, None, first, second)
+        } else {
+            dialect::translate_type(&self.target_type, &column.data_type, first, None, None)
+        };
+        ColumnDefinition {
+            name: column.name.clone(),
+            data_type,
+            nullable: column.nullable,
+            default: None,
+            identity: column.identity.as_ref().and_then(|_| identity_clause(&self.target_type)),
+            generated: None,
+            generated_stored: false,
+        }
+    }
+
+    fn translate_foreign_key(&self, fk: &ForeignKeyDefinition) -> ForeignKeyDefinition {
+        ForeignKeyDefinition {
+            referenced_table: self.target_table_name(&fk.referenced_table),
+            ..fk.clone()
+        }
+    }
+
+    /// An index as created on `table`. The engine's own statement is reused
+    /// when it names the same table; other engines get the key columns.
+    fn translate_index(&self, table: &str, source_table: &str, index: &IndexDefinition) -> Option<IndexDefinition> {
+        if self.same_engine {
+            let definition = index.definition.clone().filter(|_| table == source_table);
+            return Some(IndexDefinition { definition, ..index.clone() });
+        }
+        let columns: Option<Vec<String>> = index
+            .columns
+            .iter()
+            .map(|c| {
+                let (name, descending) = match normalize(c).strip_suffix(" desc") {
+                    Some(name) => (name.to_string(), true),
+                    None => (normalize(c), false),
+                };
+                let name = index_column_name(c, &name)?;
+                let quoted = dialect::quote_ident(&self.target_type, &name);
+                Some(if descending { format!("{} DESC", quoted) } else { quoted })
+            })
+            .collect();
+        columns.map(|columns| IndexDefinition {
+            name: index.name.clone(),
+            columns,
+            unique: index.unique,
+            definition: None,
+        })
+    }
+
+    fn create_table(&mut self, table: &TableDefinition) {
+        let name = self.target_table_name(&table.qualified_name());
+        self.report(SchemaObjectType::Table, SchemaChange::Missing, None, &name, Vec::new());
+
+        let generated: Vec<&str> = table.columns.iter().filter(|c| c.generated.is_some()).map(|c| c.name.as_str()).collect();
+        if !self.same_engine && !generated.is_empty() {
+            self.warnings.push(format!(
+                "Generated columns of {} are created as plain columns: {}",
+                name,
+                generated.join(", ")
+            ));
+        }
+        if !self.same_engine && !table.checks.is_empty() {
+            self.warnings.push(format!("Check constraints of {} are not translated", name));
+        }
+
+        let (schema, bare) = match name.split_once('.') {
+            Some((schema, bare)) => (Some(schema.to_string()), bare.to_string()),
+            None => (None, name.clone()),
+        };
+        let definition = TableDefinition {
+            schema,
+            name: bare,
+            columns: table.columns.iter().map(|c| self.translate_column(c)).collect(),
+            primary_key: table.primary_key.clone(),
+            unique_constraints: table.unique_constraints.clone(),
+            foreign_keys: table.foreign_keys.iter().map(|fk| self.translate_foreign_key(fk)).collect(),
+            checks: if self.same_engine { table.checks.clone() } else { Vec::new() },
+            indexes: Vec::new(),
+            definition: None,
+        };
+
+        // MySQL and SQLite report the exact statement, indexes and foreign keys included
+        let engine_statement = table.definition.as_ref().filter(|_| self.same_engine && name == table.qualified_name());
+        match engine_statement {
+            Some(statement) => self.push(Phase::CreateTables, statement.trim().trim_end_matches(';').to_string()),
+            // SQLite cannot add foreign keys later; it accepts references to tables created after
+            None if matches!(self.target_type, DatabaseType::SQLite) => {
+                let foreign_keys: Vec<&ForeignKeyDefinition> = definition.foreign_keys.iter().collect();
+                self.push(Phase::CreateTables, ddl::create_table(&self.target_type, &definition, &foreign_keys));
+            }
+            None => {
+                self.push(Phase::CreateTables, ddl::create_table(&self.target_type, &definition, &[]));
+                for fk in &definition.foreign_keys {
+                    self.push(Phase::AddForeignKeys, ddl::add_foreign_key(&self.target_type, &name, fk));
+                }
+            }
+        }
+
+        let indexes_included = engine_statement.is_some() && matches!(self.target_type, DatabaseType::MySQL);
+        if !indexes_included {
+            for index in &table.indexes {
+                match self.translate_index(&name, &table.qualified_name(), index) {
+                    Some(index) => self.push(Phase::Indexes, ddl::create_index(&self.target_type, &name, &index)),
+                    None => self.warnings.push(format!("Index {} on {} uses expressions and is not translated", index.name, name)),
+                }
+            }
+        }
+    }
+
+    fn drop_table(&mut self, table: &TableDefinition) {
+        let name = table.qualified_name();
+        self.report(SchemaObjectType::Table, SchemaChange::Extra, None, &name, Vec::new());
+        if matches!(self.target_type, DatabaseType::SQLServer) {
+            for fk in &table.foreign_keys {
+                if let Some(fk_name) = &fk.name {
+                    self.push_drop(Phase::DropForeignKeys, ddl::drop_foreign_key(&self.target_type, &name, fk_name));
+                }
+            }
+        }
+        self.push_drop(Phase::DropTables, ddl::drop_table(&self.target_type, &name));
+    }
+
+    fn compare_table(&mut self, source: &TableDefinition, target: &TableDefinition) {
+        let name = target.qualified_name();
+        self.compare_columns(source, target, &name);
+        self.compare_primary_key(source, target, &name);
+        self.compare_unique_constraints(source, target, &name);
+        self.compare_checks(source, target, &name);
+        self.compare_foreign_keys(source, target, &name);
+        self.compare_indexes(source, target, &name);
+    }
+
+    fn compare_columns(&mut self, source: &TableDefinition, target: &TableDefinition, table: &str) {
+        let db_type = self.target_type.clone();
+        let key = self.qualified_key(table);
+
+        for column in &source.columns {
+            let translated = self.translate_column(column);
+            let Some(existing) = target.columns.iter().find(|c| c.name.eq_ignore_ascii_case(&column.name)) else {
+                self.report(SchemaObjectType::Column, SchemaChange::Missing, Some(table), &column.name, Vec::new());
+                let without_value = translated.default.is_none() && translated.identity.is_none() && translated.generated.is_none();
+                if !translated.nullable && without_value {
+                    self.warnings.push(format!(
+                        "Adding NOT NULL column {}.{} without a default fails if the table has rows",
+                        table, column.name
+                    ));
+                }
+                self.push(Phase::Columns, ddl::add_column(&db_type, table, &translated));
+                continue;
+            };
+
+            let details = self.column_details(column, existing);
+            if details.is_empty() {
+                continue;
+            }
+            self.report(SchemaObjectType::Column, SchemaChange::Changed, Some(table), &column.name, details);
+
+            if existing.identity.is_some() != column.identity.is_some()
+                || (existing.generated.is_some() || column.generated.is_some()) && self.generated_differs(column, existing)
+            {
+                self.warnings.push(format!(
+                    "Identity or generated column {}.{} differs and must be recreated by hand",
+                    table, column.name
+                ));
+            }
+            let wanted = ColumnDefinition {
+                data_type: if self.same_type(&column.data_type, &existing.data_type) {
+                    existing.data_type.clone()
+                } else {
+                    translated.data_type.clone()
+                },
+                nullable: column.nullable,
+                default: if self.same_engine { column.default.clone() } else { existing.default.clone() },
+                ..existing.clone()
+            };
+            match ddl::alter_column(&db_type, table, existing, &wanted) {
+                Some(statements) => {
+                    for sql in statements {
+                        self.push(Phase::Columns, sql);
+                    }
+                    self.altered_tables.insert(key.clone());
+                }
+                None => self.warnings.push(format!(
+                    "{:?} cannot alter column {}.{}; the table must be rebuilt",
+                    db_type, table, column.name
+                )),
+            }
+        }
+
+        for column in &target.columns {
+            if !source.columns.iter().any(|c| c.name.eq_ignore_ascii_case(&column.name)) {
+                self.report(SchemaObjectType::Column, SchemaChange::Extra, Some(table), &column.name, Vec::new());
+                for sql in ddl::drop_column(&db_type, table, &column.name) {
+                    self.push_drop(Phase::Columns, sql);
+                }
+                if self.options.include_drops {
+                    self.altered_tables.insert(key.clone());
+                }
+            }
+        }
+    }
+
+    /// What differs between a source column and the target column of the same name.
+    fn column_details(&self, source: &ColumnDefinition, target: &ColumnDefinition) -> Vec<String> {
+        let mut details = Vec::new();
+        if !self.same_type(&source.data_type, &target.data_type) {
+            details.push(format!("type: {} -> {}", target.data_type, source.data_type));
+        }
+        if source.nullable != target.nullable {
+            let label = |nullable: bool| if nullable { "NULL" } else { "NOT NULL" };
+            details.push(format!("nullable: {} -> {}", label(target.nullable), label(source.nullable)));
+        }
+        if self.same_engine && normalize_default(&source.default) != normalize_default(&target.default) {
+            let label = |default: &Option<String>| default.clone().unwrap_or_else(|| "none".to_string());
+            details.push(format!("default: {} -> {}", label(&target.default), label(&source.default)));
+        }
+        if source.identity.is_some() != target.identity.is_some() {
+            let label = |identity: &Option<String>| if identity.is_some() { "identity" } else { "none" };
+            details.push(format!("identity: {} -> {}", label(&target.identity), label(&source.identity)));
+        }
+        if self.generated_differs(source, target) {
+            let label = |column: &ColumnDefinition| column.generated.clone().unwrap_or_else(|| "none".to_string());
+            details.push(format!("generated: {} -> {}", label(target), label(source)));
+        }
+        details
+    }
+
+    fn generated_differs(&self, source: &ColumnDefinition, target: &ColumnDefinition) -> bool {
+        match (&source.generated, &target.generated) {
+            (Some(a), Some(b)) => self.same_engine && normalize_default(&Some(a.clone())) != normalize_default(&Some(b.clone())),
+            (None, None) => false,
+            _ => true,
+        }
+    }
+
+    /// Types of one engine are compared as declared; across engines only
+    /// their kind and declared length or precision count.
+    fn same_type(&self, source: &str, target: &str) -> bool {
+        if self.same_engine {
+            return normalize(source) == normalize(target);
+        }
+        let kind = dialect::column_kind(source);
+        kind == dialect::column_kind(target)
+            && (!matches!(kind, dialect::ColumnKind::Text | dialect::ColumnKind::Decimal)
+                || dialect::type_modifiers(source) == dialect::type_modifiers(target))
+    }
+
+    fn compare_primary_key(&mut self, source: &TableDefinition, target: &TableDefinition, table: &str) {
+        let columns = |key: &Option<KeyConstraint>| key.as_ref().map(|k| column_set(&k.columns));
+        let (wanted, existing) = (columns(&source.primary_key), columns(&target.primary_key));
+        if wanted == existing {
+            return;
+        }
+        let name = target
+            .primary_key
+            .as_ref()
+            .or(source.primary_key.as_ref())
+            .and_then(|k| k.name.clone())
+            .unwrap_or_else(|| "PRIMARY KEY".to_string());
+        let (change, details) = match (&wanted, &existing) {
+            (Some(_), None) => (SchemaChange::Missing, Vec::new()),
+            (None, Some(_)) => (SchemaChange::Extra, Vec::new()),
+            _ => (
+                SchemaChange::Changed,
+                vec![format!(
+                    "columns: {} -> {}",
+                    target.primary_key.as_ref().map(|k| k.columns.join(", ")).unwrap_or_default(),
+                    source.primary_key.as_ref().map(|k| k.columns.join(", ")).unwrap_or_default(),
+                )],
+            ),
+        };
+        self.report(SchemaObjectType::PrimaryKey, change, Some(table), &name, details);
+
+        if let Some(key) = &target.primary_key {
+            self.drop_constraint(table, SchemaObjectType::PrimaryKey, key.name.as_deref(), change == SchemaChange::Extra);
+        }
+        if let Some(key) = &source.primary_key {
+            self.add_constraint(table, SchemaObjectType::PrimaryKey, |db_type| ddl::add_primary_key(db_type, table, key));
+        }
+    }
+
+    fn compare_unique_constraints(&mut self, source: &TableDefinition, target: &TableDefinition, table: &str) {
+        for key in &source.unique_constraints {
+            if !target.unique_constraints.iter().any(|k| column_set(&k.columns) == column_set(&key.columns)) {
+                let name = key.name.clone().unwrap_or_else(|| key.columns.join(", "));
+                self.report(SchemaObjectType::UniqueConstraint, SchemaChange::Missing, Some(table), &name, Vec::new());
+                self.add_constraint(table, SchemaObjectType::UniqueConstraint, |db_type| ddl::add_unique(db_type, table, key));
+            }
+        }
+        for key in &target.unique_constraints {
+            if !source.unique_constraints.iter().any(|k| column_set(&k.columns) == column_set(&key.columns)) {
+                let name = key.name.clone().unwrap_or_else(|| key.columns.join(", "));
+                self.report(SchemaObjectType::UniqueConstraint, SchemaChange::Extra, Some(table), &name, Vec::new());
+                self.drop_constraint(table, SchemaObjectType::UniqueConstraint, key.name.as_deref(), true);
+            }
+        }
+    }
+
+    fn compare_checks(&mut self, source: &TableDefinition, target: &TableDefinition, table: &str) {
+        if !self.same_engine {
+            return;
+        }
+        let same = |a: &CheckConstraint, b: &CheckConstraint| normalize_default(&Some(a.expression.clone())) == normalize_default(&Some(b.expression.clone()));
+        for check in &source.checks {
+            if !target.checks.iter().any(|c| same(c, check)) {
+                let name = check.name.clone().unwrap_or_else(|| check.expression.clone());
+                self.report(SchemaObjectType::CheckConstraint, SchemaChange::Missing, Some(table), &name, Vec::new());
+                self.add_constraint(table, SchemaObjectType::CheckConstraint, |db_type| ddl::add_check(db_type, table, check));
+            }
+        }
+        for check in &target.checks {
+            if !source.checks.iter().any(|c| same(c, check)) {
+                let name = check.name.clone().unwrap_or_else(|| check.expression.clone());
+                self.report(SchemaObjectType::CheckConstraint, SchemaChange::Extra, Some(table), &name, Vec::new());
+                self.drop_constraint(table, SchemaObjectType::CheckConstraint, check.name.as_deref(), true);
+            }
+        }
+    }
+
+    fn compare_foreign_keys(&mut self, source: &TableDefinition, target: &TableDefinition, table: &str) {
+        let signature = |differ: &Self, fk: &ForeignKeyDefinition| {
+            (column_set(&fk.columns), differ.qualified_key(&fk.referenced_table), column_set(&fk.referenced_columns))
+        };
+        let action = |action: &Option<String>| action.clone().unwrap_or_else(|| "NO ACTION".to_string()).to_uppercase();
+
+        for fk in &source.foreign_keys {
+            let wanted = self.translate_foreign_key(fk);
+            let name = fk.name.clone().unwrap_or_else(|| fk.columns.join(", "));
+            let existing = target.foreign_keys.iter().find(|t| signature(self, t) == signature(self, fk));
+            match existing {
+                None => self.report(SchemaObjectType::ForeignKey, SchemaChange::Missing, Some(table), &name, Vec::new()),
+                Some(existing) => {
+                    let mut details = Vec::new();
+                    if action(&existing.on_update) != action(&fk.on_update) {
+                        details.push(format!("on update: {} -> {}", action(&existing.on_update), action(&fk.on_update)));
+                    }
+                    if action(&existing.on_delete) != action(&fk.on_delete) {
+                        details.push(format!("on delete: {} -> {}", action(&existing.on_delete), action(&fk.on_delete)));
+                    }
+                    if details.is_empty() {
+                        continue;
+                    }
+                    self.report(SchemaObjectType::ForeignKey, SchemaChange::Changed, Some(table), &name, details);
+                    self.drop_constraint(table, SchemaObjectType::ForeignKey, existing.name.as_deref(), false);
+                }
+            }
+            self.add_constraint(table, SchemaObjectType::ForeignKey, |db_type| ddl::add_foreign_key(db_type, table, &wanted));
+        }
+
+        for fk in &target.foreign_keys {
+            if !source.foreign_keys.iter().any(|s| signature(self, s) == signature(self, fk)) {
+                let name = fk.name.clone().unwrap_or_else(|| fk.columns.join(", "));
+                self.report(SchemaObjectType::ForeignKey, SchemaChange::Extra, Some(table), &name, Vec::new());
+                self.drop_constraint(table, SchemaObjectType::ForeignKey, fk.name.as_deref(), true);
+            }
+        }
+    }
+
+    fn compare_indexes(&mut self, source: &TableDefinition, target: &TableDefinition, table: &str) {
+        let columns = |index: &IndexDefinition| index.columns.iter().map(|c| normalize(c)).collect::<Vec<_>>();
+
+        for index in &source.indexes {
+            let existing = target.indexes.iter().find(|i| i.name.eq_ignore_ascii_case(&index.name));
+            if let Some(existing) = existing {
+                let mut details = Vec::new();
+                if existing.unique != index.unique {
+                    details.push(format!("unique: {} -> {}", existing.unique, index.unique));
+                }
+                if columns(existing) != columns(index) {
+                    details.push(format!("columns: {} -> {}", existing.columns.join(", "), index.columns.join(", ")));
+                }
+                if details.is_empty() {
+                    continue;
+                }
+                self.report(SchemaObjectType::Index, SchemaChange::Changed, Some(table), &index.name, details);
+                self.push(Phase::DropConstraints, ddl::drop_index(&self.target_type, table, &existing.name));
+            } else {
+                self.report(SchemaObjectType::Index, SchemaChange::Missing, Some(table), &index.name, Vec::new());
+            }
+            match self.translate_index(table, &source.qualified_name(), index) {
+                Some(index) => self.push(Phase::Indexes, ddl::create_index(&self.target_type, table, &index)),
+                None => self.warnings.push(format!("Index {} on {} uses expressions and is not translated", index.name, table)),
+            }
+        }
+
+        for index in &target.indexes {
+            if !source.indexes.iter().any(|i| i.name.eq_ignore_ascii_case(&index.name)) {
+                self.report(SchemaObjectType::Index, SchemaChange::Extra, Some(table), &index.name, Vec::new());
+                self.push_drop(Phase::DropConstraints, ddl::drop_index(&self.target_type, table, &index.name));
+            }
+        }
+    }
+
+    /// Add a key, check or foreign key to an existing table; SQLite can only
+    /// do so by rebuilding the table.
+    fn add_constraint(&mut self, table: &str, kind: SchemaObjectType, sql: impl FnOnce(&DatabaseType) -> String) {
+        if matches!(self.target_type, DatabaseType::SQLite) {
+            self.warnings.push(format!("SQLite cannot add a {} to {}; the table must be rebuilt", object_label(kind), table));
+            return;
+        }
+        let phase = if kind == SchemaObjectType::ForeignKey { Phase::AddForeignKeys } else { Phase::AddConstraints };
+        let sql = sql(&self.target_type);
+        self.push(phase, sql);
+    }
+
+    fn drop_constraint(&mut self, table: &str, kind: SchemaObjectType, name: Option<&str>, extra: bool) {
+        if matches!(self.target_type, DatabaseType::SQLite) {
+            self.warnings.push(format!("SQLite cannot drop a {} from {}; the table must be rebuilt", object_label(kind), table));
+            return;
+        }
+        let Some(sql) = ddl::drop_constraint(&self.target_type, table, kind, name) else {
+            self.warnings.push(format!("A {} on {} has no name and must be dropped by hand", object_label(kind), table));
+            return;
+        };
+        let phase = if kind == SchemaObjectType::ForeignKey { Phase::DropForeignKeys } else { Phase::DropConstraints };
+        if extra {
+            self.push_drop(phase, sql);
+        } else {
+            self.push(phase, sql);
+        }
+    }
+
+    fn compare_views(&mut self, source: &[ViewDefinition], target: &[ViewDefinition]) {
+        let key = |differ: &Self, view: &ViewDefinition| differ.table_key(view.schema.as_deref(), &view.name);
+        let mut recreate: Vec<&ViewDefinition> = Vec::new();
+        let mut dropped: Vec<&ViewDefinition> = Vec::new();
+
+        for view in source {
+            let name = qualify(self.created_schema(view.schema.as_deref()).as_deref(), &view.name);
+            match target.iter().find(|t| key(self, t) == key(self, view)) {
+                None => {
+                    self.report(SchemaObjectType::View, SchemaChange::Missing, None, &name, Vec::new());
+                    recreate.push(view);
+                }
+                Some(existing) => {
+                    if self.same_engine && normalize(view_body(&view.definition)) != normalize(view_body(&existing.definition)) {
+                        self.report(SchemaObjectType::View, SchemaChange::Changed, None, &existing_name(existing), vec!["definition".to_string()]);
+                        dropped.push(existing);
+                        recreate.push(view);
+                    } else if self.depends_on_altered_table(existing) {
+                        // PostgreSQL refuses to change a column that a view reads
+                        dropped.push(existing);
+                        recreate.push(view);
+                    }
+                }
+            }
+        }
+
+        for view in target {
+            if !source.iter().any(|s| key(self, s) == key(self, view)) {
+                self.report(SchemaObjectType::View, SchemaChange::Extra, None, &existing_name(view), Vec::new());
+                self.push_drop(Phase::DropViews, ddl::drop_view(&self.target_type, &existing_name(view)));
+            }
+        }
+
+        let dropped = dependency_order(dropped, |view, other| view.definition.to_lowercase().contains(&other.name.to_lowercase()));
+        for view in dropped.iter().rev() {
+            self.push(Phase::DropViews, ddl::drop_view(&self.target_type, &existing_name(view)));
+        }
+
+        let recreate = dependency_order(recreate, |view, other| view.definition.to_lowercase().contains(&other.name.to_lowercase()));
+        for view in recreate {
+            let name = target
+                .iter()
+                .find(|t| key(self, t) == key(self, view))
+                .map(existing_name)
+                .unwrap_or_else(|| qualify(self.created_schema(view.schema.as_deref()).as_deref(), &view.name));
+            let statement = format!(
+                "CREATE VIEW {} AS\n{}",
+                dialect::quote_table(&self.target_type, &name),
+                view_body(&view.definition).trim().trim_end_matches(';'),
+            );
+            if self.same_engine {
+                self.push(Phase::CreateViews, statement);
+            } else {
+                self.warnings.push(format!("View {} is written in {:?} SQL and is left commented out", name, self.source_type));
+                self.phases[Phase::CreateViews as usize].push((statement, true));
+            }
+        }
+    }
+
+    fn depends_on_altered_table(&self, view: &ViewDefinition) -> bool {
+        let definition = view.definition.to_lowercase();
+        matches!(self.target_type, DatabaseType::PostgreSQL)
+            && self.altered_tables.iter().any(|key| {
+                let name = key.rsplit('.').next().unwrap_or(key);
+                definition.contains(name)
+            })
+    }
+
+    fn compare_sequences(&mut self, source: &[SequenceDefinition], target: &[SequenceDefinition]) {
+        // Identity sequences come and go with their column
+        let source: Vec<&SequenceDefinition> = source.iter().filter(|s| !s.identity).collect();
+        let target: Vec<&SequenceDefinition> = target.iter().filter(|s| !s.identity).collect();
+        if !matches!(self.target_type, DatabaseType::PostgreSQL | DatabaseType::SQLServer) {
+            if !source.is_empty() {
+                self.warnings.push(format!("{:?} has no sequences; {} sequences are not compared", self.target_type, source.len()));
+            }
+            return;
+        }
+        let key = |differ: &Self, sequence: &SequenceDefinition| differ.table_key(sequence.schema.as_deref(), &sequence.name);
+
+        for sequence in &source {
+            let Some(existing) = target.iter().find(|t| key(self, t) == key(self, sequence)) else {
+                let created = SequenceDefinition {
+                    schema: self.created_schema(sequence.schema.as_deref()),
+                    ..(*sequence).clone()
+                };
+                let name = qualify(created.schema.as_deref(), &created.name);
+                self.report(SchemaObjectType::Sequence, SchemaChange::Missing, None, &name, Vec::new());
+                self.push(Phase::Sequences, ddl::create_sequence(&self.target_type, &created));
+                continue;
+            };
+
+            let name = qualify(existing.schema.as_deref(), &existing.name);
+            let mut details = Vec::new();
+            if !existing.data_type.eq_ignore_ascii_case(&sequence.data_type) {
+                details.push(format!("type: {} -> {}", existing.data_type, sequence.data_type));
+                self.warnings.push(format!("The type of sequence {} is not changed by the script", name));
+            }
+            for (label, from, to) in [
+                ("increment", existing.increment, sequence.increment),
+                ("min value", existing.min_value, sequence.min_value),
+                ("max value", existing.max_value, sequence.max_value),
+            ] {
+                if from != to {
+                    details.push(format!("{}: {} -> {}", label, from, to));
+                }
+            }
+            if existing.cycle != sequence.cycle {
+                details.push(format!("cycle: {} -> {}", existing.cycle, sequence.cycle));
+            }
+            if details.is_empty() {
+                continue;
+            }
+            self.report(SchemaObjectType::Sequence, SchemaChange::Changed, None, &name, details);
+            let altered = SequenceDefinition {
+                schema: existing.schema.clone(),
+                name: existing.name.clone(),
+                ..(*sequence).clone()
+            };
+            self.push(Phase::Sequences, ddl::alter_sequence(&self.target_type, &altered));
+        }
+
+        for sequence in &target {
+            if !source.iter().any(|s| key(self, s) == key(self, sequence)) {
+                let name = qualify(sequence.schema.as_deref(), &sequence.name);
+                self.report(SchemaObjectType::Sequence, SchemaChange::Extra, None, &name, Vec::new());
+                self.push_drop(Phase::DropSequences, ddl::drop_sequence(&self.target_type, &name));
+            }
+        }
+    }
+
+    /// Statements end with `;`, or with a `GO` line on SQL Server, as in dumps.
+    /// Drops that were not asked for are written as comments.
+    fn render_script(&self) -> String {
+        let db_type = &self.target_type;
+        let terminate = |sql: &str| match db_type {
+            DatabaseType::SQLServer => format!("{}\nGO\n", sql),
+            _ => format!("{};\n", sql),
+        };
+        let mut script = format!(
+            "-- Datablaze migration\n-- Source: {:?}\n-- Target: {:?}\n-- Created: {}\n",
+            self.source_type,
+            self.target_type,
+            chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        );
+        if self.phases.iter().all(Vec::is_empty) {
+            script.push_str("\n-- No changes\n");
+            return script;
+        }
+        if let DatabaseType::MySQL = db_type {
+            script.push_str(&terminate("SET FOREIGN_KEY_CHECKS = 0"));
+        }
+        for (title, statements) in PHASE_TITLES.iter().zip(&self.phases) {
+            if statements.is_empty() {
+                continue;
+            }
+            script.push_str(&format!("\n-- {}\n", title));
+            for (sql, commented) in statements {
+                let text = terminate(sql);
+                if *commented {
+                    for line in text.lines() {
+                        script.push_str(&format!("-- {}\n", line));
+                    }
+                } else {
+                    script.push_str(&text);
+                }
+            }
+        }
+        if let DatabaseType::MySQL = db_type {
+            script.push_str(&format!("\n{}", terminate("SET FOREIGN_KEY_CHECKS = 1")));
+        }
+        script
+    }
+}
+
+fn object_label(kind: SchemaObjectType) -> &'static str {
+    match kind {
+        SchemaObjectType::Table => "table",
+        SchemaObjectType::Column => "column",
+        SchemaObjectType::PrimaryKey => "primary key",
+        SchemaObjectType::UniqueConstraint => "unique constraint",
+        SchemaObjectType::ForeignKey => "foreign key",
+        SchemaObjectType::CheckConstraint => "check constraint",
+        SchemaObjectType::Index => "index",
+        SchemaObjectType::View => "view",
+        SchemaObjectType::Sequence => "sequence",
+    }
+}
+
+fn existing_name(view: &ViewDefinition) -> String {
+    qualify(view.schema.as_deref(), &view.name)
+}
+
+/// Identity clause for a column created on `db_type`; SQLite has none beyond
+/// `INTEGER PRIMARY KEY`.
+fn identity_clause(db_type: &DatabaseType) -> Option<String> {
+    match db_type {
+        DatabaseType::PostgreSQL => Some("GENERATED BY DEFAULT AS IDENTITY".to_string()),
+        DatabaseType::MySQL => Some("AUTO_INCREMENT".to_string()),
+        DatabaseType::SQLServer => Some("IDENTITY(1,1)".to_string()),
+        DatabaseType::SQLite => None,
+    }
+}
+
+/// Query of a `CREATE VIEW` statement, after its first ` AS `.
+fn view_body(definition: &str) -> &str {
+    let upper = definition.to_uppercase();
+    ["\nAS\n", " AS\n", "\nAS ", " AS "]
+        .iter()
+        .filter_map(|separator| upper.find(separator).map(|i| i + separator.len()))
+        .min()
+        .map_or(definition, |start| &definition[start..])
+}
+
+/// Plain column name of an index key part, or `None` for an expression.
+fn index_column_name(original: &str, normalized: &str) -> Option<String> {
+    let plain = normalized.chars().all(|c| c.is_alphanumeric() || c == '_');
+    if !plain {
+        return None;
+    }
+    // Keep the original spelling, which may not be lowercase
+    let stripped: String = original.chars().filter(|c| !matches!(c, '"' | '`' | '[' | ']')).collect();
+    let name = stripped.trim();
+    let name = name.strip_suffix(" DESC").or_else(|| name.strip_suffix(" desc")).unwrap_or(name);
+    Some(name.trim().to_string())
+}
+
+fn column_set(columns: &[String]) -> Vec<String> {
+    columns.iter().map(|c| c.to_lowercase()).collect()
+}
+
+/// Lowercase, without identifier quotes and with whitespace collapsed, so
+/// definitions that engines print slightly differently compare equal.
+fn normalize(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut space = false;
+    for c in text.chars() {
+        match c {
+            '"' | '`' | '[' | ']' => {}
+            c if c.is_whitespace() => space = true,
+            c => {
+                if space && !out.is_empty() && !matches!(c, ')' | ',') && !out.ends_with(['(', ',']) {
+                    out.push(' ');
+                }
+                space = false;
+                out.extend(c.to_lowercase());
+            }
+        }
+    }
+    out
+}
+
+/// SQL Server wraps defaults and checks in parentheses, sometimes twice.
+fn normalize_default(default: &Option<String>) -> Option<String> {
+    let mut text = default.as_deref()?.trim();
+    while text.starts_with('(') && ddl::parenthesize(text) == text {
+        text = text[1..text.len() - 1].trim();
+    }
+    Some(normalize(text))
+}
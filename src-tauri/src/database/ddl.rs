@@ -0,0 +1,342 @@
+use crate::models::*;
+use super::dialect::{quote_ident, quote_table};
+
+fn column_list(db_type: &DatabaseType, columns: &[String]) -> String {
+    columns.iter().map(|c| quote_ident(db_type, c)).collect::<Vec<_>>().join(", ")
+}
+
+/// Wrap an expression in parentheses unless one pair already encloses all of it.
+pub fn parenthesize(expr: &str) -> String {
+    let trimmed = expr.trim();
+    if trimmed.starts_with('(') {
+        let mut depth = 0;
+        for (i, c) in trimmed.char_indices() {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                _ => {}
+            }
+            if depth == 0 {
+                if i == trimmed.len() - 1 {
+                    return trimmed.to_string();
+                }
+                break;
+            }
+        }
+    }
+    format!("({})", trimmed)
+}
+
+/// Column definition as used in `CREATE TABLE` and `ADD COLUMN`.
+pub fn column_sql(db_type: &DatabaseType, column: &ColumnDefinition) -> String {
+    let name = quote_ident(db_type, &column.name);
+    if let (DatabaseType::SQLServer, Some(expr)) = (db_type, &column.generated) {
+        let persisted = if column.generated_stored { " PERSISTED" } else { "" };
+        return format!("{} AS {}{}", name, expr, persisted);
+    }
+
+    let mut sql = format!("{} {}", name, column.data_type);
+    // SQLite's AUTOINCREMENT is part of the primary key clause in the table definition
+    if let Some(identity) = column.identity.as_ref().filter(|_| !matches!(db_type, DatabaseType::SQLite)) {
+        sql.push(' ');
+        sql.push_str(identity);
+    }
+    if let Some(expr) = &column.generated {
+        let storage = if column.generated_stored { "STORED" } else { "VIRTUAL" };
+        sql.push_str(&format!(" GENERATED ALWAYS AS {} {}", parenthesize(expr), storage));
+    } else if let Some(default) = &column.default {
+        sql.push_str(&format!(" DEFAULT {}", default));
+    }
+    if !column.nullable {
+        sql.push_str(" NOT NULL");
+    }
+    sql
+}
+
+fn constraint_prefix(db_type: &DatabaseType, name: &Option<String>) -> String {
+    match name {
+        Some(name) => format!("CONSTRAINT {} ", quote_ident(db_type, name)),
+        None => String::new(),
+    }
+}
+
+pub fn foreign_key_clause(db_type: &DatabaseType, fk: &ForeignKeyDefinition) -> String {
+    let mut sql = format!(
+        "{}FOREIGN KEY ({}) REFERENCES {} ({})",
+        constraint_prefix(db_type, &fk.name),
+        column_list(db_type, &fk.columns),
+        quote_table(db_type, &fk.referenced_table),
+        column_list(db_type, &fk.referenced_columns),
+    );
+    if let Some(action) = &fk.on_delete {
+        sql.push_str(&format!(" ON DELETE {}", action));
+    }
+    if let Some(action) = &fk.on_update {
+        sql.push_str(&format!(" ON UPDATE {}", action));
+    }
+    sql
+}
+
+/// `CREATE TABLE` with columns, keys and checks, plus the given foreign keys.
+/// Other foreign keys are left for `add_foreign_key`. Like every statement
+/// built here, it has no terminator; callers add `;` or `GO`.
+pub fn create_table(db_type: &DatabaseType, table: &TableDefinition, foreign_keys: &[&ForeignKeyDefinition]) -> String {
+    let mut parts: Vec<String> = table.columns.iter().map(|c| column_sql(db_type, c)).collect();
+
+    if let Some(pk) = &table.primary_key {
+        parts.push(format!("{}PRIMARY KEY ({})", constraint_prefix(db_type, &pk.name), column_list(db_type, &pk.columns)));
+    }
+    for unique in &table.unique_constraints {
+        parts.push(format!("{}UNIQUE ({})", constraint_prefix(db_type, &unique.name), column_list(db_type, &unique.columns)));
+    }
+    for check in &table.checks {
+        parts.push(format!("{}CHECK {}", constraint_prefix(db_type, &check.name), parenthesize(&check.expression)));
+    }
+    for fk in foreign_keys {
+        parts.push(foreign_key_clause(db_type, fk));
+    }
+
+    format!(
+        "CREATE TABLE {} (\n    {}\n)",
+        quote_table(db_type, &table.qualified_name()),
+        parts.join(",\n    "),
+    )
+}
+
+pub fn add_foreign_key(db_type: &DatabaseType, table: &str, fk: &ForeignKeyDefinition) -> String {
+    format!("ALTER TABLE {} ADD {}", quote_table(db_type, table), foreign_key_clause(db_type, fk))
+}
+
+pub fn create_index(db_type: &DatabaseType, table: &str, index: &IndexDefinition) -> String {
+    match &index.definition {
+        Some(definition) => definition.trim().trim_end_matches(';').to_string(),
+        None => format!(
+            "CREATE {}INDEX {} ON {} ({})",
+            if index.unique { "UNIQUE " } else { "" },
+            quote_ident(db_type, &index.name),
+            quote_table(db_type, table),
+            index.columns.join(", "),
+        ),
+    }
+}
+
+pub fn create_sequence(db_type: &DatabaseType, sequence: &SequenceDefinition) -> String {
+    format!(
+        "CREATE SEQUENCE {} AS {} START WITH {} INCREMENT BY {} MINVALUE {} MAXVALUE {} {}",
+        quote_table(db_type, &qualify(sequence.schema.as_deref(), &sequence.name)),
+        sequence.data_type,
+        sequence.start,
+        sequence.increment,
+        sequence.min_value,
+        sequence.max_value,
+        if sequence.cycle { "CYCLE" } else { "NO CYCLE" },
+    )
+}
+
+/// Statement that makes the sequence continue after its last value, if it has been used.
+pub fn restore_sequence_value(db_type: &DatabaseType, sequence: &SequenceDefinition) -> Option<String> {
+    let last = sequence.last_value?;
+    let name = quote_table(db_type, &qualify(sequence.schema.as_deref(), &sequence.name));
+    match db_type {
+        DatabaseType::PostgreSQL => Some(format!(
+            "SELECT setval({}, {}, true)",
+            super::dialect::quote_string(db_type, &name),
+            last,
+        )),
+        DatabaseType::SQLServer => Some(format!("ALTER SEQUENCE {} RESTART WITH {}", name, last.saturating_add(sequence.increment))),
+        DatabaseType::MySQL | DatabaseType::SQLite => None,
+    }
+}
+
+/// PostgreSQL drops with `CASCADE` so tables in a foreign key cycle can go;
+/// MySQL and SQLite dumps switch foreign key checks off instead, and SQL Server
+/// needs `drop_foreign_key` first.
+pub fn drop_table(db_type: &DatabaseType, table: &str) -> String {
+    let cascade = if matches!(db_type, DatabaseType::PostgreSQL) { " CASCADE" } else { "" };
+    format!("DROP TABLE IF EXISTS {}{}", quote_table(db_type, table), cascade)
+}
+
+/// Drop a named foreign key if both the table and the constraint exist.
+pub fn drop_foreign_key(db_type: &DatabaseType, table: &str, name: &str) -> String {
+    let quoted_table = quote_table(db_type, table);
+    let quoted_name = quote_ident(db_type, name);
+    match db_type {
+        DatabaseType::SQLServer => format!(
+            "IF OBJECT_ID({}, 'F') IS NOT NULL ALTER TABLE {} DROP CONSTRAINT {}",
+            super::dialect::quote_string(db_type, &qualify(table.split_once('.').map(|(s, _)| s), name)),
+            quoted_table,
+            quoted_name,
+        ),
+        DatabaseType::MySQL => format!("ALTER TABLE {} DROP FOREIGN KEY {}", quoted_table, quoted_name),
+        DatabaseType::PostgreSQL | DatabaseType::SQLite => {
+            format!("ALTER TABLE IF EXISTS {} DROP CONSTRAINT IF EXISTS {}", quoted_table, quoted_name)
+        }
+    }
+}
+
+pub fn drop_view(db_type: &DatabaseType, view: &str) -> String {
+    format!("DROP VIEW IF EXISTS {}", quote_table(db_type, view))
+}
+
+pub fn drop_sequence(db_type: &DatabaseType, sequence: &str) -> String {
+    format!("DROP SEQUENCE IF EXISTS {}", quote_table(db_type, sequence))
+}
+
+pub fn add_column(db_type: &DatabaseType, table: &str, column: &ColumnDefinition) -> String {
+    let keyword = if matches!(db_type, DatabaseType::SQLServer) { "ADD" } else { "ADD COLUMN" };
+    format!("ALTER TABLE {} {} {}", quote_table(db_type, table), keyword, column_sql(db_type, column))
+}
+
+/// SQL Server refuses to drop a column while a default constraint is bound to
+/// it, so that is dropped first.
+pub fn drop_column(db_type: &DatabaseType, table: &str, column: &str) -> Vec<String> {
+    let mut statements = Vec::new();
+    if matches!(db_type, DatabaseType::SQLServer) {
+        statements.push(drop_default_constraint(table, column));
+    }
+    statements.push(format!(
+        "ALTER TABLE {} DROP COLUMN {}",
+        quote_table(db_type, table),
+        quote_ident(db_type, column),
+    ));
+    statements
+}
+
+/// Statements that change a column's type, nullability and default from `from`
+/// to `to`, or `None` where the engine cannot alter columns in place (SQLite).
+/// Identity and generated columns are left as they are.
+pub fn alter_column(db_type: &DatabaseType, table: &str, from: &ColumnDefinition, to: &ColumnDefinition) -> Option<Vec<String>> {
+    let quoted_table = quote_table(db_type, table);
+    let name = quote_ident(db_type, &to.name);
+    let type_changed = !from.data_type.eq_ignore_ascii_case(&to.data_type);
+    let mut statements = Vec::new();
+
+    match db_type {
+        DatabaseType::PostgreSQL => {
+            let alter = |change: String| format!("ALTER TABLE {} ALTER COLUMN {} {}", quoted_table, name, change);
+            if type_changed {
+                statements.push(alter(format!("TYPE {} USING {}::{}", to.data_type, name, to.data_type)));
+            }
+            if from.nullable != to.nullable {
+                statements.push(alter(if to.nullable { "DROP NOT NULL" } else { "SET NOT NULL" }.to_string()));
+            }
+            if from.default != to.default {
+                statements.push(alter(match &to.default {
+                    Some(default) => format!("SET DEFAULT {}", default),
+                    None => "DROP DEFAULT".to_string(),
+                }));
+            }
+        }
+        // MODIFY restates the whole column, default and identity included
+        DatabaseType::MySQL => {
+            statements.push(format!("ALTER TABLE {} MODIFY COLUMN {}", quoted_table, column_sql(db_type, to)));
+        }
+        DatabaseType::SQLServer => {
+            if type_changed || from.nullable != to.nullable {
+                statements.push(format!(
+                    "ALTER TABLE {} ALTER COLUMN {} {} {}",
+                    quoted_table,
+                    name,
+                    to.data_type,
+                    if to.nullable { "NULL" } else { "NOT NULL" },
+                ));
+            }
+            if from.default != to.default {
+                if from.default.is_some() {
+                    statements.push(drop_default_constraint(table, &to.name));
+                }
+                if let Some(default) = &to.default {
+                    statements.push(format!("ALTER TABLE {} ADD DEFAULT {} FOR {}", quoted_table, default, name));
+                }
+            }
+        }
+        DatabaseType::SQLite => return None,
+    }
+    Some(statements)
+}
+
+/// SQL Server default constraints get generated names; look the name up and drop it.
+fn drop_default_constraint(table: &str, column: &str) -> String {
+    let db_type = DatabaseType::SQLServer;
+    format!(
+        "DECLARE @default sysname = (SELECT d.name FROM sys.default_constraints d \
+         JOIN sys.columns c ON c.object_id = d.parent_object_id AND c.column_id = d.parent_column_id \
+         WHERE d.parent_object_id = OBJECT_ID({}) AND c.name = {});\n\
+         IF @default IS NOT NULL EXEC('ALTER TABLE {} DROP CONSTRAINT ' + QUOTENAME(@default))",
+        super::dialect::quote_string(&db_type, table),
+        super::dialect::quote_string(&db_type, column),
+        quote_table(&db_type, table).replace('\'', "''"),
+    )
+}
+
+pub fn add_primary_key(db_type: &DatabaseType, table: &str, key: &KeyConstraint) -> String {
+    format!(
+        "ALTER TABLE {} ADD {}PRIMARY KEY ({})",
+        quote_table(db_type, table),
+        constraint_prefix(db_type, &key.name),
+        column_list(db_type, &key.columns),
+    )
+}
+
+pub fn add_unique(db_type: &DatabaseType, table: &str, key: &KeyConstraint) -> String {
+    format!(
+        "ALTER TABLE {} ADD {}UNIQUE ({})",
+        quote_table(db_type, table),
+        constraint_prefix(db_type, &key.name),
+        column_list(db_type, &key.columns),
+    )
+}
+
+pub fn add_check(db_type: &DatabaseType, table: &str, check: &CheckConstraint) -> String {
+    format!(
+        "ALTER TABLE {} ADD {}CHECK {}",
+        quote_table(db_type, table),
+        constraint_prefix(db_type, &check.name),
+        parenthesize(&check.expression),
+    )
+}
+
+/// MySQL has its own syntax per constraint type; elsewhere constraints are
+/// dropped by name. `None` when the constraint has no name to drop it by.
+pub fn drop_constraint(db_type: &DatabaseType, table: &str, kind: SchemaObjectType, name: Option<&str>) -> Option<String> {
+    if kind == SchemaObjectType::ForeignKey {
+        return name.map(|name| drop_foreign_key(db_type, table, name));
+    }
+    let quoted_table = quote_table(db_type, table);
+    if let (DatabaseType::MySQL, SchemaObjectType::PrimaryKey) = (db_type, kind) {
+        return Some(format!("ALTER TABLE {} DROP PRIMARY KEY", quoted_table));
+    }
+    let keyword = match (db_type, kind) {
+        (DatabaseType::MySQL, SchemaObjectType::UniqueConstraint) => "INDEX",
+        (DatabaseType::MySQL, SchemaObjectType::CheckConstraint) => "CHECK",
+        _ => "CONSTRAINT",
+    };
+    Some(format!("ALTER TABLE {} DROP {} {}", quoted_table, keyword, quote_ident(db_type, name?)))
+}
+
+/// PostgreSQL indexes live in their table's schema; the other engines name
+/// the table in the statement or need no qualifier.
+pub fn drop_index(db_type: &DatabaseType, table: &str, name: &str) -> String {
+    let quoted_name = quote_ident(db_type, name);
+    match db_type {
+        DatabaseType::PostgreSQL => {
+            let index = qualify(table.split_once('.').map(|(schema, _)| schema), name);
+            format!("DROP INDEX IF EXISTS {}", quote_table(db_type, &index))
+        }
+        DatabaseType::MySQL => format!("DROP INDEX {} ON {}", quoted_name, quote_table(db_type, table)),
+        DatabaseType::SQLServer => format!("DROP INDEX IF EXISTS {} ON {}", quoted_name, quote_table(db_type, table)),
+        DatabaseType::SQLite => format!("DROP INDEX IF EXISTS {}", quoted_name),
+    }
+}
+
+/// Bring a sequence's increment, bounds and cycling in line with `sequence`.
+pub fn alter_sequence(db_type: &DatabaseType, sequence: &SequenceDefinition) -> String {
+    format!(
+        "ALTER SEQUENCE {} INCREMENT BY {} MINVALUE {} MAXVALUE {} {}",
+        quote_table(db_type, &qualify(sequence.schema.as_deref(), &sequence.name)),
+        sequence.increment,
+        sequence.min_value,
+        sequence.max_value,
+        if sequence.cycle { "CYCLE" } else { "NO CYCLE" },
+    )
+}
@@ -0,0 +1,470 @@
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime};
+use serde::{Deserialize, Serialize};
+use crate::models::DatabaseType;
+
+/// SQL Server rejects a `VALUES` list with more than 1000 rows.
+pub const SQLSERVER_MAX_VALUES_ROWS: usize = 1000;
+
+/// Quote a single identifier (column, table or schema name) for the given engine.
+pub fn quote_ident(db_type: &DatabaseType, name: &str) -> String {
+    match db_type {
+        DatabaseType::PostgreSQL | DatabaseType::SQLite => format!("\"{}\"", name.replace('"', "\"\"")),
+        DatabaseType::MySQL => format!("`{}`", name.replace('`', "``")),
+        DatabaseType::SQLServer => format!("[{}]", name.replace(']', "]]")),
+    }
+}
+
+/// Quote a possibly schema-qualified table name (`schema.table`).
+pub fn quote_table(db_type: &DatabaseType, table: &str) -> String {
+    table
+        .splitn(2, '.')
+        .map(|part| quote_ident(db_type, part))
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Render a JSON value as an SQL literal for the given engine.
+pub fn quote_literal(db_type: &DatabaseType, value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::Null => "NULL".to_string(),
+        serde_json::Value::Bool(b) => match db_type {
+            DatabaseType::PostgreSQL | DatabaseType::MySQL => {
+                if *b { "TRUE".to_string() } else { "FALSE".to_string() }
+            }
+            DatabaseType::SQLite | DatabaseType::SQLServer => {
+                if *b { "1".to_string() } else { "0".to_string() }
+            }
+        },
+        serde_json::Value::Number(n) => n.to_string(),
+        serde_json::Value::String(s) => quote_string(db_type, s),
+        other => quote_string(db_type, &other.to_string()),
+    }
+}
+
+/// `quote_literal` for a value of a column of `type_name`. SQL Server
+/// refuses temporal strings with more fractional digits than the type
+/// holds, and values are read with nanoseconds.
+pub fn quote_column_literal(db_type: &DatabaseType, type_name: &str, value: &serde_json::Value) -> String {
+    match (value, fraction_digits(db_type, type_name)) {
+        (serde_json::Value::String(s), Some(digits)) => quote_string(db_type, &trim_fraction(s, digits)),
+        _ => quote_literal(db_type, value),
+    }
+}
+
+/// Fractional second digits SQL Server accepts in a literal for a temporal
+/// type, named as in DDL (`datetime2(3)`) or as tiberius reports it
+/// (`Datetimen`). `None` for other types and engines.
+pub fn fraction_digits(db_type: &DatabaseType, type_name: &str) -> Option<usize> {
+    if !matches!(db_type, DatabaseType::SQLServer) {
+        return None;
+    }
+    let lower = type_name.to_lowercase();
+    match lower.split('(').next().unwrap_or("").trim() {
+        "datetime" | "datetimen" | "datetime4" | "smalldatetime" => Some(3),
+        "datetime2" | "datetimeoffset" | "datetimeoffsetn" | "time" | "timen" => Some(7),
+        _ => None,
+    }
+}
+
+/// Cut the fraction of the seconds in an ISO 8601 date, time or datetime
+/// to `digits`, keeping any offset after it. `datetime` rounds the result
+/// back to the same 1/300 s step it was read as.
+pub fn trim_fraction(value: &str, digits: usize) -> String {
+    let bytes = value.as_bytes();
+    let Some(dot) = (3..bytes.len()).find(|&i| {
+        bytes[i] == b'.' && bytes[i - 3] == b':' && bytes[i - 2].is_ascii_digit() && bytes[i - 1].is_ascii_digit()
+    }) else {
+        return value.to_string();
+    };
+    let end = bytes[dot + 1..].iter().position(|b| !b.is_ascii_digit()).map_or(bytes.len(), |n| dot + 1 + n);
+    let keep = match digits {
+        0 => dot,
+        _ => end.min(dot + 1 + digits),
+    };
+    format!("{}{}", &value[..keep], &value[end..])
+}
+
+/// Render a string as an SQL string literal for the given engine.
+pub fn quote_string(db_type: &DatabaseType, s: &str) -> String {
+    let escaped = s.replace('\'', "''");
+    match db_type {
+        // MySQL treats backslash as an escape character by default
+        DatabaseType::MySQL => format!("'{}'", escaped.replace('\\', "\\\\")),
+        DatabaseType::SQLServer => format!("N'{}'", escaped),
+        DatabaseType::PostgreSQL | DatabaseType::SQLite => format!("'{}'", escaped),
+    }
+}
+
+/// Render raw bytes as a binary literal for the given engine.
+pub fn quote_bytes(db_type: &DatabaseType, bytes: &[u8]) -> String {
+    match db_type {
+        DatabaseType::PostgreSQL => format!("'\\x{}'::bytea", hex::encode(bytes)),
+        DatabaseType::MySQL | DatabaseType::SQLite => format!("X'{}'", hex::encode(bytes)),
+        DatabaseType::SQLServer => format!("0x{}", hex::encode(bytes)),
+    }
+}
+
+/// Build a `WHERE` predicate matching a row by its primary key values.
+pub fn key_predicate(
+    db_type: &DatabaseType,
+    key: &serde_json::Map<String, serde_json::Value>,
+) -> Result<String, String> {
+    if key.is_empty() {
+        return Err("A primary key value is required to identify the row".to_string());
+    }
+
+    let parts: Vec<String> = key
+        .iter()
+        .map(|(column, value)| {
+            let column = quote_ident(db_type, column);
+            if value.is_null() {
+                format!("{} IS NULL", column)
+            } else {
+                format!("{} = {}", column, quote_literal(db_type, value))
+            }
+        })
+        .collect();
+
+    Ok(parts.join(" AND "))
+}
+
+/// Whether a column type name, as reported by any of the engines, holds binary data.
+pub fn is_binary_type(type_name: &str) -> bool {
+    let upper = type_name.to_uppercase();
+    ["BYTEA", "BLOB", "BINARY", "VARBIN", "BIGBIN", "IMAGE"]
+        .iter()
+        .any(|t| upper.contains(t))
+}
+
+/// Decode a binary cell rendered as `\x..` or `0x..` hex back into bytes.
+pub fn decode_hex_cell(value: &str) -> Option<Vec<u8>> {
+    let digits = value
+        .strip_prefix("\\x")
+        .or_else(|| value.strip_prefix("0x"))?;
+    hex::decode(digits).ok()
+}
+
+/// Broad category of a result column, derived from the type name the engine reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColumnKind {
+    Boolean,
+    Integer,
+    Float,
+    Decimal,
+    Date,
+    Time,
+    /// Wall-clock date and time without offset
+    DateTime,
+    /// An instant (timestamptz, datetimeoffset)
+    Timestamp,
+    Binary,
+    /// JSON documents (json, jsonb)
+    Json,
+    Text,
+}
+
+/// Classify a column type name from any engine (sqlx type names or tiberius column types).
+pub fn column_kind(type_name: &str) -> ColumnKind {
+    let upper = type_name.to_uppercase();
+    if upper.ends_with("[]") {
+        return ColumnKind::Text;
+    }
+    if is_binary_type(&upper) {
+        return ColumnKind::Binary;
+    }
+
+    // information_schema spells out PostgreSQL's timestamptz
+    if upper.starts_with("TIMESTAMP") && upper.contains("WITH TIME ZONE") {
+        return ColumnKind::Timestamp;
+    }
+
+    // Drop modifiers such as `(10,2)` and `UNSIGNED`
+    let base = upper
+        .split(['(', ' '])
+        .next()
+        .unwrap_or_default();
+
+    match base {
+        "BOOL" | "BOOLEAN" | "BIT" | "BITN" => ColumnKind::Boolean,
+        "INT" | "INT1" | "INT2" | "INT4" | "INT8" | "INTN" | "INTEGER" | "TINYINT" | "SMALLINT"
+        | "MEDIUMINT" | "BIGINT" | "YEAR" | "OID" => ColumnKind::Integer,
+        "FLOAT" | "FLOAT4" | "FLOAT8" | "FLOATN" | "REAL" | "DOUBLE" => ColumnKind::Float,
+        "NUMERIC" | "DECIMAL" | "NUMERICN" | "DECIMALN" | "MONEY" | "MONEY4" => ColumnKind::Decimal,
+        "DATE" | "DATEN" => ColumnKind::Date,
+        "TIME" | "TIMEN" => ColumnKind::Time,
+        "TIMESTAMP" | "DATETIME" | "DATETIME2" | "DATETIMEN" | "DATETIME4" | "SMALLDATETIME" => ColumnKind::DateTime,
+        "TIMESTAMPTZ" | "DATETIMEOFFSET" | "DATETIMEOFFSETN" => ColumnKind::Timestamp,
+        "JSON" | "JSONB" => ColumnKind::Json,
+        _ => ColumnKind::Text,
+    }
+}
+
+/// Whether a type that classifies as text really holds characters, as
+/// opposed to a type with no counterpart such as `uuid` or `inet`.
+pub fn is_character_type(type_name: &str) -> bool {
+    let upper = type_name.to_uppercase();
+    // SQLite columns may have no declared type at all
+    upper.is_empty() || ["CHAR", "TEXT", "CLOB", "STRING"].iter().any(|t| upper.contains(t))
+}
+
+/// Parse a decimal in plain or exponent notation into its unscaled integer
+/// representation for the given scale. Fails if digits would be lost.
+pub fn parse_decimal(text: &str, scale: u32) -> Option<i128> {
+    let text = text.trim();
+    let scale = scale as usize;
+
+    if text.contains(['e', 'E']) {
+        let f: f64 = text.parse().ok()?;
+        return Some((f * 10f64.powi(scale as i32)).round() as i128);
+    }
+
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text.strip_prefix('+').unwrap_or(text)),
+    };
+    let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    if fraction.len() > scale || !whole.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let unscaled = format!("{}{:0<width$}", whole, fraction, width = scale);
+    let value: i128 = if unscaled.is_empty() { 0 } else { unscaled.parse().ok()? };
+    Some(if negative { -value } else { value })
+}
+
+/// A typed value on its way into the database (imports, copies).
+#[derive(Debug, Clone, PartialEq)]
+pub enum SqlValue {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    /// Kept as plain-notation text so no precision is lost
+    Decimal(String),
+    Text(String),
+    Date(NaiveDate),
+    Time(NaiveTime),
+    DateTime(NaiveDateTime),
+    Timestamp(DateTime<FixedOffset>),
+    Bytes(Vec<u8>),
+    Json(serde_json::Value),
+}
+
+impl SqlValue {
+    /// Render the value as an SQL literal for the given engine.
+    pub fn to_literal(&self, db_type: &DatabaseType) -> String {
+        match self {
+            SqlValue::Null => "NULL".to_string(),
+            SqlValue::Bool(b) => quote_literal(db_type, &serde_json::Value::Bool(*b)),
+            SqlValue::Int(i) => i.to_string(),
+            SqlValue::Float(f) if f.is_finite() => f.to_string(),
+            SqlValue::Float(_) => "NULL".to_string(),
+            SqlValue::Decimal(d) => d.clone(),
+            SqlValue::Bytes(bytes) => quote_bytes(db_type, bytes),
+            // No SQL Server type takes more than 7 digits
+            SqlValue::Time(_) | SqlValue::DateTime(_) | SqlValue::Timestamp(_) if matches!(db_type, DatabaseType::SQLServer) => {
+                quote_string(db_type, &trim_fraction(&self.to_text(), 7))
+            }
+            other => quote_string(db_type, &other.to_text()),
+        }
+    }
+
+    /// `to_literal` for a column of `type_name`, with temporal values cut to
+    /// the digits the type takes (see `quote_column_literal`).
+    pub fn to_column_literal(&self, db_type: &DatabaseType, type_name: &str) -> String {
+        match (self, fraction_digits(db_type, type_name)) {
+            (SqlValue::Time(_) | SqlValue::DateTime(_) | SqlValue::Timestamp(_), Some(digits)) => {
+                quote_string(db_type, &trim_fraction(&self.to_text(), digits))
+            }
+            _ => self.to_literal(db_type),
+        }
+    }
+
+    /// Canonical text form: ISO 8601 for temporal values, compact JSON for documents.
+    pub fn to_text(&self) -> String {
+        match self {
+            SqlValue::Null => String::new(),
+            SqlValue::Bool(b) => b.to_string(),
+            SqlValue::Int(i) => i.to_string(),
+            SqlValue::Float(f) => f.to_string(),
+            SqlValue::Decimal(s) | SqlValue::Text(s) => s.clone(),
+            SqlValue::Date(d) => d.format("%Y-%m-%d").to_string(),
+            SqlValue::Time(t) => t.format("%H:%M:%S%.f").to_string(),
+            SqlValue::DateTime(dt) => dt.format("%Y-%m-%d %H:%M:%S%.f").to_string(),
+            SqlValue::Timestamp(dt) => dt.format("%Y-%m-%d %H:%M:%S%.f%:z").to_string(),
+            SqlValue::Bytes(bytes) => format!("\\x{}", hex::encode(bytes)),
+            SqlValue::Json(value) => value.to_string(),
+        }
+    }
+}
+
+/// Column type used in generated DDL for a value kind. `max_length` is in
+/// characters; `precision`/`scale` apply to decimals.
+pub fn column_type_sql(
+    db_type: &DatabaseType,
+    kind: ColumnKind,
+    max_length: usize,
+    precision: u32,
+    scale: u32,
+) -> String {
+    let precision = precision.clamp(1, 38).max(scale);
+    match (db_type, kind) {
+        (DatabaseType::PostgreSQL, ColumnKind::Boolean) => "BOOLEAN".to_string(),
+        (DatabaseType::MySQL, ColumnKind::Boolean) => "BOOLEAN".to_string(),
+        (DatabaseType::SQLite, ColumnKind::Boolean) => "BOOLEAN".to_string(),
+        (DatabaseType::SQLServer, ColumnKind::Boolean) => "BIT".to_string(),
+
+        (DatabaseType::SQLite, ColumnKind::Integer) => "INTEGER".to_string(),
+        (_, ColumnKind::Integer) => "BIGINT".to_string(),
+
+        (DatabaseType::PostgreSQL, ColumnKind::Float) => "DOUBLE PRECISION".to_string(),
+        (DatabaseType::MySQL, ColumnKind::Float) => "DOUBLE".to_string(),
+        (DatabaseType::SQLite, ColumnKind::Float) => "REAL".to_string(),
+        (DatabaseType::SQLServer, ColumnKind::Float) => "FLOAT".to_string(),
+
+        (DatabaseType::SQLite, ColumnKind::Decimal) => "NUMERIC".to_string(),
+        (_, ColumnKind::Decimal) => format!("DECIMAL({},{})", precision, scale),
+
+        (DatabaseType::SQLite, ColumnKind::Date | ColumnKind::Time | ColumnKind::DateTime | ColumnKind::Timestamp) => {
+            "TEXT".to_string()
+        }
+        (_, ColumnKind::Date) => "DATE".to_string(),
+        (DatabaseType::MySQL, ColumnKind::Time) => "TIME(6)".to_string(),
+        (_, ColumnKind::Time) => "TIME".to_string(),
+        (DatabaseType::PostgreSQL, ColumnKind::DateTime) => "TIMESTAMP".to_string(),
+        (DatabaseType::MySQL, ColumnKind::DateTime | ColumnKind::Timestamp) => "DATETIME(6)".to_string(),
+        (DatabaseType::SQLServer, ColumnKind::DateTime) => "DATETIME2".to_string(),
+        (DatabaseType::PostgreSQL, ColumnKind::Timestamp) => "TIMESTAMPTZ".to_string(),
+        (DatabaseType::SQLServer, ColumnKind::Timestamp) => "DATETIMEOFFSET".to_string(),
+
+        (DatabaseType::PostgreSQL, ColumnKind::Binary) => "BYTEA".to_string(),
+        (DatabaseType::MySQL, ColumnKind::Binary) => "LONGBLOB".to_string(),
+        (DatabaseType::SQLite, ColumnKind::Binary) => "BLOB".to_string(),
+        (DatabaseType::SQLServer, ColumnKind::Binary) => "VARBINARY(MAX)".to_string(),
+
+        (DatabaseType::PostgreSQL, ColumnKind::Json) => "JSONB".to_string(),
+        (DatabaseType::MySQL, ColumnKind::Json) => "JSON".to_string(),
+        (DatabaseType::SQLite, ColumnKind::Json) => "TEXT".to_string(),
+        (DatabaseType::SQLServer, ColumnKind::Json) => "NVARCHAR(MAX)".to_string(),
+
+        (DatabaseType::PostgreSQL | DatabaseType::SQLite, ColumnKind::Text) => "TEXT".to_string(),
+        (DatabaseType::MySQL, ColumnKind::Text) => match max_length {
+            0..=255 => format!("VARCHAR({})", max_length.max(1)),
+            // TEXT holds 65,535 bytes, i.e. at least 16,383 four-byte characters
+            256..=16383 => "TEXT".to_string(),
+            _ => "LONGTEXT".to_string(),
+        },
+        (DatabaseType::SQLServer, ColumnKind::Text) => match max_length {
+            0..=4000 => format!("NVARCHAR({})", max_length.max(1)),
+            _ => "NVARCHAR(MAX)".to_string(),
+        },
+    }
+}
+
+/// Used for decimals whose declared precision is unknown, such as an
+/// unconstrained PostgreSQL `numeric` or SQL Server `money`.
+const DEFAULT_DECIMAL: (u32, u32) = (38, 10);
+
+/// Numbers in a declared type such as `VARCHAR(50)` or `DECIMAL(10,2)`.
+pub fn type_modifiers(declared: &str) -> (Option<i32>, Option<i32>) {
+    let Some(inner) = declared.split_once('(').and_then(|(_, rest)| rest.split_once(')')).map(|(inner, _)| inner) else {
+        return (None, None);
+    };
+    let mut numbers = inner.split(',').map(|n| n.trim().parse::<i32>().ok());
+    (numbers.next().flatten(), numbers.next().flatten())
+}
+
+/// Closest type on `db_type` for a type declared on another engine, keeping
+/// lengths, precision and scale where known. Unset lengths mean unlimited.
+pub fn translate_type(
+    db_type: &DatabaseType,
+    declared: &str,
+    max_length: Option<i32>,
+    precision: Option<i32>,
+    scale: Option<i32>,
+) -> String {
+    let kind = column_kind(declared);
+    match kind {
+        ColumnKind::Decimal => match (precision, db_type) {
+            (Some(precision), _) => column_type_sql(
+                db_type,
+                kind,
+                0,
+                precision.max(1) as u32,
+                scale.unwrap_or(0).max(0) as u32,
+            ),
+            (None, DatabaseType::PostgreSQL) => "NUMERIC".to_string(),
+            (None, _) => column_type_sql(db_type, kind, 0, DEFAULT_DECIMAL.0, DEFAULT_DECIMAL.1),
+        },
+        ColumnKind::Text | ColumnKind::Binary => {
+            let max_length = max_length.map_or(usize::MAX, |n| n.max(1) as usize);
+            match (kind, db_type) {
+                (ColumnKind::Binary, DatabaseType::MySQL) if max_length <= 255 => format!("VARBINARY({})", max_length),
+                (ColumnKind::Binary, DatabaseType::SQLServer) if max_length <= 8000 => format!("VARBINARY({})", max_length),
+                _ => column_type_sql(db_type, kind, max_length, 0, 0),
+            }
+        }
+        _ => column_type_sql(db_type, kind, 0, 0, 0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::temporal::format_datetime;
+
+    /// tiberius reads a `datetime` as 1/300 s steps; SQL Server turns a
+    /// literal's milliseconds back into the nearest step.
+    #[test]
+    fn sqlserver_datetime_literal_round_trips() {
+        let day = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap().and_hms_opt(3, 4, 5).unwrap();
+        for step in 0..300i64 {
+            let read = day + chrono::Duration::nanoseconds(step * 1_000_000_000 / 300);
+            let value = serde_json::Value::String(format_datetime(read));
+            let literal = quote_column_literal(&DatabaseType::SQLServer, "Datetimen", &value);
+
+            let text = literal.trim_start_matches("N'").trim_end_matches('\'');
+            let parsed = NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M:%S%.f").unwrap();
+            assert!(text.len() <= "2024-01-02T03:04:05.000".len(), "{}", literal);
+            let millis = (parsed - day).num_milliseconds() as f64;
+            assert_eq!((millis * 0.3).round() as i64, step, "{}", literal);
+        }
+    }
+
+    #[test]
+    fn sqlserver_literals_keep_the_digits_the_type_takes() {
+        let value = serde_json::json!("2024-01-02T03:04:05.123456789+02:00");
+        assert_eq!(
+            quote_column_literal(&DatabaseType::SQLServer, "datetimeoffset(7)", &value),
+            "N'2024-01-02T03:04:05.1234567+02:00'"
+        );
+        assert_eq!(
+            quote_column_literal(&DatabaseType::SQLServer, "Timen", &serde_json::json!("03:04:05.123456789")),
+            "N'03:04:05.1234567'"
+        );
+        assert_eq!(
+            quote_column_literal(&DatabaseType::SQLServer, "nvarchar", &serde_json::json!("03:04:05.123456789")),
+            "N'03:04:05.123456789'"
+        );
+        assert_eq!(
+            quote_column_literal(&DatabaseType::PostgreSQL, "datetime", &value),
+            "'2024-01-02T03:04:05.123456789+02:00'"
+        );
+
+        let dt = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap().and_hms_nano_opt(3, 4, 5, 123_456_789).unwrap();
+        assert_eq!(SqlValue::DateTime(dt).to_literal(&DatabaseType::SQLServer), "N'2024-01-02 03:04:05.1234567'");
+        assert_eq!(
+            SqlValue::DateTime(dt).to_column_literal(&DatabaseType::SQLServer, "datetime"),
+            "N'2024-01-02 03:04:05.123'"
+        );
+    }
+
+    #[test]
+    fn trim_fraction_leaves_other_text_alone() {
+        assert_eq!(trim_fraction("2024-01-02T03:04:05Z", 3), "2024-01-02T03:04:05Z");
+        assert_eq!(trim_fraction("2024-01-02T03:04:05.5Z", 3), "2024-01-02T03:04:05.5Z");
+        assert_eq!(trim_fraction("2024-01-02 03:04:05.123456", 0), "2024-01-02 03:04:05");
+        assert_eq!(trim_fraction("1.2345", 2), "1.2345");
+    }
+}
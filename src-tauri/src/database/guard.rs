@@ -0,0 +1,782 @@
+use std::time::Duration;
+use crate::models::*;
+use super::{count_rows, explain_sql_query, DatabaseConnection};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    /// Keyword or unquoted name, compared in upper case
+    Word,
+    /// `"name"`, `` `name` `` or `[name]`
+    QuotedName,
+    /// String or number
+    Literal,
+    Symbol,
+}
+
+#[derive(Debug, Clone)]
+pub struct Token {
+    pub kind: TokenKind,
+    /// Upper-cased text for words, the source text otherwise
+    pub text: String,
+    /// Byte range in the statement's SQL
+    pub start: usize,
+    pub end: usize,
+    /// Parentheses the token is nested in
+    pub depth: u32,
+}
+
+/// One statement of a batch with its tokens; comments are left out.
+#[derive(Debug)]
+pub struct Statement<'a> {
+    pub sql: &'a str,
+    pub tokens: Vec<Token>,
+}
+
+impl Statement<'_> {
+    /// The first keyword, skipping opening parentheses.
+    pub fn leading(&self) -> Option<&str> {
+        self.tokens.iter().find(|t| t.kind == TokenKind::Word).map(|t| t.text.as_str())
+    }
+
+    pub fn has_word(&self, word: &str) -> bool {
+        self.tokens.iter().any(|t| t.kind == TokenKind::Word && t.text == word)
+    }
+
+    /// Source text from the start of token `from` to the end of token `to`.
+    pub fn text(&self, from: usize, to: usize) -> &str {
+        self.sql[self.tokens[from].start..self.tokens[to].end].trim()
+    }
+}
+
+/// Split SQL on `;` into statements, skipping strings, quoted names and
+/// comments the way the engine does. Good enough to classify statements,
+/// not to parse them.
+pub fn statements<'a>(db_type: &DatabaseType, sql: &'a str) -> Vec<Statement<'a>> {
+    let mysql = matches!(db_type, DatabaseType::MySQL);
+    let postgres = matches!(db_type, DatabaseType::PostgreSQL);
+    let bytes = sql.as_bytes();
+    let mut statements = Vec::new();
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut statement_start = 0;
+    let mut depth = 0u32;
+    // Inside a MySQL `/*! ... */` comment, whose contents are executed
+    let mut in_versioned_comment = false;
+    let mut i = 0;
+
+    let push = |tokens: &mut Vec<Token>, kind: TokenKind, text: String, start: usize, end: usize, depth: u32| {
+        tokens.push(Token { kind, text, start, end, depth });
+    };
+
+    while i < bytes.len() {
+        let c = bytes[i];
+        let next = bytes.get(i + 1).copied();
+        let start = i;
+        match c {
+            _ if c.is_ascii_whitespace() => i += 1,
+            b'-' if next == Some(b'-') => i = find(bytes, i, b"\n").unwrap_or(bytes.len()),
+            b'#' if mysql => i = find(bytes, i, b"\n").unwrap_or(bytes.len()),
+            b'/' if next == Some(b'*') => {
+                if mysql && bytes.get(i + 2) == Some(&b'!') {
+                    in_versioned_comment = true;
+                    i += 3;
+                    while i < bytes.len() && bytes[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                    continue;
+                }
+                // PostgreSQL block comments nest
+                let mut level = 0;
+                while i < bytes.len() {
+                    if bytes[i..].starts_with(b"/*") {
+                        level += 1;
+                        i += 2;
+                    } else if bytes[i..].starts_with(b"*/") {
+                        level -= 1;
+                        i += 2;
+                        if level == 0 || !postgres {
+                            break;
+                        }
+                    } else {
+                        i += 1;
+                    }
+                }
+            }
+            b'*' if next == Some(b'/') && in_versioned_comment => {
+                in_versioned_comment = false;
+                i += 2;
+            }
+            b'\'' => {
+                // E'...' strings and MySQL strings take backslash escapes
+                let escaped = tokens.last().is_some_and(|t| t.end == i && t.kind == TokenKind::Word && t.text == "E");
+                let start = match escaped {
+                    true => tokens.pop().map_or(start, |t| t.start),
+                    false => start,
+                };
+                i = quoted_end(bytes, i, b'\'', mysql || escaped);
+                push(&mut tokens, TokenKind::Literal, sql[start..i].to_string(), start, i, depth);
+            }
+            b'"' => {
+                i = quoted_end(bytes, i, b'"', mysql);
+                // Double quotes delimit strings in MySQL
+                let kind = if mysql { TokenKind::Literal } else { TokenKind::QuotedName };
+                push(&mut tokens, kind, sql[start..i].to_string(), start, i, depth);
+            }
+            b'`' if mysql => {
+                i = quoted_end(bytes, i, b'`', false);
+                push(&mut tokens, TokenKind::QuotedName, sql[start..i].to_string(), start, i, depth);
+            }
+            b'[' if matches!(db_type, DatabaseType::SQLServer | DatabaseType::SQLite) => {
+                i = quoted_end(bytes, i, b']', false);
+                push(&mut tokens, TokenKind::QuotedName, sql[start..i].to_string(), start, i, depth);
+            }
+            b'$' if postgres && dollar_tag(bytes, i).is_some() => {
+                let tag = dollar_tag(bytes, i).unwrap_or_default();
+                i = find(bytes, i + tag.len(), tag).map_or(bytes.len(), |end| end + tag.len());
+                push(&mut tokens, TokenKind::Literal, sql[start..i].to_string(), start, i, depth);
+            }
+            _ if c.is_ascii_alphabetic() || c == b'_' || c >= 0x80 => {
+                while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_' || bytes[i] == b'$' || bytes[i] >= 0x80) {
+                    i += 1;
+                }
+                push(&mut tokens, TokenKind::Word, sql[start..i].to_uppercase(), start, i, depth);
+            }
+            _ if c.is_ascii_digit() => {
+                while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'.') {
+                    i += 1;
+                }
+                push(&mut tokens, TokenKind::Literal, sql[start..i].to_string(), start, i, depth);
+            }
+            b';' => {
+                if !tokens.is_empty() {
+                    statements.push(statement(sql, statement_start, i, std::mem::take(&mut tokens)));
+                }
+                depth = 0;
+                i += 1;
+                statement_start = i;
+            }
+            _ => {
+                if c == b')' {
+                    depth = depth.saturating_sub(1);
+                }
+                push(&mut tokens, TokenKind::Symbol, (c as char).to_string(), start, i + 1, depth);
+                if c == b'(' {
+                    depth += 1;
+                }
+                i += 1;
+            }
+        }
+    }
+    if !tokens.is_empty() {
+        statements.push(statement(sql, statement_start, sql.len(), tokens));
+    }
+    statements
+}
+
+/// Rebase token offsets on the statement's own text.
+fn statement(sql: &str, start: usize, end: usize, mut tokens: Vec<Token>) -> Statement<'_> {
+    for token in &mut tokens {
+        token.start -= start;
+        token.end -= start;
+    }
+    Statement { sql: &sql[start..end], tokens }
+}
+
+fn find(bytes: &[u8], from: usize, needle: &[u8]) -> Option<usize> {
+    bytes[from..].windows(needle.len()).position(|w| w == needle).map(|p| p + from)
+}
+
+/// Index just past the closing quote; a doubled quote stands for itself.
+fn quoted_end(bytes: &[u8], open: usize, close: u8, backslash: bool) -> usize {
+    let mut i = open + 1;
+    while i < bytes.len() {
+        if backslash && bytes[i] == b'\\' {
+            i += 2;
+        } else if bytes[i] == close {
+            if bytes.get(i + 1) == Some(&close) {
+                i += 2;
+            } else {
+                return i + 1;
+            }
+        } else {
+            i += 1;
+        }
+    }
+    bytes.len()
+}
+
+/// `$tag$` or `$$` opening a PostgreSQL dollar-quoted string.
+fn dollar_tag(bytes: &[u8], start: usize) -> Option<&[u8]> {
+    let mut i = start + 1;
+    while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+        i += 1;
+    }
+    let tag_ok = i == start + 1 || !bytes[start + 1].is_ascii_digit();
+    (bytes.get(i) == Some(&b'$') && tag_ok).then(|| &bytes[start..=i])
+}
+
+/// Statements allowed to start a query on a read-only connection. Their
+/// bodies are still checked for the keywords in `WRITE_KEYWORDS`.
+const READ_KEYWORDS: &[&str] = &[
+    "SELECT", "WITH", "VALUES", "TABLE", "EXPLAIN", "SET", "RESET", "BEGIN", "START", "COMMIT", "ROLLBACK",
+    "END", "SAVEPOINT", "RELEASE", "DECLARE", "PRINT", "USE", "FETCH", "CLOSE", "IF", "WHILE",
+];
+
+/// Words that make or ask for a change anywhere in a statement.
+const WRITE_KEYWORDS: &[&str] = &[
+    "INSERT", "UPDATE", "DELETE", "MERGE", "UPSERT", "CREATE", "ALTER", "DROP", "TRUNCATE", "RENAME", "GRANT",
+    "REVOKE", "DENY", "INTO", "CALL", "COPY", "BULK", "BACKUP", "RESTORE", "DBCC", "KILL", "SHUTDOWN",
+    "RECONFIGURE",
+];
+
+/// Settings that would turn the session's read-only mode off.
+const READ_ONLY_SETTINGS: &[&str] = &[
+    "DEFAULT_TRANSACTION_READ_ONLY",
+    "TRANSACTION_READ_ONLY",
+    "TX_READ_ONLY",
+    "QUERY_ONLY",
+    "SET_CONFIG",
+];
+
+/// SQL Server procedures that only report on the database.
+const READ_PROCEDURES: &[&str] = &["SP_HELP", "SP_WHO", "SP_COLUMNS", "SP_TABLES", "SP_DATABASES", "SP_SERVER_INFO"];
+
+/// Refuse SQL that writes, for a read-only connection. The session itself
+/// is opened read-only where the engine allows it; this also covers SQL
+/// Server, where read-only intent only picks a replica.
+pub fn check_read_only(db_type: &DatabaseType, sql: &str) -> Result<(), String> {
+    match writes(db_type, sql) {
+        Some(reason) => Err(format!("This connection is read-only: {} is not allowed", reason)),
+        None => Ok(()),
+    }
+}
+
+/// What the first writing statement of `sql` does, if any statement writes.
+pub fn writes(db_type: &DatabaseType, sql: &str) -> Option<String> {
+    statements(db_type, sql).iter().find_map(|statement| write_reason(db_type, statement))
+}
+
+fn write_reason(db_type: &DatabaseType, statement: &Statement) -> Option<String> {
+    let leading = statement.leading()?;
+    match leading {
+        "SHOW" | "DESCRIBE" | "DESC" => return None,
+        "PRAGMA" => {
+            return (statement.has_word("QUERY_ONLY") || statement.tokens.iter().any(|t| t.text == "="))
+                .then(|| "changing a PRAGMA".to_string());
+        }
+        "EXEC" | "EXECUTE" if matches!(db_type, DatabaseType::SQLServer) => {}
+        _ if READ_KEYWORDS.contains(&leading) => {}
+        _ => return Some(leading.to_string()),
+    }
+
+    let words: Vec<&Token> = statement.tokens.iter().filter(|t| t.kind == TokenKind::Word).collect();
+    for (idx, word) in words.iter().enumerate() {
+        let next = words.get(idx + 1).map(|w| w.text.as_str());
+        if word.text == "READ" && next == Some("WRITE") || READ_ONLY_SETTINGS.contains(&word.text.as_str()) {
+            return Some("switching the session to read-write".to_string());
+        }
+        if (word.text == "EXEC" || word.text == "EXECUTE")
+            && !next.is_some_and(|name| READ_PROCEDURES.iter().any(|p| name.starts_with(p)))
+        {
+            return Some("running a procedure".to_string());
+        }
+        if WRITE_KEYWORDS.contains(&word.text.as_str()) {
+            let previous = idx.checked_sub(1).map(|i| words[i].text.as_str());
+            return Some(match (previous, word.text.as_str()) {
+                (Some("FOR"), "UPDATE") => "SELECT ... FOR UPDATE".to_string(),
+                (_, "INTO") => "SELECT ... INTO".to_string(),
+                (_, keyword) => keyword.to_string(),
+            });
+        }
+    }
+    None
+}
+
+/// Object kinds named between `DROP` and the dropped names.
+const DROP_KINDS: &[&str] = &[
+    "TABLE", "VIEW", "MATERIALIZED", "INDEX", "SCHEMA", "DATABASE", "SEQUENCE", "FUNCTION", "PROCEDURE",
+    "TRIGGER", "TYPE", "DOMAIN", "EXTENSION", "FOREIGN", "EVENT", "USER", "ROLE", "LOGIN", "TEMPORARY", "TEMP",
+];
+
+/// `ALTER ... DROP` forms that change a column but keep its data.
+const ALTER_DROP_KEEPS_DATA: &[&str] = &["DEFAULT", "NOT", "IDENTITY", "EXPRESSION"];
+
+/// A statement that loses data unless the user meant it.
+#[derive(Debug, Clone)]
+pub struct Destructive {
+    pub kind: DestructiveKind,
+    pub statement: String,
+    pub description: String,
+    /// Tables whose rows are lost, to estimate how many
+    pub tables: Vec<String>,
+}
+
+/// `DELETE` and `UPDATE` without `WHERE`, `DROP`, `TRUNCATE` and
+/// `ALTER TABLE ... DROP` in the batch, in order.
+pub fn destructive_statements(db_type: &DatabaseType, sql: &str) -> Vec<Destructive> {
+    let mut found = Vec::new();
+    for statement in statements(db_type, sql) {
+        let parts = parts(&statement);
+        let whole = parts.len() == 1;
+        for part in parts {
+            let sql = match whole {
+                true => statement.sql.trim(),
+                false => statement.text(part[0], *part.last().unwrap_or(&part[0])),
+            };
+            found.extend(destructive(&statement, &part, sql));
+        }
+    }
+    found
+}
+
+/// Refuse the first destructive statement of `sql`, for a protected
+/// connection where the caller has no way to ask for confirmation.
+pub fn check_protected(db_type: &DatabaseType, sql: &str) -> Result<(), String> {
+    match destructive_statements(db_type, sql).first() {
+        Some(destructive) => Err(format!(
+            "This connection is protected: run the statement as a query to confirm it ({})",
+            destructive.description
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Words that start a statement.
+const VERBS: &[&str] = &["SELECT", "INSERT", "UPDATE", "DELETE", "MERGE", "DROP", "TRUNCATE", "ALTER", "CREATE"];
+
+/// The top-level tokens of a statement split where another statement
+/// starts, since SQL Server runs `SELECT 1 DELETE FROM t` as two. Each part
+/// starts at its verb, the first one after any `WITH` clause. A `DELETE` or
+/// `UPDATE` opening a parenthesis, as in PostgreSQL's
+/// `WITH gone AS (DELETE FROM t RETURNING *) SELECT ...`, is a part of its own.
+fn parts(statement: &Statement) -> Vec<Vec<usize>> {
+    let tokens = &statement.tokens;
+    let mut parts: Vec<Vec<usize>> = Vec::new();
+    let mut verb: Option<&str> = None;
+    let mut previous: Option<&str> = None;
+    for (i, token) in tokens.iter().enumerate().filter(|(_, t)| t.depth == 0) {
+        let word = (token.kind == TokenKind::Word).then_some(token.text.as_str());
+        let starts = word.is_some_and(|w| VERBS.contains(&w))
+            // `FOR UPDATE`, `ON DELETE CASCADE`, `ON DUPLICATE KEY UPDATE`
+            && !matches!(previous, Some("FOR" | "ON" | "KEY"))
+            // `INSERT ... SELECT`, `ALTER ... DROP` and a procedure's body
+            && !matches!((verb, word), (Some("INSERT" | "CREATE"), _) | (Some("ALTER"), Some("DROP" | "ALTER")));
+        if starts {
+            verb = word;
+            parts.push(Vec::new());
+        }
+        if let Some(part) = parts.last_mut() {
+            part.push(i);
+        }
+        previous = word;
+    }
+
+    for (i, token) in tokens.iter().enumerate().skip(1) {
+        let opens = tokens[i - 1].kind == TokenKind::Symbol && tokens[i - 1].text == "(";
+        if token.depth == 0 || !opens || token.kind != TokenKind::Word || !matches!(token.text.as_str(), "DELETE" | "UPDATE") {
+            continue;
+        }
+        let nested: Vec<usize> = (i..tokens.len())
+            .take_while(|&j| tokens[j].depth >= token.depth)
+            .filter(|&j| tokens[j].depth == token.depth)
+            .collect();
+        parts.push(nested);
+    }
+    parts.sort_by_key(|part| part.first().copied());
+    parts
+}
+
+fn destructive(statement: &Statement, part: &[usize], sql: &str) -> Option<Destructive> {
+    let tokens = &statement.tokens;
+    let word = |i: usize| (tokens[i].kind == TokenKind::Word).then_some(tokens[i].text.as_str());
+    let verb = word(*part.first()?)?;
+    let rest = &part[1..];
+    let sql = sql.to_string();
+    let result = |kind, description: String, tables: Vec<String>| Destructive { kind, statement: sql.clone(), description, tables };
+
+    match verb {
+        "DELETE" | "UPDATE" => {
+            if rest.iter().any(|&i| word(i) == Some("WHERE")) {
+                return None;
+            }
+            let skip = ["LOW_PRIORITY", "QUICK", "IGNORE", "FROM", "ONLY", "TOP"];
+            let mut at = 0;
+            while at < rest.len() && word(rest[at]).is_some_and(|w| skip.contains(&w)) {
+                // Past the row limit of `TOP (n)` too
+                at += 1;
+                while at < rest.len() && matches!(tokens[rest[at]].kind, TokenKind::Symbol | TokenKind::Literal) {
+                    at += 1;
+                }
+            }
+            let (table, _) = name_at(statement, rest, at)?;
+            Some(match verb {
+                "DELETE" => result(DestructiveKind::DeleteWithoutWhere, format!("Deletes every row of {}", table), vec![table]),
+                _ => result(DestructiveKind::UpdateWithoutWhere, format!("Updates every row of {}", table), vec![table]),
+            })
+        }
+        "DROP" => {
+            let mut at = 0;
+            let mut kind = Vec::new();
+            while let Some(w) = rest.get(at).and_then(|&i| word(i)).filter(|w| DROP_KINDS.contains(w)) {
+                if w != "TEMPORARY" && w != "TEMP" {
+                    kind.push(w.to_lowercase());
+                }
+                at += 1;
+            }
+            let names = names_from(statement, rest, at);
+            if names.is_empty() {
+                return None;
+            }
+            let kind = if kind.is_empty() { "object".to_string() } else { kind.join(" ") };
+            let tables = if kind == "table" { names.clone() } else { Vec::new() };
+            Some(result(DestructiveKind::Drop, format!("Drops {} {}", kind, names.join(", ")), tables))
+        }
+        "TRUNCATE" => {
+            let at = match rest.first().and_then(|&i| word(i)) {
+                Some("TABLE") => 1,
+                _ => 0,
+            };
+            let names = names_from(statement, rest, at);
+            if names.is_empty() {
+                return None;
+            }
+            Some(result(DestructiveKind::Truncate, format!("Removes every row of {}", names.join(", ")), names))
+        }
+        "ALTER" => {
+            if rest.first().and_then(|&i| word(i)) != Some("TABLE") {
+                return None;
+            }
+            let table = names_from(statement, rest, 1).into_iter().next()?;
+            let mut dropped = Vec::new();
+            for (idx, &i) in rest.iter().enumerate() {
+                if word(i) != Some("DROP") {
+                    continue;
+                }
+                let target = rest.get(idx + 1).and_then(|&i| word(i));
+                if target.is_some_and(|t| ALTER_DROP_KEEPS_DATA.contains(&t)) {
+                    continue;
+                }
+                let (what, at) = match target {
+                    Some(object @ ("PRIMARY" | "FOREIGN")) => (format!("{} key", object.to_lowercase()), idx + 3),
+                    Some(object @ ("COLUMN" | "CONSTRAINT" | "INDEX" | "KEY" | "PARTITION")) => (object.to_lowercase(), idx + 2),
+                    _ => ("column".to_string(), idx + 1),
+                };
+                let name = names_from(statement, rest, at).into_iter().next().unwrap_or_default();
+                dropped.push(format!("{} {}", what, name).trim_end().to_string());
+            }
+            if dropped.is_empty() {
+                return None;
+            }
+            Some(result(DestructiveKind::AlterDrop, format!("Drops {} from {}", dropped.join(", "), table), vec![table]))
+        }
+        _ => None,
+    }
+}
+
+/// A possibly qualified name starting at `rest[at]`, as written, and the
+/// position after it.
+fn name_at(statement: &Statement, rest: &[usize], at: usize) -> Option<(String, usize)> {
+    let tokens = &statement.tokens;
+    let is_name = |i: usize| matches!(tokens[i].kind, TokenKind::Word | TokenKind::QuotedName);
+    let first = *rest.get(at).filter(|&&i| is_name(i))?;
+    let mut last = first;
+    let mut next = at + 1;
+    while let (Some(&dot), Some(&part)) = (rest.get(next), rest.get(next + 1)) {
+        if tokens[dot].text != "." || !is_name(part) || tokens[dot].start != tokens[last].end {
+            break;
+        }
+        last = part;
+        next += 2;
+    }
+    Some((statement.text(first, last).to_string(), next))
+}
+
+/// A comma-separated list of names after `IF EXISTS`, `ONLY` or `CONCURRENTLY`.
+fn names_from(statement: &Statement, rest: &[usize], mut at: usize) -> Vec<String> {
+    let tokens = &statement.tokens;
+    let mut names = Vec::new();
+    loop {
+        while rest.get(at).is_some_and(|&i| {
+            tokens[i].kind == TokenKind::Word && ["IF", "EXISTS", "ONLY", "CONCURRENTLY"].contains(&tokens[i].text.as_str())
+        }) {
+            at += 1;
+        }
+        let Some((name, next)) = name_at(statement, rest, at) else {
+            break;
+        };
+        names.push(name);
+        at = next;
+        if rest.get(at).is_some_and(|&i| tokens[i].text == ",") {
+            at += 1;
+        } else {
+            break;
+        }
+    }
+    names
+}
+
+/// How long counting a table's rows may take before the planner's
+/// estimate is used instead.
+const COUNT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// The destructive statements of `sql` with the number of rows each
+/// would touch: counted when that is quick, estimated by the planner
+/// otherwise.
+pub async fn assess(conn: &DatabaseConnection, sql: &str) -> Vec<StatementImpact> {
+    let db_type = conn.db_type();
+    let mut impacts = Vec::new();
+    for destructive in destructive_statements(&db_type, sql) {
+        let mut estimated_rows = None;
+        let mut estimate_source = None;
+        for table in &destructive.tables {
+            let Some((rows, source)) = estimate_rows(conn, table).await else {
+                continue;
+            };
+            *estimated_rows.get_or_insert(0) += rows;
+            // One estimate makes the total an estimate
+            if estimate_source != Some(RowEstimateSource::Explain) {
+                estimate_source = Some(source);
+            }
+        }
+        impacts.push(StatementImpact {
+            kind: destructive.kind,
+            statement: destructive.statement,
+            description: destructive.description,
+            tables: destructive.tables,
+            estimated_rows,
+            estimate_source,
+        });
+    }
+    impacts
+}
+
+async fn estimate_rows(conn: &DatabaseConnection, table: &str) -> Option<(u64, RowEstimateSource)> {
+    match count_rows(conn, table, COUNT_TIMEOUT).await {
+        Ok(Some(count)) => return Some((count, RowEstimateSource::Count)),
+        Ok(None) => {}
+        Err(e) => log::warn!("[Guard] Could not count the rows of {}: {}", table, e),
+    }
+
+    match explain_sql_query(conn, &format!("SELECT * FROM {}", table), false).await {
+        Ok(plan) => plan_rows(&plan.root).map(|rows| (rows.round() as u64, RowEstimateSource::Explain)),
+        Err(e) => {
+            log::warn!("[Guard] Could not estimate the rows of {}: {}", table, e);
+            None
+        }
+    }
+}
+
+/// The largest row estimate in the plan, which for a plain scan is the table's.
+fn plan_rows(node: &PlanNode) -> Option<f64> {
+    node.children.iter().filter_map(plan_rows).chain(node.estimated_rows).reduce(f64::max)
+}
+
+/// Statements that only read, so running them twice does no harm.
+const REPEATABLE_KEYWORDS: &[&str] = &["SELECT", "WITH", "VALUES", "TABLE", "SHOW", "DESCRIBE", "DESC", "EXPLAIN"];
+
+/// Words followed by a parenthesis that are not function calls: clauses,
+/// operators and type modifiers such as `VARCHAR(10)`.
+const PAREN_KEYWORDS: &[&str] = &[
+    "SELECT", "FROM", "JOIN", "ON", "USING", "WHERE", "AND", "OR", "NOT", "IN", "EXISTS", "ANY", "SOME", "ALL",
+    "AS", "WITH", "VALUES", "OVER", "FILTER", "WITHIN", "GROUP", "BY", "HAVING", "ORDER", "PARTITION", "UNION",
+    "INTERSECT", "EXCEPT", "LATERAL", "APPLY", "CASE", "WHEN", "THEN", "ELSE", "IS", "LIKE", "BETWEEN",
+    "DISTINCT", "TABLE", "EXPLAIN", "ROW", "ARRAY", "LIMIT", "OFFSET", "TOP", "ROWS", "RANGE", "ESCAPE", "CHAR",
+    "VARCHAR", "NCHAR", "NVARCHAR", "CHARACTER", "VARYING", "BINARY", "VARBINARY", "DECIMAL", "NUMERIC", "FLOAT",
+    "TIME", "TIMESTAMP", "DATETIME2", "DATETIMEOFFSET", "INTERVAL", "BIT",
+];
+
+/// Built-in functions that only compute a value. Anything else called in a
+/// query, like `nextval` or a user function, may change data.
+const PURE_FUNCTIONS: &[&str] = &[
+    // Aggregates and window functions
+    "COUNT", "SUM", "AVG", "MIN", "MAX", "STDDEV", "STDDEV_POP", "STDDEV_SAMP", "VARIANCE", "VAR_POP", "VAR_SAMP",
+    "STRING_AGG", "ARRAY_AGG", "GROUP_CONCAT", "JSON_AGG", "JSONB_AGG", "BOOL_AND", "BOOL_OR", "ROW_NUMBER",
+    "RANK", "DENSE_RANK", "NTILE", "LAG", "LEAD", "FIRST_VALUE", "LAST_VALUE", "NTH_VALUE", "PERCENT_RANK",
+    "CUME_DIST",
+    // Conditions and conversions
+    "COALESCE", "NULLIF", "IFNULL", "ISNULL", "NVL", "IIF", "IF", "GREATEST", "LEAST", "CAST", "TRY_CAST",
+    "CONVERT", "TRY_CONVERT", "TO_CHAR", "TO_DATE", "TO_NUMBER", "TO_TIMESTAMP", "FORMAT",
+    // Text
+    "LOWER", "UPPER", "LENGTH", "LEN", "CHAR_LENGTH", "CHARACTER_LENGTH", "OCTET_LENGTH", "DATALENGTH",
+    "SUBSTRING", "SUBSTR", "LEFT", "RIGHT", "TRIM", "LTRIM", "RTRIM", "BTRIM", "REPLACE", "CONCAT", "CONCAT_WS",
+    "POSITION", "STRPOS", "CHARINDEX", "INSTR", "LOCATE", "LPAD", "RPAD", "REVERSE", "REPEAT", "REPLICATE",
+    "SPLIT_PART", "INITCAP", "TRANSLATE", "STUFF", "ASCII", "CHR", "UNICODE", "SPACE", "REGEXP_REPLACE",
+    "REGEXP_SUBSTR", "REGEXP_LIKE", "MD5", "SHA1", "SHA2", "HEX", "UNHEX", "ENCODE", "DECODE", "QUOTENAME",
+    // Numbers
+    "ABS", "CEIL", "CEILING", "FLOOR", "ROUND", "TRUNC", "MOD", "POWER", "POW", "SQRT", "EXP", "LN", "LOG",
+    "LOG10", "SIGN", "PI", "RANDOM", "RAND",
+    // Dates and times
+    "NOW", "GETDATE", "GETUTCDATE", "SYSDATETIME", "SYSUTCDATETIME", "DATEADD", "DATEDIFF", "DATEPART",
+    "DATENAME", "DATE_TRUNC", "DATE_PART", "EXTRACT", "DATE_FORMAT", "STRFTIME", "JULIANDAY", "DATETIME", "DATE",
+    "YEAR", "MONTH", "DAY", "HOUR", "MINUTE", "SECOND", "AGE", "EOMONTH", "UNIX_TIMESTAMP", "FROM_UNIXTIME",
+    // JSON and arrays
+    "JSON_EXTRACT", "JSON_VALUE", "JSON_QUERY", "JSON_OBJECT", "JSON_ARRAY", "JSON_BUILD_OBJECT",
+    "JSON_BUILD_ARRAY", "JSONB_BUILD_OBJECT", "TO_JSON", "TO_JSONB", "JSON_EACH", "JSON_ARRAY_LENGTH",
+    "JSON_ARRAY_ELEMENTS", "JSONB_ARRAY_ELEMENTS", "UNNEST", "ARRAY_LENGTH", "CARDINALITY", "GENERATE_SERIES",
+    // Catalog lookups
+    "OBJECT_ID", "OBJECT_NAME", "SCHEMA_NAME", "DB_NAME", "TYPE_NAME", "DATABASE", "VERSION", "TYPEOF",
+    "PG_TYPEOF", "FORMAT_TYPE", "PG_SIZE_PRETTY", "PG_RELATION_SIZE", "PG_TOTAL_RELATION_SIZE",
+];
+
+/// Whether `sql` only reads and can be run again after the connection was
+/// reopened halfway through. The first attempt may have run on the server,
+/// so a call to a function that is not known to be pure rules that out.
+pub fn is_repeatable_read(db_type: &DatabaseType, sql: &str) -> bool {
+    let statements = statements(db_type, sql);
+    !statements.is_empty()
+        && statements.iter().all(|statement| {
+            statement.leading().is_some_and(|leading| REPEATABLE_KEYWORDS.contains(&leading))
+                && write_reason(db_type, statement).is_none()
+                && !has_side_effect_call(statement)
+        })
+}
+
+/// A call to anything but a `PURE_FUNCTIONS` built-in, or a SQL Server
+/// `NEXT VALUE FOR`, which draws from a sequence.
+fn has_side_effect_call(statement: &Statement) -> bool {
+    let tokens = &statement.tokens;
+    tokens.windows(2).enumerate().any(|(idx, pair)| {
+        let (name, next) = (&pair[0], &pair[1]);
+        if name.kind == TokenKind::Word && name.text == "NEXT" && next.text == "VALUE" {
+            return true;
+        }
+        if next.kind != TokenKind::Symbol || next.text != "(" {
+            return false;
+        }
+        // A schema may hold a function with a built-in's name
+        let qualified = idx > 0 && tokens[idx - 1].text == "." && tokens[idx - 1].end == name.start;
+        match name.kind {
+            TokenKind::Word if qualified => true,
+            TokenKind::Word => {
+                !PAREN_KEYWORDS.contains(&name.text.as_str()) && !PURE_FUNCTIONS.contains(&name.text.as_str())
+            }
+            TokenKind::QuotedName => true,
+            _ => false,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_only(db_type: DatabaseType, sql: &str) -> Result<(), String> {
+        check_read_only(&db_type, sql)
+    }
+
+    fn descriptions(db_type: DatabaseType, sql: &str) -> Vec<String> {
+        destructive_statements(&db_type, sql).into_iter().map(|d| d.description).collect()
+    }
+
+    #[test]
+    fn comments_are_not_statements() {
+        assert!(read_only(DatabaseType::PostgreSQL, "-- DELETE FROM t\nSELECT 1").is_ok());
+        assert!(read_only(DatabaseType::PostgreSQL, "/* DROP TABLE t; */ SELECT 1").is_ok());
+        assert!(read_only(DatabaseType::PostgreSQL, "/* outer /* DROP TABLE t; */ still outer */ SELECT 1").is_ok());
+        assert!(read_only(DatabaseType::MySQL, "# DELETE FROM t\nSELECT 1").is_ok());
+        assert!(descriptions(DatabaseType::SQLite, "-- DELETE FROM t\nDELETE FROM t WHERE id = 1").is_empty());
+        // MySQL runs the contents of a versioned comment
+        assert!(read_only(DatabaseType::MySQL, "/*!50000 DELETE FROM t */").is_err());
+    }
+
+    #[test]
+    fn quoted_names_and_strings_are_not_keywords() {
+        assert!(read_only(DatabaseType::PostgreSQL, r#"SELECT "delete", "into" FROM "update""#).is_ok());
+        assert!(read_only(DatabaseType::MySQL, "SELECT `drop` FROM `insert`").is_ok());
+        assert!(read_only(DatabaseType::SQLServer, "SELECT [insert] FROM [delete]").is_ok());
+        assert!(read_only(DatabaseType::SQLite, "SELECT 'DROP TABLE t; --' AS s").is_ok());
+        assert!(read_only(DatabaseType::MySQL, r"SELECT 'it\'s; DROP TABLE t'").is_ok());
+        assert!(read_only(DatabaseType::PostgreSQL, r"SELECT E'it\'s; DROP TABLE t'").is_ok());
+        assert!(read_only(DatabaseType::PostgreSQL, r"SELECT 'a\'; DROP TABLE t").is_err());
+        assert_eq!(descriptions(DatabaseType::PostgreSQL, r#"DELETE FROM "my table""#), ["Deletes every row of \"my table\""]);
+    }
+
+    #[test]
+    fn dollar_quoted_strings_are_skipped() {
+        assert!(read_only(DatabaseType::PostgreSQL, "SELECT $$DELETE FROM t; DROP TABLE t$$").is_ok());
+        assert!(read_only(DatabaseType::PostgreSQL, "SELECT $body$ ; DROP TABLE t; $body$").is_ok());
+        assert!(descriptions(DatabaseType::PostgreSQL, "SELECT $x$DROP TABLE t$x$, $1").is_empty());
+    }
+
+    #[test]
+    fn ctes_ending_in_dml_write() {
+        let sql = "WITH old AS (SELECT id FROM t) DELETE FROM t";
+        assert!(read_only(DatabaseType::PostgreSQL, sql).is_err());
+        assert_eq!(descriptions(DatabaseType::PostgreSQL, sql), ["Deletes every row of t"]);
+        assert!(read_only(DatabaseType::PostgreSQL, "WITH gone AS (DELETE FROM t RETURNING *) SELECT * FROM gone").is_err());
+        assert!(read_only(DatabaseType::PostgreSQL, "WITH x AS (SELECT 1) SELECT * FROM x").is_ok());
+    }
+
+    #[test]
+    fn dml_inside_ctes_is_destructive() {
+        let sql = "WITH gone AS (DELETE FROM orders RETURNING *) SELECT count(*) FROM gone";
+        assert_eq!(descriptions(DatabaseType::PostgreSQL, sql), ["Deletes every row of orders"]);
+        assert!(check_protected(&DatabaseType::PostgreSQL, sql).is_err());
+
+        let sql = "WITH a AS (UPDATE accounts SET active = false RETURNING id), \
+                   b AS (DELETE FROM sessions WHERE user_id IN (SELECT id FROM a)) SELECT 1";
+        assert_eq!(descriptions(DatabaseType::PostgreSQL, sql), ["Updates every row of accounts"]);
+
+        let statements = destructive_statements(&DatabaseType::PostgreSQL, "WITH gone AS (DELETE FROM orders) SELECT 1");
+        assert_eq!(statements[0].statement, "DELETE FROM orders");
+        assert_eq!(statements[0].tables, ["orders"]);
+
+        // Not opening a parenthesis, or with a WHERE at the CTE's own level
+        assert!(descriptions(DatabaseType::PostgreSQL, "WITH x AS (DELETE FROM t WHERE id = 1 RETURNING *) SELECT * FROM x").is_empty());
+        assert!(descriptions(DatabaseType::PostgreSQL, "CREATE TABLE c (p int REFERENCES p (id) ON DELETE CASCADE)").is_empty());
+    }
+
+    #[test]
+    fn select_into_writes() {
+        assert_eq!(
+            read_only(DatabaseType::SQLServer, "SELECT * INTO backup FROM t"),
+            Err("This connection is read-only: SELECT ... INTO is not allowed".to_string())
+        );
+        assert_eq!(
+            read_only(DatabaseType::PostgreSQL, "SELECT * FROM t FOR UPDATE"),
+            Err("This connection is read-only: SELECT ... FOR UPDATE is not allowed".to_string())
+        );
+        assert!(descriptions(DatabaseType::PostgreSQL, "SELECT * FROM t FOR UPDATE").is_empty());
+    }
+
+    #[test]
+    fn every_statement_of_a_batch_is_checked() {
+        assert!(read_only(DatabaseType::PostgreSQL, "SELECT 1; DROP TABLE t").is_err());
+        assert!(read_only(DatabaseType::SQLServer, "SELECT 1 DELETE FROM t").is_err());
+        assert_eq!(descriptions(DatabaseType::SQLServer, "SELECT 1 DELETE FROM t"), ["Deletes every row of t"]);
+        assert_eq!(
+            descriptions(DatabaseType::MySQL, "DROP TABLE a, b; TRUNCATE c; UPDATE d SET x = 1 WHERE y = 2; UPDATE e SET x = 1"),
+            ["Drops table a, b", "Removes every row of c", "Updates every row of e"]
+        );
+        assert!(is_repeatable_read(&DatabaseType::PostgreSQL, "SELECT 1; SELECT 2"));
+        assert!(!is_repeatable_read(&DatabaseType::PostgreSQL, "SELECT 1; DELETE FROM t WHERE id = 1"));
+    }
+
+    #[test]
+    fn only_pure_calls_are_repeated() {
+        let repeatable = |db_type: DatabaseType, sql: &str| is_repeatable_read(&db_type, sql);
+        assert!(repeatable(DatabaseType::PostgreSQL, "SELECT count(*), max(id) FROM t WHERE id IN (1, 2)"));
+        assert!(repeatable(
+            DatabaseType::PostgreSQL,
+            "WITH x AS (SELECT row_number() OVER (PARTITION BY a ORDER BY b) AS n FROM t) SELECT * FROM x WHERE EXISTS (SELECT 1)"
+        ));
+        assert!(repeatable(DatabaseType::SQLServer, "SELECT CAST(a AS NVARCHAR(20)), COALESCE(b, GETDATE()) FROM t"));
+        assert!(repeatable(DatabaseType::PostgreSQL, "EXPLAIN (FORMAT JSON) SELECT lower(name) FROM t"));
+        // Quoted names and strings are not calls
+        assert!(repeatable(DatabaseType::PostgreSQL, "SELECT 'nextval(1)', \"nextval\" FROM t"));
+
+        assert!(!repeatable(DatabaseType::PostgreSQL, "SELECT nextval('s')"));
+        assert!(!repeatable(DatabaseType::PostgreSQL, "SELECT pg_terminate_backend(42)"));
+        assert!(!repeatable(DatabaseType::PostgreSQL, "SELECT my_proc()"));
+        assert!(!repeatable(DatabaseType::PostgreSQL, "SELECT * FROM app.lower(x)"));
+        assert!(!repeatable(DatabaseType::PostgreSQL, "SELECT \"audit\"(1)"));
+        assert!(!repeatable(DatabaseType::MySQL, "SELECT GET_LOCK('l', 10)"));
+        assert!(!repeatable(DatabaseType::SQLServer, "SELECT NEXT VALUE FOR dbo.seq"));
+    }
+
+    #[test]
+    fn alter_drop_only_counts_when_data_goes() {
+        assert!(descriptions(DatabaseType::PostgreSQL, "ALTER TABLE t ALTER COLUMN c DROP DEFAULT").is_empty());
+        assert!(descriptions(DatabaseType::PostgreSQL, "ALTER TABLE t ALTER COLUMN c DROP NOT NULL").is_empty());
+        assert_eq!(descriptions(DatabaseType::PostgreSQL, "ALTER TABLE t DROP COLUMN c"), ["Drops column c from t"]);
+    }
+}
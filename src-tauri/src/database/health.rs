@@ -0,0 +1,206 @@
+//! Health checks for open connections, and opening a pool again after the
+//! server restarted or the machine woke from sleep.
+
+use std::future::Future;
+use std::time::{Duration, Instant};
+use futures::future::join_all;
+use crate::models::*;
+use super::{connect_to_database, ping_database, ConnectionManager, DatabaseConnection, StatusListener};
+
+/// Time between two health checks of every connection.
+pub const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+/// A ping slower than this marks the connection degraded.
+const SLOW_PING: Duration = Duration::from_secs(2);
+/// A ping without an answer after this has failed.
+const PING_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Parts of error messages that mean the connection is gone rather than the
+/// statement failing, in lower case. Waiting too long for a free connection
+/// is not one: the pool is busy, and opening it again would only add load.
+const FATAL_ERRORS: &[&str] = &[
+    "error communicating with database",
+    "crashed background worker",
+    "connection refused",
+    "connection reset",
+    "connection closed",
+    "broken pipe",
+    "unexpected eof",
+    "server closed the connection",
+    "terminating connection",
+    "server has gone away",
+    "lost connection",
+    "network is unreachable",
+    "no route to host",
+];
+
+/// Whether an error means the pool has to be opened again.
+pub fn is_fatal_error(error: &str) -> bool {
+    let error = error.to_lowercase();
+    FATAL_ERRORS.iter().any(|fatal| error.contains(fatal))
+}
+
+/// The time `SELECT 1` took, or the driver's error. No answer in time is
+/// not fatal: a pool whose connections are all busy looks the same.
+async fn ping(conn: &DatabaseConnection) -> Result<Duration, String> {
+    let started = Instant::now();
+    match tokio::time::timeout(PING_TIMEOUT, ping_database(conn)).await {
+        Ok(result) => result.map(|_| started.elapsed()),
+        Err(_) => Err(format!("no answer within {} seconds", PING_TIMEOUT.as_secs())),
+    }
+}
+
+impl ConnectionManager {
+    pub fn on_status(&self, listener: StatusListener) {
+        *self.listener.lock().unwrap_or_else(|e| e.into_inner()) = Some(listener);
+    }
+
+    pub async fn get_state(&self, id: &str) -> Option<ConnectionState> {
+        self.connections.lock().await.get(id).map(|m| m.state)
+    }
+
+    /// Record a connection's state, telling the listener when it changed.
+    async fn set_state(&self, id: &str, state: ConnectionState, message: Option<String>) {
+        let changed = match self.connections.lock().await.get_mut(id) {
+            Some(managed) if managed.state != state => {
+                managed.state = state;
+                true
+            }
+            _ => false,
+        };
+        if !changed {
+            return;
+        }
+        log::info!("[ConnectionManager] Connection {} is {:?}", id, state);
+        if let Some(listener) = self.listener.lock().unwrap_or_else(|e| e.into_inner()).as_ref() {
+            listener(ConnectionStatus { connection_id: id.to_string(), state, message });
+        }
+    }
+
+    /// Open a connection's pool and tunnel again with the settings it was
+    /// opened with, unless it answers by now (e.g. another caller already
+    /// did). Returns the working connection.
+    pub async fn reconnect(&self, id: &str, cause: &str) -> Result<DatabaseConnection, String> {
+        // Other connections go on while this one waits for its server
+        let reconnecting = {
+            let conns = self.connections.lock().await;
+            conns.get(id).map(|m| m.reconnecting.clone()).ok_or_else(|| "Connection not found".to_string())?
+        };
+        let _reconnecting = reconnecting.lock().await;
+        let conn = self.get_connection(id).await.ok_or_else(|| "Connection not found".to_string())?;
+        if ping(&conn).await.is_ok() {
+            self.set_state(id, ConnectionState::Connected, None).await;
+            return Ok(conn);
+        }
+
+        // Attempts while lost are not news
+        if self.get_state(id).await != Some(ConnectionState::Lost) {
+            self.set_state(id, ConnectionState::Reconnecting, Some(cause.to_string())).await;
+        }
+        log::warn!("[ConnectionManager] Reopening connection {} after: {}", id, cause);
+        let config = self.get_config(id).await.ok_or_else(|| "Connection not found".to_string())?;
+        let (conn, tunnel) = match connect_to_database(&config).await {
+            Ok(opened) => opened,
+            Err(e) => {
+                self.set_state(id, ConnectionState::Lost, Some(e.clone())).await;
+                return Err(format!("Connection lost: {}", e));
+            }
+        };
+
+        let replaced = {
+            let mut conns = self.connections.lock().await;
+            match conns.get_mut(id) {
+                Some(managed) => {
+                    let old_conn = std::mem::replace(&mut managed.conn, conn.clone());
+                    Ok((old_conn, std::mem::replace(&mut managed.tunnel, tunnel)))
+                }
+                // Disconnected meanwhile
+                None => Err(tunnel),
+            }
+        };
+        match replaced {
+            Ok((old_conn, old_tunnel)) => {
+                // The old pool waits for connections still checked out, so
+                // it and its tunnel are released off the caller's path
+                tokio::spawn(async move {
+                    old_conn.close().await;
+                    if let Some(old_tunnel) = old_tunnel {
+                        old_tunnel.close().await;
+                    }
+                });
+                self.set_state(id, ConnectionState::Connected, None).await;
+                Ok(conn)
+            }
+            Err(tunnel) => {
+                if let Some(tunnel) = tunnel {
+                    tunnel.close().await;
+                }
+                Err("Connection not found".to_string())
+            }
+        }
+    }
+
+    /// Run a read, and if the connection turns out to be gone, open it again
+    /// and run the read once more. Only for reads that are safe to repeat.
+    pub async fn retry_read<T, F, Fut>(&self, id: &str, read: F) -> Result<T, String>
+    where
+        F: Fn(DatabaseConnection) -> Fut,
+        Fut: Future<Output = Result<T, String>>,
+    {
+        let conn = self.get_connection(id).await.ok_or_else(|| "Connection not found".to_string())?;
+        match read(conn).await {
+            Err(e) if is_fatal_error(&e) => {
+                let conn = self.reconnect(id, &e).await?;
+                log::info!("[ConnectionManager] Retrying read on connection {}", id);
+                read(conn).await
+            }
+            result => result,
+        }
+    }
+
+    /// After a statement that must not run twice failed, open the connection
+    /// again if that is why, so the next statement works.
+    pub async fn recover(&self, id: &str, error: &str) {
+        if is_fatal_error(error) {
+            if let Err(e) = self.reconnect(id, error).await {
+                log::warn!("[ConnectionManager] Could not reopen connection {}: {}", id, e);
+            }
+        }
+    }
+
+    /// Ping every connection once, all at the same time: slow or busy ones
+    /// are degraded, and ones whose server went away are opened again.
+    pub async fn check_health(&self) {
+        let snapshot: Vec<(String, DatabaseConnection)> = {
+            let conns = self.connections.lock().await;
+            conns.iter().map(|(id, m)| (id.clone(), m.conn.clone())).collect()
+        };
+        join_all(snapshot.into_iter().map(|(id, conn)| self.check_one(id, conn))).await;
+    }
+
+    /// Only a connection the driver reports gone, or whose SSH tunnel
+    /// exited, is opened again. Anything else leaves the pool alone: it may
+    /// only be busy, and replacing it would end the queries it is running.
+    async fn check_one(&self, id: String, conn: DatabaseConnection) {
+        match ping(&conn).await {
+            Ok(elapsed) if elapsed > SLOW_PING => {
+                let message = format!("The server took {} ms to answer", elapsed.as_millis());
+                self.set_state(&id, ConnectionState::Degraded, Some(message)).await;
+            }
+            Ok(_) => self.set_state(&id, ConnectionState::Connected, None).await,
+            Err(e) if is_fatal_error(&e) || self.tunnel_exited(&id).await => {
+                if let Err(e) = self.reconnect(&id, &format!("Ping failed: {}", e)).await {
+                    log::warn!("[ConnectionManager] Health check of {} failed: {}", id, e);
+                }
+            }
+            Err(e) => {
+                let message = format!("Ping failed: {}", e);
+                self.set_state(&id, ConnectionState::Degraded, Some(message)).await;
+            }
+        }
+    }
+
+    async fn tunnel_exited(&self, id: &str) -> bool {
+        let mut conns = self.connections.lock().await;
+        conns.get_mut(id).and_then(|m| m.tunnel.as_mut()).is_some_and(|tunnel| !tunnel.is_running())
+    }
+}
@@ -2,12 +2,34 @@ pub mod postgres;
 pub mod mysql;
 pub mod sqlite;
 pub mod sqlserver;
+pub mod dialect;
+pub mod ddl;
+pub mod temporal;
+pub mod tunnel;
+pub mod dsn;
+pub mod guard;
+pub mod health;
+pub mod stats;
 
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::Mutex;
+use tokio::io::AsyncWriteExt;
 use crate::models::*;
+use temporal::{TemporalFormat, TimeZoneSpec};
+use tunnel::SshTunnel;
 
+/// Binary cells larger than this are sent to the frontend as a preview only.
+pub const BLOB_PREVIEW_THRESHOLD: usize = 16 * 1024;
+/// Number of leading bytes included in a binary cell preview.
+pub const BLOB_PREVIEW_BYTES: usize = 64;
+/// Chunk size used when streaming a single cell to or from a file.
+const CELL_CHUNK_SIZE: usize = 1024 * 1024;
+/// Largest file loaded into a cell on engines that bind the value whole.
+const MAX_BOUND_CELL_SIZE: u64 = 256 * 1024 * 1024;
+
+#[derive(Clone)]
 pub enum DatabaseConnection {
     PostgreSQL(sqlx::PgPool),
     MySQL(sqlx::MySqlPool),
@@ -15,36 +37,266 @@ pub enum DatabaseConnection {
     SQLServer(sqlserver::SqlServerPool),
 }
 
+impl DatabaseConnection {
+    pub fn db_type(&self) -> DatabaseType {
+        match self {
+            DatabaseConnection::PostgreSQL(_) => DatabaseType::PostgreSQL,
+            DatabaseConnection::MySQL(_) => DatabaseType::MySQL,
+            DatabaseConnection::SQLite(_) => DatabaseType::SQLite,
+            DatabaseConnection::SQLServer(_) => DatabaseType::SQLServer,
+        }
+    }
+
+    /// Check out a single connection for work that spans several statements.
+    pub async fn session(&self) -> Result<DatabaseSession<'_>, String> {
+        let session = match self {
+            DatabaseConnection::PostgreSQL(pool) => DatabaseSession::PostgreSQL(pool.acquire().await.map_err(connection_error)?),
+            DatabaseConnection::MySQL(pool) => DatabaseSession::MySQL(pool.acquire().await.map_err(connection_error)?),
+            DatabaseConnection::SQLite(pool) => DatabaseSession::SQLite(pool.acquire().await.map_err(connection_error)?),
+            DatabaseConnection::SQLServer(pool) => DatabaseSession::SQLServer(Box::new(pool.get().await.map_err(connection_error)?)),
+        };
+        Ok(session)
+    }
+
+    /// Close the pool's connections once they are returned. A SQL Server
+    /// pool has no close; its connections go with the last handle to it.
+    pub async fn close(&self) {
+        match self {
+            DatabaseConnection::PostgreSQL(pool) => pool.close().await,
+            DatabaseConnection::MySQL(pool) => pool.close().await,
+            DatabaseConnection::SQLite(pool) => pool.close().await,
+            DatabaseConnection::SQLServer(_) => {}
+        }
+    }
+}
+
+fn connection_error(e: impl std::fmt::Display) -> String {
+    format!("Failed to get connection: {}", e)
+}
+
+/// One pooled connection held for a multi-statement operation, so that
+/// transactions and savepoints apply to every statement sent through it.
+///
+/// Transactions are driven with plain SQL. Callers must end every transaction
+/// they begin, otherwise it stays open when the connection returns to the pool.
+pub enum DatabaseSession<'a> {
+    PostgreSQL(sqlx::pool::PoolConnection<sqlx::Postgres>),
+    MySQL(sqlx::pool::PoolConnection<sqlx::MySql>),
+    SQLite(sqlx::pool::PoolConnection<sqlx::Sqlite>),
+    SQLServer(Box<bb8::PooledConnection<'a, sqlserver::SqlServerManager>>),
+}
+
+impl DatabaseSession<'_> {
+    pub fn db_type(&self) -> DatabaseType {
+        match self {
+            DatabaseSession::PostgreSQL(_) => DatabaseType::PostgreSQL,
+            DatabaseSession::MySQL(_) => DatabaseType::MySQL,
+            DatabaseSession::SQLite(_) => DatabaseType::SQLite,
+            DatabaseSession::SQLServer(_) => DatabaseType::SQLServer,
+        }
+    }
+
+    /// Run one or more statements that return no rows, without preparing them.
+    pub async fn execute(&mut self, sql: &str) -> Result<(), String> {
+        match self {
+            DatabaseSession::PostgreSQL(conn) => sqlx::raw_sql(sql).execute(&mut **conn).await.map(|_| ()).map_err(|e| e.to_string()),
+            DatabaseSession::MySQL(conn) => sqlx::raw_sql(sql).execute(&mut **conn).await.map(|_| ()).map_err(|e| e.to_string()),
+            DatabaseSession::SQLite(conn) => sqlx::raw_sql(sql).execute(&mut **conn).await.map(|_| ()).map_err(|e| e.to_string()),
+            DatabaseSession::SQLServer(conn) => sqlserver::run_batch(conn, sql).await.map_err(|e| e.to_string()),
+        }
+    }
+
+    pub async fn begin(&mut self) -> Result<(), String> {
+        let sql = match self.db_type() {
+            DatabaseType::SQLServer => "BEGIN TRANSACTION",
+            _ => "BEGIN",
+        };
+        self.execute(sql).await.map_err(|e| format!("Failed to begin transaction: {}", e))
+    }
+
+    pub async fn commit(&mut self) -> Result<(), String> {
+        self.execute("COMMIT").await.map_err(|e| format!("Failed to commit transaction: {}", e))
+    }
+
+    pub async fn rollback(&mut self) -> Result<(), String> {
+        self.execute("ROLLBACK").await.map_err(|e| format!("Failed to roll back transaction: {}", e))
+    }
+
+    pub async fn savepoint(&mut self, name: &str) -> Result<(), String> {
+        let sql = match self.db_type() {
+            DatabaseType::SQLServer => format!("SAVE TRANSACTION {}", name),
+            _ => format!("SAVEPOINT {}", name),
+        };
+        self.execute(&sql).await.map_err(|e| format!("Failed to create savepoint: {}", e))
+    }
+
+    pub async fn rollback_to_savepoint(&mut self, name: &str) -> Result<(), String> {
+        let sql = match self.db_type() {
+            DatabaseType::SQLServer => format!("ROLLBACK TRANSACTION {}", name),
+            _ => format!("ROLLBACK TO SAVEPOINT {}", name),
+        };
+        self.execute(&sql).await.map_err(|e| format!("Failed to roll back to savepoint: {}", e))
+    }
+
+    /// SQL Server has no way to release a savepoint, so this does nothing there.
+    pub async fn release_savepoint(&mut self, name: &str) -> Result<(), String> {
+        if let DatabaseType::SQLServer = self.db_type() {
+            return Ok(());
+        }
+        self.execute(&format!("RELEASE SAVEPOINT {}", name))
+            .await
+            .map_err(|e| format!("Failed to release savepoint: {}", e))
+    }
+
+    /// Run a PostgreSQL `COPY ... FROM STDIN` fed with chunks of COPY text
+    /// until `next_chunk` returns `None`. Returns the number of rows copied.
+    pub async fn copy_from_stdin(
+        &mut self,
+        statement: &str,
+        next_chunk: &mut (dyn FnMut() -> Result<Option<String>, String> + Send),
+    ) -> Result<u64, String> {
+        match self {
+            DatabaseSession::PostgreSQL(conn) => postgres::copy_from_stdin(conn, statement, next_chunk).await,
+            _ => Err("COPY FROM STDIN is only supported on PostgreSQL".to_string()),
+        }
+    }
+
+    /// Insert rows into `table` using the fastest path the engine offers:
+    /// `COPY FROM STDIN` on PostgreSQL, bulk load on SQL Server and multi-row
+    /// `INSERT` elsewhere. Values are in `columns` order.
+    pub async fn insert_rows(&mut self, table: &str, columns: &[String], rows: &[Vec<dialect::SqlValue>]) -> Result<(), String> {
+        if rows.is_empty() {
+            return Ok(());
+        }
+        match self {
+            DatabaseSession::PostgreSQL(conn) => postgres::copy_rows(conn, table, columns, rows).await,
+            DatabaseSession::SQLServer(conn) => match sqlserver::bulk_insert(conn, table, columns, rows).await? {
+                true => Ok(()),
+                // Column types the bulk loader cannot encode
+                false => self.insert_statements(table, columns, rows).await,
+            },
+            DatabaseSession::MySQL(_) | DatabaseSession::SQLite(_) => self.insert_statements(table, columns, rows).await,
+        }
+    }
+
+    /// Insert rows with multi-row `INSERT ... VALUES` statements.
+    pub async fn insert_statements(&mut self, table: &str, columns: &[String], rows: &[Vec<dialect::SqlValue>]) -> Result<(), String> {
+        let db_type = self.db_type();
+        let target = format!(
+            "INSERT INTO {} ({}) VALUES ",
+            dialect::quote_table(&db_type, table),
+            columns.iter().map(|c| dialect::quote_ident(&db_type, c)).collect::<Vec<_>>().join(", "),
+        );
+
+        let chunk_size = match db_type {
+            DatabaseType::SQLServer => dialect::SQLSERVER_MAX_VALUES_ROWS,
+            _ => rows.len(),
+        };
+        for chunk in rows.chunks(chunk_size) {
+            let values = chunk
+                .iter()
+                .map(|row| {
+                    let literals: Vec<String> = row.iter().map(|v| v.to_literal(&db_type)).collect();
+                    format!("({})", literals.join(", "))
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            self.execute(&format!("{}{}", target, values)).await?;
+        }
+        Ok(())
+    }
+}
+
+/// A live connection together with the settings it was opened with.
+struct ManagedConnection {
+    conn: DatabaseConnection,
+    config: ConnectionConfig,
+    temporal: TemporalFormat,
+    /// Kept open for as long as the connection is
+    tunnel: Option<SshTunnel>,
+    /// Last state reported to the listener
+    state: ConnectionState,
+    /// Held while the pool is opened again, so only one caller does it
+    reconnecting: Arc<Mutex<()>>,
+}
+
+/// Told about connection state changes, to forward them to the frontend.
+pub type StatusListener = Box<dyn Fn(ConnectionStatus) + Send + Sync>;
+
+/// A destructive query waiting for the user to confirm it.
+struct PendingConfirmation {
+    connection_id: String,
+    sql: String,
+    expires: std::time::Instant,
+}
+
+/// How long a confirmation token stays valid.
+const CONFIRMATION_TTL: std::time::Duration = std::time::Duration::from_secs(5 * 60);
+
 pub struct ConnectionManager {
-    connections: Arc<Mutex<HashMap<String, DatabaseConnection>>>,
+    connections: Arc<Mutex<HashMap<String, ManagedConnection>>>,
+    confirmations: Mutex<HashMap<String, PendingConfirmation>>,
+    listener: std::sync::Mutex<Option<StatusListener>>,
 }
 
 impl ConnectionManager {
     pub fn new() -> Self {
         Self {
             connections: Arc::new(Mutex::new(HashMap::new())),
+            confirmations: Mutex::new(HashMap::new()),
+            listener: std::sync::Mutex::new(None),
         }
     }
 
-    pub async fn add_connection(&self, id: String, conn: DatabaseConnection) {
+    pub async fn add_connection(
+        &self,
+        id: String,
+        conn: DatabaseConnection,
+        config: ConnectionConfig,
+        temporal: TemporalFormat,
+        tunnel: Option<SshTunnel>,
+    ) {
         let mut conns = self.connections.lock().await;
-        conns.insert(id, conn);
+        conns.insert(id, ManagedConnection {
+            conn,
+            config,
+            temporal,
+            tunnel,
+            state: ConnectionState::Connected,
+            reconnecting: Arc::new(Mutex::new(())),
+        });
     }
 
     pub async fn remove_connection(&self, id: &str) -> bool {
-        let mut conns = self.connections.lock().await;
-        let removed = conns.remove(id).is_some();
-        if removed {
-            log::info!("[ConnectionManager] Removed connection: {}", id);
-        } else {
-            log::warn!("[ConnectionManager] Connection not found to remove: {}", id);
+        let removed = self.connections.lock().await.remove(id);
+        match removed {
+            Some(managed) => {
+                if let Some(tunnel) = managed.tunnel {
+                    tunnel.close().await;
+                }
+                log::info!("[ConnectionManager] Removed connection: {}", id);
+                true
+            }
+            None => {
+                log::warn!("[ConnectionManager] Connection not found to remove: {}", id);
+                false
+            }
+        }
+    }
+
+    /// Drop every connection and close their tunnels, when the app exits.
+    pub async fn close_all(&self) {
+        let removed: Vec<ManagedConnection> = self.connections.lock().await.drain().map(|(_, m)| m).collect();
+        for managed in removed {
+            if let Some(tunnel) = managed.tunnel {
+                tunnel.close().await;
+            }
         }
-        removed
     }
 
     pub async fn get_connection(&self, id: &str) -> Option<DatabaseConnection> {
         let conns = self.connections.lock().await;
-        match conns.get(id) {
+        match conns.get(id).map(|m| &m.conn) {
             Some(DatabaseConnection::PostgreSQL(pool)) => {
                 Some(DatabaseConnection::PostgreSQL(pool.clone()))
             }
@@ -60,6 +312,50 @@ impl ConnectionManager {
             None => None,
         }
     }
+
+    pub async fn get_config(&self, id: &str) -> Option<ConnectionConfig> {
+        let conns = self.connections.lock().await;
+        conns.get(id).map(|m| m.config.clone())
+    }
+
+    /// Refuse an import, upload or script run on a read-only connection.
+    pub async fn ensure_writable(&self, id: &str) -> Result<(), String> {
+        match self.get_config(id).await {
+            Some(config) if config.read_only => Err(format!("{} is a read-only connection", config.name)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Hold `sql` until the user confirms it, returning the token to run it with.
+    pub async fn request_confirmation(&self, id: &str, sql: &str) -> String {
+        let token = uuid::Uuid::new_v4().to_string();
+        let mut pending = self.confirmations.lock().await;
+        pending.retain(|_, p| p.expires > std::time::Instant::now());
+        pending.insert(
+            token.clone(),
+            PendingConfirmation {
+                connection_id: id.to_string(),
+                sql: sql.to_string(),
+                expires: std::time::Instant::now() + CONFIRMATION_TTL,
+            },
+        );
+        token
+    }
+
+    /// Use up a confirmation token; only valid for the same SQL on the same
+    /// connection, once, before it expires.
+    pub async fn take_confirmation(&self, token: &str, id: &str, sql: &str) -> bool {
+        match self.confirmations.lock().await.remove(token) {
+            Some(p) => p.connection_id == id && p.sql == sql && p.expires > std::time::Instant::now(),
+            None => false,
+        }
+    }
+
+    /// Temporal formatting for a connection; falls back to the defaults if unknown.
+    pub async fn get_temporal_format(&self, id: &str) -> TemporalFormat {
+        let conns = self.connections.lock().await;
+        conns.get(id).map(|m| m.temporal.clone()).unwrap_or_default()
+    }
 }
 
 impl Default for ConnectionManager {
@@ -69,35 +365,105 @@ impl Default for ConnectionManager {
 }
 
 pub async fn test_database_connection(config: &ConnectionConfig) -> TestConnectionResult {
-    match config.db_type {
-        DatabaseType::PostgreSQL => postgres::test_connection(config).await,
-        DatabaseType::MySQL => mysql::test_connection(config).await,
-        DatabaseType::SQLite => sqlite::test_connection(config).await,
-        DatabaseType::SQLServer => sqlserver::test_connection(config).await,
+    let opened = match dsn::resolve(config).and_then(|config| check_session_settings(&config).map(|_| config)) {
+        Ok(config) => tunnel::open_for(&config).await,
+        Err(e) => Err(e),
+    };
+    let (config, tunnel) = match opened {
+        Ok(opened) => opened,
+        Err(e) => {
+            return TestConnectionResult {
+                success: false,
+                message: format!("Connection failed: {}", e),
+                version: None,
+            }
+        }
+    };
+    
+    let result = match config.db_type {
+        DatabaseType::PostgreSQL => postgres::test_connection(&config).await,
+        DatabaseType::MySQL => mysql::test_connection(&config).await,
+        DatabaseType::SQLite => sqlite::test_connection(&config).await,
+        DatabaseType::SQLServer => sqlserver::test_connection(&config).await,
+    };
+    if let Some(tunnel) = tunnel {
+        tunnel.close().await;
     }
+    result
 }
 
-pub async fn connect_to_database(config: &ConnectionConfig) -> Result<DatabaseConnection, String> {
-    match config.db_type {
-        DatabaseType::PostgreSQL => {
-            let pool = postgres::connect(config).await?;
-            Ok(DatabaseConnection::PostgreSQL(pool))
-        }
-        DatabaseType::MySQL => {
-            let pool = mysql::connect(config).await?;
-            Ok(DatabaseConnection::MySQL(pool))
-        }
-        DatabaseType::SQLite => {
-            let pool = sqlite::connect(config).await?;
-            Ok(DatabaseConnection::SQLite(pool))
-        }
-        DatabaseType::SQLServer => {
-            let pool = sqlserver::connect(config).await?;
-            Ok(DatabaseConnection::SQLServer(pool))
+/// Open a pool for a connection, through its SSH tunnel if it has one. The
+/// tunnel must be kept for as long as the pool is used.
+pub async fn connect_to_database(config: &ConnectionConfig) -> Result<(DatabaseConnection, Option<SshTunnel>), String> {
+    let config = dsn::resolve(config)?;
+    check_session_settings(&config)?;
+    let (config, tunnel) = tunnel::open_for(&config).await?;
+    
+    let conn = match config.db_type {
+        DatabaseType::PostgreSQL => postgres::connect(&config).await.map(DatabaseConnection::PostgreSQL),
+        DatabaseType::MySQL => mysql::connect(&config).await.map(DatabaseConnection::MySQL),
+        DatabaseType::SQLite => sqlite::connect(&config).await.map(DatabaseConnection::SQLite),
+        DatabaseType::SQLServer => sqlserver::connect(&config).await.map(DatabaseConnection::SQLServer),
+    };
+    match conn {
+        Ok(conn) => Ok((conn, tunnel)),
+        Err(e) => {
+            if let Some(tunnel) = tunnel {
+                tunnel.close().await;
+            }
+            Err(e)
         }
     }
 }
 
+/// Pool limits that make sense, and init SQL that does not write on a
+/// read-only connection.
+fn check_session_settings(config: &ConnectionConfig) -> Result<(), String> {
+    config.pool.settings(&config.db_type)?;
+    if let Some(sql) = config.init_sql.as_deref().filter(|_| config.read_only) {
+        guard::check_read_only(&config.db_type, sql).map_err(|e| format!("Session init SQL: {}", e))?;
+    }
+    Ok(())
+}
+
+/// Run the session init SQL once on a connection of its own: a pool retries
+/// connections whose init fails until it times out, without saying why.
+/// Connection errors are left for the pool to report.
+async fn check_init_sql<O>(options: &O, sql: &str, timeout: std::time::Duration) -> Result<(), String>
+where
+    O: sqlx::ConnectOptions,
+    O::Connection: Sized,
+    for<'c> &'c mut O::Connection: sqlx::Executor<'c>,
+{
+    use sqlx::Connection;
+    let Ok(Ok(mut conn)) = tokio::time::timeout(timeout, options.connect()).await else {
+        return Ok(());
+    };
+    let result = sqlx::Executor::execute(&mut conn, sql).await;
+    let _ = conn.close().await;
+    result.map(|_| ()).map_err(|e| format!("Session init SQL failed: {}", e))
+}
+
+/// Work out how temporal values are rendered for a freshly opened connection.
+pub async fn resolve_temporal_format(
+    conn: &DatabaseConnection,
+    config: &ConnectionConfig,
+) -> Result<TemporalFormat, String> {
+    let display = match &config.display_timezone {
+        Some(tz) => TimeZoneSpec::parse(tz)?,
+        None => TimeZoneSpec::Original,
+    };
+
+    let session = match conn {
+        DatabaseConnection::PostgreSQL(pool) => postgres::session_timezone(pool).await,
+        DatabaseConnection::MySQL(pool) => mysql::session_timezone(pool).await,
+        // SQLite has no session timezone and SQL Server's datetimeoffset carries its own offset
+        DatabaseConnection::SQLite(_) | DatabaseConnection::SQLServer(_) => TimeZoneSpec::Original,
+    };
+
+    Ok(TemporalFormat { display, session })
+}
+
 pub async fn get_tables_list(conn: &DatabaseConnection) -> Result<Vec<TableInfo>, String> {
     match conn {
         DatabaseConnection::PostgreSQL(pool) => postgres::get_tables(pool).await,
@@ -116,21 +482,97 @@ pub async fn get_table_structure_info(conn: &DatabaseConnection, table: &str) ->
     }
 }
 
-pub async fn execute_sql_query(conn: &DatabaseConnection, sql: &str) -> Result<QueryResult, String> {
+/// Tables, views and sequences with their keys, constraints and indexes.
+pub async fn get_schema_snapshot(conn: &DatabaseConnection) -> Result<SchemaSnapshot, String> {
+    match conn {
+        DatabaseConnection::PostgreSQL(pool) => postgres::get_schema(pool).await,
+        DatabaseConnection::MySQL(pool) => mysql::get_schema(pool).await,
+        DatabaseConnection::SQLite(pool) => sqlite::get_schema(pool).await,
+        DatabaseConnection::SQLServer(pool) => sqlserver::get_schema(pool).await,
+    }
+}
+
+pub async fn execute_sql_query(conn: &DatabaseConnection, sql: &str, temporal: &TemporalFormat) -> Result<QueryResult, String> {
+    match conn {
+        DatabaseConnection::PostgreSQL(pool) => postgres::execute_query(pool, sql, temporal).await,
+        DatabaseConnection::MySQL(pool) => mysql::execute_query(pool, sql, temporal).await,
+        DatabaseConnection::SQLite(pool) => sqlite::execute_query(pool, sql, temporal).await,
+        DatabaseConnection::SQLServer(pool) => sqlserver::execute_query(pool, sql, temporal).await,
+    }
+}
+
+/// Run a query and hand its rows to `sink` as they arrive, without buffering
+/// the result. Returns the number of rows streamed.
+pub async fn stream_sql_query(
+    conn: &DatabaseConnection,
+    sql: &str,
+    temporal: &TemporalFormat,
+    sink: &mut dyn RowSink,
+) -> Result<u64, String> {
     match conn {
-        DatabaseConnection::PostgreSQL(pool) => postgres::execute_query(pool, sql).await,
-        DatabaseConnection::MySQL(pool) => mysql::execute_query(pool, sql).await,
-        DatabaseConnection::SQLite(pool) => sqlite::execute_query(pool, sql).await,
-        DatabaseConnection::SQLServer(pool) => sqlserver::execute_query(pool, sql).await,
+        DatabaseConnection::PostgreSQL(pool) => postgres::stream_query(pool, sql, temporal, sink).await,
+        DatabaseConnection::MySQL(pool) => mysql::stream_query(pool, sql, temporal, sink).await,
+        DatabaseConnection::SQLite(pool) => sqlite::stream_query(pool, sql, temporal, sink).await,
+        DatabaseConnection::SQLServer(pool) => sqlserver::stream_query(pool, sql, temporal, sink).await,
     }
 }
 
-pub async fn get_table_data_rows(conn: &DatabaseConnection, table: &str, limit: u32) -> Result<QueryResult, String> {
+/// Cheapest round trip to the server, to tell whether the connection works.
+pub async fn ping_database(conn: &DatabaseConnection) -> Result<(), String> {
+    match conn {
+        DatabaseConnection::PostgreSQL(pool) => sqlx::query("SELECT 1").execute(pool).await.map(|_| ()),
+        DatabaseConnection::MySQL(pool) => sqlx::query("SELECT 1").execute(pool).await.map(|_| ()),
+        DatabaseConnection::SQLite(pool) => sqlx::query("SELECT 1").execute(pool).await.map(|_| ()),
+        DatabaseConnection::SQLServer(pool) => return sqlserver::ping(pool).await,
+    }
+    .map_err(|e| e.to_string())
+}
+
+/// Count a table's rows, giving up after `limit`; `None` if it took
+/// longer. A count given up on is not left on a pooled connection, whose
+/// next user would otherwise wait for it or read its results.
+pub async fn count_rows(conn: &DatabaseConnection, table: &str, limit: Duration) -> Result<Option<u64>, String> {
+    let sql = format!("SELECT COUNT(*) FROM {}", table);
+    match conn {
+        DatabaseConnection::PostgreSQL(pool) => count_or_close(pool, &sql, limit).await,
+        DatabaseConnection::MySQL(pool) => count_or_close(pool, &sql, limit).await,
+        DatabaseConnection::SQLite(pool) => count_or_close(pool, &sql, limit).await,
+        DatabaseConnection::SQLServer(pool) => sqlserver::count_rows(pool, table, limit).await,
+    }
+}
+
+async fn count_or_close<DB>(pool: &sqlx::Pool<DB>, sql: &str, limit: Duration) -> Result<Option<u64>, String>
+where
+    DB: sqlx::Database,
+    (i64,): for<'r> sqlx::FromRow<'r, DB::Row>,
+    for<'c> &'c mut DB::Connection: sqlx::Executor<'c, Database = DB>,
+    for<'q> DB::Arguments<'q>: sqlx::IntoArguments<'q, DB>,
+{
+    let mut conn = pool.acquire().await.map_err(|e| format!("Failed to get connection: {}", e))?;
+    match tokio::time::timeout(limit, sqlx::query_scalar::<DB, i64>(sql).fetch_one(&mut *conn)).await {
+        Ok(count) => count.map(|n| Some(n as u64)).map_err(|e| format!("Failed to count rows: {}", e)),
+        Err(_) => {
+            conn.close_on_drop();
+            Ok(None)
+        }
+    }
+}
+
+pub async fn explain_sql_query(conn: &DatabaseConnection, sql: &str, analyze: bool) -> Result<QueryPlan, String> {
+    match conn {
+        DatabaseConnection::PostgreSQL(pool) => postgres::explain_query(pool, sql, analyze).await,
+        DatabaseConnection::MySQL(pool) => mysql::explain_query(pool, sql, analyze).await,
+        DatabaseConnection::SQLite(pool) => sqlite::explain_query(pool, sql, analyze).await,
+        DatabaseConnection::SQLServer(pool) => sqlserver::explain_query(pool, sql, analyze).await,
+    }
+}
+
+pub async fn get_table_data_rows(conn: &DatabaseConnection, table: &str, limit: u32, temporal: &TemporalFormat) -> Result<QueryResult, String> {
     let sql = match conn {
         DatabaseConnection::SQLServer(_) => format!("SELECT TOP {} * FROM {}", limit, table),
         _ => format!("SELECT * FROM {} LIMIT {}", table, limit),
     };
-    execute_sql_query(conn, &sql).await
+    execute_sql_query(conn, &sql, temporal).await
 }
 
 pub async fn list_databases(conn: &DatabaseConnection) -> Result<Vec<String>, String> {
@@ -141,3 +583,222 @@ pub async fn list_databases(conn: &DatabaseConnection) -> Result<Vec<String>, St
         DatabaseConnection::SQLServer(pool) => sqlserver::list_databases(pool).await,
     }
 }
+
+/// Receives streamed query results one row at a time.
+pub trait RowSink: Send {
+    /// Called before the rows of each result set, with that result set's columns.
+    fn begin(&mut self, columns: &[ResultColumn]) -> Result<(), String>;
+    /// One row, with values in column order. Binary values are full hex strings.
+    fn write_row(&mut self, values: &[serde_json::Value]) -> Result<(), String>;
+    /// Called once after the last result set.
+    fn finish(&mut self) -> Result<(), String>;
+}
+
+/// How binary cells are rendered when rows are converted to JSON.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryFormat {
+    /// Large values are replaced with a `$binary` preview (result grid)
+    Preview,
+    /// Values are always rendered in full (exports)
+    Full,
+}
+
+/// Convert a binary cell to a hex string. In preview mode, large values are
+/// replaced with a `$binary` object so whole images or documents are not
+/// pushed through IPC.
+pub fn binary_to_json(bytes: &[u8], hex_prefix: &str, format: BinaryFormat) -> serde_json::Value {
+    if format == BinaryFormat::Full || bytes.len() <= BLOB_PREVIEW_THRESHOLD {
+        return serde_json::Value::String(format!("{}{}", hex_prefix, hex::encode(bytes)));
+    }
+
+    let preview = &bytes[..BLOB_PREVIEW_BYTES];
+    serde_json::json!({
+        "$binary": {
+            "length": bytes.len(),
+            "preview": format!("{}{}", hex_prefix, hex::encode(preview)),
+        }
+    })
+}
+
+/// Stream a single cell to a file in chunks. Returns the number of bytes written.
+pub async fn download_cell(
+    conn: &DatabaseConnection,
+    table: &str,
+    key: &serde_json::Map<String, serde_json::Value>,
+    column: &str,
+    path: &str,
+) -> Result<u64, String> {
+    let db_type = conn.db_type();
+    let table = dialect::quote_table(&db_type, table);
+    let column = dialect::quote_ident(&db_type, column);
+    let predicate = dialect::key_predicate(&db_type, key)?;
+
+    // Written beside the target and moved into place once complete, so a
+    // missing row or a failed read never leaves a file behind
+    let temp = format!("{}.part", path);
+    let written = match read_cell_into(conn, &table, &column, &predicate, &temp).await {
+        Ok(written) => written,
+        Err(e) => {
+            let _ = tokio::fs::remove_file(&temp).await;
+            return Err(e);
+        }
+    };
+    if let Err(e) = tokio::fs::rename(&temp, path).await {
+        let _ = tokio::fs::remove_file(&temp).await;
+        return Err(format!("Failed to write file: {}", e));
+    }
+    log::info!("[download_cell] Wrote {} bytes to {}", written, path);
+
+    Ok(written)
+}
+
+async fn read_cell_into(
+    conn: &DatabaseConnection,
+    table: &str,
+    column: &str,
+    predicate: &str,
+    path: &str,
+) -> Result<u64, String> {
+    let file = tokio::fs::File::create(path)
+        .await
+        .map_err(|e| format!("Failed to create file: {}", e))?;
+    let mut sink = CellSink { file, offset: 0, written: 0 };
+
+    match conn {
+        DatabaseConnection::PostgreSQL(pool) => postgres::read_cell(pool, table, column, predicate, &mut sink).await?,
+        DatabaseConnection::MySQL(pool) => mysql::read_cell(pool, table, column, predicate, &mut sink).await?,
+        DatabaseConnection::SQLite(pool) => sqlite::read_cell(pool, table, column, predicate, &mut sink).await?,
+        DatabaseConnection::SQLServer(pool) => sqlserver::read_cell(pool, table, column, predicate, &mut sink).await?,
+    }
+
+    sink.file.flush().await.map_err(|e| format!("Failed to write file: {}", e))?;
+    Ok(sink.written)
+}
+
+/// A file a cell is downloaded into. Engines read the value a chunk at a
+/// time, all within one transaction so that a value updated mid-download is
+/// never stitched together from both versions, and hand each chunk to `write`.
+pub struct CellSink {
+    file: tokio::fs::File,
+    offset: usize,
+    written: u64,
+}
+
+impl CellSink {
+    /// Where the next chunk starts, in the units the engine's SUBSTRING counts
+    /// (bytes for binary columns, characters for text columns, UTF-16 code
+    /// units for SQL Server's Unicode columns).
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Append one chunk and its length in SUBSTRING units, `None` when the
+    /// read found no value. Returns whether there may be more to read.
+    pub async fn write(&mut self, chunk: Option<(Vec<u8>, usize)>) -> Result<bool, String> {
+        let Some((bytes, units)) = chunk else {
+            if self.offset == 0 {
+                return Err("Row not found or cell is NULL".to_string());
+            }
+            return Ok(false);
+        };
+
+        self.file
+            .write_all(&bytes)
+            .await
+            .map_err(|e| format!("Failed to write file: {}", e))?;
+        self.written += bytes.len() as u64;
+        self.offset += units;
+
+        Ok(units >= CELL_CHUNK_SIZE)
+    }
+}
+
+/// Load a file into a binary column. Returns the number of bytes stored.
+pub async fn upload_cell(
+    conn: &DatabaseConnection,
+    table: &str,
+    key: &serde_json::Map<String, serde_json::Value>,
+    column: &str,
+    path: &str,
+) -> Result<u64, String> {
+    let db_type = conn.db_type();
+    let table = dialect::quote_table(&db_type, table);
+    let column = dialect::quote_ident(&db_type, column);
+    let predicate = dialect::key_predicate(&db_type, key)?;
+
+    let file = tokio::fs::File::open(path)
+        .await
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+    let len = file
+        .metadata()
+        .await
+        .map_err(|e| format!("Failed to read file: {}", e))?
+        .len();
+    let mut source = CellSource { file, len, read: 0 };
+
+    let affected = match conn {
+        DatabaseConnection::PostgreSQL(pool) => postgres::write_cell(pool, &table, &column, &predicate, &mut source).await?,
+        DatabaseConnection::MySQL(pool) => mysql::write_cell(pool, &table, &column, &predicate, &mut source).await?,
+        DatabaseConnection::SQLite(pool) => sqlite::write_cell(pool, &table, &column, &predicate, &mut source).await?,
+        DatabaseConnection::SQLServer(pool) => sqlserver::write_cell(pool, &table, &column, &predicate, &mut source).await?,
+    };
+
+    if affected == 0 {
+        return Err("Row not found".to_string());
+    }
+
+    log::info!("[upload_cell] Stored {} bytes from {}", source.read, path);
+    Ok(source.read)
+}
+
+/// A file loaded into a cell. Engines that can write part of a value take it
+/// a chunk at a time; the others bind the whole file once.
+pub struct CellSource {
+    file: tokio::fs::File,
+    len: u64,
+    read: u64,
+}
+
+impl CellSource {
+    /// Bytes not read yet.
+    pub fn remaining(&self) -> u64 {
+        self.len.saturating_sub(self.read)
+    }
+
+    /// The next chunk of the file, empty once it is all read.
+    pub async fn next_chunk(&mut self) -> Result<Vec<u8>, String> {
+        use tokio::io::AsyncReadExt;
+
+        let mut chunk = Vec::with_capacity(CELL_CHUNK_SIZE);
+        (&mut self.file)
+            .take(CELL_CHUNK_SIZE as u64)
+            .read_to_end(&mut chunk)
+            .await
+            .map_err(|e| format!("Failed to read file: {}", e))?;
+        self.read += chunk.len() as u64;
+        Ok(chunk)
+    }
+
+    /// The rest of the file as one value, for engines where appending chunk by
+    /// chunk would rewrite the whole value each time. The file is held in
+    /// memory, so one over `MAX_BOUND_CELL_SIZE` is refused before it is read.
+    pub async fn read_all(&mut self) -> Result<Vec<u8>, String> {
+        use tokio::io::AsyncReadExt;
+
+        if self.remaining() > MAX_BOUND_CELL_SIZE {
+            return Err(format!(
+                "File is {} bytes; this database takes at most {} bytes in one upload",
+                self.remaining(),
+                MAX_BOUND_CELL_SIZE
+            ));
+        }
+
+        let mut bytes = Vec::new();
+        self.file
+            .read_to_end(&mut bytes)
+            .await
+            .map_err(|e| format!("Failed to read file: {}", e))?;
+        self.read += bytes.len() as u64;
+        Ok(bytes)
+    }
+}
@@ -1,14 +1,64 @@
-use sqlx::{mysql::MySqlPoolOptions, MySqlPool, Row, Column, TypeInfo};
+use sqlx::{mysql::{MySqlConnectOptions, MySqlPoolOptions, MySqlSslMode}, MySqlPool, Row, Column, TypeInfo, Executor};
 use crate::models::*;
+use super::dialect;
+use super::temporal::{self, TemporalFormat, TimeZoneSpec};
+use super::{BinaryFormat, CellSink, CellSource, RowSink, CELL_CHUNK_SIZE};
 use std::time::Instant;
 
+/// Room left in a packet for the statement header around a bound cell value.
+const PACKET_HEADROOM: u64 = 1024;
+
+/// Settings from the connection URL with the TLS options applied.
+fn connect_options(config: &ConnectionConfig) -> Result<MySqlConnectOptions, String> {
+    let tls = &config.tls;
+    tls.validate()?;
+    // The driver skips parameters it does not know without a word
+    if let Some(key) = config.parameters.keys().find(|k| !super::dsn::MYSQL_PARAMETERS.contains(&k.as_str())) {
+        return Err(format!("Unsupported MySQL connection parameter: {}", key));
+    }
+    
+    let mut options: MySqlConnectOptions = config
+        .connection_string()
+        .parse()
+        .map_err(|e| format!("Invalid MySQL connection settings: {}", e))?;
+    options = options.ssl_mode(match tls.mode {
+        TlsMode::Disable => MySqlSslMode::Disabled,
+        TlsMode::Prefer => MySqlSslMode::Preferred,
+        TlsMode::Require => MySqlSslMode::Required,
+        TlsMode::VerifyCa => MySqlSslMode::VerifyCa,
+        TlsMode::VerifyFull => MySqlSslMode::VerifyIdentity,
+    });
+    if let Some(path) = tls.ca_cert_path.as_deref().filter(|p| !p.is_empty()) {
+        options = options.ssl_ca(path);
+    }
+    if let Some(path) = tls.client_cert_path.as_deref().filter(|p| !p.is_empty()) {
+        options = options.ssl_client_cert(path);
+    }
+    if let Some(path) = tls.client_key_path.as_deref().filter(|p| !p.is_empty()) {
+        options = options.ssl_client_key(path);
+    }
+    if let Some(socket) = config.socket.as_deref().filter(|s| !s.is_empty()) {
+        options = options.socket(socket);
+    }
+    Ok(options)
+}
+
 pub async fn test_connection(config: &ConnectionConfig) -> TestConnectionResult {
-    let conn_str = config.connection_string();
+    let options = match connect_options(config) {
+        Ok(options) => options,
+        Err(e) => {
+            return TestConnectionResult {
+                success: false,
+                message: e,
+                version: None,
+            }
+        }
+    };
     
     match MySqlPoolOptions::new()
         .max_connections(1)
         .acquire_timeout(std::time::Duration::from_secs(5))
-        .connect(&conn_str)
+        .connect_with(options)
         .await
     {
         Ok(pool) => {
@@ -38,16 +88,74 @@ pub async fn test_connection(config: &ConnectionConfig) -> TestConnectionResult
 }
 
 pub async fn connect(config: &ConnectionConfig) -> Result<MySqlPool, String> {
-    let conn_str = config.connection_string();
+    let options = connect_options(config)?;
+    let settings = config.pool.settings(&config.db_type)?;
     
+    let read_only = config.read_only;
+    let init_sql = config.init_sql.clone().filter(|sql| !sql.trim().is_empty());
+    if let Some(sql) = &init_sql {
+        super::check_init_sql(&options, sql, settings.acquire_timeout).await?;
+    }
     MySqlPoolOptions::new()
-        .max_connections(5)
-        .acquire_timeout(std::time::Duration::from_secs(10))
-        .connect(&conn_str)
+        .max_connections(settings.max_connections)
+        .min_connections(settings.min_connections)
+        .acquire_timeout(settings.acquire_timeout)
+        .idle_timeout(settings.idle_timeout)
+        .max_lifetime(settings.max_lifetime)
+        .after_connect(move |conn, _| {
+            let init_sql = init_sql.clone();
+            Box::pin(async move {
+                if read_only {
+                    conn.execute("SET SESSION TRANSACTION READ ONLY").await?;
+                }
+                if let Some(sql) = init_sql {
+                    conn.execute(sql.as_str()).await?;
+                }
+                Ok(())
+            })
+        })
+        .connect_with(options)
         .await
         .map_err(|e| format!("MySQL connection failed: {}", e))
 }
 
+/// Session timezone, used to attach an offset to TIMESTAMP values.
+/// `SYSTEM` is the zone the server started in, which is only used when it is
+/// an IANA name: an abbreviation such as `EST` or a fixed offset would put
+/// values from the other side of a DST change an hour off.
+pub async fn session_timezone(pool: &MySqlPool) -> TimeZoneSpec {
+    let query = "SELECT CAST(@@session.time_zone AS CHAR) AS tz, CAST(@@system_time_zone AS CHAR) AS system_tz";
+    
+    let row = match sqlx::query(query).fetch_one(pool).await {
+        Ok(row) => row,
+        Err(e) => {
+            log::warn!("MySQL: Could not read session timezone: {}", e);
+            return TimeZoneSpec::Original;
+        }
+    };
+    
+    let tz: String = row.try_get("tz").unwrap_or_default();
+    if !tz.eq_ignore_ascii_case("SYSTEM") {
+        return TimeZoneSpec::parse(&tz).unwrap_or_else(|e| {
+            log::warn!("MySQL: {}, keeping TIMESTAMP values as returned", e);
+            TimeZoneSpec::Original
+        });
+    }
+    
+    let system: String = row.try_get("system_tz").unwrap_or_default();
+    match TimeZoneSpec::parse(&system) {
+        Ok(TimeZoneSpec::Utc) => TimeZoneSpec::Utc,
+        Ok(TimeZoneSpec::Named(tz)) if system.contains('/') => TimeZoneSpec::Named(tz),
+        _ => {
+            log::warn!(
+                "MySQL: System timezone '{}' is not an IANA name, keeping TIMESTAMP values as returned",
+                system
+            );
+            TimeZoneSpec::Original
+        }
+    }
+}
+
 pub async fn get_tables(pool: &MySqlPool) -> Result<Vec<TableInfo>, String> {
     let query = r#"
         SELECT 
@@ -84,7 +192,10 @@ pub async fn get_table_structure(pool: &MySqlPool, table: &str) -> Result<TableS
             DATA_TYPE as data_type,
             IS_NULLABLE as is_nullable,
             COLUMN_DEFAULT as column_default,
-            COLUMN_KEY as column_key
+            COLUMN_KEY as column_key,
+            CAST(CHARACTER_MAXIMUM_LENGTH AS SIGNED) as max_length,
+            CASE WHEN DATA_TYPE IN ('decimal', 'numeric') THEN CAST(NUMERIC_PRECISION AS SIGNED) END as numeric_precision,
+            CASE WHEN DATA_TYPE IN ('decimal', 'numeric') THEN CAST(NUMERIC_SCALE AS SIGNED) END as numeric_scale
         FROM information_schema.COLUMNS
         WHERE TABLE_NAME = ?
         AND TABLE_SCHEMA = DATABASE()
@@ -126,6 +237,13 @@ pub async fn get_table_structure(pool: &MySqlPool, table: &str) -> Result<TableS
             let is_nullable = get_string("is_nullable");
             let column_default = get_optional_string("column_default");
             let column_key = get_string("column_key");
+            // LONGTEXT reports 4294967295
+            let get_optional_int = |col: &str| -> Option<i32> {
+                row.try_get::<Option<i64>, _>(col)
+                    .ok()
+                    .flatten()
+                    .map(|v| i32::try_from(v).unwrap_or(i32::MAX))
+            };
             
             ColumnInfo {
                 name: column_name,
@@ -138,7 +256,9 @@ pub async fn get_table_structure(pool: &MySqlPool, table: &str) -> Result<TableS
                 foreign_key_table: None,
                 foreign_key_column: None,
                 is_auto_increment: None,
-                max_length: None,
+                max_length: get_optional_int("max_length"),
+                numeric_precision: get_optional_int("numeric_precision"),
+                numeric_scale: get_optional_int("numeric_scale"),
                 check_constraint: None,
             }
         })
@@ -150,7 +270,7 @@ pub async fn get_table_structure(pool: &MySqlPool, table: &str) -> Result<TableS
     })
 }
 
-pub async fn execute_query(pool: &MySqlPool, sql: &str) -> Result<QueryResult, String> {
+pub async fn execute_query(pool: &MySqlPool, sql: &str, temporal: &TemporalFormat) -> Result<QueryResult, String> {
     let start = Instant::now();
     
     let sql_upper = sql.trim().to_uppercase();
@@ -170,14 +290,12 @@ pub async fn execute_query(pool: &MySqlPool, sql: &str) -> Result<QueryResult, S
         let execution_time = start.elapsed().as_millis() as u64;
         
         return Ok(QueryResult {
-            columns: vec![ResultColumn {
-                name: "affected_rows".to_string(),
-                type_name: "BIGINT".to_string(),
-            }],
+            columns: vec![ResultColumn::new("affected_rows", "BIGINT")],
             rows: vec![serde_json::json!({"affected_rows": affected})],
             row_count: affected as usize,
             execution_time_ms: execution_time,
             truncated: false,
+            confirmation_required: None,
         });
     }
     
@@ -189,31 +307,23 @@ pub async fn execute_query(pool: &MySqlPool, sql: &str) -> Result<QueryResult, S
     
     let execution_time = start.elapsed().as_millis() as u64;
     
-    if rows.is_empty() {
-        return Ok(QueryResult {
-            columns: vec![],
-            rows: vec![],
-            row_count: 0,
-            execution_time_ms: execution_time,
-            truncated: false,
-        });
-    }
-    
-    let columns: Vec<ResultColumn> = rows[0]
-        .columns()
-        .iter()
-        .map(|c| ResultColumn {
-            name: c.name().to_string(),
-            type_name: c.type_info().name().to_string(),
-        })
-        .collect();
+    // Rows carry their columns; an empty result only reports them through a
+    // description of the statement, which costs another round trip
+    let columns: Vec<ResultColumn> = match rows.first() {
+        Some(row) => row
+            .columns()
+            .iter()
+            .map(|c| ResultColumn::new(c.name(), c.type_info().name()))
+            .collect(),
+        None => describe_columns(pool, sql).await.unwrap_or_default(),
+    };
     
     let mut result_rows: Vec<serde_json::Value> = Vec::new();
     
     for row in &rows {
         let mut obj = serde_json::Map::new();
         for (i, col) in columns.iter().enumerate() {
-            let value = row_value_to_json(&row, i);
+            let value = row_value_to_json(&row, i, temporal, BinaryFormat::Preview);
             obj.insert(col.name.clone(), value);
         }
         result_rows.push(serde_json::Value::Object(obj));
@@ -227,10 +337,98 @@ pub async fn execute_query(pool: &MySqlPool, sql: &str) -> Result<QueryResult, S
         row_count,
         execution_time_ms: execution_time,
         truncated: false,
+        confirmation_required: None,
     })
 }
 
-fn row_value_to_json(row: &sqlx::mysql::MySqlRow, idx: usize) -> serde_json::Value {
+/// Stream the rows of a query into `sink` one at a time. Scripts with several
+/// statements are streamed as one result set per statement. Returns the row count.
+pub async fn stream_query(pool: &MySqlPool, sql: &str, temporal: &TemporalFormat, sink: &mut dyn RowSink) -> Result<u64, String> {
+    use futures::{StreamExt, TryStreamExt};
+    use sqlx::Either;
+    
+    // Statements that cannot be prepared (e.g. several statements at once) run
+    // unprepared; their column metadata then comes from the rows themselves
+    let mut columns = describe_columns(pool, sql).await;
+    let mut stream = match columns {
+        Some(_) => sqlx::query(sql).fetch(pool).map_ok(Either::Right).boxed(),
+        None => sqlx::raw_sql(sql).fetch_many(pool),
+    };
+    
+    let mut count = 0u64;
+    let mut in_result = false;
+    let mut any_result = false;
+    
+    while let Some(item) = stream.try_next().await.map_err(|e| format!("Query execution failed: {}", e))? {
+        match item {
+            Either::Left(_) => {
+                // End of a statement; an empty result still gets its header
+                if !in_result {
+                    if let Some(described) = columns.take().filter(|c| !c.is_empty()) {
+                        sink.begin(&described)?;
+                        any_result = true;
+                    }
+                }
+                columns = None;
+                in_result = false;
+            }
+            Either::Right(row) => {
+                if !in_result {
+                    let described = columns
+                        .take()
+                        .filter(|c| c.len() == row.len())
+                        .unwrap_or_else(|| {
+                            row.columns()
+                                .iter()
+                                .map(|c| ResultColumn::new(c.name(), c.type_info().name()))
+                                .collect()
+                        });
+                    sink.begin(&described)?;
+                    in_result = true;
+                    any_result = true;
+                }
+                
+                let values: Vec<serde_json::Value> = (0..row.len())
+                    .map(|i| row_value_to_json(&row, i, temporal, BinaryFormat::Full))
+                    .collect();
+                sink.write_row(&values)?;
+                count += 1;
+            }
+        }
+    }
+    
+    if !any_result {
+        sink.begin(columns.as_deref().unwrap_or_default())?;
+    }
+    
+    log::info!("MySQL: Streamed {} rows", count);
+    Ok(count)
+}
+
+/// Describe the result columns of a statement without running it, including nullability.
+async fn describe_columns(pool: &MySqlPool, sql: &str) -> Option<Vec<ResultColumn>> {
+    let describe = match pool.describe(sql).await {
+        Ok(d) => d,
+        Err(e) => {
+            log::debug!("MySQL: Could not describe query: {}", e);
+            return None;
+        }
+    };
+    
+    let columns = describe
+        .columns()
+        .iter()
+        .enumerate()
+        .map(|(i, c)| ResultColumn {
+            nullable: describe.nullable(i),
+            ..ResultColumn::new(c.name(), c.type_info().name())
+        })
+        .collect();
+    
+    Some(columns)
+}
+
+fn row_value_to_json(row: &sqlx::mysql::MySqlRow, idx: usize, temporal: &TemporalFormat, binary: BinaryFormat) -> serde_json::Value {
     // Try unsigned integers first (common for MySQL IDs)
     if let Ok(v) = row.try_get::<u64, _>(idx) {
         return serde_json::Value::Number(v.into());
@@ -260,16 +458,17 @@ fn row_value_to_json(row: &sqlx::mysql::MySqlRow, idx: usize) -> serde_json::Val
     }
     // Date and Time types - IMPORTANT for MySQL dates
     if let Ok(v) = row.try_get::<chrono::NaiveDate, _>(idx) {
-        return serde_json::Value::String(v.format("%Y-%m-%d").to_string());
+        return serde_json::Value::String(temporal::format_date(v));
     }
     if let Ok(v) = row.try_get::<chrono::NaiveDateTime, _>(idx) {
-        return serde_json::Value::String(v.format("%Y-%m-%d %H:%M:%S").to_string());
+        // TIMESTAMP is an instant rendered in the session timezone, DATETIME is wall-clock time
+        if row.column(idx).type_info().name() == "TIMESTAMP" {
+            return serde_json::Value::String(temporal.format_session_local(v));
+        }
+        return serde_json::Value::String(temporal::format_datetime(v));
     }
     if let Ok(v) = row.try_get::<chrono::NaiveTime, _>(idx) {
-        return serde_json::Value::String(v.format("%H:%M:%S").to_string());
-    }
-    if let Ok(v) = row.try_get::<chrono::DateTime<chrono::Utc>, _>(idx) {
-        return serde_json::Value::String(v.format("%Y-%m-%d %H:%M:%S").to_string());
+        return serde_json::Value::String(temporal::format_time(v));
     }
     // Strings
     if let Ok(v) = row.try_get::<String, _>(idx) {
@@ -283,12 +482,16 @@ fn row_value_to_json(row: &sqlx::mysql::MySqlRow, idx: usize) -> serde_json::Val
     }
     // Try bytes (BLOB, BINARY, VARBINARY) - convert to string if valid UTF-8, otherwise hex
     if let Ok(v) = row.try_get::<Vec<u8>, _>(idx) {
+        // Large values are never inlined, whatever their content
+        if binary == BinaryFormat::Preview && v.len() > super::BLOB_PREVIEW_THRESHOLD {
+            return super::binary_to_json(&v, "0x", binary);
+        }
         // Try to convert to UTF-8 string first
         if let Ok(s) = String::from_utf8(v.clone()) {
             return serde_json::Value::String(s);
         }
         // Otherwise return as hex
-        return serde_json::Value::String(format!("0x{}", hex::encode(v)));
+        return super::binary_to_json(&v, "0x", binary);
     }
     // JSON values
     if let Ok(v) = row.try_get::<serde_json::Value, _>(idx) {
@@ -302,6 +505,313 @@ fn row_value_to_json(row: &sqlx::mysql::MySqlRow, idx: usize) -> serde_json::Val
     serde_json::Value::Null
 }
 
+/// Read a cell into `sink` a chunk at a time, in one consistent-snapshot
+/// transaction so every chunk comes from the same version of the row.
+pub async fn read_cell(
+    pool: &MySqlPool,
+    table: &str,
+    column: &str,
+    predicate: &str,
+    sink: &mut CellSink,
+) -> Result<(), String> {
+    let mut conn = pool.acquire().await.map_err(|e| format!("Failed to get connection: {}", e))?;
+    // Applies to the next transaction only
+    sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ")
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+    let mut tx = sqlx::Connection::begin_with(&mut *conn, "START TRANSACTION WITH CONSISTENT SNAPSHOT, READ ONLY")
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    loop {
+        let chunk = read_cell_chunk(&mut tx, table, column, predicate, sink.offset(), CELL_CHUNK_SIZE).await?;
+        if !sink.write(chunk).await? {
+            break;
+        }
+    }
+
+    tx.commit().await.map_err(|e| format!("Failed to commit transaction: {}", e))
+}
+
+async fn read_cell_chunk(
+    conn: &mut sqlx::MySqlConnection,
+    table: &str,
+    column: &str,
+    predicate: &str,
+    offset: usize,
+    len: usize,
+) -> Result<Option<(Vec<u8>, usize)>, String> {
+    let sql = format!(
+        "SELECT SUBSTRING({}, {}, {}) FROM {} WHERE {}",
+        column, offset + 1, len, table, predicate
+    );
+
+    let row = sqlx::query(&sql)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| format!("Failed to read cell: {}", e))?;
+
+    let Some(row) = row else {
+        return Ok(None);
+    };
+
+    // Text columns are counted in characters by SUBSTRING, binary ones in bytes
+    if let Ok(Some(v)) = row.try_get::<Option<String>, _>(0) {
+        let units = v.chars().count();
+        return Ok(Some((v.into_bytes(), units)));
+    }
+    if let Ok(Some(v)) = row.try_get::<Option<Vec<u8>>, _>(0) {
+        let units = v.len();
+        return Ok(Some((v, units)));
+    }
+
+    Ok(None)
+}
+
+pub async fn write_cell(
+    pool: &MySqlPool,
+    table: &str,
+    column: &str,
+    predicate: &str,
+    source: &mut CellSource,
+) -> Result<u64, String> {
+    // The whole value travels in one packet, which the server refuses above max_allowed_packet
+    let max_packet: u64 = sqlx::query_scalar("SELECT CAST(@@max_allowed_packet AS UNSIGNED)")
+        .fetch_one(pool)
+        .await
+        .map_err(|e| format!("Failed to read max_allowed_packet: {}", e))?;
+    if source.remaining() + PACKET_HEADROOM > max_packet {
+        return Err(format!(
+            "File is {} bytes, too large for the server's max_allowed_packet of {} bytes",
+            source.remaining(),
+            max_packet
+        ));
+    }
+
+    let sql = format!("UPDATE {} SET {} = ? WHERE {}", table, column, predicate);
+    let result = sqlx::query(&sql)
+        .bind(source.read_all().await?)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to write cell: {}", e))?;
+    Ok(result.rows_affected())
+}
+
+pub async fn explain_query(pool: &MySqlPool, sql: &str, analyze: bool) -> Result<QueryPlan, String> {
+    // EXPLAIN ANALYZE only has a tree format; plain EXPLAIN gives JSON with estimates
+    let explain = if analyze {
+        format!("EXPLAIN ANALYZE {}", sql)
+    } else {
+        format!("EXPLAIN FORMAT=JSON {}", sql)
+    };
+    
+    // ANALYZE executes the statement, so run it in a transaction that is always rolled back
+    let mut tx = pool.begin().await.map_err(|e| format!("Failed to start transaction: {}", e))?;
+    
+    let result = sqlx::query(&explain).fetch_one(&mut *tx).await;
+    
+    tx.rollback().await.map_err(|e| format!("Failed to roll back: {}", e))?;
+    
+    let row = result.map_err(|e| format!("Failed to explain query: {}", e))?;
+    let raw = row
+        .try_get::<String, _>(0)
+        .or_else(|_| row.try_get::<Vec<u8>, _>(0).map(|bytes| String::from_utf8_lossy(&bytes).to_string()))
+        .map_err(|e| format!("Failed to read plan: {}", e))?;
+    
+    let root = if analyze {
+        plan_node_from_tree(&raw)
+    } else {
+        let plan: serde_json::Value = serde_json::from_str(&raw)
+            .map_err(|e| format!("Failed to parse plan: {}", e))?;
+        plan_node_from_json("query_block", plan.get("query_block").unwrap_or(&plan))
+    };
+    
+    Ok(QueryPlan {
+        root,
+        raw,
+        analyzed: analyze,
+        planning_time_ms: None,
+        execution_time_ms: None,
+    })
+}
+
+/// Normalize a node of `EXPLAIN FORMAT=JSON` output. Nested operations
+/// (`nested_loop`, `ordering_operation`, `table`, ...) become children.
+fn plan_node_from_json(key: &str, value: &serde_json::Value) -> PlanNode {
+    let mut node = PlanNode::new(humanize_plan_key(key));
+    
+    let Some(obj) = value.as_object() else {
+        return node;
+    };
+    
+    for (key, value) in obj {
+        match (key.as_str(), value) {
+            ("table_name", serde_json::Value::String(table)) => {
+                node.relation = Some(table.clone());
+            }
+            ("access_type", serde_json::Value::String(access)) => {
+                node.node_type = format!("Table access: {}", access);
+            }
+            ("cost_info", serde_json::Value::Object(cost)) => {
+                node.total_cost = cost
+                    .get("query_cost")
+                    .or_else(|| cost.get("prefix_cost"))
+                    .and_then(json_number);
+                for (name, v) in cost {
+                    node.details.insert(name.clone(), v.clone());
+                }
+            }
+            ("rows_produced_per_join", v) => {
+                node.estimated_rows = json_number(v);
+            }
+            (_, serde_json::Value::Object(_)) => {
+                node.children.push(plan_node_from_json(key, value));
+            }
+            (_, serde_json::Value::Array(items)) if items.iter().all(|i| i.is_object()) && !items.is_empty() => {
+                // Arrays such as nested_loop hold wrapper objects like {"table": {...}}
+                let mut group = PlanNode::new(humanize_plan_key(key));
+                for item in items {
+                    group.children.extend(plan_node_from_json(key, item).children);
+                }
+                node.children.push(group);
+            }
+            _ => {
+                node.details.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    
+    node
+}
+
+fn humanize_plan_key(key: &str) -> String {
+    let text = key.replace('_', " ");
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => text,
+    }
+}
+
+/// MySQL reports costs as strings ("1.25") and row counts as numbers.
+fn json_number(value: &serde_json::Value) -> Option<f64> {
+    match value {
+        serde_json::Value::Number(n) => n.as_f64(),
+        serde_json::Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+/// Parse the indented tree printed by `EXPLAIN ANALYZE`, e.g.
+/// `-> Table scan on t1  (cost=0.65 rows=4) (actual time=0.022..0.026 rows=4 loops=1)`.
+fn plan_node_from_tree(text: &str) -> PlanNode {
+    let mut root = PlanNode::new("Query");
+    let mut stack: Vec<(usize, PlanNode)> = Vec::new();
+    
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        let Some(description) = trimmed.strip_prefix("->") else {
+            continue;
+        };
+        let indent = line.len() - trimmed.len();
+        
+        while stack.last().map(|(level, _)| *level >= indent).unwrap_or(false) {
+            let (_, child) = stack.pop().unwrap();
+            attach_plan_child(&mut stack, &mut root, child);
+        }
+        
+        stack.push((indent, plan_node_from_tree_line(description.trim())));
+    }
+    
+    while let Some((_, child)) = stack.pop() {
+        attach_plan_child(&mut stack, &mut root, child);
+    }
+    
+    // A single top-level operator is the real root
+    if root.children.len() == 1 {
+        return root.children.pop().unwrap();
+    }
+    root
+}
+
+fn attach_plan_child(stack: &mut [(usize, PlanNode)], root: &mut PlanNode, child: PlanNode) {
+    match stack.last_mut() {
+        Some((_, parent)) => parent.children.push(child),
+        None => root.children.push(child),
+    }
+}
+
+fn plan_node_from_tree_line(line: &str) -> PlanNode {
+    let description_end = [line.find("  (cost="), line.find(" (cost="), line.find(" (actual "), line.find(" (never executed)")]
+        .into_iter()
+        .flatten()
+        .min()
+        .unwrap_or(line.len());
+    let description = line[..description_end].trim();
+    
+    let mut node = PlanNode::new(description);
+    
+    if let Some(pos) = description.find(" on ") {
+        node.relation = description[pos + 4..]
+            .split_whitespace()
+            .next()
+            .map(|s| s.to_string());
+    }
+    
+    if let Some(cost) = plan_tree_segment(line, "(cost=") {
+        for (name, value) in plan_tree_pairs(&format!("cost={}", cost)) {
+            match name.as_str() {
+                "cost" => match value.split_once("..") {
+                    Some((startup, total)) => {
+                        node.startup_cost = startup.parse().ok();
+                        node.total_cost = total.parse().ok();
+                    }
+                    None => node.total_cost = value.parse().ok(),
+                },
+                "rows" => node.estimated_rows = value.parse().ok(),
+                _ => {}
+            }
+        }
+    }
+    
+    if let Some(actual) = plan_tree_segment(line, "(actual ") {
+        for (name, value) in plan_tree_pairs(actual) {
+            match name.as_str() {
+                "time" => {
+                    if let Some((first, total)) = value.split_once("..") {
+                        node.actual_time_ms = total.parse().ok();
+                        if let Ok(first) = first.parse::<f64>() {
+                            node.details.insert("first_row_ms".to_string(), serde_json::json!(first));
+                        }
+                    }
+                }
+                "rows" => node.actual_rows = value.parse().ok(),
+                "loops" => node.loops = value.parse().ok(),
+                _ => {}
+            }
+        }
+    } else if line.contains("(never executed)") {
+        node.loops = Some(0);
+    }
+    
+    node
+}
+
+fn plan_tree_segment<'a>(line: &'a str, start: &str) -> Option<&'a str> {
+    let begin = line.find(start)? + start.len();
+    let end = line[begin..].find(')')? + begin;
+    Some(&line[begin..end])
+}
+
+fn plan_tree_pairs(segment: &str) -> Vec<(String, String)> {
+    segment
+        .split_whitespace()
+        .filter_map(|part| part.split_once('='))
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect()
+}
+
 pub async fn list_databases(pool: &MySqlPool) -> Result<Vec<String>, String> {
     let rows = sqlx::query("SHOW DATABASES")
         .fetch_all(pool)
@@ -331,3 +841,262 @@ pub async fn list_databases(pool: &MySqlPool) -> Result<Vec<String>, String> {
     Ok(databases)
 }
 
+
+/// Read a text column that the server may send as either a string or bytes.
+fn text_column(row: &sqlx::mysql::MySqlRow, col: &str) -> Option<String> {
+    if let Ok(v) = row.try_get::<Option<String>, _>(col) {
+        return v;
+    }
+    row.try_get::<Option<Vec<u8>>, _>(col)
+        .ok()
+        .flatten()
+        .map(|bytes| String::from_utf8_lossy(&bytes).to_string())
+}
+
+/// Run a query whose columns are all read as text.
+async fn fetch_text_rows(pool: &MySqlPool, sql: &str, columns: &[&str]) -> Result<Vec<Vec<Option<String>>>, sqlx::Error> {
+    let rows = sqlx::query(sql).fetch_all(pool).await?;
+    Ok(rows
+        .iter()
+        .map(|row| columns.iter().map(|col| text_column(row, col)).collect())
+        .collect())
+}
+
+/// Read tables and views of the current database. Table definitions come
+/// from `SHOW CREATE TABLE`, so engine options and collations are kept.
+pub async fn get_schema(pool: &MySqlPool) -> Result<SchemaSnapshot, String> {
+    let fail = |e: sqlx::Error| format!("Failed to read schema: {}", e);
+    let field = |row: &[Option<String>], idx: usize| row[idx].clone().unwrap_or_default();
+    
+    let columns = fetch_text_rows(pool, r#"
+        SELECT 
+            c.TABLE_NAME AS table_name,
+            c.COLUMN_NAME AS column_name,
+            c.COLUMN_TYPE AS column_type,
+            c.DATA_TYPE AS data_type,
+            c.IS_NULLABLE AS is_nullable,
+            c.COLUMN_DEFAULT AS column_default,
+            c.EXTRA AS extra,
+            c.GENERATION_EXPRESSION AS generation_expression
+        FROM information_schema.COLUMNS c
+        JOIN information_schema.TABLES t ON t.TABLE_SCHEMA = c.TABLE_SCHEMA AND t.TABLE_NAME = c.TABLE_NAME
+        WHERE c.TABLE_SCHEMA = DATABASE()
+        AND t.TABLE_TYPE = 'BASE TABLE'
+        ORDER BY c.TABLE_NAME, c.ORDINAL_POSITION
+    "#, &["table_name", "column_name", "column_type", "data_type", "is_nullable", "column_default", "extra", "generation_expression"])
+        .await
+        .map_err(fail)?;
+    
+    let mut tables: Vec<TableDefinition> = Vec::new();
+    let mut index: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
+    
+    for row in &columns {
+        let table_name = field(row, 0);
+        let idx = *index.entry(table_name.clone()).or_insert_with(|| {
+            tables.push(TableDefinition {
+                schema: None,
+                name: table_name.clone(),
+                columns: Vec::new(),
+                primary_key: None,
+                unique_constraints: Vec::new(),
+                foreign_keys: Vec::new(),
+                checks: Vec::new(),
+                indexes: Vec::new(),
+                definition: None,
+            });
+            tables.len() - 1
+        });
+        
+        let extra = field(row, 6).to_uppercase();
+        let generated = row[7].clone().filter(|expr| !expr.is_empty());
+        tables[idx].columns.push(ColumnDefinition {
+            name: field(row, 1),
+            data_type: field(row, 2),
+            nullable: field(row, 4) == "YES",
+            default: match generated {
+                Some(_) => None,
+                None => row[5].as_deref().map(|d| default_expression(d, &field(row, 3), &extra)),
+            },
+            identity: extra.contains("AUTO_INCREMENT").then(|| "AUTO_INCREMENT".to_string()),
+            generated_stored: extra.contains("STORED GENERATED"),
+            generated,
+        });
+    }
+    
+    let statistics = fetch_text_rows(pool, r#"
+        SELECT 
+            TABLE_NAME AS table_name,
+            INDEX_NAME AS index_name,
+            CAST(NON_UNIQUE AS CHAR) AS non_unique,
+            COLUMN_NAME AS column_name,
+            CAST(SUB_PART AS CHAR) AS sub_part,
+            COLLATION AS collation
+        FROM information_schema.STATISTICS
+        WHERE TABLE_SCHEMA = DATABASE()
+        ORDER BY TABLE_NAME, INDEX_NAME = 'PRIMARY' DESC, INDEX_NAME, SEQ_IN_INDEX
+    "#, &["table_name", "index_name", "non_unique", "column_name", "sub_part", "collation"])
+        .await
+        .map_err(fail)?;
+    
+    for row in &statistics {
+        let Some(&idx) = index.get(&field(row, 0)) else {
+            continue;
+        };
+        let table = &mut tables[idx];
+        let name = field(row, 1);
+        
+        // Functional key parts have no column name
+        let mut column = row[3].as_deref().map_or_else(|| "(expression)".to_string(), |c| dialect::quote_ident(&DatabaseType::MySQL, c));
+        if let Some(length) = &row[4] {
+            column = format!("{}({})", column, length);
+        }
+        if row[5].as_deref() == Some("D") {
+            column.push_str(" DESC");
+        }
+        
+        if name == "PRIMARY" {
+            let key = table.primary_key.get_or_insert_with(|| KeyConstraint { name: None, columns: Vec::new() });
+            key.columns.push(field(row, 3));
+            continue;
+        }
+        match table.indexes.iter_mut().find(|i| i.name == name) {
+            Some(index) => index.columns.push(column),
+            None => table.indexes.push(IndexDefinition {
+                name,
+                columns: vec![column],
+                unique: field(row, 2) == "0",
+                definition: None,
+            }),
+        }
+    }
+    
+    let foreign_keys = fetch_text_rows(pool, r#"
+        SELECT 
+            k.TABLE_NAME AS table_name,
+            k.CONSTRAINT_NAME AS constraint_name,
+            k.COLUMN_NAME AS column_name,
+            k.REFERENCED_TABLE_NAME AS referenced_table,
+            k.REFERENCED_COLUMN_NAME AS referenced_column,
+            r.UPDATE_RULE AS update_rule,
+            r.DELETE_RULE AS delete_rule
+        FROM information_schema.KEY_COLUMN_USAGE k
+        JOIN information_schema.REFERENTIAL_CONSTRAINTS r
+            ON r.CONSTRAINT_SCHEMA = k.CONSTRAINT_SCHEMA
+            AND r.CONSTRAINT_NAME = k.CONSTRAINT_NAME
+            AND r.TABLE_NAME = k.TABLE_NAME
+        WHERE k.TABLE_SCHEMA = DATABASE()
+        AND k.REFERENCED_TABLE_NAME IS NOT NULL
+        ORDER BY k.TABLE_NAME, k.CONSTRAINT_NAME, k.ORDINAL_POSITION
+    "#, &["table_name", "constraint_name", "column_name", "referenced_table", "referenced_column", "update_rule", "delete_rule"])
+        .await
+        .map_err(fail)?;
+    
+    for row in &foreign_keys {
+        let Some(&idx) = index.get(&field(row, 0)) else {
+            continue;
+        };
+        let name = field(row, 1);
+        let table = &mut tables[idx];
+        match table.foreign_keys.iter_mut().find(|fk| fk.name.as_deref() == Some(name.as_str())) {
+            Some(fk) => {
+                fk.columns.push(field(row, 2));
+                fk.referenced_columns.push(field(row, 4));
+            }
+            None => table.foreign_keys.push(ForeignKeyDefinition {
+                name: Some(name),
+                columns: vec![field(row, 2)],
+                referenced_table: field(row, 3),
+                referenced_columns: vec![field(row, 4)],
+                on_update: row[5].clone().filter(|rule| rule != "NO ACTION"),
+                on_delete: row[6].clone().filter(|rule| rule != "NO ACTION"),
+            }),
+        }
+    }
+    
+    // CHECK constraints are only reported from MySQL 8.0.16 on
+    let checks = fetch_text_rows(pool, r#"
+        SELECT tc.TABLE_NAME AS table_name, cc.CONSTRAINT_NAME AS constraint_name, cc.CHECK_CLAUSE AS check_clause
+        FROM information_schema.CHECK_CONSTRAINTS cc
+        JOIN information_schema.TABLE_CONSTRAINTS tc
+            ON tc.CONSTRAINT_SCHEMA = cc.CONSTRAINT_SCHEMA
+            AND tc.CONSTRAINT_NAME = cc.CONSTRAINT_NAME
+            AND tc.CONSTRAINT_TYPE = 'CHECK'
+        WHERE cc.CONSTRAINT_SCHEMA = DATABASE()
+        ORDER BY tc.TABLE_NAME, cc.CONSTRAINT_NAME
+    "#, &["table_name", "constraint_name", "check_clause"])
+        .await
+        .unwrap_or_default();
+    
+    for row in &checks {
+        if let Some(&idx) = index.get(&field(row, 0)) {
+            tables[idx].checks.push(CheckConstraint { name: row[1].clone(), expression: field(row, 2) });
+        }
+    }
+    
+    for table in &mut tables {
+        let sql = format!("SHOW CREATE TABLE {}", dialect::quote_ident(&DatabaseType::MySQL, &table.name));
+        let row = fetch_text_rows(pool, &sql, &["Create Table"]).await.map_err(fail)?;
+        table.definition = row.into_iter().next().and_then(|mut r| r.remove(0));
+    }
+    
+    let view_names = fetch_text_rows(pool, r#"
+        SELECT TABLE_NAME AS table_name
+        FROM information_schema.VIEWS
+        WHERE TABLE_SCHEMA = DATABASE()
+        ORDER BY TABLE_NAME
+    "#, &["table_name"])
+        .await
+        .map_err(fail)?;
+    
+    let mut views = Vec::new();
+    for row in &view_names {
+        let name = field(row, 0);
+        let sql = format!("SHOW CREATE VIEW {}", dialect::quote_ident(&DatabaseType::MySQL, &name));
+        let row = fetch_text_rows(pool, &sql, &["Create View"]).await.map_err(fail)?;
+        if let Some(definition) = row.into_iter().next().and_then(|mut r| r.remove(0)) {
+            views.push(ViewDefinition { schema: None, name, definition: strip_definer(&definition) });
+        }
+    }
+    
+    Ok(SchemaSnapshot { tables, views, sequences: Vec::new() })
+}
+
+/// `COLUMN_DEFAULT` as an SQL expression. MySQL reports string defaults
+/// unquoted and marks expression defaults with `DEFAULT_GENERATED`; MariaDB
+/// quotes them itself.
+fn default_expression(default: &str, data_type: &str, extra: &str) -> String {
+    let numeric = ["int", "decimal", "numeric", "float", "double", "real", "bit", "year"]
+        .iter()
+        .any(|t| data_type.to_lowercase().contains(t));
+    let upper = default.to_uppercase();
+    
+    if extra.contains("DEFAULT_GENERATED") {
+        if ["CURRENT_TIMESTAMP", "NOW(", "LOCALTIME"].iter().any(|f| upper.starts_with(f)) {
+            default.to_string()
+        } else {
+            format!("({})", default)
+        }
+    } else if numeric || default.starts_with('\'') || upper == "NULL" || upper.starts_with("CURRENT_TIMESTAMP") {
+        default.to_string()
+    } else {
+        dialect::quote_string(&DatabaseType::MySQL, default)
+    }
+}
+
+/// Remove the `DEFINER=user@host` clause so the view can be created by any account.
+fn strip_definer(definition: &str) -> String {
+    let Some(start) = definition.find("DEFINER=") else {
+        return definition.to_string();
+    };
+    let mut in_quotes = false;
+    let end = definition[start..]
+        .char_indices()
+        .find(|&(_, c)| {
+            if c == '`' {
+                in_quotes = !in_quotes;
+            }
+            c == ' ' && !in_quotes
+        })
+        .map_or(definition.len(), |(i, _)| start + i + 1);
+    format!("{}{}", &definition[..start], &definition[end..])
+}
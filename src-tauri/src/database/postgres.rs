@@ -1,17 +1,99 @@
-use sqlx::{postgres::PgPoolOptions, PgPool, Row, Column, TypeInfo};
+use sqlx::{postgres::{types::PgTimeTz, PgConnectOptions, PgPoolOptions, PgSslMode}, PgPool, Row, Column, TypeInfo, Executor};
 use uuid::Uuid;
 use crate::models::*;
+use super::temporal::{self, TemporalFormat, TimeZoneSpec};
+use super::dialect::{self, SqlValue};
+use super::{dsn, BinaryFormat, CellSink, CellSource, RowSink, CELL_CHUNK_SIZE};
 use std::time::Instant;
 
+/// Bytes of COPY data buffered before each send.
+const COPY_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Settings for each server to try, in order: the Unix socket, or every host
+/// listed in the connection as libpq does with `host=a,b`.
+fn connect_options(config: &ConnectionConfig) -> Result<Vec<PgConnectOptions>, String> {
+    if let Some(socket) = config.socket.as_deref().filter(|s| !s.is_empty()) {
+        return Ok(vec![server_options(config)?.socket(socket)]);
+    }
+    
+    let hosts = dsn::hosts(config);
+    if hosts.is_empty() {
+        return Err("PostgreSQL host is required".to_string());
+    }
+    hosts
+        .into_iter()
+        .map(|(host, port)| server_options(&ConnectionConfig { host, port, ..config.clone() }))
+        .collect()
+}
+
+/// Open a pool on the first server that accepts connections.
+async fn connect_first(pool: PgPoolOptions, servers: Vec<PgConnectOptions>) -> Result<PgPool, sqlx::Error> {
+    let mut last_error = None;
+    for options in servers {
+        let server = format!("{}:{}", options.get_host(), options.get_port());
+        match pool.clone().connect_with(options).await {
+            Ok(pool) => return Ok(pool),
+            Err(e) => {
+                log::warn!("PostgreSQL: Could not connect to {}: {}", server, e);
+                last_error = Some(e);
+            }
+        }
+    }
+    Err(last_error.unwrap_or_else(|| sqlx::Error::Configuration("No PostgreSQL host to connect to".into())))
+}
+
+/// Settings from the connection URL with the TLS options applied.
+fn server_options(config: &ConnectionConfig) -> Result<PgConnectOptions, String> {
+    let tls = &config.tls;
+    tls.validate()?;
+    // The driver skips parameters it does not know without a word
+    if let Some(key) = config.parameters.keys().find(|k| !dsn::POSTGRES_PARAMETERS.contains(&k.as_str())) {
+        return Err(format!("Unsupported PostgreSQL connection parameter: {}", key));
+    }
+    
+    let mut options: PgConnectOptions = config
+        .connection_string()
+        .parse()
+        .map_err(|e| format!("Invalid PostgreSQL connection settings: {}", e))?;
+    options = options.ssl_mode(match tls.mode {
+        TlsMode::Disable => PgSslMode::Disable,
+        TlsMode::Prefer => PgSslMode::Prefer,
+        TlsMode::Require => PgSslMode::Require,
+        TlsMode::VerifyCa => PgSslMode::VerifyCa,
+        TlsMode::VerifyFull => PgSslMode::VerifyFull,
+    });
+    if let Some(path) = tls.ca_cert_path.as_deref().filter(|p| !p.is_empty()) {
+        options = options.ssl_root_cert(path);
+    }
+    if let Some(path) = tls.client_cert_path.as_deref().filter(|p| !p.is_empty()) {
+        options = options.ssl_client_cert(path);
+    }
+    if let Some(path) = tls.client_key_path.as_deref().filter(|p| !p.is_empty()) {
+        options = options.ssl_client_key(path);
+    }
+    if config.read_only {
+        // Every transaction starts read-only; the statement check keeps it that way
+        options = options.options([("default_transaction_read_only", "on")]);
+    }
+    Ok(options)
+}
+
 pub async fn test_connection(config: &ConnectionConfig) -> TestConnectionResult {
-    let conn_str = config.connection_string();
+    let options = match connect_options(config) {
+        Ok(options) => options,
+        Err(e) => {
+            return TestConnectionResult {
+                success: false,
+                message: e,
+                version: None,
+            }
+        }
+    };
     
-    match PgPoolOptions::new()
+    let pool = PgPoolOptions::new()
         .max_connections(1)
-        .acquire_timeout(std::time::Duration::from_secs(5))
-        .connect(&conn_str)
-        .await
-    {
+        .acquire_timeout(std::time::Duration::from_secs(5));
+    match connect_first(pool, options).await {
         Ok(pool) => {
             let version: Result<(String,), _> = sqlx::query_as("SELECT version()")
                 .fetch_one(&pool)
@@ -39,17 +121,51 @@ pub async fn test_connection(config: &ConnectionConfig) -> TestConnectionResult
 }
 
 pub async fn connect(config: &ConnectionConfig) -> Result<PgPool, String> {
-    let conn_str = config.connection_string();
+    let options = connect_options(config)?;
+    let settings = config.pool.settings(&config.db_type)?;
+    let init_sql = config.init_sql.clone().filter(|sql| !sql.trim().is_empty());
+    if let (Some(sql), Some(first)) = (&init_sql, options.first()) {
+        super::check_init_sql(first, sql, settings.acquire_timeout).await?;
+    }
     
-    PgPoolOptions::new()
-        .max_connections(20)
-        .acquire_timeout(std::time::Duration::from_secs(30))
-        .idle_timeout(std::time::Duration::from_secs(600))
-        .connect(&conn_str)
+    let pool = PgPoolOptions::new()
+        .max_connections(settings.max_connections)
+        .min_connections(settings.min_connections)
+        .acquire_timeout(settings.acquire_timeout)
+        .idle_timeout(settings.idle_timeout)
+        .max_lifetime(settings.max_lifetime)
+        .after_connect(move |conn, _| {
+            let init_sql = init_sql.clone();
+            Box::pin(async move {
+                if let Some(sql) = init_sql {
+                    conn.execute(sql.as_str()).await?;
+                }
+                Ok(())
+            })
+        });
+    connect_first(pool, options)
         .await
         .map_err(|e| format!("PostgreSQL connection failed: {}", e))
 }
 
+/// Session timezone reported by the server, used when no display timezone is configured.
+pub async fn session_timezone(pool: &PgPool) -> TimeZoneSpec {
+    let tz: Result<(String,), _> = sqlx::query_as("SHOW TimeZone")
+        .fetch_one(pool)
+        .await;
+    
+    match tz {
+        Ok((tz,)) => TimeZoneSpec::parse(&tz).unwrap_or_else(|e| {
+            log::warn!("PostgreSQL: {}, keeping UTC offsets", e);
+            TimeZoneSpec::Original
+        }),
+        Err(e) => {
+            log::warn!("PostgreSQL: Could not read session timezone: {}", e);
+            TimeZoneSpec::Original
+        }
+    }
+}
+
 pub async fn get_tables(pool: &PgPool) -> Result<Vec<TableInfo>, String> {
     let query = r#"
         SELECT 
@@ -80,13 +196,22 @@ pub async fn get_tables(pool: &PgPool) -> Result<Vec<TableInfo>, String> {
 }
 
 pub async fn get_table_structure(pool: &PgPool, table: &str) -> Result<TableStructure, String> {
+    // "schema.table" limits the lookup to one schema
+    let (schema, name) = match table.split_once('.') {
+        Some((schema, name)) => (Some(schema), name),
+        None => (None, table),
+    };
+    
     let query = r#"
         SELECT 
             c.column_name,
             c.data_type,
             c.is_nullable,
             c.column_default,
-            CASE WHEN pk.column_name IS NOT NULL THEN true ELSE false END as is_primary_key
+            CASE WHEN pk.column_name IS NOT NULL THEN true ELSE false END as is_primary_key,
+            c.character_maximum_length::int AS max_length,
+            CASE WHEN c.data_type = 'numeric' THEN c.numeric_precision::int END AS numeric_precision,
+            CASE WHEN c.data_type = 'numeric' THEN c.numeric_scale::int END AS numeric_scale
         FROM information_schema.columns c
         LEFT JOIN (
             SELECT ku.column_name
@@ -95,13 +220,16 @@ pub async fn get_table_structure(pool: &PgPool, table: &str) -> Result<TableStru
                 ON tc.constraint_name = ku.constraint_name
             WHERE tc.constraint_type = 'PRIMARY KEY'
             AND ku.table_name = $1
+            AND ku.table_schema = COALESCE($2, ku.table_schema)
         ) pk ON c.column_name = pk.column_name
         WHERE c.table_name = $1
+        AND c.table_schema = COALESCE($2, c.table_schema)
         ORDER BY c.ordinal_position
     "#;
     
     let rows = sqlx::query(query)
-        .bind(table)
+        .bind(name)
+        .bind(schema)
         .fetch_all(pool)
         .await
         .map_err(|e| format!("Failed to get table structure: {}", e))?;
@@ -119,7 +247,9 @@ pub async fn get_table_structure(pool: &PgPool, table: &str) -> Result<TableStru
             foreign_key_table: None,
             foreign_key_column: None,
             is_auto_increment: None,
-            max_length: None,
+            max_length: row.get("max_length"),
+            numeric_precision: row.get("numeric_precision"),
+            numeric_scale: row.get("numeric_scale"),
             check_constraint: None,
         })
         .collect();
@@ -130,7 +260,7 @@ pub async fn get_table_structure(pool: &PgPool, table: &str) -> Result<TableStru
     })
 }
 
-pub async fn execute_query(pool: &PgPool, sql: &str) -> Result<QueryResult, String> {
+pub async fn execute_query(pool: &PgPool, sql: &str, temporal: &TemporalFormat) -> Result<QueryResult, String> {
     println!("[DEBUG postgres] execute_query starting: {}", sql);
     let start = Instant::now();
     
@@ -151,14 +281,12 @@ pub async fn execute_query(pool: &PgPool, sql: &str) -> Result<QueryResult, Stri
         let execution_time = start.elapsed().as_millis() as u64;
         
         return Ok(QueryResult {
-            columns: vec![ResultColumn {
-                name: "affected_rows".to_string(),
-                type_name: "BIGINT".to_string(),
-            }],
+            columns: vec![ResultColumn::new("affected_rows", "BIGINT")],
             rows: vec![serde_json::json!({"affected_rows": affected})],
             row_count: affected as usize,
             execution_time_ms: execution_time,
             truncated: false,
+            confirmation_required: None,
         });
     }
     
@@ -180,24 +308,16 @@ pub async fn execute_query(pool: &PgPool, sql: &str) -> Result<QueryResult, Stri
     println!("[DEBUG postgres] Query fetched {} rows (truncated: {})", rows.len(), truncated);
     let execution_time = start.elapsed().as_millis() as u64;
     
-    if rows.is_empty() {
-        return Ok(QueryResult {
-            columns: vec![],
-            rows: vec![],
-            row_count: 0,
-            execution_time_ms: execution_time,
-            truncated: false,
-        });
-    }
-    
-    let columns: Vec<ResultColumn> = rows[0]
-        .columns()
-        .iter()
-        .map(|c| ResultColumn {
-            name: c.name().to_string(),
-            type_name: c.type_info().name().to_string(),
-        })
-        .collect();
+    // Rows carry their columns; an empty result only reports them through a
+    // description of the statement, which costs another round trip
+    let columns: Vec<ResultColumn> = match rows.first() {
+        Some(row) => row
+            .columns()
+            .iter()
+            .map(|c| ResultColumn::new(c.name(), c.type_info().name()))
+            .collect(),
+        None => describe_columns(pool, sql).await.unwrap_or_default(),
+    };
     
     println!("[DEBUG postgres] Columns: {:?}", columns);
     
@@ -206,7 +326,7 @@ pub async fn execute_query(pool: &PgPool, sql: &str) -> Result<QueryResult, Stri
     for (row_idx, row) in rows.iter().enumerate() {
         let mut obj = serde_json::Map::new();
         for (i, col) in columns.iter().enumerate() {
-            let value = row_value_to_json(&row, i);
+            let value = row_value_to_json(&row, i, temporal, BinaryFormat::Preview);
             obj.insert(col.name.clone(), value);
         }
         result_rows.push(serde_json::Value::Object(obj));
@@ -226,9 +346,150 @@ pub async fn execute_query(pool: &PgPool, sql: &str) -> Result<QueryResult, Stri
         row_count,
         execution_time_ms: execution_time,
         truncated,
+        confirmation_required: None,
     })
 }
-fn row_value_to_json(row: &sqlx::postgres::PgRow, idx: usize) -> serde_json::Value {
+
+/// Stream the rows of a query into `sink` one at a time. Scripts with several
+/// statements are streamed as one result set per statement. Returns the row count.
+pub async fn stream_query(pool: &PgPool, sql: &str, temporal: &TemporalFormat, sink: &mut dyn RowSink) -> Result<u64, String> {
+    use futures::{StreamExt, TryStreamExt};
+    use sqlx::Either;
+    
+    // Statements that cannot be prepared (e.g. several statements at once) run
+    // unprepared; their column metadata then comes from the rows themselves
+    let mut columns = describe_columns(pool, sql).await;
+    let mut stream = match columns {
+        Some(_) => sqlx::query(sql).fetch(pool).map_ok(Either::Right).boxed(),
+        None => sqlx::raw_sql(sql).fetch_many(pool),
+    };
+    
+    let mut count = 0u64;
+    let mut in_result = false;
+    let mut any_result = false;
+    
+    while let Some(item) = stream.try_next().await.map_err(|e| format!("Query execution failed: {}", e))? {
+        match item {
+            Either::Left(_) => {
+                // End of a statement; an empty result still gets its header
+                if !in_result {
+                    if let Some(described) = columns.take().filter(|c| !c.is_empty()) {
+                        sink.begin(&described)?;
+                        any_result = true;
+                    }
+                }
+                columns = None;
+                in_result = false;
+            }
+            Either::Right(row) => {
+                if !in_result {
+                    let described = columns
+                        .take()
+                        .filter(|c| c.len() == row.len())
+                        .unwrap_or_else(|| {
+                            row.columns()
+                                .iter()
+                                .map(|c| ResultColumn::new(c.name(), c.type_info().name()))
+                                .collect()
+                        });
+                    sink.begin(&described)?;
+                    in_result = true;
+                    any_result = true;
+                }
+                
+                let values: Vec<serde_json::Value> = (0..row.len())
+                    .map(|i| row_value_to_json(&row, i, temporal, BinaryFormat::Full))
+                    .collect();
+                sink.write_row(&values)?;
+                count += 1;
+            }
+        }
+    }
+    
+    if !any_result {
+        sink.begin(columns.as_deref().unwrap_or_default())?;
+    }
+    
+    log::info!("PostgreSQL: Streamed {} rows", count);
+    Ok(count)
+}
+
+/// Describe the result columns of a statement without running it, including
+/// nullability, numeric precision/scale and the source table where known.
+async fn describe_columns(pool: &PgPool, sql: &str) -> Option<Vec<ResultColumn>> {
+    let describe = match pool.describe(sql).await {
+        Ok(d) => d,
+        Err(e) => {
+            log::debug!("PostgreSQL: Could not describe query: {}", e);
+            return None;
+        }
+    };
+    
+    let mut columns: Vec<ResultColumn> = describe
+        .columns()
+        .iter()
+        .enumerate()
+        .map(|(i, c)| ResultColumn {
+            nullable: describe.nullable(i),
+            ..ResultColumn::new(c.name(), c.type_info().name())
+        })
+        .collect();
+    
+    let relation_ids: Vec<i64> = describe
+        .columns()
+        .iter()
+        .filter_map(|c| c.relation_id())
+        .map(|oid| oid.0 as i64)
+        .collect();
+    
+    if relation_ids.is_empty() {
+        return Some(columns);
+    }
+    
+    let query = r#"
+        SELECT 
+            a.attrelid::int8 AS relation_id,
+            a.attnum AS attnum,
+            n.nspname || '.' || c.relname AS source_table,
+            CASE WHEN a.atttypid = 1700 AND a.atttypmod >= 4
+                THEN ((a.atttypmod - 4) >> 16) & 65535 END AS precision,
+            CASE WHEN a.atttypid = 1700 AND a.atttypmod >= 4
+                THEN (a.atttypmod - 4) & 65535 END AS scale
+        FROM pg_attribute a
+        JOIN pg_class c ON c.oid = a.attrelid
+        JOIN pg_namespace n ON n.oid = c.relnamespace
+        WHERE a.attrelid::int8 = ANY($1)
+        AND a.attnum > 0
+    "#;
+    
+    let rows = match sqlx::query(query).bind(&relation_ids).fetch_all(pool).await {
+        Ok(rows) => rows,
+        Err(e) => {
+            log::debug!("PostgreSQL: Could not read column origins: {}", e);
+            return Some(columns);
+        }
+    };
+    
+    for (column, described) in columns.iter_mut().zip(describe.columns()) {
+        let (Some(relation_id), Some(attnum)) = (described.relation_id(), described.relation_attribute_no()) else {
+            continue;
+        };
+        
+        let origin = rows.iter().find(|row| {
+            row.get::<i64, _>("relation_id") == relation_id.0 as i64 && row.get::<i16, _>("attnum") == attnum
+        });
+        
+        if let Some(row) = origin {
+            column.source_table = row.get("source_table");
+            column.precision = row.get::<Option<i32>, _>("precision").map(|v| v as u32);
+            column.scale = row.get::<Option<i32>, _>("scale").map(|v| v as u32);
+        }
+    }
+    
+    Some(columns)
+}
+
+fn row_value_to_json(row: &sqlx::postgres::PgRow, idx: usize, temporal: &TemporalFormat, binary: BinaryFormat) -> serde_json::Value {
     use sqlx::ValueRef;
     
     // First check if the value is null
@@ -270,24 +531,29 @@ fn row_value_to_json(row: &sqlx::postgres::PgRow, idx: usize) -> serde_json::Val
         return serde_json::Value::Bool(v);
     }
     
-    // Try chrono datetime with timezone (timestamptz)
+    // Try chrono datetime with timezone (timestamptz), shown in the display or session timezone
     if let Ok(v) = row.try_get::<chrono::DateTime<chrono::Utc>, _>(idx) {
-        return serde_json::Value::String(v.to_rfc3339());
+        return serde_json::Value::String(temporal.format_instant(v.fixed_offset()));
     }
     
     // Try chrono without timezone (timestamp)
     if let Ok(v) = row.try_get::<chrono::NaiveDateTime, _>(idx) {
-        return serde_json::Value::String(v.format("%Y-%m-%d %H:%M:%S").to_string());
+        return serde_json::Value::String(temporal::format_datetime(v));
     }
     
     // Try date
     if let Ok(v) = row.try_get::<chrono::NaiveDate, _>(idx) {
-        return serde_json::Value::String(v.format("%Y-%m-%d").to_string());
+        return serde_json::Value::String(temporal::format_date(v));
     }
     
     // Try time
     if let Ok(v) = row.try_get::<chrono::NaiveTime, _>(idx) {
-        return serde_json::Value::String(v.format("%H:%M:%S").to_string());
+        return serde_json::Value::String(temporal::format_time(v));
+    }
+    
+    // Try time with timezone (timetz)
+    if let Ok(v) = row.try_get::<PgTimeTz<chrono::NaiveTime, chrono::FixedOffset>, _>(idx) {
+        return serde_json::Value::String(temporal::format_time_tz(v.time, v.offset));
     }
     
     // Try JSON
@@ -295,15 +561,289 @@ fn row_value_to_json(row: &sqlx::postgres::PgRow, idx: usize) -> serde_json::Val
         return v;
     }
     
-    // Try bytes as hex
+    // Try bytes as hex (large values are replaced with a preview)
     if let Ok(v) = row.try_get::<Vec<u8>, _>(idx) {
-        return serde_json::Value::String(format!("\\x{}", hex::encode(v)));
+        return super::binary_to_json(&v, "\\x", binary);
     }
     
     // Fallback: return null
     serde_json::Value::Null
 }
 
+/// Read a cell into `sink` a chunk at a time, in one REPEATABLE READ
+/// transaction so every chunk comes from the same version of the row.
+pub async fn read_cell(
+    pool: &PgPool,
+    table: &str,
+    column: &str,
+    predicate: &str,
+    sink: &mut CellSink,
+) -> Result<(), String> {
+    let mut tx = pool
+        .begin_with("BEGIN ISOLATION LEVEL REPEATABLE READ READ ONLY")
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    loop {
+        let chunk = read_cell_chunk(&mut tx, table, column, predicate, sink.offset(), CELL_CHUNK_SIZE).await?;
+        if !sink.write(chunk).await? {
+            break;
+        }
+    }
+
+    tx.commit().await.map_err(|e| format!("Failed to commit transaction: {}", e))
+}
+
+async fn read_cell_chunk(
+    conn: &mut sqlx::PgConnection,
+    table: &str,
+    column: &str,
+    predicate: &str,
+    offset: usize,
+    len: usize,
+) -> Result<Option<(Vec<u8>, usize)>, String> {
+    let sql = format!(
+        "SELECT substring({} from {} for {}) FROM {} WHERE {}",
+        column, offset + 1, len, table, predicate
+    );
+
+    let row = sqlx::query(&sql)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| format!("Failed to read cell: {}", e))?;
+
+    let Some(row) = row else {
+        return Ok(None);
+    };
+
+    // Text columns are counted in characters by substring, bytea in bytes
+    if let Ok(Some(v)) = row.try_get::<Option<String>, _>(0) {
+        let units = v.chars().count();
+        return Ok(Some((v.into_bytes(), units)));
+    }
+    if let Ok(Some(v)) = row.try_get::<Option<Vec<u8>>, _>(0) {
+        let units = v.len();
+        return Ok(Some((v, units)));
+    }
+
+    Ok(None)
+}
+
+pub async fn write_cell(
+    pool: &PgPool,
+    table: &str,
+    column: &str,
+    predicate: &str,
+    source: &mut CellSource,
+) -> Result<u64, String> {
+    // The file is staged in a large object a chunk at a time, so the cell
+    // is written once rather than rebuilt for every chunk
+    let mut tx = pool.begin().await.map_err(|e| format!("Failed to start transaction: {}", e))?;
+    let oid: i64 = sqlx::query_scalar("SELECT lo_create(0)::bigint")
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to write cell: {}", e))?;
+
+    let mut offset = 0i64;
+    loop {
+        let chunk = source.next_chunk().await?;
+        if chunk.is_empty() {
+            break;
+        }
+        let len = chunk.len() as i64;
+        sqlx::query("SELECT lo_put($1::oid, $2, $3)")
+            .bind(oid)
+            .bind(offset)
+            .bind(chunk)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to write cell: {}", e))?;
+        offset += len;
+    }
+
+    let set = format!("UPDATE {} SET {} = lo_get($1::oid) WHERE {}", table, column, predicate);
+    let result = sqlx::query(&set)
+        .bind(oid)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to write cell: {}", e))?;
+    if result.rows_affected() == 0 {
+        return Ok(0);
+    }
+    sqlx::query("SELECT lo_unlink($1::oid)")
+        .bind(oid)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to write cell: {}", e))?;
+
+    tx.commit().await.map_err(|e| format!("Failed to commit transaction: {}", e))?;
+    Ok(result.rows_affected())
+}
+
+/// Load rows with `COPY ... FROM STDIN` in text format.
+pub async fn copy_rows(
+    conn: &mut sqlx::PgConnection,
+    table: &str,
+    columns: &[String],
+    rows: &[Vec<SqlValue>],
+) -> Result<(), String> {
+    let db_type = DatabaseType::PostgreSQL;
+    let statement = format!(
+        "COPY {} ({}) FROM STDIN",
+        dialect::quote_table(&db_type, table),
+        columns.iter().map(|c| dialect::quote_ident(&db_type, c)).collect::<Vec<_>>().join(", "),
+    );
+
+    let mut copy = conn
+        .copy_in_raw(&statement)
+        .await
+        .map_err(|e| format!("Failed to start COPY: {}", e))?;
+
+    let mut buffer = String::new();
+    for row in rows {
+        for (i, value) in row.iter().enumerate() {
+            if i > 0 {
+                buffer.push('\t');
+            }
+            match value {
+                SqlValue::Null => buffer.push_str("\\N"),
+                SqlValue::Bool(b) => buffer.push(if *b { 't' } else { 'f' }),
+                other => push_copy_text(&mut buffer, &other.to_text()),
+            }
+        }
+        buffer.push('\n');
+
+        if buffer.len() >= COPY_CHUNK_SIZE {
+            copy.send(std::mem::take(&mut buffer).into_bytes())
+                .await
+                .map_err(|e| format!("Failed to send COPY data: {}", e))?;
+        }
+    }
+    if !buffer.is_empty() {
+        copy.send(buffer.into_bytes())
+            .await
+            .map_err(|e| format!("Failed to send COPY data: {}", e))?;
+    }
+
+    copy.finish().await.map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Run a `COPY ... FROM STDIN` statement as written in a script, sending
+/// its rows as they are read.
+pub async fn copy_from_stdin(
+    conn: &mut sqlx::PgConnection,
+    statement: &str,
+    next_chunk: &mut (dyn FnMut() -> Result<Option<String>, String> + Send),
+) -> Result<u64, String> {
+    let mut copy = conn
+        .copy_in_raw(statement)
+        .await
+        .map_err(|e| format!("Failed to start COPY: {}", e))?;
+
+    loop {
+        let chunk = match next_chunk() {
+            Ok(Some(chunk)) => chunk,
+            Ok(None) => break,
+            Err(e) => {
+                let _ = copy.abort(e.clone()).await;
+                return Err(e);
+            }
+        };
+        if let Err(e) = copy.send(chunk.into_bytes()).await {
+            let _ = copy.abort("COPY data rejected").await;
+            return Err(format!("Failed to send COPY data: {}", e));
+        }
+    }
+
+    copy.finish().await.map_err(|e| e.to_string())
+}
+
+/// Escape a field for COPY text format.
+pub fn push_copy_text(buffer: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '\\' => buffer.push_str("\\\\"),
+            '\n' => buffer.push_str("\\n"),
+            '\r' => buffer.push_str("\\r"),
+            '\t' => buffer.push_str("\\t"),
+            c => buffer.push(c),
+        }
+    }
+}
+
+pub async fn explain_query(pool: &PgPool, sql: &str, analyze: bool) -> Result<QueryPlan, String> {
+    let explain = if analyze {
+        format!("EXPLAIN (FORMAT JSON, ANALYZE, BUFFERS) {}", sql)
+    } else {
+        format!("EXPLAIN (FORMAT JSON) {}", sql)
+    };
+    
+    // ANALYZE executes the statement, so run it in a transaction that is always rolled back
+    let mut tx = pool.begin().await.map_err(|e| format!("Failed to start transaction: {}", e))?;
+    
+    let result: Result<(serde_json::Value,), _> = sqlx::query_as(&explain)
+        .fetch_one(&mut *tx)
+        .await;
+    
+    tx.rollback().await.map_err(|e| format!("Failed to roll back: {}", e))?;
+    
+    let (plan,) = result.map_err(|e| format!("Failed to explain query: {}", e))?;
+    
+    let top = plan
+        .get(0)
+        .ok_or_else(|| "PostgreSQL returned an empty plan".to_string())?;
+    let root = top
+        .get("Plan")
+        .map(plan_node_from_json)
+        .ok_or_else(|| "PostgreSQL returned a plan without a root node".to_string())?;
+    
+    Ok(QueryPlan {
+        root,
+        raw: serde_json::to_string_pretty(&plan).unwrap_or_default(),
+        analyzed: analyze,
+        planning_time_ms: top.get("Planning Time").and_then(|v| v.as_f64()),
+        execution_time_ms: top.get("Execution Time").and_then(|v| v.as_f64()),
+    })
+}
+
+fn plan_node_from_json(plan: &serde_json::Value) -> PlanNode {
+    let mut node = PlanNode::new(plan.get("Node Type").and_then(|v| v.as_str()).unwrap_or("Unknown"));
+    
+    node.relation = plan.get("Relation Name").and_then(|v| v.as_str()).map(|relation| {
+        match plan.get("Schema").and_then(|v| v.as_str()) {
+            Some(schema) => format!("{}.{}", schema, relation),
+            None => relation.to_string(),
+        }
+    });
+    node.startup_cost = plan.get("Startup Cost").and_then(|v| v.as_f64());
+    node.total_cost = plan.get("Total Cost").and_then(|v| v.as_f64());
+    node.estimated_rows = plan.get("Plan Rows").and_then(|v| v.as_f64());
+    node.actual_rows = plan.get("Actual Rows").and_then(|v| v.as_f64());
+    node.actual_time_ms = plan.get("Actual Total Time").and_then(|v| v.as_f64());
+    node.loops = plan.get("Actual Loops").and_then(|v| v.as_u64());
+    
+    if let Some(obj) = plan.as_object() {
+        for (key, value) in obj {
+            match key.as_str() {
+                "Node Type" | "Relation Name" | "Schema" | "Startup Cost" | "Total Cost" | "Plan Rows"
+                | "Actual Rows" | "Actual Total Time" | "Actual Loops" | "Plans" => {}
+                _ => {
+                    node.details.insert(key.clone(), value.clone());
+                }
+            }
+        }
+    }
+    
+    node.children = plan
+        .get("Plans")
+        .and_then(|v| v.as_array())
+        .map(|plans| plans.iter().map(plan_node_from_json).collect())
+        .unwrap_or_default();
+    
+    node
+}
+
 pub async fn list_databases(pool: &PgPool) -> Result<Vec<String>, String> {
     let query = r#"
         SELECT datname 
@@ -325,3 +865,277 @@ pub async fn list_databases(pool: &PgPool) -> Result<Vec<String>, String> {
     
     Ok(databases)
 }
+
+/// Schemas holding user objects.
+const USER_SCHEMAS: &str = "n.nspname NOT IN ('pg_catalog', 'information_schema') AND n.nspname NOT LIKE 'pg\\_toast%' AND n.nspname NOT LIKE 'pg\\_temp%'";
+
+/// Read tables, views and sequences of every user schema. Objects that
+/// belong to extensions are left out.
+pub async fn get_schema(pool: &PgPool) -> Result<SchemaSnapshot, String> {
+    let fail = |e: sqlx::Error| format!("Failed to read schema: {}", e);
+    
+    let columns = sqlx::query(&format!(r#"
+        SELECT 
+            n.nspname AS schema_name,
+            c.relname AS table_name,
+            a.attname AS column_name,
+            format_type(a.atttypid, a.atttypmod) AS data_type,
+            NOT a.attnotnull AS nullable,
+            pg_get_expr(d.adbin, d.adrelid) AS default_value,
+            a.attidentity::text AS identity,
+            a.attgenerated::text AS generated
+        FROM pg_class c
+        JOIN pg_namespace n ON n.oid = c.relnamespace
+        JOIN pg_attribute a ON a.attrelid = c.oid AND a.attnum > 0 AND NOT a.attisdropped
+        LEFT JOIN pg_attrdef d ON d.adrelid = c.oid AND d.adnum = a.attnum
+        WHERE c.relkind IN ('r', 'p')
+        AND {}
+        AND NOT EXISTS (SELECT 1 FROM pg_depend e WHERE e.objid = c.oid AND e.deptype = 'e')
+        ORDER BY n.nspname, c.relname, a.attnum
+    "#, USER_SCHEMAS))
+        .fetch_all(pool)
+        .await
+        .map_err(fail)?;
+    
+    let mut tables: Vec<TableDefinition> = Vec::new();
+    let mut index: std::collections::HashMap<(String, String), usize> = std::collections::HashMap::new();
+    
+    for row in &columns {
+        let key: (String, String) = (row.get("schema_name"), row.get("table_name"));
+        let idx = *index.entry(key.clone()).or_insert_with(|| {
+            tables.push(TableDefinition {
+                schema: Some(key.0.clone()),
+                name: key.1.clone(),
+                columns: Vec::new(),
+                primary_key: None,
+                unique_constraints: Vec::new(),
+                foreign_keys: Vec::new(),
+                checks: Vec::new(),
+                indexes: Vec::new(),
+                definition: None,
+            });
+            tables.len() - 1
+        });
+        
+        let default: Option<String> = row.get("default_value");
+        let generated = row.get::<String, _>("generated") == "s";
+        let identity = match row.get::<String, _>("identity").as_str() {
+            "a" => Some("GENERATED ALWAYS AS IDENTITY".to_string()),
+            "d" => Some("GENERATED BY DEFAULT AS IDENTITY".to_string()),
+            _ => None,
+        };
+        
+        tables[idx].columns.push(ColumnDefinition {
+            name: row.get("column_name"),
+            data_type: row.get("data_type"),
+            nullable: row.get("nullable"),
+            default: if generated { None } else { default.clone() },
+            identity,
+            generated: if generated { default } else { None },
+            generated_stored: generated,
+        });
+    }
+    
+    let constraints = sqlx::query(&format!(r#"
+        SELECT 
+            n.nspname AS schema_name,
+            c.relname AS table_name,
+            con.conname AS name,
+            con.contype::text AS kind,
+            pg_get_constraintdef(con.oid) AS definition,
+            ARRAY(
+                SELECT a.attname::text
+                FROM unnest(con.conkey) WITH ORDINALITY AS k(attnum, ord)
+                JOIN pg_attribute a ON a.attrelid = con.conrelid AND a.attnum = k.attnum
+                ORDER BY k.ord
+            ) AS columns,
+            fn.nspname AS ref_schema,
+            fc.relname AS ref_table,
+            ARRAY(
+                SELECT a.attname::text
+                FROM unnest(con.confkey) WITH ORDINALITY AS k(attnum, ord)
+                JOIN pg_attribute a ON a.attrelid = con.confrelid AND a.attnum = k.attnum
+                ORDER BY k.ord
+            ) AS ref_columns,
+            con.confupdtype::text AS on_update,
+            con.confdeltype::text AS on_delete
+        FROM pg_constraint con
+        JOIN pg_class c ON c.oid = con.conrelid
+        JOIN pg_namespace n ON n.oid = c.relnamespace
+        LEFT JOIN pg_class fc ON fc.oid = con.confrelid
+        LEFT JOIN pg_namespace fn ON fn.oid = fc.relnamespace
+        WHERE con.contype IN ('p', 'u', 'f', 'c')
+        AND {}
+        ORDER BY n.nspname, c.relname, con.conname
+    "#, USER_SCHEMAS))
+        .fetch_all(pool)
+        .await
+        .map_err(fail)?;
+    
+    for row in &constraints {
+        let Some(&idx) = index.get(&(row.get("schema_name"), row.get("table_name"))) else {
+            continue;
+        };
+        let table = &mut tables[idx];
+        let name: String = row.get("name");
+        let columns: Vec<String> = row.get("columns");
+        
+        match row.get::<String, _>("kind").as_str() {
+            "p" => table.primary_key = Some(KeyConstraint { name: Some(name), columns }),
+            "u" => table.unique_constraints.push(KeyConstraint { name: Some(name), columns }),
+            "f" => table.foreign_keys.push(ForeignKeyDefinition {
+                name: Some(name),
+                columns,
+                referenced_table: qualify(row.get::<Option<&str>, _>("ref_schema"), row.get("ref_table")),
+                referenced_columns: row.get("ref_columns"),
+                on_update: referential_action(&row.get::<String, _>("on_update")),
+                on_delete: referential_action(&row.get::<String, _>("on_delete")),
+            }),
+            _ => {
+                let definition: String = row.get("definition");
+                table.checks.push(CheckConstraint {
+                    name: Some(name),
+                    expression: definition.trim_start_matches("CHECK ").to_string(),
+                });
+            }
+        }
+    }
+    
+    // Indexes backing constraints are created by the constraints themselves
+    let indexes = sqlx::query(&format!(r#"
+        SELECT 
+            n.nspname AS schema_name,
+            t.relname AS table_name,
+            i.relname AS index_name,
+            ix.indisunique AS is_unique,
+            pg_get_indexdef(ix.indexrelid) AS definition,
+            ARRAY(
+                SELECT pg_get_indexdef(ix.indexrelid, k, true)
+                FROM generate_series(1, ix.indnkeyatts) AS k
+                ORDER BY k
+            ) AS columns
+        FROM pg_index ix
+        JOIN pg_class i ON i.oid = ix.indexrelid
+        JOIN pg_class t ON t.oid = ix.indrelid
+        JOIN pg_namespace n ON n.oid = t.relnamespace
+        WHERE t.relkind IN ('r', 'p')
+        AND {}
+        AND NOT EXISTS (SELECT 1 FROM pg_constraint con WHERE con.conindid = ix.indexrelid AND con.contype IN ('p', 'u', 'x'))
+        ORDER BY n.nspname, t.relname, i.relname
+    "#, USER_SCHEMAS))
+        .fetch_all(pool)
+        .await
+        .map_err(fail)?;
+    
+    for row in &indexes {
+        if let Some(&idx) = index.get(&(row.get("schema_name"), row.get("table_name"))) {
+            tables[idx].indexes.push(IndexDefinition {
+                name: row.get("index_name"),
+                columns: row.get("columns"),
+                unique: row.get("is_unique"),
+                definition: Some(row.get("definition")),
+            });
+        }
+    }
+    
+    let views = sqlx::query(&format!(r#"
+        SELECT n.nspname AS schema_name, c.relname AS view_name, pg_get_viewdef(c.oid) AS definition
+        FROM pg_class c
+        JOIN pg_namespace n ON n.oid = c.relnamespace
+        WHERE c.relkind = 'v'
+        AND {}
+        AND NOT EXISTS (SELECT 1 FROM pg_depend e WHERE e.objid = c.oid AND e.deptype = 'e')
+        ORDER BY n.nspname, c.relname
+    "#, USER_SCHEMAS))
+        .fetch_all(pool)
+        .await
+        .map_err(fail)?;
+    
+    let views = views
+        .iter()
+        .map(|row| {
+            let schema: String = row.get("schema_name");
+            let name: String = row.get("view_name");
+            let body: String = row.get("definition");
+            ViewDefinition {
+                definition: format!(
+                    "CREATE VIEW {} AS\n{}",
+                    dialect::quote_table(&DatabaseType::PostgreSQL, &qualify(Some(&schema), &name)),
+                    body.trim().trim_end_matches(';'),
+                ),
+                schema: Some(schema),
+                name,
+            }
+        })
+        .collect();
+    
+    // `pg_sequences.last_value` is NULL until the sequence is first used
+    let sequences = sqlx::query(r#"
+        SELECT 
+            s.schemaname AS schema_name,
+            s.sequencename AS sequence_name,
+            s.data_type::text AS data_type,
+            s.start_value,
+            s.increment_by,
+            s.min_value,
+            s.max_value,
+            s.cycle,
+            s.last_value,
+            tn.nspname AS owner_schema,
+            t.relname AS owner_table,
+            a.attname::text AS owner_column,
+            COALESCE(d.deptype = 'i', false) AS is_identity
+        FROM pg_sequences s
+        JOIN pg_namespace sn ON sn.nspname = s.schemaname
+        JOIN pg_class sc ON sc.relname = s.sequencename AND sc.relnamespace = sn.oid
+        LEFT JOIN pg_depend d ON d.objid = sc.oid
+            AND d.classid = 'pg_class'::regclass
+            AND d.refclassid = 'pg_class'::regclass
+            AND d.deptype IN ('a', 'i')
+        LEFT JOIN pg_class t ON t.oid = d.refobjid
+        LEFT JOIN pg_namespace tn ON tn.oid = t.relnamespace
+        LEFT JOIN pg_attribute a ON a.attrelid = d.refobjid AND a.attnum = d.refobjsubid
+        WHERE NOT EXISTS (SELECT 1 FROM pg_depend e WHERE e.objid = sc.oid AND e.deptype = 'e')
+        ORDER BY s.schemaname, s.sequencename
+    "#)
+        .fetch_all(pool)
+        .await
+        .map_err(fail)?;
+    
+    let sequences = sequences
+        .iter()
+        .map(|row| {
+            let owner_table: Option<String> = row.get("owner_table");
+            let owned_by = owner_table.map(|table| {
+                let schema: Option<String> = row.get("owner_schema");
+                (qualify(schema.as_deref(), &table), row.get::<String, _>("owner_column"))
+            });
+            SequenceDefinition {
+                schema: Some(row.get("schema_name")),
+                name: row.get("sequence_name"),
+                data_type: row.get("data_type"),
+                start: row.get("start_value"),
+                increment: row.get("increment_by"),
+                min_value: row.get("min_value"),
+                max_value: row.get("max_value"),
+                cycle: row.get("cycle"),
+                last_value: row.get("last_value"),
+                owned_by,
+                identity: row.get("is_identity"),
+            }
+        })
+        .collect();
+    
+    Ok(SchemaSnapshot { tables, views, sequences })
+}
+
+/// Referential action for a `pg_constraint` action code; `NO ACTION` is `None`.
+fn referential_action(code: &str) -> Option<String> {
+    match code {
+        "r" => Some("RESTRICT".to_string()),
+        "c" => Some("CASCADE".to_string()),
+        "n" => Some("SET NULL".to_string()),
+        "d" => Some("SET DEFAULT".to_string()),
+        _ => None,
+    }
+}
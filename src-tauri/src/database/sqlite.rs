@@ -1,5 +1,8 @@
-use sqlx::{sqlite::SqlitePoolOptions, SqlitePool, Row, Column, TypeInfo};
+use sqlx::{sqlite::{SqliteConnectOptions, SqlitePoolOptions}, SqlitePool, Row, Column, TypeInfo, Executor};
 use crate::models::*;
+use super::dialect;
+use super::temporal::{self, TemporalFormat};
+use super::{BinaryFormat, CellSink, CellSource, RowSink, CELL_CHUNK_SIZE};
 use std::time::Instant;
 
 pub async fn test_connection(config: &ConnectionConfig) -> TestConnectionResult {
@@ -38,12 +41,38 @@ pub async fn test_connection(config: &ConnectionConfig) -> TestConnectionResult
 }
 
 pub async fn connect(config: &ConnectionConfig) -> Result<SqlitePool, String> {
-    let conn_str = config.connection_string();
+    let mut options: SqliteConnectOptions = config
+        .connection_string()
+        .parse()
+        .map_err(|e| format!("Invalid SQLite connection settings: {}", e))?;
+    if config.read_only {
+        // mode=ro stops writes to the file; query_only also stops them
+        // to attached and temporary databases
+        options = options.pragma("query_only", "ON");
+    }
+    
+    let settings = config.pool.settings(&config.db_type)?;
+    let init_sql = config.init_sql.clone().filter(|sql| !sql.trim().is_empty());
+    if let Some(sql) = &init_sql {
+        super::check_init_sql(&options, sql, settings.acquire_timeout).await?;
+    }
     
     SqlitePoolOptions::new()
-        .max_connections(5)
-        .acquire_timeout(std::time::Duration::from_secs(10))
-        .connect(&conn_str)
+        .max_connections(settings.max_connections)
+        .min_connections(settings.min_connections)
+        .acquire_timeout(settings.acquire_timeout)
+        .idle_timeout(settings.idle_timeout)
+        .max_lifetime(settings.max_lifetime)
+        .after_connect(move |conn, _| {
+            let init_sql = init_sql.clone();
+            Box::pin(async move {
+                if let Some(sql) = init_sql {
+                    conn.execute(sql.as_str()).await?;
+                }
+                Ok(())
+            })
+        })
+        .connect_with(options)
         .await
         .map_err(|e| format!("SQLite connection failed: {}", e))
 }
@@ -105,6 +134,8 @@ pub async fn get_table_structure(pool: &SqlitePool, table: &str) -> Result<Table
             let notnull: i32 = row.try_get("notnull").unwrap_or(0);
             let pk: i32 = row.try_get("pk").unwrap_or(0);
             let default_value: Option<String> = row.try_get("dflt_value").ok();
+            let (first, second) = dialect::type_modifiers(&data_type);
+            let is_decimal = dialect::column_kind(&data_type) == dialect::ColumnKind::Decimal;
             
             ColumnInfo {
                 name,
@@ -117,7 +148,9 @@ pub async fn get_table_structure(pool: &SqlitePool, table: &str) -> Result<Table
                 foreign_key_table: None,
                 foreign_key_column: None,
                 is_auto_increment: None,
-                max_length: None,
+                max_length: if is_decimal { None } else { first },
+                numeric_precision: if is_decimal { first } else { None },
+                numeric_scale: if is_decimal { first.map(|_| second.unwrap_or(0)) } else { None },
                 check_constraint: None,
             }
         })
@@ -129,7 +162,7 @@ pub async fn get_table_structure(pool: &SqlitePool, table: &str) -> Result<Table
     })
 }
 
-pub async fn execute_query(pool: &SqlitePool, sql: &str) -> Result<QueryResult, String> {
+pub async fn execute_query(pool: &SqlitePool, sql: &str, temporal: &TemporalFormat) -> Result<QueryResult, String> {
     let start = Instant::now();
     
     let sql_upper = sql.trim().to_uppercase();
@@ -149,14 +182,12 @@ pub async fn execute_query(pool: &SqlitePool, sql: &str) -> Result<QueryResult,
         let execution_time = start.elapsed().as_millis() as u64;
         
         return Ok(QueryResult {
-            columns: vec![ResultColumn {
-                name: "affected_rows".to_string(),
-                type_name: "INTEGER".to_string(),
-            }],
+            columns: vec![ResultColumn::new("affected_rows", "INTEGER")],
             rows: vec![serde_json::json!({"affected_rows": affected})],
             row_count: affected as usize,
             execution_time_ms: execution_time,
             truncated: false,
+            confirmation_required: None,
         });
     }
     
@@ -168,31 +199,23 @@ pub async fn execute_query(pool: &SqlitePool, sql: &str) -> Result<QueryResult,
     
     let execution_time = start.elapsed().as_millis() as u64;
     
-    if rows.is_empty() {
-        return Ok(QueryResult {
-            columns: vec![],
-            rows: vec![],
-            row_count: 0,
-            execution_time_ms: execution_time,
-            truncated: false,
-        });
-    }
-    
-    let columns: Vec<ResultColumn> = rows[0]
-        .columns()
-        .iter()
-        .map(|c| ResultColumn {
-            name: c.name().to_string(),
-            type_name: c.type_info().name().to_string(),
-        })
-        .collect();
+    // Rows carry their columns; an empty result only reports them through a
+    // description of the statement, which prepares it a second time
+    let columns: Vec<ResultColumn> = match rows.first() {
+        Some(row) => row
+            .columns()
+            .iter()
+            .map(|c| ResultColumn::new(c.name(), c.type_info().name()))
+            .collect(),
+        None => describe_columns(pool, sql).await.unwrap_or_default(),
+    };
     
     let mut result_rows: Vec<serde_json::Value> = Vec::new();
     
     for row in &rows {
         let mut obj = serde_json::Map::new();
         for (i, col) in columns.iter().enumerate() {
-            let value = row_value_to_json(&row, i);
+            let value = row_value_to_json(&row, i, temporal, BinaryFormat::Preview);
             obj.insert(col.name.clone(), value);
         }
         result_rows.push(serde_json::Value::Object(obj));
@@ -206,10 +229,102 @@ pub async fn execute_query(pool: &SqlitePool, sql: &str) -> Result<QueryResult,
         row_count,
         execution_time_ms: execution_time,
         truncated: false,
+        confirmation_required: None,
     })
 }
 
-fn row_value_to_json(row: &sqlx::sqlite::SqliteRow, idx: usize) -> serde_json::Value {
+/// Stream the rows of a query into `sink` one at a time. Scripts with several
+/// statements are streamed as one result set per statement. Returns the row count.
+pub async fn stream_query(pool: &SqlitePool, sql: &str, temporal: &TemporalFormat, sink: &mut dyn RowSink) -> Result<u64, String> {
+    use futures::TryStreamExt;
+    use sqlx::Either;
+    
+    // SQLite describes only the first statement of a script, so the described
+    // columns are used for the first result set and the rest come from the rows
+    let mut columns = describe_columns(pool, sql).await;
+    let mut stream = sqlx::raw_sql(sql).fetch_many(pool);
+    
+    let mut count = 0u64;
+    let mut in_result = false;
+    let mut any_result = false;
+    
+    while let Some(item) = stream.try_next().await.map_err(|e| format!("Query execution failed: {}", e))? {
+        match item {
+            Either::Left(_) => {
+                // End of a statement; an empty result still gets its header
+                if !in_result {
+                    if let Some(described) = columns.take().filter(|c| !c.is_empty()) {
+                        sink.begin(&described)?;
+                        any_result = true;
+                    }
+                }
+                columns = None;
+                in_result = false;
+            }
+            Either::Right(row) => {
+                if !in_result {
+                    let described = columns
+                        .take()
+                        .filter(|c| c.len() == row.len())
+                        .unwrap_or_else(|| {
+                            row.columns()
+                                .iter()
+                                .map(|c| ResultColumn::new(c.name(), c.type_info().name()))
+                                .collect()
+                        });
+                    sink.begin(&described)?;
+                    in_result = true;
+                    any_result = true;
+                }
+                
+                let values: Vec<serde_json::Value> = (0..row.len())
+                    .map(|i| row_value_to_json(&row, i, temporal, BinaryFormat::Full))
+                    .collect();
+                sink.write_row(&values)?;
+                count += 1;
+            }
+        }
+    }
+    
+    if !any_result {
+        sink.begin(columns.as_deref().unwrap_or_default())?;
+    }
+    
+    log::info!("SQLite: Streamed {} rows", count);
+    Ok(count)
+}
+
+/// Describe the result columns of a statement without running it, including nullability.
+async fn describe_columns(pool: &SqlitePool, sql: &str) -> Option<Vec<ResultColumn>> {
+    let describe = match pool.describe(sql).await {
+        Ok(d) => d,
+        Err(e) => {
+            log::debug!("SQLite: Could not describe query: {}", e);
+            return None;
+        }
+    };
+    
+    let columns = describe
+        .columns()
+        .iter()
+        .enumerate()
+        .map(|(i, c)| ResultColumn {
+            nullable: describe.nullable(i),
+            ..ResultColumn::new(c.name(), c.type_info().name())
+        })
+        .collect();
+    
+    Some(columns)
+}
+
+fn row_value_to_json(row: &sqlx::sqlite::SqliteRow, idx: usize, temporal: &TemporalFormat, binary: BinaryFormat) -> serde_json::Value {
+    use sqlx::ValueRef;
+    
+    // SQLite happily decodes NULL as 0 or an empty string, so check it first
+    if row.try_get_raw(idx).map(|v| v.is_null()).unwrap_or(true) {
+        return serde_json::Value::Null;
+    }
+    
     // Try different types
     if let Ok(v) = row.try_get::<i32, _>(idx) {
         return serde_json::Value::Number(v.into());
@@ -225,16 +340,19 @@ fn row_value_to_json(row: &sqlx::sqlite::SqliteRow, idx: usize) -> serde_json::V
     }
     // Date and Time types - SQLite stores as TEXT but sqlx can decode them
     if let Ok(v) = row.try_get::<chrono::NaiveDate, _>(idx) {
-        return serde_json::Value::String(v.format("%Y-%m-%d").to_string());
+        return serde_json::Value::String(temporal::format_date(v));
+    }
+    // Text with an explicit offset is an instant; keep or convert its offset
+    if let Ok(raw) = row.try_get::<&str, _>(idx) {
+        if let Some(v) = temporal::parse_offset_datetime(raw) {
+            return serde_json::Value::String(temporal.format_instant(v));
+        }
     }
     if let Ok(v) = row.try_get::<chrono::NaiveDateTime, _>(idx) {
-        return serde_json::Value::String(v.format("%Y-%m-%d %H:%M:%S").to_string());
+        return serde_json::Value::String(temporal::format_datetime(v));
     }
     if let Ok(v) = row.try_get::<chrono::NaiveTime, _>(idx) {
-        return serde_json::Value::String(v.format("%H:%M:%S").to_string());
-    }
-    if let Ok(v) = row.try_get::<chrono::DateTime<chrono::Utc>, _>(idx) {
-        return serde_json::Value::String(v.format("%Y-%m-%d %H:%M:%S").to_string());
+        return serde_json::Value::String(temporal::format_time(v));
     }
     // Strings
     if let Ok(v) = row.try_get::<String, _>(idx) {
@@ -242,10 +360,13 @@ fn row_value_to_json(row: &sqlx::sqlite::SqliteRow, idx: usize) -> serde_json::V
     }
     // Try bytes (BLOB) - convert to string if valid UTF-8, otherwise hex
     if let Ok(v) = row.try_get::<Vec<u8>, _>(idx) {
+        if binary == BinaryFormat::Preview && v.len() > super::BLOB_PREVIEW_THRESHOLD {
+            return super::binary_to_json(&v, "0x", binary);
+        }
         if let Ok(s) = String::from_utf8(v.clone()) {
             return serde_json::Value::String(s);
         }
-        return serde_json::Value::String(format!("0x{}", hex::encode(v)));
+        return super::binary_to_json(&v, "0x", binary);
     }
     if let Ok(v) = row.try_get::<serde_json::Value, _>(idx) {
         return v;
@@ -260,8 +381,322 @@ fn row_value_to_json(row: &sqlx::sqlite::SqliteRow, idx: usize) -> serde_json::V
     serde_json::Value::Null
 }
 
+/// Read a cell into `sink` a chunk at a time, in one transaction so every
+/// chunk comes from the same version of the row.
+pub async fn read_cell(
+    pool: &SqlitePool,
+    table: &str,
+    column: &str,
+    predicate: &str,
+    sink: &mut CellSink,
+) -> Result<(), String> {
+    let mut tx = pool.begin().await.map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    loop {
+        let chunk = read_cell_chunk(&mut tx, table, column, predicate, sink.offset(), CELL_CHUNK_SIZE).await?;
+        if !sink.write(chunk).await? {
+            break;
+        }
+    }
+
+    tx.commit().await.map_err(|e| format!("Failed to commit transaction: {}", e))
+}
+
+async fn read_cell_chunk(
+    conn: &mut sqlx::SqliteConnection,
+    table: &str,
+    column: &str,
+    predicate: &str,
+    offset: usize,
+    len: usize,
+) -> Result<Option<(Vec<u8>, usize)>, String> {
+    let sql = format!(
+        "SELECT substr({}, {}, {}) FROM {} WHERE {}",
+        column, offset + 1, len, table, predicate
+    );
+
+    let row = sqlx::query(&sql)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|e| format!("Failed to read cell: {}", e))?;
+
+    let Some(row) = row else {
+        return Ok(None);
+    };
+
+    // Text values are counted in characters by substr, blobs in bytes
+    if let Ok(Some(v)) = row.try_get::<Option<String>, _>(0) {
+        let units = v.chars().count();
+        return Ok(Some((v.into_bytes(), units)));
+    }
+    if let Ok(Some(v)) = row.try_get::<Option<Vec<u8>>, _>(0) {
+        let units = v.len();
+        return Ok(Some((v, units)));
+    }
+
+    Ok(None)
+}
+
+pub async fn write_cell(
+    pool: &SqlitePool,
+    table: &str,
+    column: &str,
+    predicate: &str,
+    source: &mut CellSource,
+) -> Result<u64, String> {
+    let sql = format!("UPDATE {} SET {} = ? WHERE {}", table, column, predicate);
+    let result = sqlx::query(&sql)
+        .bind(source.read_all().await?)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to write cell: {}", e))?;
+    Ok(result.rows_affected())
+}
+
+pub async fn explain_query(pool: &SqlitePool, sql: &str, analyze: bool) -> Result<QueryPlan, String> {
+    let explain = format!("EXPLAIN QUERY PLAN {}", sql);
+    
+    let rows = sqlx::query(&explain)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Failed to explain query: {}", e))?;
+    
+    let steps: Vec<(i64, i64, String)> = rows
+        .iter()
+        .map(|row| {
+            (
+                row.try_get::<i64, _>("id").unwrap_or_default(),
+                row.try_get::<i64, _>("parent").unwrap_or_default(),
+                row.try_get::<String, _>("detail").unwrap_or_default(),
+            )
+        })
+        .collect();
+    
+    let raw = steps
+        .iter()
+        .map(|(id, parent, detail)| format!("{}|{}|{}", id, parent, detail))
+        .collect::<Vec<_>>()
+        .join("\n");
+    
+    let mut root = PlanNode::new("QUERY PLAN");
+    root.children = plan_children(&steps, 0);
+    
+    // SQLite has no EXPLAIN ANALYZE; run the statement in a rolled-back
+    // transaction and report its total time and row count on the root
+    let mut execution_time_ms = None;
+    if analyze {
+        let start = Instant::now();
+        let mut tx = pool.begin().await.map_err(|e| format!("Failed to start transaction: {}", e))?;
+        let result = sqlx::query(sql).fetch_all(&mut *tx).await;
+        tx.rollback().await.map_err(|e| format!("Failed to roll back: {}", e))?;
+        let rows = result.map_err(|e| format!("Query execution failed: {}", e))?;
+        
+        let elapsed = start.elapsed().as_secs_f64() * 1000.0;
+        root.actual_rows = Some(rows.len() as f64);
+        root.actual_time_ms = Some(elapsed);
+        root.loops = Some(1);
+        execution_time_ms = Some(elapsed);
+    }
+    
+    Ok(QueryPlan {
+        root,
+        raw,
+        analyzed: analyze,
+        planning_time_ms: None,
+        execution_time_ms,
+    })
+}
+
+fn plan_children(steps: &[(i64, i64, String)], parent: i64) -> Vec<PlanNode> {
+    steps
+        .iter()
+        .filter(|(id, p, _)| *p == parent && *id != parent)
+        .map(|(id, _, detail)| {
+            let mut node = PlanNode::new(detail.clone());
+            // "SCAN t", "SCAN TABLE t" (older versions) or "SEARCH t USING INDEX ..."
+            let mut words = detail.split_whitespace();
+            if matches!(words.next(), Some("SCAN") | Some("SEARCH")) {
+                node.relation = words
+                    .find(|w| *w != "TABLE")
+                    .map(|w| w.to_string());
+            }
+            node.children = plan_children(steps, *id);
+            node
+        })
+        .collect()
+}
+
 pub async fn list_databases(_pool: &SqlitePool) -> Result<Vec<String>, String> {
     // SQLite doesn't support multiple databases in the traditional sense
     // Return an empty list or the current database name
     Ok(vec!["main".to_string()])
 }
+
+/// Read tables, indexes and views from `sqlite_master`. Table and index
+/// definitions are the statements SQLite stored when they were created.
+pub async fn get_schema(pool: &SqlitePool) -> Result<SchemaSnapshot, String> {
+    let fail = |e: sqlx::Error| format!("Failed to read schema: {}", e);
+    
+    let objects = sqlx::query(r#"
+        SELECT type, name, tbl_name, sql
+        FROM sqlite_master
+        WHERE name NOT LIKE 'sqlite_%'
+        AND sql IS NOT NULL
+        ORDER BY type = 'table' DESC, rowid
+    "#)
+        .fetch_all(pool)
+        .await
+        .map_err(fail)?;
+    
+    let mut tables: Vec<TableDefinition> = Vec::new();
+    let mut views = Vec::new();
+    
+    for object in &objects {
+        let kind: String = object.get("type");
+        let name: String = object.get("name");
+        let sql: String = object.get("sql");
+        
+        match kind.as_str() {
+            "table" => {
+                let mut table = read_table(pool, &name, &sql).await.map_err(fail)?;
+                table.definition = Some(sql);
+                tables.push(table);
+            }
+            "index" => {
+                let table_name: String = object.get("tbl_name");
+                let Some(table) = tables.iter_mut().find(|t| t.name == table_name) else {
+                    continue;
+                };
+                let columns = sqlx::query("SELECT name FROM pragma_index_info(?) ORDER BY seqno")
+                    .bind(&name)
+                    .fetch_all(pool)
+                    .await
+                    .map_err(fail)?
+                    .iter()
+                    .map(|row| match row.get::<Option<String>, _>("name") {
+                        Some(column) => dialect::quote_ident(&DatabaseType::SQLite, &column),
+                        None => "(expression)".to_string(),
+                    })
+                    .collect();
+                table.indexes.push(IndexDefinition {
+                    name,
+                    columns,
+                    unique: sql.to_uppercase().starts_with("CREATE UNIQUE"),
+                    definition: Some(sql),
+                });
+            }
+            "view" => views.push(ViewDefinition { schema: None, name, definition: sql }),
+            // Triggers are not part of the snapshot
+            _ => {}
+        }
+    }
+    
+    // A foreign key without target columns references the parent's primary key
+    let primary_keys: std::collections::HashMap<String, Vec<String>> = tables
+        .iter()
+        .filter_map(|t| Some((t.name.clone(), t.primary_key.as_ref()?.columns.clone())))
+        .collect();
+    for fk in tables.iter_mut().flat_map(|t| t.foreign_keys.iter_mut()) {
+        if fk.referenced_columns.iter().any(String::is_empty) {
+            if let Some(columns) = primary_keys.get(&fk.referenced_table) {
+                fk.referenced_columns = columns.clone();
+            }
+        }
+    }
+    
+    Ok(SchemaSnapshot { tables, views, sequences: Vec::new() })
+}
+
+async fn read_table(pool: &SqlitePool, name: &str, sql: &str) -> Result<TableDefinition, sqlx::Error> {
+    // `hidden` is 2 for virtual and 3 for stored generated columns
+    let columns = sqlx::query(r#"SELECT name, type, "notnull", dflt_value, pk, hidden FROM pragma_table_xinfo(?) ORDER BY cid"#)
+        .bind(name)
+        .fetch_all(pool)
+        .await?;
+    
+    let autoincrement = sql.to_uppercase().contains("AUTOINCREMENT");
+    let mut primary_key: Vec<(i64, String)> = Vec::new();
+    let mut definitions = Vec::new();
+    
+    for row in &columns {
+        let column: String = row.get("name");
+        let data_type: String = row.get("type");
+        let pk: i64 = row.get("pk");
+        let hidden: i64 = row.get("hidden");
+        if pk > 0 {
+            primary_key.push((pk, column.clone()));
+        }
+        
+        definitions.push(ColumnDefinition {
+            identity: (autoincrement && pk == 1 && data_type.eq_ignore_ascii_case("INTEGER")).then(|| "AUTOINCREMENT".to_string()),
+            name: column,
+            data_type,
+            nullable: row.get::<i64, _>("notnull") == 0,
+            default: row.get("dflt_value"),
+            // SQLite does not report the expression of generated columns
+            generated: (hidden >= 2).then(String::new),
+            generated_stored: hidden == 3,
+        });
+    }
+    primary_key.sort();
+    
+    let unique_constraints = sqlx::query(r#"SELECT name FROM pragma_index_list(?) WHERE origin = 'u' ORDER BY seq"#)
+        .bind(name)
+        .fetch_all(pool)
+        .await?;
+    let mut uniques = Vec::new();
+    for row in &unique_constraints {
+        let index: String = row.get("name");
+        let columns = sqlx::query("SELECT name FROM pragma_index_info(?) ORDER BY seqno")
+            .bind(&index)
+            .fetch_all(pool)
+            .await?
+            .iter()
+            .map(|r| r.get::<Option<String>, _>("name").unwrap_or_default())
+            .collect();
+        uniques.push(KeyConstraint { name: None, columns });
+    }
+    
+    let foreign_key_rows = sqlx::query(r#"SELECT id, "table", "from", "to", on_update, on_delete FROM pragma_foreign_key_list(?) ORDER BY id, seq"#)
+        .bind(name)
+        .fetch_all(pool)
+        .await?;
+    let mut foreign_keys: Vec<(i64, ForeignKeyDefinition)> = Vec::new();
+    for row in &foreign_key_rows {
+        let id: i64 = row.get("id");
+        let from: String = row.get("from");
+        let to: String = row.get::<Option<String>, _>("to").unwrap_or_default();
+        match foreign_keys.iter_mut().find(|(fk_id, _)| *fk_id == id) {
+            Some((_, fk)) => {
+                fk.columns.push(from);
+                fk.referenced_columns.push(to);
+            }
+            None => {
+                let action = |col: &str| Some(row.get::<String, _>(col)).filter(|a| a != "NO ACTION");
+                foreign_keys.push((id, ForeignKeyDefinition {
+                    name: None,
+                    columns: vec![from],
+                    referenced_table: row.get("table"),
+                    referenced_columns: vec![to],
+                    on_update: action("on_update"),
+                    on_delete: action("on_delete"),
+                }));
+            }
+        }
+    }
+    
+    Ok(TableDefinition {
+        schema: None,
+        name: name.to_string(),
+        columns: definitions,
+        primary_key: (!primary_key.is_empty()).then(|| KeyConstraint {
+            name: None,
+            columns: primary_key.into_iter().map(|(_, c)| c).collect(),
+        }),
+        unique_constraints: uniques,
+        foreign_keys: foreign_keys.into_iter().map(|(_, fk)| fk).collect(),
+        checks: Vec::new(),
+        indexes: Vec::new(),
+        definition: None,
+    })
+}
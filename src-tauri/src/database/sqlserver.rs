@@ -1,30 +1,148 @@
-use tiberius::{Client, Config, AuthMethod, Row, Column};
+use chrono::{NaiveDate, Timelike};
+use tiberius::numeric::Numeric;
+use tiberius::time::{DateTime2, DateTimeOffset, Time};
+use tiberius::{Client, Config, AuthMethod, EncryptionLevel, Row, Column, ColumnData, ColumnType, IntoSql, TokenRow};
 use tokio::net::TcpStream;
-use tokio_util::compat::TokioAsyncWriteCompatExt;
+use tokio_util::compat::{Compat, TokioAsyncWriteCompatExt};
 use bb8::Pool;
-use bb8_tiberius::ConnectionManager;
 use crate::models::*;
-use std::time::Instant;
+use super::temporal::{self, TemporalFormat};
+use super::dialect::{self, SqlValue};
+use super::{BinaryFormat, CellSink, CellSource, RowSink, CELL_CHUNK_SIZE};
+use std::time::{Duration, Instant};
 use log::{info, error, debug};
 
-pub type SqlServerPool = Pool<ConnectionManager>;
+pub type SqlServerPool = Pool<SqlServerManager>;
 
-pub async fn test_connection(config: &ConnectionConfig) -> TestConnectionResult {
-    info!("SQL Server: Testing connection to {}:{}", config.host, config.port);
+/// Opens the pool's connections. The TCP address is kept apart from the
+/// configured host, which is the name the server certificate is checked
+/// against and may be overridden.
+pub struct SqlServerManager {
+    config: Config,
+    addr: String,
+    /// Run on every new connection
+    init_sql: Option<String>,
+}
+
+impl SqlServerManager {
+    async fn open(&self, config: Config, addr: &str) -> tiberius::Result<Client<Compat<TcpStream>>> {
+        let tcp = TcpStream::connect(addr).await?;
+        tcp.set_nodelay(true)?;
+        let mut client = Client::connect(config, tcp.compat_write()).await?;
+        if let Some(sql) = &self.init_sql {
+            client.simple_query(sql.as_str()).await?.into_results().await?;
+        }
+        Ok(client)
+    }
+}
+
+#[async_trait::async_trait]
+impl bb8::ManageConnection for SqlServerManager {
+    type Connection = Client<Compat<TcpStream>>;
+    type Error = tiberius::error::Error;
+
+    async fn connect(&self) -> Result<Self::Connection, Self::Error> {
+        match self.open(self.config.clone(), &self.addr).await {
+            // The server sent us to another address, e.g. an Azure gateway
+            Err(tiberius::error::Error::Routing { host, port }) => {
+                let mut config = self.config.clone();
+                config.host(&host);
+                config.port(port);
+                let addr = config.get_addr();
+                self.open(config, &addr).await
+            }
+            result => result,
+        }
+    }
+
+    async fn is_valid(&self, conn: &mut Self::Connection) -> Result<(), Self::Error> {
+        conn.simple_query("SELECT 1").await?.into_results().await?;
+        Ok(())
+    }
+
+    fn has_broken(&self, _conn: &mut Self::Connection) -> bool {
+        false
+    }
+}
+
+/// tiberius settings for a connection and the address to open it on.
+fn tiberius_config(config: &ConnectionConfig) -> Result<(Config, String), String> {
+    let tls = &config.tls;
+    tls.validate()?;
+    if tls.client_cert_path.as_deref().is_some_and(|p| !p.is_empty()) {
+        return Err("SQL Server connections do not use client certificates".to_string());
+    }
+    // tiberius has no setting for the others; `Integrated Security` would
+    // quietly become a SQL login
+    let supported = |key: &String| super::dsn::SQLSERVER_PARAMETERS.contains(&super::dsn::sqlserver_key(key).as_str());
+    if let Some(key) = config.parameters.keys().find(|k| !supported(k)) {
+        return Err(format!("Unsupported SQL Server connection parameter: {}", key));
+    }
     
     let mut tiberius_config = Config::new();
     
-    tiberius_config.host(&config.host);
+    let server_name = tls.server_name.as_deref().filter(|n| !n.is_empty()).unwrap_or(&config.host);
+    tiberius_config.host(server_name);
     tiberius_config.port(config.port);
     tiberius_config.authentication(AuthMethod::sql_server(&config.username, &config.password));
-    tiberius_config.trust_cert();
+    
+    match tls.mode {
+        TlsMode::Disable => tiberius_config.encryption(EncryptionLevel::NotSupported),
+        TlsMode::Prefer => {
+            tiberius_config.encryption(EncryptionLevel::On);
+            tiberius_config.trust_cert();
+        }
+        TlsMode::Require => {
+            tiberius_config.encryption(EncryptionLevel::Required);
+            tiberius_config.trust_cert();
+        }
+        // tiberius always checks the certificate name, so verify-ca is verify-full
+        TlsMode::VerifyCa | TlsMode::VerifyFull => {
+            tiberius_config.encryption(EncryptionLevel::Required);
+            if let Some(path) = tls.ca_cert_path.as_deref().filter(|p| !p.is_empty()) {
+                tiberius_config.trust_cert_ca(path);
+            }
+        }
+    }
     
     if !config.database.is_empty() {
         tiberius_config.database(&config.database);
         debug!("SQL Server: Using database '{}'", config.database);
     }
+    
+    for (key, value) in &config.parameters {
+        match super::dsn::sqlserver_key(key).as_str() {
+            "applicationname" | "app" => tiberius_config.application_name(value),
+            "applicationintent" => tiberius_config.readonly(value.eq_ignore_ascii_case("readonly")),
+            _ => {}
+        }
+    }
+    if config.read_only {
+        // Routes to a readable secondary where there is one; writes are
+        // refused by the statement check
+        tiberius_config.readonly(true);
+    }
+    
+    let addr = match config.host.contains(':') {
+        true => format!("[{}]:{}", config.host, config.port),
+        false => format!("{}:{}", config.host, config.port),
+    };
+    Ok((tiberius_config, addr))
+}
 
-    let addr = tiberius_config.get_addr();
+pub async fn test_connection(config: &ConnectionConfig) -> TestConnectionResult {
+    info!("SQL Server: Testing connection to {}:{}", config.host, config.port);
+    
+    let (tiberius_config, addr) = match tiberius_config(config) {
+        Ok(settings) => settings,
+        Err(e) => {
+            return TestConnectionResult {
+                success: false,
+                message: e,
+                version: None,
+            }
+        }
+    };
     info!("SQL Server: Connecting to address {:?}", addr);
     
     match TcpStream::connect(&addr).await {
@@ -89,24 +207,17 @@ pub async fn test_connection(config: &ConnectionConfig) -> TestConnectionResult
 pub async fn connect(config: &ConnectionConfig) -> Result<SqlServerPool, String> {
     info!("SQL Server: Creating connection pool to {}:{}/{}", config.host, config.port, config.database);
     
-    let mut tiberius_config = Config::new();
-    
-    tiberius_config.host(&config.host);
-    tiberius_config.port(config.port);
-    tiberius_config.authentication(AuthMethod::sql_server(&config.username, &config.password));
-    tiberius_config.trust_cert();
-    
-    if !config.database.is_empty() {
-        tiberius_config.database(&config.database);
-    }
-
-    let manager = ConnectionManager::new(tiberius_config);
+    let (tiberius_config, addr) = tiberius_config(config)?;
+    let settings = config.pool.settings(&config.db_type)?;
+    let init_sql = config.init_sql.clone().filter(|sql| !sql.trim().is_empty());
+    let manager = SqlServerManager { config: tiberius_config, addr, init_sql };
     
     match Pool::builder()
-        .max_size(10) // Increased from 5
-        .min_idle(Some(1)) // Keep at least 1 connection ready
-        .connection_timeout(std::time::Duration::from_secs(10)) // Timeout for getting connection
-        .idle_timeout(Some(std::time::Duration::from_secs(60))) // Close idle connections after 60s
+        .max_size(settings.max_connections)
+        .min_idle(Some(settings.min_connections))
+        .connection_timeout(settings.acquire_timeout)
+        .idle_timeout(settings.idle_timeout)
+        .max_lifetime(settings.max_lifetime)
         .build(manager)
         .await {
         Ok(pool) => {
@@ -120,6 +231,35 @@ pub async fn connect(config: &ConnectionConfig) -> Result<SqlServerPool, String>
     }
 }
 
+/// `SELECT 1`, with the driver's error as it is so the caller can tell a
+/// busy pool from a lost server.
+pub async fn ping(pool: &SqlServerPool) -> Result<(), String> {
+    let mut conn = pool.get().await.map_err(|e| e.to_string())?;
+    conn.simple_query("SELECT 1").await
+        .map_err(|e| e.to_string())?
+        .into_results().await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// `COUNT_BIG(*)` of a table on a connection of its own, closed when the
+/// count takes longer than `limit`: a pooled client given up on mid-query
+/// would hand its unread results to the next caller.
+pub async fn count_rows(pool: &SqlServerPool, table: &str, limit: Duration) -> Result<Option<u64>, String> {
+    let mut conn = pool.dedicated_connection().await.map_err(|e| format!("Failed to get connection: {}", e))?;
+    let sql = format!("SELECT COUNT_BIG(*) FROM {}", table);
+    let counted = tokio::time::timeout(limit, async {
+        conn.simple_query(sql).await?.into_row().await
+    }).await;
+    match counted {
+        Ok(row) => {
+            let row = row.map_err(|e| format!("Failed to count rows: {}", e))?;
+            Ok(row.and_then(|r| r.get::<i64, _>(0)).map(|n| n as u64))
+        }
+        Err(_) => Ok(None),
+    }
+}
+
 pub async fn get_tables(pool: &SqlServerPool) -> Result<Vec<TableInfo>, String> {
     info!("SQL Server: Getting tables list");
     
@@ -199,7 +339,14 @@ pub async fn get_table_structure(pool: &SqlServerPool, table: &str) -> Result<Ta
             t.name AS data_type,
             c.is_nullable,
             CASE WHEN pk.column_id IS NOT NULL THEN 1 ELSE 0 END AS is_primary_key,
-            dc.definition AS default_value
+            dc.definition AS default_value,
+            CAST(CASE
+                WHEN c.max_length = -1 THEN NULL
+                WHEN t.name IN ('nchar', 'nvarchar') THEN c.max_length / 2
+                WHEN t.name IN ('char', 'varchar', 'binary', 'varbinary') THEN c.max_length
+            END AS INT) AS max_length,
+            CAST(CASE WHEN t.name IN ('decimal', 'numeric') THEN c.precision END AS INT) AS numeric_precision,
+            CAST(CASE WHEN t.name IN ('decimal', 'numeric') THEN c.scale END AS INT) AS numeric_scale
         FROM sys.columns c
         INNER JOIN sys.types t ON c.user_type_id = t.user_type_id
         INNER JOIN sys.tables tb ON c.object_id = tb.object_id
@@ -237,7 +384,9 @@ pub async fn get_table_structure(pool: &SqlServerPool, table: &str) -> Result<Ta
             foreign_key_table: None,
             foreign_key_column: None,
             is_auto_increment: None,
-            max_length: None,
+            max_length: row.get::<i32, _>("max_length"),
+            numeric_precision: row.get::<i32, _>("numeric_precision"),
+            numeric_scale: row.get::<i32, _>("numeric_scale"),
             check_constraint: None,
         }
     }).collect();
@@ -248,7 +397,7 @@ pub async fn get_table_structure(pool: &SqlServerPool, table: &str) -> Result<Ta
     })
 }
 
-pub async fn execute_query(pool: &SqlServerPool, sql: &str) -> Result<QueryResult, String> {
+pub async fn execute_query(pool: &SqlServerPool, sql: &str, temporal: &TemporalFormat) -> Result<QueryResult, String> {
     let start = Instant::now();
     let mut conn = pool.get().await.map_err(|e| format!("Failed to get connection: {}", e))?;
     
@@ -268,50 +417,42 @@ pub async fn execute_query(pool: &SqlServerPool, sql: &str) -> Result<QueryResul
         let execution_time = start.elapsed().as_millis() as u64;
         
         return Ok(QueryResult {
-            columns: vec![ResultColumn {
-                name: "affected_rows".to_string(),
-                type_name: "BIGINT".to_string(),
-            }],
+            columns: vec![ResultColumn::new("affected_rows", "BIGINT")],
             rows: vec![serde_json::json!({"affected_rows": affected})],
             row_count: affected as usize,
             execution_time_ms: execution_time,
             truncated: false,
+            confirmation_required: None,
         });
     }
     
     // For SELECT queries, use simple_query
-    let stream = conn.simple_query(sql).await
+    let mut stream = conn.simple_query(sql).await
         .map_err(|e| format!("Query execution failed: {}", e))?;
     
+    // Column metadata arrives before any rows, so empty results still have headers
+    let mut columns: Vec<ResultColumn> = stream.columns().await
+        .map_err(|e| format!("Query execution failed: {}", e))?
+        .map(|cols| {
+            cols.iter()
+                .map(|c| ResultColumn::new(c.name(), format!("{:?}", c.column_type())))
+                .collect()
+        })
+        .unwrap_or_default();
+    
     let rows: Vec<Row> = stream.into_first_result().await
         .map_err(|e| format!("Query execution failed: {}", e))?;
     
     let execution_time = start.elapsed().as_millis() as u64;
     
-    if rows.is_empty() {
-        return Ok(QueryResult {
-            columns: vec![],
-            rows: vec![],
-            row_count: 0,
-            execution_time_ms: execution_time,
-            truncated: false,
-        });
-    }
-    
-    // Get column information from the first row
-    let columns: Vec<ResultColumn> = rows[0].columns().iter().map(|c| {
-        ResultColumn {
-            name: c.name().to_string(),
-            type_name: format!("{:?}", c.column_type()),
-        }
-    }).collect();
+    describe_columns(&mut conn, sql, &mut columns).await;
     
     let mut result_rows: Vec<serde_json::Value> = Vec::new();
     
     for row in &rows {
         let mut obj = serde_json::Map::new();
         for (i, col) in row.columns().iter().enumerate() {
-            let value = column_to_json(&row, i, col);
+            let value = column_to_json(&row, i, col, temporal, BinaryFormat::Preview);
             obj.insert(col.name().to_string(), value);
         }
         result_rows.push(serde_json::Value::Object(obj));
@@ -325,10 +466,116 @@ pub async fn execute_query(pool: &SqlServerPool, sql: &str) -> Result<QueryResul
         row_count,
         execution_time_ms: execution_time,
         truncated: false,
+        confirmation_required: None,
     })
 }
 
-fn column_to_json(row: &Row, idx: usize, _col: &Column) -> serde_json::Value {
+/// Stream the rows of a query into `sink` one at a time. Every result set of
+/// a batch is streamed in turn. Returns the total row count. The connection
+/// is kept out of the pool: a sink error or cancellation stops reading
+/// midway, which would hand the unread results to the next caller.
+pub async fn stream_query(pool: &SqlServerPool, sql: &str, temporal: &TemporalFormat, sink: &mut dyn RowSink) -> Result<u64, String> {
+    use futures::TryStreamExt;
+    use tiberius::QueryItem;
+    
+    let mut conn = pool.dedicated_connection().await.map_err(|e| format!("Failed to get connection: {}", e))?;
+    let mut stream = conn.simple_query(sql).await
+        .map_err(|e| format!("Query execution failed: {}", e))?;
+    
+    let mut count = 0u64;
+    let mut started = false;
+    
+    while let Some(item) = stream.try_next().await.map_err(|e| format!("Query execution failed: {}", e))? {
+        match item {
+            QueryItem::Metadata(meta) => {
+                let columns: Vec<ResultColumn> = meta
+                    .columns()
+                    .iter()
+                    .map(|c| ResultColumn::new(c.name(), format!("{:?}", c.column_type())))
+                    .collect();
+                sink.begin(&columns)?;
+                started = true;
+            }
+            QueryItem::Row(row) => {
+                let values: Vec<serde_json::Value> = row
+                    .columns()
+                    .iter()
+                    .enumerate()
+                    .map(|(i, col)| column_to_json(&row, i, col, temporal, BinaryFormat::Full))
+                    .collect();
+                sink.write_row(&values)?;
+                count += 1;
+            }
+        }
+    }
+    
+    if !started {
+        sink.begin(&[])?;
+    }
+    
+    info!("SQL Server: Streamed {} rows", count);
+    Ok(count)
+}
+
+/// Add nullability, precision/scale and source table to result columns using
+/// `sys.dm_exec_describe_first_result_set`. Failures leave the columns as they are.
+async fn describe_columns(conn: &mut Client<Compat<TcpStream>>, sql: &str, columns: &mut [ResultColumn]) {
+    if columns.is_empty() {
+        return;
+    }
+    
+    let query = format!(r#"
+        SELECT 
+            column_ordinal,
+            is_nullable,
+            system_type_name,
+            precision,
+            scale,
+            source_schema,
+            source_table
+        FROM sys.dm_exec_describe_first_result_set({}, NULL, 1)
+        WHERE is_hidden = 0
+    "#, super::dialect::quote_string(&DatabaseType::SQLServer, sql));
+    
+    let rows = match conn.simple_query(query).await {
+        Ok(stream) => stream.into_first_result().await,
+        Err(e) => Err(e),
+    };
+    
+    let rows = match rows {
+        Ok(rows) => rows,
+        Err(e) => {
+            debug!("SQL Server: Could not describe query: {}", e);
+            return;
+        }
+    };
+    
+    for row in rows {
+        let Some(ordinal) = row.get::<i32, _>("column_ordinal") else {
+            continue;
+        };
+        let Some(column) = columns.get_mut((ordinal - 1) as usize) else {
+            continue;
+        };
+        
+        column.nullable = row.get::<bool, _>("is_nullable");
+        
+        let type_name = row.get::<&str, _>("system_type_name").unwrap_or_default();
+        if type_name.starts_with("decimal") || type_name.starts_with("numeric") {
+            column.precision = row.get::<u8, _>("precision").map(u32::from);
+            column.scale = row.get::<u8, _>("scale").map(u32::from);
+        }
+        
+        if let Some(table) = row.get::<&str, _>("source_table") {
+            column.source_table = Some(match row.get::<&str, _>("source_schema") {
+                Some(schema) => format!("{}.{}", schema, table),
+                None => table.to_string(),
+            });
+        }
+    }
+}
+
+fn column_to_json(row: &Row, idx: usize, _col: &Column, temporal: &TemporalFormat, binary: BinaryFormat) -> serde_json::Value {
     // Try different types in order of likelihood
     // Use try_get to avoid panics on type mismatches
     // Check i64 first (BIGINT is common for IDs in SQL Server)
@@ -360,21 +607,598 @@ fn column_to_json(row: &Row, idx: usize, _col: &Column) -> serde_json::Value {
         return serde_json::Value::String(v.to_string());
     }
     if let Ok(Some(v)) = row.try_get::<&[u8], _>(idx) {
-        return serde_json::Value::String(format!("0x{}", hex::encode(v)));
+        return super::binary_to_json(v, "0x", binary);
     }
+    // datetimeoffset keeps its own offset unless a display timezone is set
+    if let Ok(Some(v)) = row.try_get::<chrono::DateTime<chrono::FixedOffset>, _>(idx) {
+        return serde_json::Value::String(temporal.format_instant(v));
+    }
+    // datetime2 and time carry up to 100ns precision
     if let Ok(Some(v)) = row.try_get::<chrono::NaiveDateTime, _>(idx) {
-        return serde_json::Value::String(v.format("%Y-%m-%d %H:%M:%S").to_string());
+        return serde_json::Value::String(temporal::format_datetime(v));
     }
     if let Ok(Some(v)) = row.try_get::<chrono::NaiveDate, _>(idx) {
-        return serde_json::Value::String(v.format("%Y-%m-%d").to_string());
+        return serde_json::Value::String(temporal::format_date(v));
     }
     if let Ok(Some(v)) = row.try_get::<chrono::NaiveTime, _>(idx) {
-        return serde_json::Value::String(v.format("%H:%M:%S").to_string());
+        return serde_json::Value::String(temporal::format_time(v));
     }
     
     serde_json::Value::Null
 }
 
+/// Read a cell into `sink` a chunk at a time, in one transaction.
+pub async fn read_cell(
+    pool: &SqlServerPool,
+    table: &str,
+    column: &str,
+    predicate: &str,
+    sink: &mut CellSink,
+) -> Result<(), String> {
+    let mut conn = pool.get().await.map_err(|e| format!("Failed to get connection: {}", e))?;
+
+    let mut stream = conn.simple_query(format!("SELECT TOP 0 {} FROM {}", column, table)).await
+        .map_err(|e| format!("Failed to read cell: {}", e))?;
+    let column_type = stream.columns().await
+        .map_err(|e| format!("Failed to read cell: {}", e))?
+        .and_then(|columns| columns.first().map(|c| c.column_type()));
+    drop(stream);
+    let unicode = matches!(column_type, Some(ColumnType::NVarchar | ColumnType::NChar | ColumnType::NText));
+
+    // The hint keeps the row share-locked until the transaction ends, so it
+    // cannot change between chunks
+    let table = format!("{} WITH (REPEATABLEREAD)", table);
+
+    run_batch(&mut conn, "BEGIN TRANSACTION").await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    match read_chunks(&mut conn, &table, column, predicate, unicode, sink).await {
+        Ok(()) => run_batch(&mut conn, "COMMIT TRANSACTION").await
+            .map_err(|e| format!("Failed to commit transaction: {}", e)),
+        result => {
+            if let Err(e) = run_batch(&mut conn, "IF @@TRANCOUNT > 0 ROLLBACK TRANSACTION").await {
+                error!("SQL Server: Failed to roll back: {}", e);
+            }
+            result
+        }
+    }
+}
+
+async fn read_chunks(
+    conn: &mut Client<Compat<TcpStream>>,
+    table: &str,
+    column: &str,
+    predicate: &str,
+    unicode: bool,
+    sink: &mut CellSink,
+) -> Result<(), String> {
+    loop {
+        let chunk = if unicode {
+            read_utf16_chunk(conn, table, column, predicate, sink.offset(), CELL_CHUNK_SIZE).await?
+        } else {
+            read_cell_chunk(conn, table, column, predicate, sink.offset(), CELL_CHUNK_SIZE).await?
+        };
+        if !sink.write(chunk).await? {
+            return Ok(());
+        }
+    }
+}
+
+async fn read_cell_chunk(
+    conn: &mut Client<Compat<TcpStream>>,
+    table: &str,
+    column: &str,
+    predicate: &str,
+    offset: usize,
+    len: usize,
+) -> Result<Option<(Vec<u8>, usize)>, String> {
+    let sql = format!(
+        "SELECT SUBSTRING({}, {}, {}) FROM {} WHERE {}",
+        column, offset + 1, len, table, predicate
+    );
+
+    let stream = conn.simple_query(sql).await
+        .map_err(|e| format!("Failed to read cell: {}", e))?;
+
+    let row = stream.into_row().await
+        .map_err(|e| format!("Failed to read cell: {}", e))?;
+
+    let Some(row) = row else {
+        return Ok(None);
+    };
+
+    // Text columns are counted in characters by SUBSTRING, binary ones in bytes
+    if let Ok(Some(v)) = row.try_get::<&str, _>(0) {
+        return Ok(Some((v.as_bytes().to_vec(), v.chars().count())));
+    }
+    if let Ok(Some(v)) = row.try_get::<&[u8], _>(0) {
+        return Ok(Some((v.to_vec(), v.len())));
+    }
+
+    Ok(None)
+}
+
+/// SUBSTRING counts UTF-16 code units in Unicode columns, and may end a
+/// chunk halfway through a surrogate pair, which would not decode. The
+/// chunk is read as bytes with one unit more, kept when it completes a pair.
+async fn read_utf16_chunk(
+    conn: &mut Client<Compat<TcpStream>>,
+    table: &str,
+    column: &str,
+    predicate: &str,
+    offset: usize,
+    len: usize,
+) -> Result<Option<(Vec<u8>, usize)>, String> {
+    let sql = format!(
+        "SELECT CAST(SUBSTRING({}, {}, {}) AS VARBINARY(MAX)) FROM {} WHERE {}",
+        column, offset + 1, len + 1, table, predicate
+    );
+
+    let row = conn.simple_query(sql).await
+        .map_err(|e| format!("Failed to read cell: {}", e))?
+        .into_row().await
+        .map_err(|e| format!("Failed to read cell: {}", e))?;
+
+    let Some(bytes) = row.as_ref().and_then(|r| r.get::<&[u8], _>(0)) else {
+        return Ok(None);
+    };
+
+    let mut units: Vec<u16> = bytes.chunks_exact(2).map(|pair| u16::from_le_bytes([pair[0], pair[1]])).collect();
+    let splits_pair = len > 0 && units.get(len - 1).is_some_and(|unit| (0xD800..0xDC00).contains(unit));
+    if !splits_pair {
+        units.truncate(len);
+    }
+    let text = String::from_utf16(&units).map_err(|e| format!("Failed to read cell: {}", e))?;
+    Ok(Some((text.into_bytes(), units.len())))
+}
+
+pub async fn write_cell(
+    pool: &SqlServerPool,
+    table: &str,
+    column: &str,
+    predicate: &str,
+    source: &mut CellSource,
+) -> Result<u64, String> {
+    let mut conn = pool.get().await.map_err(|e| format!("Failed to get connection: {}", e))?;
+
+    run_batch(&mut conn, "BEGIN TRANSACTION").await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    match write_chunks(&mut conn, table, column, predicate, source).await {
+        Ok(affected) if affected > 0 => {
+            run_batch(&mut conn, "COMMIT TRANSACTION").await
+                .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+            Ok(affected)
+        }
+        result => {
+            if let Err(e) = run_batch(&mut conn, "IF @@TRANCOUNT > 0 ROLLBACK TRANSACTION").await {
+                error!("SQL Server: Failed to roll back: {}", e);
+            }
+            result
+        }
+    }
+}
+
+async fn write_chunks(
+    conn: &mut Client<Compat<TcpStream>>,
+    table: &str,
+    column: &str,
+    predicate: &str,
+    source: &mut CellSource,
+) -> Result<u64, String> {
+    let mut stream = conn.simple_query(format!("SELECT TOP 0 {} FROM {}", column, table)).await
+        .map_err(|e| format!("Failed to write cell: {}", e))?;
+    let column_type = stream.columns().await
+        .map_err(|e| format!("Failed to write cell: {}", e))?
+        .and_then(|columns| columns.first().map(|c| c.column_type()));
+    drop(stream);
+
+    let set = format!("UPDATE {} SET {} = @P1 WHERE {}", table, column, predicate);
+
+    // `.WRITE` does not take the legacy image and text types; bind the whole file once
+    if matches!(column_type, Some(ColumnType::Image | ColumnType::Text | ColumnType::NText)) {
+        let bytes = source.read_all().await?;
+        return Ok(conn.execute(set, &[&bytes]).await
+            .map_err(|e| format!("Failed to write cell: {}", e))?
+            .total());
+    }
+
+    // `.WRITE` with a NULL offset appends in place instead of rebuilding the value
+    let append = format!("UPDATE {} SET {}.WRITE(@P1, NULL, 0) WHERE {}", table, column, predicate);
+
+    let affected = conn.execute(set, &[&source.next_chunk().await?]).await
+        .map_err(|e| format!("Failed to write cell: {}", e))?
+        .total();
+    if affected == 0 {
+        return Ok(0);
+    }
+
+    loop {
+        let chunk = source.next_chunk().await?;
+        if chunk.is_empty() {
+            break;
+        }
+        conn.execute(append.as_str(), &[&chunk]).await
+            .map_err(|e| format!("Failed to write cell: {}", e))?;
+    }
+
+    Ok(affected)
+}
+
+/// Wire type used for one target column during a bulk load.
+#[derive(Debug, Clone, Copy)]
+enum BulkType {
+    Bit,
+    TinyInt,
+    SmallInt,
+    Int,
+    BigInt,
+    Real,
+    Float,
+    Numeric(u8),
+    String,
+    Binary,
+    Guid,
+    Date,
+    Time(u8),
+    DateTime,
+    DateTime2,
+    DateTimeOffset(u8),
+}
+
+impl BulkType {
+    fn from_sql(type_name: &str, max_length: i16, scale: u8) -> Option<Self> {
+        Some(match type_name {
+            "bit" => BulkType::Bit,
+            "tinyint" => BulkType::TinyInt,
+            "smallint" => BulkType::SmallInt,
+            "int" => BulkType::Int,
+            "bigint" => BulkType::BigInt,
+            "real" => BulkType::Real,
+            "float" if max_length == 4 => BulkType::Real,
+            "float" => BulkType::Float,
+            "decimal" | "numeric" => BulkType::Numeric(scale),
+            "char" | "varchar" | "nchar" | "nvarchar" => BulkType::String,
+            "binary" | "varbinary" => BulkType::Binary,
+            "uniqueidentifier" => BulkType::Guid,
+            "date" => BulkType::Date,
+            "time" => BulkType::Time(scale),
+            "datetime" => BulkType::DateTime,
+            "datetime2" => BulkType::DateTime2,
+            "datetimeoffset" => BulkType::DateTimeOffset(scale),
+            _ => return None,
+        })
+    }
+
+    fn null(self) -> ColumnData<'static> {
+        match self {
+            BulkType::Bit => ColumnData::Bit(None),
+            BulkType::TinyInt => ColumnData::U8(None),
+            BulkType::SmallInt => ColumnData::I16(None),
+            BulkType::Int => ColumnData::I32(None),
+            BulkType::BigInt => ColumnData::I64(None),
+            BulkType::Real => ColumnData::F32(None),
+            BulkType::Float => ColumnData::F64(None),
+            BulkType::Numeric(_) => ColumnData::Numeric(None),
+            BulkType::String => ColumnData::String(None),
+            BulkType::Binary => ColumnData::Binary(None),
+            BulkType::Guid => ColumnData::Guid(None),
+            BulkType::Date => ColumnData::Date(None),
+            BulkType::Time(_) => ColumnData::Time(None),
+            BulkType::DateTime => ColumnData::DateTime(None),
+            BulkType::DateTime2 => ColumnData::DateTime2(None),
+            BulkType::DateTimeOffset(_) => ColumnData::DateTimeOffset(None),
+        }
+    }
+
+    fn encode(self, value: &SqlValue) -> Option<ColumnData<'static>> {
+        if matches!(value, SqlValue::Null) {
+            return Some(self.null());
+        }
+
+        Some(match (self, value) {
+            (BulkType::Bit, SqlValue::Bool(b)) => ColumnData::Bit(Some(*b)),
+            (BulkType::Bit, SqlValue::Int(i)) => ColumnData::Bit(Some(*i != 0)),
+            (BulkType::TinyInt, SqlValue::Int(i)) => ColumnData::U8(Some(u8::try_from(*i).ok()?)),
+            (BulkType::SmallInt, SqlValue::Int(i)) => ColumnData::I16(Some(i16::try_from(*i).ok()?)),
+            (BulkType::Int, SqlValue::Int(i)) => ColumnData::I32(Some(i32::try_from(*i).ok()?)),
+            (BulkType::BigInt, SqlValue::Int(i)) => ColumnData::I64(Some(*i)),
+            (BulkType::TinyInt | BulkType::SmallInt | BulkType::Int | BulkType::BigInt, SqlValue::Bool(b)) => {
+                return self.encode(&SqlValue::Int(*b as i64));
+            }
+            (BulkType::Real, SqlValue::Float(f)) => ColumnData::F32(Some(*f as f32)),
+            (BulkType::Real, SqlValue::Int(i)) => ColumnData::F32(Some(*i as f32)),
+            (BulkType::Real, SqlValue::Decimal(d)) => ColumnData::F32(Some(d.parse().ok()?)),
+            (BulkType::Float, SqlValue::Float(f)) => ColumnData::F64(Some(*f)),
+            (BulkType::Float, SqlValue::Int(i)) => ColumnData::F64(Some(*i as f64)),
+            (BulkType::Float, SqlValue::Decimal(d)) => ColumnData::F64(Some(d.parse().ok()?)),
+            // The server's scale must be matched exactly
+            (BulkType::Numeric(scale), SqlValue::Decimal(_) | SqlValue::Int(_) | SqlValue::Float(_)) => {
+                let unscaled = dialect::parse_decimal(&value.to_text(), scale as u32)?;
+                ColumnData::Numeric(Some(Numeric::new_with_scale(unscaled, scale)))
+            }
+            (BulkType::String, SqlValue::Bytes(_)) => return None,
+            (BulkType::String, other) => ColumnData::String(Some(other.to_text().into())),
+            (BulkType::Binary, SqlValue::Bytes(bytes)) => ColumnData::Binary(Some(bytes.clone().into())),
+            (BulkType::Guid, SqlValue::Text(s)) => ColumnData::Guid(Some(s.trim().parse().ok()?)),
+            (BulkType::Date, SqlValue::Date(d)) => d.into_sql(),
+            (BulkType::Time(scale), SqlValue::Time(t)) => {
+                let ColumnData::Time(Some(time)) = t.into_sql() else { return None };
+                ColumnData::Time(Some(rescale_time(time, scale)))
+            }
+            (BulkType::DateTime, SqlValue::DateTime(dt)) => {
+                let days = (dt.date() - NaiveDate::from_ymd_opt(1900, 1, 1)?).num_days();
+                let nanos = dt.time().num_seconds_from_midnight() as u64 * 1_000_000_000 + dt.time().nanosecond() as u64;
+                // datetime counts time in 1/300 second ticks
+                let ticks = (nanos as f64 * 300.0 / 1e9).round() as u32;
+                ColumnData::DateTime(Some(tiberius::time::DateTime::new(i32::try_from(days).ok()?, ticks)))
+            }
+            (BulkType::DateTime2, SqlValue::DateTime(dt)) => dt.into_sql(),
+            (BulkType::DateTime2, SqlValue::Date(d)) => d.and_hms_opt(0, 0, 0)?.into_sql(),
+            (BulkType::DateTimeOffset(scale), SqlValue::Timestamp(dt)) => {
+                let ColumnData::DateTimeOffset(Some(dto)) = dt.into_sql() else { return None };
+                let datetime2 = DateTime2::new(dto.datetime2().date(), rescale_time(dto.datetime2().time(), scale));
+                ColumnData::DateTimeOffset(Some(DateTimeOffset::new(datetime2, dto.offset())))
+            }
+            _ => return None,
+        })
+    }
+}
+
+/// tiberius produces 100ns precision; `time` and `datetimeoffset` columns
+/// expect increments at their own scale.
+fn rescale_time(time: Time, scale: u8) -> Time {
+    let divisor = 10u64.pow(time.scale().saturating_sub(scale) as u32);
+    Time::new(time.increments() / divisor, scale)
+}
+
+/// Load rows through the TDS bulk load protocol. Returns `Ok(false)` without
+/// sending anything when a column type is not supported by the bulk loader,
+/// so the caller can fall back to `INSERT` statements.
+pub async fn bulk_insert(
+    conn: &mut Client<Compat<TcpStream>>,
+    table: &str,
+    columns: &[String],
+    rows: &[Vec<SqlValue>],
+) -> Result<bool, String> {
+    let quoted = dialect::quote_table(&DatabaseType::SQLServer, table);
+
+    // The bulk loader expects a value for every insertable column, in table order
+    let query = format!(
+        r#"
+        SELECT c.name, t.name AS type_name, c.max_length, c.scale
+        FROM sys.columns c
+        INNER JOIN sys.types t ON c.system_type_id = t.user_type_id
+        WHERE c.object_id = OBJECT_ID({})
+            AND c.is_computed = 0 AND c.is_identity = 0 AND t.name <> 'timestamp'
+        ORDER BY c.column_id
+        "#,
+        dialect::quote_string(&DatabaseType::SQLServer, &quoted),
+    );
+    let metadata: Vec<_> = conn.simple_query(query).await
+        .map_err(|e| format!("Failed to read table columns: {}", e))?
+        .into_first_result().await
+        .map_err(|e| format!("Failed to read table columns: {}", e))?;
+
+    let mut targets = Vec::with_capacity(metadata.len());
+    for row in &metadata {
+        let name = row.get::<&str, _>("name").unwrap_or_default();
+        let type_name = row.get::<&str, _>("type_name").unwrap_or_default();
+        let max_length = row.get::<i16, _>("max_length").unwrap_or_default();
+        let scale = row.get::<u8, _>("scale").unwrap_or_default();
+
+        let Some(bulk_type) = BulkType::from_sql(type_name, max_length, scale) else {
+            debug!("SQL Server: Column {} ({}) is not supported by bulk load", name, type_name);
+            return Ok(false);
+        };
+        let source = columns.iter().position(|c| c.eq_ignore_ascii_case(name));
+        targets.push((bulk_type, source));
+    }
+
+    let mapped = targets.iter().filter(|(_, source)| source.is_some()).count();
+    if targets.is_empty() || mapped != columns.len() {
+        return Ok(false);
+    }
+
+    // Encode everything up front so a bad value fails before the load starts
+    let mut encoded = Vec::with_capacity(rows.len());
+    for row in rows {
+        let mut token_row = TokenRow::with_capacity(targets.len());
+        for (bulk_type, source) in &targets {
+            let data = match source {
+                Some(idx) => bulk_type.encode(&row[*idx]).ok_or_else(|| {
+                    format!("Value {} does not fit column type {:?}", row[*idx].to_text(), bulk_type)
+                })?,
+                None => bulk_type.null(),
+            };
+            token_row.push(data);
+        }
+        encoded.push(token_row);
+    }
+
+    let mut request = conn.bulk_insert(&quoted).await
+        .map_err(|e| format!("Failed to start bulk load: {}", e))?;
+    for token_row in encoded {
+        request.send(token_row).await.map_err(|e| e.to_string())?;
+    }
+    request.finalize().await.map_err(|e| e.to_string())?;
+
+    Ok(true)
+}
+
+pub async fn explain_query(pool: &SqlServerPool, sql: &str, analyze: bool) -> Result<QueryPlan, String> {
+    let mut conn = pool.get().await.map_err(|e| format!("Failed to get connection: {}", e))?;
+    
+    // SHOWPLAN_XML compiles without executing; STATISTICS XML executes and
+    // appends the actual plan as an extra result set
+    let (enable, disable) = if analyze {
+        ("SET STATISTICS XML ON", "SET STATISTICS XML OFF")
+    } else {
+        ("SET SHOWPLAN_XML ON", "SET SHOWPLAN_XML OFF")
+    };
+    
+    run_batch(&mut conn, enable).await
+        .map_err(|e| format!("Failed to enable plan capture: {}", e))?;
+    
+    let result = capture_plan_xml(&mut conn, sql, analyze).await;
+    
+    // Always restore the session so the pooled connection is not left in plan mode
+    if let Err(e) = run_batch(&mut conn, disable).await {
+        error!("SQL Server: Failed to disable plan capture: {}", e);
+    }
+    
+    let raw = result?;
+    let root = plan_node_from_xml(&raw)?;
+    
+    Ok(QueryPlan {
+        root,
+        raw,
+        analyzed: analyze,
+        planning_time_ms: None,
+        execution_time_ms: None,
+    })
+}
+
+pub async fn run_batch(conn: &mut Client<Compat<TcpStream>>, sql: &str) -> tiberius::Result<()> {
+    conn.simple_query(sql).await?.into_results().await?;
+    Ok(())
+}
+
+async fn capture_plan_xml(conn: &mut Client<Compat<TcpStream>>, sql: &str, analyze: bool) -> Result<String, String> {
+    if analyze {
+        // The statement really runs, so keep its changes from being committed
+        run_batch(conn, "BEGIN TRANSACTION").await
+            .map_err(|e| format!("Failed to start transaction: {}", e))?;
+    }
+    
+    let results = match conn.simple_query(sql).await {
+        Ok(stream) => stream.into_results().await,
+        Err(e) => Err(e),
+    };
+    
+    if analyze {
+        if let Err(e) = run_batch(conn, "IF @@TRANCOUNT > 0 ROLLBACK TRANSACTION").await {
+            error!("SQL Server: Failed to roll back: {}", e);
+        }
+    }
+    
+    let results = results.map_err(|e| format!("Failed to explain query: {}", e))?;
+    
+    // The plan is the single cell of the last result set
+    results
+        .iter()
+        .rev()
+        .flat_map(|rows| rows.first())
+        .find_map(|row| {
+            if let Ok(Some(xml)) = row.try_get::<&str, _>(0) {
+                return Some(xml.to_string());
+            }
+            if let Ok(Some(xml)) = row.try_get::<&tiberius::xml::XmlData, _>(0) {
+                return Some(xml.as_ref().to_string());
+            }
+            None
+        })
+        .ok_or_else(|| "SQL Server did not return an execution plan".to_string())
+}
+
+/// Normalize showplan XML: one node per statement, with its `RelOp` tree as children.
+fn plan_node_from_xml(xml: &str) -> Result<PlanNode, String> {
+    let doc = roxmltree::Document::parse(xml)
+        .map_err(|e| format!("Failed to parse plan: {}", e))?;
+    
+    let statements: Vec<PlanNode> = doc
+        .descendants()
+        .filter(|n| n.has_tag_name("StmtSimple"))
+        .map(|stmt| {
+            let mut node = PlanNode::new(stmt.attribute("StatementType").unwrap_or("Statement"));
+            node.total_cost = xml_f64(&stmt, "StatementSubTreeCost");
+            node.estimated_rows = xml_f64(&stmt, "StatementEstRows");
+            if let Some(text) = stmt.attribute("StatementText") {
+                node.details.insert("StatementText".to_string(), serde_json::json!(text));
+            }
+            if let Some(rel_op) = stmt
+                .descendants()
+                .find(|n| n.has_tag_name("QueryPlan"))
+                .and_then(|plan| plan.children().find(|n| n.has_tag_name("RelOp")))
+            {
+                node.children.push(plan_node_from_rel_op(&rel_op));
+            }
+            node
+        })
+        .collect();
+    
+    match statements.len() {
+        0 => Err("Execution plan contains no statements".to_string()),
+        1 => Ok(statements.into_iter().next().unwrap()),
+        _ => {
+            let mut batch = PlanNode::new("Batch");
+            batch.total_cost = statements.iter().filter_map(|s| s.total_cost).reduce(|a, b| a + b);
+            batch.children = statements;
+            Ok(batch)
+        }
+    }
+}
+
+fn plan_node_from_rel_op(rel_op: &roxmltree::Node) -> PlanNode {
+    let mut node = PlanNode::new(rel_op.attribute("PhysicalOp").unwrap_or("Unknown"));
+    node.total_cost = xml_f64(rel_op, "EstimatedTotalSubtreeCost");
+    node.estimated_rows = xml_f64(rel_op, "EstimateRows");
+    
+    for attr in ["LogicalOp", "EstimateIO", "EstimateCPU", "Parallel"] {
+        if let Some(value) = rel_op.attribute(attr) {
+            node.details.insert(attr.to_string(), serde_json::json!(value));
+        }
+    }
+    
+    for child in rel_op.children().filter(|n| n.is_element()) {
+        if child.has_tag_name("RunTimeInformation") {
+            // Counters are reported per thread
+            let counters: Vec<_> = child
+                .children()
+                .filter(|n| n.has_tag_name("RunTimeCountersPerThread"))
+                .collect();
+            node.actual_rows = counters.iter().filter_map(|c| xml_f64(c, "ActualRows")).reduce(|a, b| a + b);
+            node.loops = counters
+                .iter()
+                .filter_map(|c| c.attribute("ActualExecutions").and_then(|v| v.parse::<u64>().ok()))
+                .reduce(|a, b| a + b);
+            node.actual_time_ms = counters.iter().filter_map(|c| xml_f64(c, "ActualElapsedms")).reduce(f64::max);
+        } else if !child.has_tag_name("OutputList") {
+            // The operator-specific element holds the object reference and the input operators
+            if let Some(object) = child.children().find(|n| n.has_tag_name("Object")) {
+                node.relation = object.attribute("Table").map(|table| {
+                    let table = table.trim_matches(|c| c == '[' || c == ']');
+                    match object.attribute("Schema") {
+                        Some(schema) => format!("{}.{}", schema.trim_matches(|c| c == '[' || c == ']'), table),
+                        None => table.to_string(),
+                    }
+                });
+                if let Some(index) = object.attribute("Index") {
+                    node.details.insert("Index".to_string(), serde_json::json!(index));
+                }
+            }
+            collect_rel_ops(&child, &mut node.children);
+        }
+    }
+    
+    node
+}
+
+/// Find the nearest nested `RelOp` elements below `parent` without descending into them.
+fn collect_rel_ops(parent: &roxmltree::Node, out: &mut Vec<PlanNode>) {
+    for child in parent.children().filter(|n| n.is_element()) {
+        if child.has_tag_name("RelOp") {
+            out.push(plan_node_from_rel_op(&child));
+        } else {
+            collect_rel_ops(&child, out);
+        }
+    }
+}
+
+fn xml_f64(node: &roxmltree::Node, attr: &str) -> Option<f64> {
+    node.attribute(attr).and_then(|v| v.parse().ok())
+}
+
 pub async fn list_databases(pool: &SqlServerPool) -> Result<Vec<String>, String> {
     let mut conn = pool.get().await.map_err(|e| format!("Failed to get connection: {}", e))?;
     
@@ -390,3 +1214,335 @@ pub async fn list_databases(pool: &SqlServerPool) -> Result<Vec<String>, String>
     
     Ok(databases)
 }
+
+async fn fetch_rows(conn: &mut Client<Compat<TcpStream>>, sql: &str) -> Result<Vec<Row>, String> {
+    let stream = conn.simple_query(sql).await.map_err(|e| format!("Failed to read schema: {}", e))?;
+    stream.into_first_result().await.map_err(|e| format!("Failed to read schema: {}", e))
+}
+
+fn text(row: &Row, col: &str) -> String {
+    row.get::<&str, _>(col).unwrap_or_default().to_string()
+}
+
+/// Declared type from `sys.columns`, whose `max_length` is in bytes.
+fn declared_type(name: &str, max_length: i32, precision: i32, scale: i32) -> String {
+    match name {
+        "varchar" | "char" | "varbinary" | "binary" => match max_length {
+            -1 => format!("{}(max)", name),
+            n => format!("{}({})", name, n),
+        },
+        "nvarchar" | "nchar" => match max_length {
+            -1 => format!("{}(max)", name),
+            n => format!("{}({})", name, n / 2),
+        },
+        "decimal" | "numeric" => format!("{}({},{})", name, precision, scale),
+        "datetime2" | "time" | "datetimeoffset" => format!("{}({})", name, scale),
+        "float" if precision != 53 => format!("float({})", precision),
+        _ => name.to_string(),
+    }
+}
+
+/// Read tables, views and sequences of every schema, skipping system objects.
+pub async fn get_schema(pool: &SqlServerPool) -> Result<SchemaSnapshot, String> {
+    let mut conn = pool.get().await.map_err(|e| format!("Failed to get connection: {}", e))?;
+    
+    let columns = fetch_rows(&mut conn, r#"
+        SELECT 
+            s.name AS schema_name,
+            t.name AS table_name,
+            c.name AS column_name,
+            ty.name AS type_name,
+            CAST(c.max_length AS INT) AS max_length,
+            CAST(c.precision AS INT) AS num_precision,
+            CAST(c.scale AS INT) AS num_scale,
+            c.is_nullable,
+            dc.definition AS default_value,
+            CAST(ic.seed_value AS BIGINT) AS seed,
+            CAST(ic.increment_value AS BIGINT) AS increment,
+            cc.definition AS computed,
+            cc.is_persisted
+        FROM sys.tables t
+        INNER JOIN sys.schemas s ON t.schema_id = s.schema_id
+        INNER JOIN sys.columns c ON c.object_id = t.object_id
+        INNER JOIN sys.types ty ON ty.user_type_id = c.user_type_id
+        LEFT JOIN sys.default_constraints dc ON dc.object_id = c.default_object_id
+        LEFT JOIN sys.identity_columns ic ON ic.object_id = c.object_id AND ic.column_id = c.column_id
+        LEFT JOIN sys.computed_columns cc ON cc.object_id = c.object_id AND cc.column_id = c.column_id
+        WHERE t.is_ms_shipped = 0
+        ORDER BY s.name, t.name, c.column_id
+    "#).await?;
+    
+    let mut tables: Vec<TableDefinition> = Vec::new();
+    let mut index: std::collections::HashMap<(String, String), usize> = std::collections::HashMap::new();
+    
+    for row in &columns {
+        let key = (text(row, "schema_name"), text(row, "table_name"));
+        let idx = *index.entry(key.clone()).or_insert_with(|| {
+            tables.push(TableDefinition {
+                schema: Some(key.0.clone()),
+                name: key.1.clone(),
+                columns: Vec::new(),
+                primary_key: None,
+                unique_constraints: Vec::new(),
+                foreign_keys: Vec::new(),
+                checks: Vec::new(),
+                indexes: Vec::new(),
+                definition: None,
+            });
+            tables.len() - 1
+        });
+        
+        let identity = match (row.get::<i64, _>("seed"), row.get::<i64, _>("increment")) {
+            (Some(seed), Some(increment)) => Some(format!("IDENTITY({},{})", seed, increment)),
+            _ => None,
+        };
+        tables[idx].columns.push(ColumnDefinition {
+            name: text(row, "column_name"),
+            data_type: declared_type(
+                &text(row, "type_name"),
+                row.get::<i32, _>("max_length").unwrap_or(0),
+                row.get::<i32, _>("num_precision").unwrap_or(0),
+                row.get::<i32, _>("num_scale").unwrap_or(0),
+            ),
+            nullable: row.get::<bool, _>("is_nullable").unwrap_or(true),
+            default: row.get::<&str, _>("default_value").map(str::to_string),
+            identity,
+            generated: row.get::<&str, _>("computed").map(str::to_string),
+            generated_stored: row.get::<bool, _>("is_persisted").unwrap_or(false),
+        });
+    }
+    
+    let keys = fetch_rows(&mut conn, r#"
+        SELECT 
+            s.name AS schema_name,
+            t.name AS table_name,
+            kc.name AS constraint_name,
+            kc.type AS kind,
+            col.name AS column_name
+        FROM sys.key_constraints kc
+        INNER JOIN sys.tables t ON t.object_id = kc.parent_object_id
+        INNER JOIN sys.schemas s ON t.schema_id = s.schema_id
+        INNER JOIN sys.index_columns ic ON ic.object_id = kc.parent_object_id AND ic.index_id = kc.unique_index_id
+        INNER JOIN sys.columns col ON col.object_id = ic.object_id AND col.column_id = ic.column_id
+        WHERE t.is_ms_shipped = 0
+        ORDER BY s.name, t.name, kc.name, ic.key_ordinal
+    "#).await?;
+    
+    for row in &keys {
+        let Some(&idx) = index.get(&(text(row, "schema_name"), text(row, "table_name"))) else {
+            continue;
+        };
+        let table = &mut tables[idx];
+        let name = text(row, "constraint_name");
+        let column = text(row, "column_name");
+        
+        if text(row, "kind").trim() == "PK" {
+            table
+                .primary_key
+                .get_or_insert_with(|| KeyConstraint { name: Some(name), columns: Vec::new() })
+                .columns
+                .push(column);
+            continue;
+        }
+        match table.unique_constraints.iter_mut().find(|k| k.name.as_deref() == Some(name.as_str())) {
+            Some(key) => key.columns.push(column),
+            None => table.unique_constraints.push(KeyConstraint { name: Some(name), columns: vec![column] }),
+        }
+    }
+    
+    let foreign_keys = fetch_rows(&mut conn, r#"
+        SELECT 
+            s.name AS schema_name,
+            t.name AS table_name,
+            fk.name AS constraint_name,
+            pc.name AS column_name,
+            rs.name AS ref_schema,
+            rt.name AS ref_table,
+            rc.name AS ref_column,
+            fk.update_referential_action_desc AS on_update,
+            fk.delete_referential_action_desc AS on_delete
+        FROM sys.foreign_keys fk
+        INNER JOIN sys.tables t ON t.object_id = fk.parent_object_id
+        INNER JOIN sys.schemas s ON t.schema_id = s.schema_id
+        INNER JOIN sys.foreign_key_columns fkc ON fkc.constraint_object_id = fk.object_id
+        INNER JOIN sys.columns pc ON pc.object_id = fkc.parent_object_id AND pc.column_id = fkc.parent_column_id
+        INNER JOIN sys.tables rt ON rt.object_id = fkc.referenced_object_id
+        INNER JOIN sys.schemas rs ON rs.schema_id = rt.schema_id
+        INNER JOIN sys.columns rc ON rc.object_id = fkc.referenced_object_id AND rc.column_id = fkc.referenced_column_id
+        WHERE t.is_ms_shipped = 0
+        ORDER BY s.name, t.name, fk.name, fkc.constraint_column_id
+    "#).await?;
+    
+    for row in &foreign_keys {
+        let Some(&idx) = index.get(&(text(row, "schema_name"), text(row, "table_name"))) else {
+            continue;
+        };
+        let name = text(row, "constraint_name");
+        let table = &mut tables[idx];
+        match table.foreign_keys.iter_mut().find(|fk| fk.name.as_deref() == Some(name.as_str())) {
+            Some(fk) => {
+                fk.columns.push(text(row, "column_name"));
+                fk.referenced_columns.push(text(row, "ref_column"));
+            }
+            None => {
+                let action = |col: &str| Some(text(row, col).replace('_', " ")).filter(|a| a != "NO ACTION");
+                table.foreign_keys.push(ForeignKeyDefinition {
+                    name: Some(name),
+                    columns: vec![text(row, "column_name")],
+                    referenced_table: qualify(Some(&text(row, "ref_schema")), &text(row, "ref_table")),
+                    referenced_columns: vec![text(row, "ref_column")],
+                    on_update: action("on_update"),
+                    on_delete: action("on_delete"),
+                });
+            }
+        }
+    }
+    
+    let checks = fetch_rows(&mut conn, r#"
+        SELECT s.name AS schema_name, t.name AS table_name, cc.name AS constraint_name, cc.definition
+        FROM sys.check_constraints cc
+        INNER JOIN sys.tables t ON t.object_id = cc.parent_object_id
+        INNER JOIN sys.schemas s ON t.schema_id = s.schema_id
+        WHERE t.is_ms_shipped = 0
+        ORDER BY s.name, t.name, cc.name
+    "#).await?;
+    
+    for row in &checks {
+        if let Some(&idx) = index.get(&(text(row, "schema_name"), text(row, "table_name"))) {
+            tables[idx].checks.push(CheckConstraint {
+                name: Some(text(row, "constraint_name")),
+                expression: text(row, "definition"),
+            });
+        }
+    }
+    
+    // Indexes backing primary keys and unique constraints come with the constraint
+    let index_columns = fetch_rows(&mut conn, r#"
+        SELECT 
+            s.name AS schema_name,
+            t.name AS table_name,
+            i.name AS index_name,
+            i.is_unique,
+            i.type_desc,
+            i.filter_definition,
+            col.name AS column_name,
+            ic.is_descending_key,
+            ic.is_included_column
+        FROM sys.indexes i
+        INNER JOIN sys.tables t ON t.object_id = i.object_id
+        INNER JOIN sys.schemas s ON t.schema_id = s.schema_id
+        INNER JOIN sys.index_columns ic ON ic.object_id = i.object_id AND ic.index_id = i.index_id
+        INNER JOIN sys.columns col ON col.object_id = ic.object_id AND col.column_id = ic.column_id
+        WHERE t.is_ms_shipped = 0
+        AND i.is_primary_key = 0
+        AND i.is_unique_constraint = 0
+        AND i.is_hypothetical = 0
+        AND i.type IN (1, 2)
+        ORDER BY s.name, t.name, i.name, ic.is_included_column, ic.key_ordinal, ic.index_column_id
+    "#).await?;
+    
+    // Key columns, included columns, unique, clustered and filter, per index
+    type IndexParts = (Vec<String>, Vec<String>, bool, String, Option<String>);
+    let mut parts: Vec<((String, String, String), IndexParts)> = Vec::new();
+    for row in &index_columns {
+        let key = (text(row, "schema_name"), text(row, "table_name"), text(row, "index_name"));
+        let pos = match parts.iter().position(|(k, _)| *k == key) {
+            Some(pos) => pos,
+            None => {
+                parts.push((key, (
+                    Vec::new(),
+                    Vec::new(),
+                    row.get::<bool, _>("is_unique").unwrap_or(false),
+                    text(row, "type_desc"),
+                    row.get::<&str, _>("filter_definition").map(str::to_string),
+                )));
+                parts.len() - 1
+            }
+        };
+        let column = dialect::quote_ident(&DatabaseType::SQLServer, &text(row, "column_name"));
+        let entry = &mut parts[pos].1;
+        if row.get::<bool, _>("is_included_column").unwrap_or(false) {
+            entry.1.push(column);
+        } else if row.get::<bool, _>("is_descending_key").unwrap_or(false) {
+            entry.0.push(format!("{} DESC", column));
+        } else {
+            entry.0.push(column);
+        }
+    }
+    
+    for ((schema, table_name, name), (columns, included, unique, kind, filter)) in parts {
+        let Some(&idx) = index.get(&(schema.clone(), table_name.clone())) else {
+            continue;
+        };
+        let mut definition = format!(
+            "CREATE {}{} INDEX {} ON {} ({})",
+            if unique { "UNIQUE " } else { "" },
+            kind,
+            dialect::quote_ident(&DatabaseType::SQLServer, &name),
+            dialect::quote_table(&DatabaseType::SQLServer, &qualify(Some(&schema), &table_name)),
+            columns.join(", "),
+        );
+        if !included.is_empty() {
+            definition.push_str(&format!(" INCLUDE ({})", included.join(", ")));
+        }
+        if let Some(filter) = filter {
+            definition.push_str(&format!(" WHERE {}", filter));
+        }
+        tables[idx].indexes.push(IndexDefinition { name, columns, unique, definition: Some(definition) });
+    }
+    
+    // Encrypted views have no readable definition and are skipped
+    let views = fetch_rows(&mut conn, r#"
+        SELECT s.name AS schema_name, v.name AS view_name, m.definition
+        FROM sys.views v
+        INNER JOIN sys.schemas s ON v.schema_id = s.schema_id
+        INNER JOIN sys.sql_modules m ON m.object_id = v.object_id
+        WHERE v.is_ms_shipped = 0
+        AND m.definition IS NOT NULL
+        ORDER BY s.name, v.name
+    "#).await?;
+    
+    let views = views
+        .iter()
+        .map(|row| ViewDefinition {
+            schema: Some(text(row, "schema_name")),
+            name: text(row, "view_name"),
+            definition: text(row, "definition").trim().to_string(),
+        })
+        .collect();
+    
+    let sequences = fetch_rows(&mut conn, r#"
+        SELECT 
+            s.name AS schema_name,
+            sq.name AS sequence_name,
+            TYPE_NAME(sq.user_type_id) AS type_name,
+            CAST(sq.start_value AS BIGINT) AS start_value,
+            CAST(sq.increment AS BIGINT) AS increment,
+            CAST(sq.minimum_value AS BIGINT) AS minimum_value,
+            CAST(sq.maximum_value AS BIGINT) AS maximum_value,
+            sq.is_cycling,
+            CAST(sq.last_used_value AS BIGINT) AS last_used_value
+        FROM sys.sequences sq
+        INNER JOIN sys.schemas s ON sq.schema_id = s.schema_id
+        ORDER BY s.name, sq.name
+    "#).await?;
+    
+    let sequences = sequences
+        .iter()
+        .map(|row| SequenceDefinition {
+            schema: Some(text(row, "schema_name")),
+            name: text(row, "sequence_name"),
+            data_type: text(row, "type_name"),
+            start: row.get::<i64, _>("start_value").unwrap_or(1),
+            increment: row.get::<i64, _>("increment").unwrap_or(1),
+            min_value: row.get::<i64, _>("minimum_value").unwrap_or(i64::MIN),
+            max_value: row.get::<i64, _>("maximum_value").unwrap_or(i64::MAX),
+            cycle: row.get::<bool, _>("is_cycling").unwrap_or(false),
+            last_value: row.get::<i64, _>("last_used_value"),
+            owned_by: None,
+            identity: false,
+        })
+        .collect();
+    
+    Ok(SchemaSnapshot { tables, views, sequences })
+}
@@ -0,0 +1,123 @@
+//! Pool and session diagnostics, to tell why queries wait for a connection.
+
+use std::future::Future;
+use std::time::{Duration, Instant};
+use crate::models::*;
+use super::temporal::TemporalFormat;
+use super::{execute_sql_query, DatabaseConnection};
+
+/// One row of text columns named after the fields of `SessionInfo`.
+const POSTGRES_SESSION: &str = "SELECT pg_backend_pid()::text AS backend_pid, current_user::text AS user_name, \
+    current_database()::text AS database_name, current_schema()::text AS schema_name, \
+    current_setting('server_version') AS server_version, current_setting('server_encoding') AS encoding, \
+    current_setting('TimeZone') AS timezone";
+
+const MYSQL_SESSION: &str = "SELECT CAST(CONNECTION_ID() AS CHAR) AS backend_pid, CAST(CURRENT_USER() AS CHAR) AS user_name, \
+    CAST(DATABASE() AS CHAR) AS database_name, CAST(DATABASE() AS CHAR) AS schema_name, \
+    CAST(VERSION() AS CHAR) AS server_version, CAST(@@character_set_connection AS CHAR) AS encoding, \
+    CAST(@@session.time_zone AS CHAR) AS timezone";
+
+const SQLITE_SESSION: &str = "SELECT NULL AS backend_pid, NULL AS user_name, \
+    (SELECT file FROM pragma_database_list WHERE name = 'main') AS database_name, 'main' AS schema_name, \
+    sqlite_version() AS server_version, (SELECT encoding FROM pragma_encoding) AS encoding, NULL AS timezone";
+
+const SQLSERVER_SESSION: &str = "SELECT CAST(@@SPID AS NVARCHAR(20)) AS backend_pid, SUSER_SNAME() AS user_name, \
+    DB_NAME() AS database_name, SCHEMA_NAME() AS schema_name, \
+    CAST(SERVERPROPERTY('ProductVersion') AS NVARCHAR(128)) AS server_version, \
+    CAST(DATABASEPROPERTYEX(DB_NAME(), 'Collation') AS NVARCHAR(128)) AS encoding, \
+    DATENAME(TZOFFSET, SYSDATETIMEOFFSET()) AS timezone";
+
+/// How long the report waits for a free connection. A starved pool is
+/// what it has to show, so it does not wait out the acquire timeout.
+const ACQUIRE_PROBE: Duration = Duration::from_secs(1);
+
+/// Counts are read from the pool's state without taking a connection, so
+/// they show the pool as the app left it.
+pub async fn pool_stats(conn: &DatabaseConnection, config: &ConnectionConfig) -> Result<PoolStats, String> {
+    match conn {
+        DatabaseConnection::PostgreSQL(pool) => Ok(sqlx_stats(pool).await),
+        DatabaseConnection::MySQL(pool) => Ok(sqlx_stats(pool).await),
+        DatabaseConnection::SQLite(pool) => Ok(sqlx_stats(pool).await),
+        DatabaseConnection::SQLServer(pool) => {
+            // bb8 does not report its limits
+            let settings = config.pool.settings(&config.db_type)?;
+            let state = pool.state();
+            let acquire_ms = probe(pool.get()).await;
+            Ok(PoolStats {
+                size: state.connections,
+                idle: state.idle_connections,
+                in_use: state.connections.saturating_sub(state.idle_connections),
+                max_connections: settings.max_connections,
+                min_connections: settings.min_connections,
+                acquire_ms,
+                waited: Some(state.statistics.get_waited),
+                timed_out: Some(state.statistics.get_timed_out),
+                total_wait_ms: Some(state.statistics.get_wait_time.as_millis() as u64),
+                connections_created: Some(state.statistics.connections_created),
+            })
+        }
+    }
+}
+
+/// sqlx keeps no history, so the wait is only the one measured here.
+async fn sqlx_stats<DB: sqlx::Database>(pool: &sqlx::Pool<DB>) -> PoolStats {
+    let size = pool.size();
+    let idle = pool.num_idle() as u32;
+    let acquire_ms = probe(pool.acquire()).await;
+    PoolStats {
+        size,
+        idle,
+        in_use: size.saturating_sub(idle),
+        max_connections: pool.options().get_max_connections(),
+        min_connections: pool.options().get_min_connections(),
+        acquire_ms,
+        waited: None,
+        timed_out: None,
+        total_wait_ms: None,
+        connections_created: None,
+    }
+}
+
+/// Time taking a connection and handing it straight back; `None` if none
+/// came within `ACQUIRE_PROBE` or the pool failed to open one. Giving up
+/// on the wait leaves no connection half used.
+async fn probe<T, E: std::fmt::Display>(acquire: impl Future<Output = Result<T, E>>) -> Option<f64> {
+    let started = Instant::now();
+    match tokio::time::timeout(ACQUIRE_PROBE, acquire).await {
+        Ok(Ok(_)) => Some(started.elapsed().as_secs_f64() * 1000.0),
+        Ok(Err(e)) => {
+            log::warn!("[Stats] Could not get a connection: {}", e);
+            None
+        }
+        Err(_) => None,
+    }
+}
+
+pub async fn session_info(conn: &DatabaseConnection) -> Result<SessionInfo, String> {
+    let sql = match conn {
+        DatabaseConnection::PostgreSQL(_) => POSTGRES_SESSION,
+        DatabaseConnection::MySQL(_) => MYSQL_SESSION,
+        DatabaseConnection::SQLite(_) => SQLITE_SESSION,
+        DatabaseConnection::SQLServer(_) => SQLSERVER_SESSION,
+    };
+    let result = execute_sql_query(conn, sql, &TemporalFormat::default())
+        .await
+        .map_err(|e| format!("Failed to read session info: {}", e))?;
+    let Some(row) = result.rows.first() else {
+        return Ok(SessionInfo::default());
+    };
+    let text = |column: &str| match row.get(column) {
+        Some(serde_json::Value::String(s)) => Some(s.clone()),
+        Some(serde_json::Value::Null) | None => None,
+        Some(other) => Some(other.to_string()),
+    };
+    Ok(SessionInfo {
+        backend_pid: text("backend_pid"),
+        user: text("user_name"),
+        database: text("database_name"),
+        schema: text("schema_name"),
+        server_version: text("server_version"),
+        encoding: text("encoding"),
+        timezone: text("timezone"),
+    })
+}
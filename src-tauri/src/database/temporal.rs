@@ -0,0 +1,266 @@
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, SecondsFormat, TimeZone};
+use chrono_tz::Tz;
+
+/// A timezone as configured by the user or reported by the server session.
+#[derive(Debug, Clone)]
+pub enum TimeZoneSpec {
+    /// Keep whatever offset the value carries
+    Original,
+    Utc,
+    Local,
+    Fixed(FixedOffset),
+    Named(Tz),
+}
+
+impl TimeZoneSpec {
+    /// Parse `UTC`, `local`, a fixed offset such as `+05:30`, or an IANA name
+    /// such as `America/Lima`. Empty input means "keep the original offset".
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let value = value.trim();
+
+        if value.is_empty() || value.eq_ignore_ascii_case("original") {
+            return Ok(TimeZoneSpec::Original);
+        }
+        if value.eq_ignore_ascii_case("utc") || value.eq_ignore_ascii_case("z") || value.eq_ignore_ascii_case("etc/utc") {
+            return Ok(TimeZoneSpec::Utc);
+        }
+        if value.eq_ignore_ascii_case("local") || value.eq_ignore_ascii_case("localtime") || value.eq_ignore_ascii_case("system") {
+            return Ok(TimeZoneSpec::Local);
+        }
+        if let Some(offset) = parse_offset(value) {
+            return Ok(TimeZoneSpec::Fixed(offset));
+        }
+
+        value
+            .parse::<Tz>()
+            .map(TimeZoneSpec::Named)
+            .map_err(|_| format!("Unknown timezone: {}", value))
+    }
+
+    fn is_original(&self) -> bool {
+        matches!(self, TimeZoneSpec::Original)
+    }
+
+    fn convert(&self, dt: DateTime<FixedOffset>) -> DateTime<FixedOffset> {
+        match self {
+            TimeZoneSpec::Original => dt,
+            TimeZoneSpec::Utc => dt.with_timezone(&chrono::Utc).fixed_offset(),
+            TimeZoneSpec::Local => dt.with_timezone(&chrono::Local).fixed_offset(),
+            TimeZoneSpec::Fixed(offset) => dt.with_timezone(offset),
+            TimeZoneSpec::Named(tz) => dt.with_timezone(tz).fixed_offset(),
+        }
+    }
+
+    /// Interpret a wall-clock time as a local time in this zone.
+    fn localize(&self, naive: &NaiveDateTime) -> Option<DateTime<FixedOffset>> {
+        match self {
+            TimeZoneSpec::Original | TimeZoneSpec::Utc => Some(naive.and_utc().fixed_offset()),
+            TimeZoneSpec::Local => chrono::Local.from_local_datetime(naive).earliest().map(|d| d.fixed_offset()),
+            TimeZoneSpec::Fixed(offset) => offset.from_local_datetime(naive).single(),
+            TimeZoneSpec::Named(tz) => tz.from_local_datetime(naive).earliest().map(|d| d.fixed_offset()),
+        }
+    }
+}
+
+/// How temporal values are rendered for one connection.
+///
+/// `display` is the per-connection setting from `ConnectionConfig::display_timezone`;
+/// `session` is the server session timezone detected at connect time.
+/// Instants are shown in the display zone when one is set, otherwise in the
+/// session zone, otherwise with the offset they carry. Wall-clock values
+/// (`timestamp`, `DATETIME`, `datetime2`) are never shifted.
+#[derive(Debug, Clone)]
+pub struct TemporalFormat {
+    pub display: TimeZoneSpec,
+    pub session: TimeZoneSpec,
+}
+
+impl Default for TemporalFormat {
+    fn default() -> Self {
+        Self {
+            display: TimeZoneSpec::Original,
+            session: TimeZoneSpec::Original,
+        }
+    }
+}
+
+impl TemporalFormat {
+    /// Format an instant (timestamptz, datetimeoffset, MySQL TIMESTAMP) as ISO-8601 with offset.
+    pub fn format_instant(&self, dt: DateTime<FixedOffset>) -> String {
+        let target = if self.display.is_original() { &self.session } else { &self.display };
+        target.convert(dt).to_rfc3339_opts(SecondsFormat::AutoSi, true)
+    }
+
+    /// Format a wall-clock value that the server has already rendered in the
+    /// session timezone (MySQL TIMESTAMP).
+    pub fn format_session_local(&self, naive: NaiveDateTime) -> String {
+        if self.display.is_original() && self.session.is_original() {
+            return format_datetime(naive);
+        }
+        match self.session.localize(&naive) {
+            Some(dt) => self.format_instant(dt),
+            None => format_datetime(naive),
+        }
+    }
+}
+
+/// Full-precision ISO-8601 rendering of a datetime without timezone.
+pub fn format_datetime(v: NaiveDateTime) -> String {
+    v.format("%Y-%m-%dT%H:%M:%S%.f").to_string()
+}
+
+pub fn format_date(v: NaiveDate) -> String {
+    v.format("%Y-%m-%d").to_string()
+}
+
+/// Full-precision rendering of a time of day.
+pub fn format_time(v: NaiveTime) -> String {
+    v.format("%H:%M:%S%.f").to_string()
+}
+
+/// Format a time of day with offset (PostgreSQL `timetz`).
+pub fn format_time_tz(v: NaiveTime, offset: FixedOffset) -> String {
+    format!("{}{}", format_time(v), offset)
+}
+
+/// Parse a textual datetime that carries an explicit offset, as stored in
+/// SQLite or printed by PostgreSQL (`+00`, with the minutes left out).
+pub fn parse_offset_datetime(value: &str) -> Option<DateTime<FixedOffset>> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+        return Some(dt);
+    }
+    ["%F %T%.f%:z", "%F %T%.f%#z", "%F %T%.fZ", "%F %R%:z", "%FT%R%:z"]
+        .iter()
+        .find_map(|format| {
+            if format.ends_with('Z') {
+                NaiveDateTime::parse_from_str(value, format).ok().map(|n| n.and_utc().fixed_offset())
+            } else {
+                DateTime::parse_from_str(value, format).ok()
+            }
+        })
+}
+
+/// Parse offsets such as `+05:30`, `-0800` or `+05`.
+fn parse_offset(value: &str) -> Option<FixedOffset> {
+    let sign = match value.chars().next()? {
+        '+' => 1,
+        '-' => -1,
+        _ => return None,
+    };
+
+    let digits: String = value[1..].chars().filter(|c| *c != ':').collect();
+    if digits.is_empty() || digits.len() > 4 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let (hours, minutes) = if digits.len() <= 2 {
+        (digits.parse::<i32>().ok()?, 0)
+    } else {
+        let split = digits.len() - 2;
+        (digits[..split].parse::<i32>().ok()?, digits[split..].parse::<i32>().ok()?)
+    };
+
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instant(text: &str) -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339(text).unwrap()
+    }
+
+    fn naive(text: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(text, "%F %T").unwrap()
+    }
+
+    fn format(display: &str, session: &str) -> TemporalFormat {
+        TemporalFormat {
+            display: TimeZoneSpec::parse(display).unwrap(),
+            session: TimeZoneSpec::parse(session).unwrap(),
+        }
+    }
+
+    #[test]
+    fn parse_specs() {
+        let offset = |seconds: i32| FixedOffset::east_opt(seconds).unwrap();
+        let cases = [
+            ("+05:30", offset(5 * 3600 + 30 * 60)),
+            ("-0800", offset(-8 * 3600)),
+            ("+05", offset(5 * 3600)),
+            ("-00:30", offset(-30 * 60)),
+        ];
+        for (text, expected) in cases {
+            match TimeZoneSpec::parse(text) {
+                Ok(TimeZoneSpec::Fixed(parsed)) => assert_eq!(parsed, expected, "{}", text),
+                other => panic!("{}: {:?}", text, other),
+            }
+        }
+
+        assert!(matches!(TimeZoneSpec::parse(""), Ok(TimeZoneSpec::Original)));
+        assert!(matches!(TimeZoneSpec::parse(" original "), Ok(TimeZoneSpec::Original)));
+        assert!(matches!(TimeZoneSpec::parse("Etc/UTC"), Ok(TimeZoneSpec::Utc)));
+        assert!(matches!(TimeZoneSpec::parse("SYSTEM"), Ok(TimeZoneSpec::Local)));
+        assert!(matches!(TimeZoneSpec::parse("America/Lima"), Ok(TimeZoneSpec::Named(chrono_tz::America::Lima))));
+
+        for invalid in ["Mars/Olympus", "+12345", "+24:00", "05:00"] {
+            assert!(TimeZoneSpec::parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn instants_in_the_display_zone() {
+        let dt = instant("2024-01-15T12:00:00.5+00:00");
+        assert_eq!(format("", "").format_instant(dt), "2024-01-15T12:00:00.500Z");
+        assert_eq!(format("+05:30", "").format_instant(dt), "2024-01-15T17:30:00.500+05:30");
+        assert_eq!(format("America/Lima", "").format_instant(dt), "2024-01-15T07:00:00.500-05:00");
+        // The display zone wins over the session zone
+        assert_eq!(format("UTC", "+02:00").format_instant(instant("2024-01-15T14:00:00+02:00")), "2024-01-15T12:00:00Z");
+        assert_eq!(format("", "+02:00").format_instant(dt), "2024-01-15T14:00:00.500+02:00");
+    }
+
+    #[test]
+    fn named_zones_follow_dst() {
+        let berlin = format("Europe/Berlin", "");
+        assert_eq!(berlin.format_instant(instant("2024-01-15T12:00:00Z")), "2024-01-15T13:00:00+01:00");
+        assert_eq!(berlin.format_instant(instant("2024-07-15T12:00:00Z")), "2024-07-15T14:00:00+02:00");
+        // Either side of the spring change
+        assert_eq!(berlin.format_instant(instant("2024-03-31T00:59:59Z")), "2024-03-31T01:59:59+01:00");
+        assert_eq!(berlin.format_instant(instant("2024-03-31T01:00:00Z")), "2024-03-31T03:00:00+02:00");
+    }
+
+    #[test]
+    fn session_local_values() {
+        // Neither zone known: shown as the server rendered it
+        assert_eq!(format("", "").format_session_local(naive("2024-07-01 12:00:00")), "2024-07-01T12:00:00");
+
+        let session = format("", "Europe/Berlin");
+        assert_eq!(session.format_session_local(naive("2024-01-15 12:00:00")), "2024-01-15T12:00:00+01:00");
+        assert_eq!(session.format_session_local(naive("2024-07-15 12:00:00")), "2024-07-15T12:00:00+02:00");
+        // The repeated autumn hour takes its first, summer-time, occurrence
+        assert_eq!(session.format_session_local(naive("2024-10-27 02:30:00")), "2024-10-27T02:30:00+02:00");
+        // A time skipped by the spring change is kept as written
+        assert_eq!(session.format_session_local(naive("2024-03-31 02:30:00")), "2024-03-31T02:30:00");
+
+        let shifted = format("UTC", "Europe/Berlin");
+        assert_eq!(shifted.format_session_local(naive("2024-07-15 12:00:00")), "2024-07-15T10:00:00Z");
+        assert_eq!(format("UTC", "-03:00").format_session_local(naive("2024-07-15 12:00:00")), "2024-07-15T15:00:00Z");
+    }
+
+    #[test]
+    fn offset_datetimes() {
+        let cases = [
+            ("2024-01-15T12:00:00+02:00", "2024-01-15T12:00:00+02:00"),
+            ("2024-01-15 12:00:00.25+02", "2024-01-15T12:00:00.250+02:00"),
+            ("2024-01-15 12:00:00-0330", "2024-01-15T12:00:00-03:30"),
+            ("2024-01-15 12:00:00Z", "2024-01-15T12:00:00+00:00"),
+            ("2024-01-15 12:00+05:30", "2024-01-15T12:00:00+05:30"),
+        ];
+        for (text, expected) in cases {
+            let parsed = parse_offset_datetime(text).unwrap_or_else(|| panic!("{}", text));
+            assert_eq!(parsed.to_rfc3339_opts(SecondsFormat::AutoSi, false), expected, "{}", text);
+        }
+        assert!(parse_offset_datetime("2024-01-15 12:00:00").is_none());
+    }
+}
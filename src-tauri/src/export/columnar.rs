@@ -0,0 +1,428 @@
+use std::io::Write;
+use std::sync::Arc;
+use arrow::array::{
+    ArrayRef, BinaryArray, BooleanArray, Date32Array, Decimal128Array, Float64Array, Int64Array,
+    StringArray, Time64NanosecondArray, TimestampMicrosecondArray, UInt64Array,
+};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use arrow::ipc::writer::FileWriter;
+use arrow::record_batch::RecordBatch;
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::{BrotliLevel, Compression, GzipLevel, ZstdLevel};
+use parquet::file::properties::WriterProperties;
+use crate::database::dialect::{self, ColumnKind};
+use crate::database::{temporal, RowSink};
+use crate::models::*;
+use super::value_to_text;
+
+/// Rows buffered before they are converted into one Arrow record batch.
+const BATCH_SIZE: usize = 8192;
+
+/// Which columnar file is written.
+pub enum ColumnarFormat {
+    Parquet(Box<WriterProperties>),
+    ArrowIpc,
+}
+
+impl ColumnarFormat {
+    pub fn parquet(options: &ExportOptions) -> Result<Self, String> {
+        let level = options.compression_level;
+        let compression = match options.compression {
+            ParquetCompression::None => Compression::UNCOMPRESSED,
+            ParquetCompression::Snappy => Compression::SNAPPY,
+            ParquetCompression::Lz4 => Compression::LZ4_RAW,
+            ParquetCompression::Gzip => Compression::GZIP(match level {
+                Some(l) => GzipLevel::try_new(l).map_err(|e| format!("Invalid compression level: {}", e))?,
+                None => GzipLevel::default(),
+            }),
+            ParquetCompression::Zstd => Compression::ZSTD(match level {
+                Some(l) => ZstdLevel::try_new(l as i32).map_err(|e| format!("Invalid compression level: {}", e))?,
+                None => ZstdLevel::default(),
+            }),
+            ParquetCompression::Brotli => Compression::BROTLI(match level {
+                Some(l) => BrotliLevel::try_new(l).map_err(|e| format!("Invalid compression level: {}", e))?,
+                None => BrotliLevel::default(),
+            }),
+        };
+
+        let mut properties = WriterProperties::builder().set_compression(compression);
+        if let Some(size) = options.row_group_size {
+            properties = properties.set_max_row_group_size(size.max(1));
+        }
+
+        Ok(ColumnarFormat::Parquet(Box::new(properties.build())))
+    }
+}
+
+enum Writer<W: Write + Send> {
+    Parquet(ArrowWriter<W>),
+    Ipc(FileWriter<W>),
+}
+
+/// Parquet and Arrow IPC (Feather v2) output. Rows are buffered into record
+/// batches typed from the result column metadata. The file is started with
+/// the first batch: a column whose values there do not fit its type, such as
+/// text in an SQLite INTEGER column, is written as text instead.
+pub struct ColumnarSink<W: Write + Send> {
+    format: Option<ColumnarFormat>,
+    out: Option<W>,
+    source: DatabaseType,
+    writer: Option<Writer<W>>,
+    /// Schema from the column metadata, to tell result sets apart
+    declared: Option<SchemaRef>,
+    schema: Option<SchemaRef>,
+    kinds: Vec<ColumnKind>,
+    rows: Vec<Vec<serde_json::Value>>,
+}
+
+impl<W: Write + Send> ColumnarSink<W> {
+    pub fn new(out: W, format: ColumnarFormat, source: DatabaseType) -> Self {
+        Self {
+            format: Some(format),
+            out: Some(out),
+            source,
+            writer: None,
+            declared: None,
+            schema: None,
+            kinds: Vec::new(),
+            rows: Vec::new(),
+        }
+    }
+
+    /// Create the writer, with the declared schema adjusted to the first batch.
+    fn start(&mut self) -> Result<(), String> {
+        let Some(declared) = &self.declared else {
+            return Ok(());
+        };
+        let fields: Vec<Field> = declared
+            .fields()
+            .iter()
+            .enumerate()
+            .map(|(i, field)| {
+                if field.data_type() == &DataType::Utf8 || build_array(field, self.rows.iter().map(|row| &row[i])).is_ok() {
+                    return field.as_ref().clone();
+                }
+                log::warn!("[export] Column \"{}\" holds values that are not {}; writing it as text", field.name(), field.data_type());
+                Field::new(field.name(), DataType::Utf8, true)
+            })
+            .collect();
+        let schema = Arc::new(Schema::new(fields));
+
+        let out = self.out.take().ok_or_else(|| "Export file already closed".to_string())?;
+        let writer = match self.format.take() {
+            Some(ColumnarFormat::Parquet(properties)) => Writer::Parquet(
+                ArrowWriter::try_new(out, schema.clone(), Some(*properties))
+                    .map_err(|e| format!("Failed to create Parquet writer: {}", e))?,
+            ),
+            Some(ColumnarFormat::ArrowIpc) => Writer::Ipc(
+                FileWriter::try_new(out, &schema)
+                    .map_err(|e| format!("Failed to create Arrow writer: {}", e))?,
+            ),
+            None => return Err("Export file already closed".to_string()),
+        };
+
+        self.schema = Some(schema);
+        self.writer = Some(writer);
+        Ok(())
+    }
+
+    fn flush_batch(&mut self) -> Result<(), String> {
+        if self.writer.is_none() {
+            self.start()?;
+        }
+        if self.rows.is_empty() {
+            return Ok(());
+        }
+        let (Some(schema), Some(writer)) = (&self.schema, &mut self.writer) else {
+            return Ok(());
+        };
+
+        let rows = std::mem::take(&mut self.rows);
+        let arrays = schema
+            .fields()
+            .iter()
+            .enumerate()
+            .map(|(i, field)| build_array(field, rows.iter().map(|row| &row[i])))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let batch = RecordBatch::try_new(schema.clone(), arrays)
+            .map_err(|e| format!("Failed to build record batch: {}", e))?;
+
+        match writer {
+            Writer::Parquet(w) => w.write(&batch).map_err(|e| format!("Failed to write file: {}", e)),
+            Writer::Ipc(w) => w.write(&batch).map_err(|e| format!("Failed to write file: {}", e)),
+        }
+    }
+}
+
+impl<W: Write + Send> RowSink for ColumnarSink<W> {
+    fn begin(&mut self, columns: &[ResultColumn]) -> Result<(), String> {
+        let schema = Arc::new(Schema::new(
+            columns.iter().map(|c| arrow_field(c, &self.source)).collect::<Vec<_>>(),
+        ));
+
+        if let Some(existing) = &self.declared {
+            if existing.fields() != schema.fields() {
+                return Err("Parquet and Arrow files hold a single result set, but the query returned several".to_string());
+            }
+            return Ok(());
+        }
+        if columns.is_empty() {
+            return Err("The query did not return a result set".to_string());
+        }
+
+        self.kinds = columns.iter().map(|c| dialect::column_kind(&c.type_name)).collect();
+        self.declared = Some(schema);
+        Ok(())
+    }
+
+    fn write_row(&mut self, values: &[serde_json::Value]) -> Result<(), String> {
+        if values.len() != self.kinds.len() {
+            return Err("Row does not match the result columns".to_string());
+        }
+        self.rows.push(values.to_vec());
+        if self.rows.len() >= BATCH_SIZE {
+            self.flush_batch()?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), String> {
+        self.flush_batch()?;
+        match self.writer.take() {
+            Some(Writer::Parquet(w)) => w.close().map(|_| ()).map_err(|e| format!("Failed to write file: {}", e)),
+            Some(Writer::Ipc(mut w)) => w.finish().map_err(|e| format!("Failed to write file: {}", e)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Map a result column to an Arrow field using its engine type name. Types
+/// whose values may not fit the closest Arrow type are written as text.
+fn arrow_field(column: &ResultColumn, source: &DatabaseType) -> Field {
+    let upper = column.type_name.to_uppercase();
+    let data_type = match dialect::column_kind(&column.type_name) {
+        // Only SQL Server's bit is a boolean; elsewhere it is a bit string
+        ColumnKind::Boolean if upper.starts_with("BIT") && !matches!(source, DatabaseType::SQLServer) => DataType::Utf8,
+        ColumnKind::Boolean => DataType::Boolean,
+        ColumnKind::Integer if upper.contains("BIGINT UNSIGNED") => DataType::UInt64,
+        ColumnKind::Integer => DataType::Int64,
+        ColumnKind::Float => DataType::Float64,
+        // PostgreSQL formats money with the currency symbol of its locale
+        ColumnKind::Decimal if upper.starts_with("MONEY") => match source {
+            DatabaseType::SQLServer if upper == "MONEY4" => DataType::Decimal128(10, 4),
+            DatabaseType::SQLServer => DataType::Decimal128(19, 4),
+            _ => DataType::Utf8,
+        },
+        // Unbounded decimals keep every digit as text
+        ColumnKind::Decimal => match (column.precision, column.scale) {
+            (Some(p), Some(s)) if (1..=38).contains(&p) && s <= p => DataType::Decimal128(p as u8, s as i8),
+            _ => DataType::Utf8,
+        },
+        ColumnKind::Date => DataType::Date32,
+        // MySQL's TIME is a duration that may be negative or exceed a day
+        ColumnKind::Time if matches!(source, DatabaseType::MySQL) => DataType::Utf8,
+        ColumnKind::Time => DataType::Time64(TimeUnit::Nanosecond),
+        ColumnKind::DateTime => DataType::Timestamp(TimeUnit::Microsecond, None),
+        ColumnKind::Timestamp => DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+        ColumnKind::Binary => DataType::Binary,
+        ColumnKind::Json | ColumnKind::Text => DataType::Utf8,
+    };
+    Field::new(&column.name, data_type, column.nullable.unwrap_or(true))
+}
+
+fn build_array<'a>(
+    field: &Field,
+    values: impl Iterator<Item = &'a serde_json::Value>,
+) -> Result<ArrayRef, String> {
+    // Convert every non-null value, failing on the first one that does not fit
+    fn collect<'a, T>(
+        field: &Field,
+        values: impl Iterator<Item = &'a serde_json::Value>,
+        convert: impl Fn(&serde_json::Value) -> Option<T>,
+    ) -> Result<Vec<Option<T>>, String> {
+        values
+            .map(|v| {
+                if v.is_null() {
+                    return Ok(None);
+                }
+                convert(v).map(Some).ok_or_else(|| {
+                    format!("Value {} in column \"{}\" cannot be written as {}", v, field.name(), field.data_type())
+                })
+            })
+            .collect()
+    }
+
+    let array: ArrayRef = match field.data_type() {
+        DataType::Boolean => Arc::new(BooleanArray::from(collect(field, values, to_bool)?)),
+        DataType::Int64 => Arc::new(Int64Array::from(collect(field, values, to_i64)?)),
+        DataType::UInt64 => Arc::new(UInt64Array::from(collect(field, values, to_u64)?)),
+        DataType::Float64 => Arc::new(Float64Array::from(collect(field, values, to_f64)?)),
+        DataType::Decimal128(precision, scale) => {
+            let scale = *scale;
+            Arc::new(
+                Decimal128Array::from(collect(field, values, |v| to_decimal(v, scale))?)
+                    .with_precision_and_scale(*precision, scale)
+                    .map_err(|e| format!("Invalid decimal column \"{}\": {}", field.name(), e))?,
+            )
+        }
+        DataType::Date32 => Arc::new(Date32Array::from(collect(field, values, to_date32)?)),
+        DataType::Time64(_) => Arc::new(Time64NanosecondArray::from(collect(field, values, to_time_ns)?)),
+        DataType::Timestamp(_, Some(tz)) => Arc::new(
+            TimestampMicrosecondArray::from(collect(field, values, to_instant_us)?).with_timezone(tz.clone()),
+        ),
+        DataType::Timestamp(_, None) => {
+            Arc::new(TimestampMicrosecondArray::from(collect(field, values, to_datetime_us)?))
+        }
+        DataType::Binary => {
+            let bytes = collect(field, values, to_bytes)?;
+            Arc::new(BinaryArray::from_iter(bytes))
+        }
+        _ => Arc::new(StringArray::from(collect(field, values, |v| Some(value_to_text(v)))?)),
+    };
+    Ok(array)
+}
+
+fn to_bool(v: &serde_json::Value) -> Option<bool> {
+    match v {
+        serde_json::Value::Bool(b) => Some(*b),
+        serde_json::Value::Number(n) => n.as_f64().map(|n| n != 0.0),
+        serde_json::Value::String(s) => match s.to_lowercase().as_str() {
+            "true" | "t" | "1" => Some(true),
+            "false" | "f" | "0" => Some(false),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn to_i64(v: &serde_json::Value) -> Option<i64> {
+    match v {
+        serde_json::Value::Number(n) => n.as_i64(),
+        serde_json::Value::Bool(b) => Some(*b as i64),
+        serde_json::Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+fn to_u64(v: &serde_json::Value) -> Option<u64> {
+    match v {
+        serde_json::Value::Number(n) => n.as_u64(),
+        serde_json::Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+fn to_f64(v: &serde_json::Value) -> Option<f64> {
+    match v {
+        serde_json::Value::Number(n) => n.as_f64(),
+        serde_json::Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+fn to_decimal(v: &serde_json::Value, scale: i8) -> Option<i128> {
+    match v {
+        serde_json::Value::Number(n) => dialect::parse_decimal(&n.to_string(), scale.max(0) as u32),
+        serde_json::Value::String(s) => dialect::parse_decimal(s, scale.max(0) as u32),
+        _ => None,
+    }
+}
+
+fn to_date32(v: &serde_json::Value) -> Option<i32> {
+    let date = NaiveDate::parse_from_str(v.as_str()?.get(..10)?, "%Y-%m-%d").ok()?;
+    Some((date - NaiveDate::from_ymd_opt(1970, 1, 1)?).num_days() as i32)
+}
+
+fn to_time_ns(v: &serde_json::Value) -> Option<i64> {
+    let time = NaiveTime::parse_from_str(v.as_str()?, "%H:%M:%S%.f").ok()?;
+    Some(time.num_seconds_from_midnight() as i64 * 1_000_000_000 + time.nanosecond() as i64)
+}
+
+/// Wall-clock datetimes are kept as shown; values carrying an offset keep their local time.
+fn to_datetime_us(v: &serde_json::Value) -> Option<i64> {
+    let s = v.as_str()?;
+    let naive = ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(s, format).ok())
+        .or_else(|| temporal::parse_offset_datetime(s).map(|dt| dt.naive_local()))
+        .or_else(|| NaiveDate::parse_from_str(s, "%Y-%m-%d").ok().and_then(|d| d.and_hms_opt(0, 0, 0)))?;
+    Some(naive.and_utc().timestamp_micros())
+}
+
+fn to_instant_us(v: &serde_json::Value) -> Option<i64> {
+    let s = v.as_str()?;
+    match temporal::parse_offset_datetime(s) {
+        Some(dt) => Some(dt.timestamp_micros()),
+        // Instants without an offset are already in UTC
+        None => to_datetime_us(v),
+    }
+}
+
+fn to_bytes(v: &serde_json::Value) -> Option<Vec<u8>> {
+    let s = v.as_str()?;
+    // Binary values that are valid UTF-8 arrive as plain text on MySQL and SQLite
+    Some(dialect::decode_hex_cell(s).unwrap_or_else(|| s.as_bytes().to_vec()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn column(type_name: &str, precision: Option<u32>, scale: Option<u32>) -> ResultColumn {
+        ResultColumn { precision, scale, ..ResultColumn::new("c", type_name) }
+    }
+
+    fn arrow_type(source: DatabaseType, type_name: &str) -> DataType {
+        arrow_field(&column(type_name, None, None), &source).data_type().clone()
+    }
+
+    /// Write one column of `values` as an Arrow file and return the type it got.
+    fn written_type(source: DatabaseType, column: ResultColumn, values: &[serde_json::Value]) -> DataType {
+        let mut sink = ColumnarSink::new(Vec::new(), ColumnarFormat::ArrowIpc, source);
+        sink.begin(&[column]).unwrap();
+        for value in values {
+            sink.write_row(std::slice::from_ref(value)).unwrap();
+        }
+        sink.finish().unwrap();
+        sink.schema.unwrap().field(0).data_type().clone()
+    }
+
+    #[test]
+    fn types_that_may_not_fit_are_written_as_text() {
+        assert_eq!(arrow_type(DatabaseType::PostgreSQL, "BIT"), DataType::Utf8);
+        assert_eq!(arrow_type(DatabaseType::PostgreSQL, "VARBIT"), DataType::Utf8);
+        assert_eq!(arrow_type(DatabaseType::MySQL, "BIT"), DataType::Utf8);
+        assert_eq!(arrow_type(DatabaseType::SQLServer, "Bitn"), DataType::Boolean);
+        assert_eq!(arrow_type(DatabaseType::PostgreSQL, "MONEY"), DataType::Utf8);
+        assert_eq!(arrow_type(DatabaseType::SQLServer, "Money"), DataType::Decimal128(19, 4));
+        assert_eq!(arrow_type(DatabaseType::MySQL, "TIME"), DataType::Utf8);
+        assert_eq!(arrow_type(DatabaseType::PostgreSQL, "TIME"), DataType::Time64(TimeUnit::Nanosecond));
+    }
+
+    #[test]
+    fn only_bounded_decimals_are_decimal128() {
+        let bounded = arrow_field(&column("NUMERIC", Some(10), Some(2)), &DatabaseType::PostgreSQL);
+        assert_eq!(bounded.data_type(), &DataType::Decimal128(10, 2));
+        assert_eq!(arrow_type(DatabaseType::PostgreSQL, "NUMERIC"), DataType::Utf8);
+        let wide = arrow_field(&column("DECIMAL", Some(65), Some(30)), &DatabaseType::MySQL);
+        assert_eq!(wide.data_type(), &DataType::Utf8);
+    }
+
+    #[test]
+    fn columns_holding_other_values_fall_back_to_text() {
+        let values = [json!(1), json!("abc"), serde_json::Value::Null];
+        assert_eq!(written_type(DatabaseType::SQLite, column("INTEGER", None, None), &values), DataType::Utf8);
+        let values = [json!(1), json!(2)];
+        assert_eq!(written_type(DatabaseType::SQLite, column("INTEGER", None, None), &values), DataType::Int64);
+        let values = [json!("-838:59:59")];
+        assert_eq!(written_type(DatabaseType::PostgreSQL, column("TIME", None, None), &values), DataType::Utf8);
+    }
+
+    #[test]
+    fn empty_results_still_write_a_file() {
+        assert_eq!(written_type(DatabaseType::SQLite, column("INTEGER", None, None), &[]), DataType::Int64);
+    }
+}
@@ -0,0 +1,75 @@
+use std::io::Write;
+use crate::database::RowSink;
+use crate::models::*;
+use super::value_to_text;
+
+/// CSV and TSV output.
+pub struct DelimitedSink<W: Write> {
+    writer: csv::Writer<W>,
+    header: bool,
+    null_token: String,
+}
+
+impl<W: Write + Send> DelimitedSink<W> {
+    pub fn new(out: W, options: &ExportOptions, delimiter: char) -> Result<Self, String> {
+        let delimiter = ascii_byte(delimiter, "Delimiter")?;
+        let quote = ascii_byte(options.quote, "Quote character")?;
+
+        let quote_style = match options.quote_style {
+            CsvQuoteStyle::Necessary => csv::QuoteStyle::Necessary,
+            CsvQuoteStyle::Always => csv::QuoteStyle::Always,
+            CsvQuoteStyle::NonNumeric => csv::QuoteStyle::NonNumeric,
+            CsvQuoteStyle::Never => csv::QuoteStyle::Never,
+        };
+
+        let writer = csv::WriterBuilder::new()
+            .delimiter(delimiter)
+            .quote(quote)
+            .quote_style(quote_style)
+            // A script may return result sets with different widths
+            .flexible(true)
+            .from_writer(out);
+
+        Ok(Self {
+            writer,
+            header: options.header,
+            null_token: options.null_token.clone(),
+        })
+    }
+}
+
+impl<W: Write + Send> RowSink for DelimitedSink<W> {
+    fn begin(&mut self, columns: &[ResultColumn]) -> Result<(), String> {
+        if !self.header || columns.is_empty() {
+            return Ok(());
+        }
+        self.writer
+            .write_record(columns.iter().map(|c| c.name.as_str()))
+            .map_err(|e| format!("Failed to write file: {}", e))
+    }
+
+    fn write_row(&mut self, values: &[serde_json::Value]) -> Result<(), String> {
+        let fields = values.iter().map(|v| {
+            if v.is_null() {
+                self.null_token.clone()
+            } else {
+                value_to_text(v)
+            }
+        });
+        self.writer
+            .write_record(fields)
+            .map_err(|e| format!("Failed to write file: {}", e))
+    }
+
+    fn finish(&mut self) -> Result<(), String> {
+        self.writer.flush().map_err(|e| format!("Failed to write file: {}", e))
+    }
+}
+
+fn ascii_byte(c: char, what: &str) -> Result<u8, String> {
+    if c.is_ascii() {
+        Ok(c as u8)
+    } else {
+        Err(format!("{} must be a single ASCII character", what))
+    }
+}
@@ -0,0 +1,531 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::time::Instant;
+use tokio_util::sync::CancellationToken;
+use crate::database::temporal::TemporalFormat;
+use crate::database::{ddl, dialect, postgres, DatabaseConnection, RowSink};
+use crate::models::*;
+
+/// Dump file being written. Statements end with `;`, or with a `GO` line on
+/// SQL Server, where views and schemas must start their own batch.
+struct DumpWriter {
+    out: BufWriter<File>,
+    db_type: DatabaseType,
+}
+
+impl DumpWriter {
+    fn raw(&mut self, text: &str) -> Result<(), String> {
+        self.out
+            .write_all(text.as_bytes())
+            .map_err(|e| format!("Failed to write file: {}", e))
+    }
+
+    fn statement(&mut self, sql: &str) -> Result<(), String> {
+        match self.db_type {
+            DatabaseType::SQLServer => self.raw(&format!("{}\nGO\n", sql)),
+            _ => self.raw(&format!("{};\n", sql)),
+        }
+    }
+
+    fn section(&mut self, title: &str) -> Result<(), String> {
+        self.raw(&format!("\n-- {}\n", title))
+    }
+}
+
+/// Order items so each comes after the items it depends on, keeping the
+/// original order otherwise. Items in a dependency cycle are appended in
+/// their original order.
+pub(crate) fn dependency_order<T>(items: Vec<&T>, depends_on: impl Fn(&T, &T) -> bool) -> Vec<&T> {
+    let mut remaining = items;
+    let mut ordered: Vec<&T> = Vec::with_capacity(remaining.len());
+
+    while !remaining.is_empty() {
+        let ready = remaining.iter().position(|&item| {
+            !remaining
+                .iter()
+                .any(|&other| !std::ptr::eq(item, other) && depends_on(item, other))
+        });
+        match ready {
+            Some(pos) => ordered.push(remaining.remove(pos)),
+            None => {
+                ordered.append(&mut remaining);
+                break;
+            }
+        }
+    }
+    ordered
+}
+
+fn references(table: &TableDefinition, other: &TableDefinition) -> bool {
+    let name = other.qualified_name();
+    table.foreign_keys.iter().any(|fk| fk.referenced_table == name)
+}
+
+pub(crate) fn matches_filter(filter: &str, table: &TableDefinition) -> bool {
+    filter.eq_ignore_ascii_case(&table.name) || filter.eq_ignore_ascii_case(&table.qualified_name())
+}
+
+/// Columns that can be written back: generated columns and SQL Server row
+/// versions are filled in by the engine.
+fn insertable_columns<'a>(db_type: &DatabaseType, table: &'a TableDefinition) -> Vec<&'a ColumnDefinition> {
+    table
+        .columns
+        .iter()
+        .filter(|c| c.generated.is_none())
+        .filter(|c| {
+            !matches!(db_type, DatabaseType::SQLServer)
+                || !matches!(c.data_type.to_lowercase().as_str(), "timestamp" | "rowversion")
+        })
+        .collect()
+}
+
+/// Writes the rows of one table as `INSERT` statements or a `COPY` block.
+struct DataSink<'a> {
+    out: &'a mut DumpWriter,
+    table: String,
+    /// `INSERT INTO t (cols) VALUES ` or `COPY t (cols) FROM stdin;`
+    target: String,
+    copy: bool,
+    binary: Vec<bool>,
+    types: Vec<String>,
+    /// SQL Server needs `IDENTITY_INSERT` to write identity values
+    identity_insert: bool,
+    rows_per_statement: usize,
+    pending: Vec<String>,
+    started: bool,
+    rows: u64,
+    progress: &'a mut (dyn FnMut(u64) + Send),
+}
+
+impl DataSink<'_> {
+    fn start(&mut self) -> Result<(), String> {
+        self.started = true;
+        if self.identity_insert {
+            let sql = format!("SET IDENTITY_INSERT {} ON", dialect::quote_table(&self.out.db_type, &self.table));
+            self.out.statement(&sql)?;
+        }
+        if self.copy {
+            let header = format!("{}\n", self.target);
+            self.out.raw(&header)?;
+        }
+        Ok(())
+    }
+
+    fn literal(&self, idx: usize, value: &serde_json::Value) -> String {
+        let db_type = &self.out.db_type;
+        if self.binary[idx] {
+            if let Some(bytes) = value.as_str().and_then(dialect::decode_hex_cell) {
+                return dialect::quote_bytes(db_type, &bytes);
+            }
+        }
+        dialect::quote_column_literal(db_type, &self.types[idx], value)
+    }
+
+    fn flush_pending(&mut self) -> Result<(), String> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let statement = format!("{}\n  {}", self.target, self.pending.join(",\n  "));
+        self.pending.clear();
+        self.out.statement(&statement)
+    }
+}
+
+impl RowSink for DataSink<'_> {
+    fn begin(&mut self, _columns: &[ResultColumn]) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn write_row(&mut self, values: &[serde_json::Value]) -> Result<(), String> {
+        if !self.started {
+            self.start()?;
+        }
+
+        if self.copy {
+            let mut line = String::new();
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    line.push('\t');
+                }
+                match value {
+                    serde_json::Value::Null => line.push_str("\\N"),
+                    value => postgres::push_copy_text(&mut line, &super::value_to_text(value)),
+                }
+            }
+            line.push('\n');
+            self.out.raw(&line)?;
+        } else {
+            let tuple = values
+                .iter()
+                .enumerate()
+                .map(|(i, v)| self.literal(i, v))
+                .collect::<Vec<_>>()
+                .join(", ");
+            self.pending.push(format!("({})", tuple));
+            if self.pending.len() >= self.rows_per_statement {
+                self.flush_pending()?;
+            }
+        }
+
+        self.rows += 1;
+        (self.progress)(self.rows);
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), String> {
+        if !self.started {
+            return Ok(());
+        }
+        if self.copy {
+            self.out.raw("\\.\n")?;
+        } else {
+            self.flush_pending()?;
+        }
+        if self.identity_insert {
+            let sql = format!("SET IDENTITY_INSERT {} OFF", dialect::quote_table(&self.out.db_type, &self.table));
+            self.out.statement(&sql)?;
+        }
+        Ok(())
+    }
+}
+
+/// Write schema DDL and table data to a SQL file that recreates the database
+/// on the same engine. Tables are created and filled in foreign key order;
+/// foreign keys that cannot be satisfied that way (cycles, self references)
+/// are added after the data. The partial file is removed on failure.
+pub async fn dump_database(
+    conn: &DatabaseConnection,
+    path: &str,
+    options: &DumpOptions,
+    cancel: CancellationToken,
+    progress: &mut (dyn FnMut(u64) + Send),
+) -> Result<DumpSummary, String> {
+    let start = Instant::now();
+    let snapshot = crate::database::get_schema_snapshot(conn).await?;
+
+    let file = File::create(path).map_err(|e| format!("Failed to create file: {}", e))?;
+    let mut out = DumpWriter { out: BufWriter::new(file), db_type: conn.db_type() };
+
+    let result = write_dump(conn, &snapshot, &mut out, options, &cancel, progress).await;
+    let result = result.and_then(|summary| {
+        out.out.flush().map_err(|e| format!("Failed to write file: {}", e))?;
+        Ok(summary)
+    });
+    drop(out);
+
+    let mut summary = match result {
+        Ok(summary) => summary,
+        Err(e) => {
+            if let Err(remove_err) = std::fs::remove_file(path) {
+                log::warn!("[dump_database] Could not remove partial file {}: {}", path, remove_err);
+            }
+            return Err(e);
+        }
+    };
+
+    summary.path = path.to_string();
+    summary.bytes = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    summary.duration_ms = start.elapsed().as_millis() as u64;
+    log::info!(
+        "[dump_database] Dumped {} tables and {} rows ({} bytes) to {}",
+        summary.tables, summary.rows, summary.bytes, path,
+    );
+    Ok(summary)
+}
+
+async fn write_dump(
+    conn: &DatabaseConnection,
+    snapshot: &SchemaSnapshot,
+    out: &mut DumpWriter,
+    options: &DumpOptions,
+    cancel: &CancellationToken,
+    progress: &mut (dyn FnMut(u64) + Send),
+) -> Result<DumpSummary, String> {
+    let db_type = conn.db_type();
+    let with_schema = !matches!(options.content, DumpContent::DataOnly);
+    let with_data = !matches!(options.content, DumpContent::SchemaOnly);
+
+    let selected: Vec<&TableDefinition> = snapshot
+        .tables
+        .iter()
+        .filter(|t| options.tables.as_ref().is_none_or(|only| only.iter().any(|f| matches_filter(f, t))))
+        .filter(|t| !options.exclude_tables.iter().any(|f| matches_filter(f, t)))
+        .collect();
+    let tables = dependency_order(selected, references);
+    let table_names: HashSet<String> = tables.iter().map(|t| t.qualified_name()).collect();
+
+    // Sequences follow their table; free-standing ones are only dumped in full
+    let sequences: Vec<&SequenceDefinition> = snapshot
+        .sequences
+        .iter()
+        .filter(|s| match &s.owned_by {
+            Some((table, _)) => table_names.contains(table),
+            None => options.tables.is_none(),
+        })
+        .collect();
+    let created_sequences: Vec<&SequenceDefinition> = sequences.iter().copied().filter(|s| !s.identity).collect();
+
+    let views: Vec<&ViewDefinition> = match options.tables {
+        Some(_) => Vec::new(),
+        None => {
+            // A view that mentions another view's name is created after it
+            let all: Vec<&ViewDefinition> = snapshot.views.iter().collect();
+            dependency_order(all, |view, other| {
+                view.definition.to_lowercase().contains(&other.name.to_lowercase())
+            })
+        }
+    };
+
+    out.raw(&format!(
+        "-- Datablaze dump\n-- Engine: {:?}\n-- Created: {}\n",
+        db_type,
+        chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+    ))?;
+    match db_type {
+        DatabaseType::PostgreSQL => {
+            out.statement("SET client_encoding = 'UTF8'")?;
+            out.statement("SET standard_conforming_strings = on")?;
+        }
+        DatabaseType::MySQL => {
+            out.statement("SET NAMES utf8mb4")?;
+            out.statement("SET FOREIGN_KEY_CHECKS = 0")?;
+        }
+        DatabaseType::SQLite => out.statement("PRAGMA foreign_keys = OFF")?,
+        DatabaseType::SQLServer => {}
+    }
+
+    // Foreign keys created with their table; the rest are added after the data
+    let mut deferred: Vec<(String, &ForeignKeyDefinition)> = Vec::new();
+
+    if with_schema {
+        if options.drop_existing {
+            out.section("Drop existing objects")?;
+            for view in views.iter().rev() {
+                out.statement(&ddl::drop_view(&db_type, &qualify(view.schema.as_deref(), &view.name)))?;
+            }
+            if matches!(db_type, DatabaseType::SQLServer) {
+                for table in &tables {
+                    for fk in table.foreign_keys.iter() {
+                        if let Some(name) = &fk.name {
+                            out.statement(&ddl::drop_foreign_key(&db_type, &table.qualified_name(), name))?;
+                        }
+                    }
+                }
+            }
+            for table in tables.iter().rev() {
+                out.statement(&ddl::drop_table(&db_type, &table.qualified_name()))?;
+            }
+            for sequence in &created_sequences {
+                out.statement(&ddl::drop_sequence(&db_type, &qualify(sequence.schema.as_deref(), &sequence.name)))?;
+            }
+        }
+
+        let mut schemas: Vec<&str> = tables
+            .iter()
+            .filter_map(|t| t.schema.as_deref())
+            .chain(created_sequences.iter().filter_map(|s| s.schema.as_deref()))
+            .chain(views.iter().filter_map(|v| v.schema.as_deref()))
+            .filter(|s| !matches!(*s, "public" | "dbo"))
+            .collect();
+        schemas.sort();
+        schemas.dedup();
+        if !schemas.is_empty() {
+            out.section("Schemas")?;
+        }
+        for schema in schemas {
+            let sql = match db_type {
+                DatabaseType::SQLServer => format!(
+                    "IF SCHEMA_ID({}) IS NULL EXEC({})",
+                    dialect::quote_string(&db_type, schema),
+                    dialect::quote_string(&db_type, &format!("CREATE SCHEMA {}", dialect::quote_ident(&db_type, schema))),
+                ),
+                _ => format!("CREATE SCHEMA IF NOT EXISTS {}", dialect::quote_ident(&db_type, schema)),
+            };
+            out.statement(&sql)?;
+        }
+
+        if !created_sequences.is_empty() {
+            out.section("Sequences")?;
+        }
+        for sequence in &created_sequences {
+            out.statement(&ddl::create_sequence(&db_type, sequence))?;
+        }
+
+        let mut created: HashSet<String> = HashSet::new();
+        for table in &tables {
+            let name = table.qualified_name();
+            out.section(&format!("Table {}", name))?;
+            match &table.definition {
+                // Engine-provided definitions reference other tables freely;
+                // foreign key checks are off while the file loads
+                Some(definition) => out.statement(definition.trim().trim_end_matches(';'))?,
+                None => {
+                    let (inline, later): (Vec<&ForeignKeyDefinition>, Vec<&ForeignKeyDefinition>) = table
+                        .foreign_keys
+                        .iter()
+                        .partition(|fk| fk.referenced_table != name && created.contains(&fk.referenced_table));
+                    deferred.extend(later.into_iter().map(|fk| (name.clone(), fk)));
+                    out.statement(&ddl::create_table(&db_type, table, &inline))?;
+                }
+            }
+            created.insert(name);
+        }
+    }
+
+    let mut rows = 0u64;
+    if with_data {
+        let temporal = TemporalFormat::default();
+        for table in &tables {
+            if cancel.is_cancelled() {
+                return Err("Dump cancelled".to_string());
+            }
+            rows += dump_table_data(conn, table, out, options, &temporal, cancel, rows, progress).await?;
+        }
+    }
+
+    let restores: Vec<String> = sequences.iter().filter_map(|s| ddl::restore_sequence_value(&db_type, s)).collect();
+    if with_data && !restores.is_empty() {
+        out.section("Sequence values")?;
+        for sql in restores {
+            out.statement(&sql)?;
+        }
+    }
+
+    if with_schema {
+        let owned: Vec<&SequenceDefinition> = created_sequences.iter().copied().filter(|s| s.owned_by.is_some()).collect();
+        if matches!(db_type, DatabaseType::PostgreSQL) && !owned.is_empty() {
+            out.section("Sequence ownership")?;
+            for sequence in owned {
+                let (table, column) = sequence.owned_by.as_ref().expect("filtered on owned_by");
+                out.statement(&format!(
+                    "ALTER SEQUENCE {} OWNED BY {}.{}",
+                    dialect::quote_table(&db_type, &qualify(sequence.schema.as_deref(), &sequence.name)),
+                    dialect::quote_table(&db_type, table),
+                    dialect::quote_ident(&db_type, column),
+                ))?;
+            }
+        }
+
+        // MySQL's `SHOW CREATE TABLE` already includes the indexes
+        if !matches!(db_type, DatabaseType::MySQL) && tables.iter().any(|t| !t.indexes.is_empty()) {
+            out.section("Indexes")?;
+            for table in &tables {
+                for index in &table.indexes {
+                    out.statement(&ddl::create_index(&db_type, &table.qualified_name(), index))?;
+                }
+            }
+        }
+
+        if !deferred.is_empty() {
+            out.section("Foreign keys")?;
+            for (table, fk) in &deferred {
+                out.statement(&ddl::add_foreign_key(&db_type, table, fk))?;
+            }
+        }
+
+        for view in &views {
+            out.section(&format!("View {}", qualify(view.schema.as_deref(), &view.name)))?;
+            out.statement(view.definition.trim().trim_end_matches(';'))?;
+        }
+    }
+
+    match db_type {
+        DatabaseType::MySQL => out.statement("SET FOREIGN_KEY_CHECKS = 1")?,
+        DatabaseType::SQLite => out.statement("PRAGMA foreign_keys = ON")?,
+        DatabaseType::PostgreSQL | DatabaseType::SQLServer => {}
+    }
+
+    Ok(DumpSummary {
+        path: String::new(),
+        tables: tables.len(),
+        views: if with_schema { views.len() } else { 0 },
+        sequences: if with_schema { created_sequences.len() } else { 0 },
+        rows,
+        bytes: 0,
+        duration_ms: 0,
+    })
+}
+
+/// Stream one table's rows into the dump. PostgreSQL values are read as
+/// their text form, which round-trips every type exactly.
+#[allow(clippy::too_many_arguments)]
+async fn dump_table_data(
+    conn: &DatabaseConnection,
+    table: &TableDefinition,
+    out: &mut DumpWriter,
+    options: &DumpOptions,
+    temporal: &TemporalFormat,
+    cancel: &CancellationToken,
+    rows_before: u64,
+    progress: &mut (dyn FnMut(u64) + Send),
+) -> Result<u64, String> {
+    let db_type = conn.db_type();
+    let name = table.qualified_name();
+    let quoted = dialect::quote_table(&db_type, &name);
+    let columns = insertable_columns(&db_type, table);
+    if columns.is_empty() {
+        return Ok(0);
+    }
+
+    let select_list = columns
+        .iter()
+        .map(|c| match db_type {
+            DatabaseType::PostgreSQL => format!("{}::text", dialect::quote_ident(&db_type, &c.name)),
+            _ => dialect::quote_ident(&db_type, &c.name),
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    let column_list = columns
+        .iter()
+        .map(|c| dialect::quote_ident(&db_type, &c.name))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let copy = matches!((&db_type, options.data_format), (DatabaseType::PostgreSQL, DumpDataFormat::Copy));
+    let overriding = matches!(db_type, DatabaseType::PostgreSQL)
+        && columns.iter().any(|c| c.identity.as_deref() == Some("GENERATED ALWAYS AS IDENTITY"));
+    let target = if copy {
+        format!("COPY {} ({}) FROM stdin;", quoted, column_list)
+    } else if overriding {
+        format!("INSERT INTO {} ({}) OVERRIDING SYSTEM VALUE VALUES", quoted, column_list)
+    } else {
+        format!("INSERT INTO {} ({}) VALUES", quoted, column_list)
+    };
+
+    let mut rows_per_statement = options.rows_per_statement.max(1);
+    if matches!(db_type, DatabaseType::SQLServer) {
+        rows_per_statement = rows_per_statement.min(dialect::SQLSERVER_MAX_VALUES_ROWS);
+    }
+
+    out.section(&format!("Data for {}", name))?;
+    let mut report = |n: u64| progress(rows_before + n);
+    let mut sink = DataSink {
+        out,
+        table: name.clone(),
+        target,
+        copy,
+        // PostgreSQL values arrive as text, which bytea accepts as is
+        binary: columns
+            .iter()
+            .map(|c| !matches!(db_type, DatabaseType::PostgreSQL) && dialect::is_binary_type(&c.data_type))
+            .collect(),
+        types: columns.iter().map(|c| c.data_type.clone()).collect(),
+        identity_insert: matches!(db_type, DatabaseType::SQLServer) && columns.iter().any(|c| c.identity.is_some()),
+        rows_per_statement,
+        pending: Vec::new(),
+        started: false,
+        rows: 0,
+        progress: &mut report,
+    };
+
+    let sql = format!("SELECT {} FROM {}", select_list, quoted);
+    tokio::select! {
+        result = crate::database::stream_sql_query(conn, &sql, temporal, &mut sink) => {
+            result.map_err(|e| format!("Failed to read {}: {}", name, e))?;
+        }
+        _ = cancel.cancelled() => return Err("Dump cancelled".to_string()),
+    }
+    sink.finish()?;
+    Ok(sink.rows)
+}
@@ -0,0 +1,111 @@
+use std::io::Write;
+use crate::database::dialect;
+use crate::database::RowSink;
+use crate::models::*;
+
+/// `INSERT` statements in the dialect of the target engine.
+pub struct InsertSink<W: Write> {
+    out: W,
+    db_type: DatabaseType,
+    table_name: Option<String>,
+    rows_per_statement: usize,
+    /// Quoted `table (col, ...)` for the current result set
+    target: String,
+    binary: Vec<bool>,
+    types: Vec<String>,
+    pending: Vec<String>,
+}
+
+impl<W: Write + Send> InsertSink<W> {
+    pub fn new(out: W, db_type: DatabaseType, options: &ExportOptions) -> Self {
+        let mut rows_per_statement = options.rows_per_statement.max(1);
+        if matches!(db_type, DatabaseType::SQLServer) {
+            rows_per_statement = rows_per_statement.min(dialect::SQLSERVER_MAX_VALUES_ROWS);
+        }
+
+        Self {
+            out,
+            db_type,
+            table_name: options.table_name.clone(),
+            rows_per_statement,
+            target: String::new(),
+            binary: Vec::new(),
+            types: Vec::new(),
+            pending: Vec::new(),
+        }
+    }
+
+    fn literal(&self, idx: usize, value: &serde_json::Value) -> String {
+        if self.binary.get(idx).copied().unwrap_or(false) {
+            if let Some(bytes) = value.as_str().and_then(dialect::decode_hex_cell) {
+                return dialect::quote_bytes(&self.db_type, &bytes);
+            }
+        }
+        let type_name = self.types.get(idx).map(String::as_str).unwrap_or("");
+        dialect::quote_column_literal(&self.db_type, type_name, value)
+    }
+
+    fn flush_pending(&mut self) -> Result<(), String> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+
+        let statement = if self.pending.len() == 1 {
+            format!("INSERT INTO {} VALUES {};\n", self.target, self.pending[0])
+        } else {
+            format!("INSERT INTO {} VALUES\n  {};\n", self.target, self.pending.join(",\n  "))
+        };
+        self.pending.clear();
+
+        self.out
+            .write_all(statement.as_bytes())
+            .map_err(|e| format!("Failed to write file: {}", e))
+    }
+}
+
+impl<W: Write + Send> RowSink for InsertSink<W> {
+    fn begin(&mut self, columns: &[ResultColumn]) -> Result<(), String> {
+        self.flush_pending()?;
+
+        // Without an explicit name, use the source table if all columns share one
+        let table = self.table_name.clone().or_else(|| {
+            let first = columns.first()?.source_table.clone()?;
+            columns
+                .iter()
+                .all(|c| c.source_table.as_deref() == Some(first.as_str()))
+                .then_some(first)
+        });
+        let table = table.unwrap_or_else(|| "exported_rows".to_string());
+
+        let column_list = columns
+            .iter()
+            .map(|c| dialect::quote_ident(&self.db_type, &c.name))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        self.target = format!("{} ({})", dialect::quote_table(&self.db_type, &table), column_list);
+        self.binary = columns.iter().map(|c| dialect::is_binary_type(&c.type_name)).collect();
+        self.types = columns.iter().map(|c| c.type_name.clone()).collect();
+        Ok(())
+    }
+
+    fn write_row(&mut self, values: &[serde_json::Value]) -> Result<(), String> {
+        let tuple = values
+            .iter()
+            .enumerate()
+            .map(|(i, v)| self.literal(i, v))
+            .collect::<Vec<_>>()
+            .join(", ");
+        self.pending.push(format!("({})", tuple));
+
+        if self.pending.len() >= self.rows_per_statement {
+            self.flush_pending()?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), String> {
+        self.flush_pending()?;
+        self.out.flush().map_err(|e| format!("Failed to write file: {}", e))
+    }
+}
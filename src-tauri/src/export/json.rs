@@ -0,0 +1,69 @@
+use std::io::Write;
+use crate::database::RowSink;
+use crate::models::*;
+
+/// NDJSON (one object per line) or a single JSON array of objects.
+pub struct JsonSink<W: Write> {
+    out: W,
+    array: bool,
+    columns: Vec<String>,
+    rows: u64,
+}
+
+impl<W: Write + Send> JsonSink<W> {
+    pub fn new(out: W, array: bool) -> Self {
+        Self {
+            out,
+            array,
+            columns: Vec::new(),
+            rows: 0,
+        }
+    }
+}
+
+impl<W: Write + Send> RowSink for JsonSink<W> {
+    fn begin(&mut self, columns: &[ResultColumn]) -> Result<(), String> {
+        self.columns = columns.iter().map(|c| c.name.clone()).collect();
+        Ok(())
+    }
+
+    fn write_row(&mut self, values: &[serde_json::Value]) -> Result<(), String> {
+        // Written by hand so keys keep the column order
+        let mut object = String::from("{");
+        for (i, (name, value)) in self.columns.iter().zip(values).enumerate() {
+            if i > 0 {
+                object.push(',');
+            }
+            object.push_str(&serde_json::Value::String(name.clone()).to_string());
+            object.push(':');
+            object.push_str(&value.to_string());
+        }
+        object.push('}');
+
+        let separator = match (self.array, self.rows) {
+            (true, 0) => "[\n",
+            (true, _) => ",\n",
+            (false, _) => "",
+        };
+        self.out
+            .write_all(separator.as_bytes())
+            .and_then(|_| self.out.write_all(object.as_bytes()))
+            .and_then(|_| if self.array { Ok(()) } else { self.out.write_all(b"\n") })
+            .map_err(|e| format!("Failed to write file: {}", e))?;
+
+        self.rows += 1;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), String> {
+        let closing: &[u8] = match (self.array, self.rows) {
+            (true, 0) => b"[]\n",
+            (true, _) => b"\n]\n",
+            (false, _) => b"",
+        };
+        self.out
+            .write_all(closing)
+            .and_then(|_| self.out.flush())
+            .map_err(|e| format!("Failed to write file: {}", e))
+    }
+}
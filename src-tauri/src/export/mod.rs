@@ -0,0 +1,133 @@
+pub mod delimited;
+pub mod json;
+pub mod insert;
+pub mod columnar;
+pub mod xlsx;
+pub mod dump;
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::time::Instant;
+use tokio_util::sync::CancellationToken;
+use crate::database::temporal::TemporalFormat;
+use crate::database::{dialect, DatabaseConnection, RowSink};
+use crate::models::*;
+
+/// Text rendering of a cell for text-based formats. NULL is handled by the caller.
+pub fn value_to_text(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::Null => String::new(),
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Wraps a format sink to count rows and report progress.
+struct Tracked<'a> {
+    inner: Box<dyn RowSink>,
+    rows: u64,
+    progress: &'a mut (dyn FnMut(u64) + Send),
+}
+
+impl RowSink for Tracked<'_> {
+    fn begin(&mut self, columns: &[ResultColumn]) -> Result<(), String> {
+        self.inner.begin(columns)
+    }
+
+    fn write_row(&mut self, values: &[serde_json::Value]) -> Result<(), String> {
+        self.inner.write_row(values)?;
+        self.rows += 1;
+        (self.progress)(self.rows);
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), String> {
+        self.inner.finish()
+    }
+}
+
+fn create_sink(
+    format: ExportFormat,
+    file: BufWriter<File>,
+    options: &ExportOptions,
+    source: DatabaseType,
+) -> Result<Box<dyn RowSink>, String> {
+    Ok(match format {
+        ExportFormat::Csv => Box::new(delimited::DelimitedSink::new(file, options, options.delimiter)?),
+        ExportFormat::Tsv => Box::new(delimited::DelimitedSink::new(file, options, '\t')?),
+        ExportFormat::Ndjson => Box::new(json::JsonSink::new(file, false)),
+        ExportFormat::JsonArray => Box::new(json::JsonSink::new(file, true)),
+        ExportFormat::SqlInsert => {
+            let dialect = options.target_dialect.clone().unwrap_or(source);
+            Box::new(insert::InsertSink::new(file, dialect, options))
+        }
+        ExportFormat::Parquet => {
+            Box::new(columnar::ColumnarSink::new(file, columnar::ColumnarFormat::parquet(options)?, source))
+        }
+        ExportFormat::ArrowIpc => Box::new(columnar::ColumnarSink::new(file, columnar::ColumnarFormat::ArrowIpc, source)),
+        ExportFormat::Xlsx => Box::new(xlsx::XlsxSink::new(file, options)),
+    })
+}
+
+/// `SELECT` for a whole-table export, with the sheet and `INSERT` table
+/// names defaulting to the table.
+pub fn table_export(db_type: &DatabaseType, table: &str, options: &ExportOptions) -> (String, ExportOptions) {
+    let sql = format!("SELECT * FROM {}", dialect::quote_table(db_type, table));
+
+    let mut options = options.clone();
+    if options.table_name.is_none() {
+        options.table_name = Some(table.to_string());
+    }
+    if options.sheet_name.is_none() {
+        options.sheet_name = Some(table.rsplit('.').next().unwrap_or(table).to_string());
+    }
+
+    (sql, options)
+}
+
+/// Stream the result of `sql` straight into a file. The partial file is
+/// removed if the export fails or is cancelled.
+#[allow(clippy::too_many_arguments)]
+pub async fn export_query(
+    conn: &DatabaseConnection,
+    sql: &str,
+    temporal: &TemporalFormat,
+    format: ExportFormat,
+    path: &str,
+    options: &ExportOptions,
+    cancel: CancellationToken,
+    progress: &mut (dyn FnMut(u64) + Send),
+) -> Result<ExportSummary, String> {
+    let start = Instant::now();
+
+    let file = File::create(path).map_err(|e| format!("Failed to create file: {}", e))?;
+    let inner = create_sink(format, BufWriter::new(file), options, conn.db_type())?;
+    let mut sink = Tracked { inner, rows: 0, progress };
+
+    let result = tokio::select! {
+        result = crate::database::stream_sql_query(conn, sql, temporal, &mut sink) => result,
+        _ = cancel.cancelled() => Err("Export cancelled".to_string()),
+    };
+    let result = result.and_then(|rows| sink.finish().map(|_| rows));
+
+    let rows = match result {
+        Ok(rows) => rows,
+        Err(e) => {
+            drop(sink);
+            if let Err(remove_err) = std::fs::remove_file(path) {
+                log::warn!("[export_query] Could not remove partial file {}: {}", path, remove_err);
+            }
+            return Err(e);
+        }
+    };
+
+    let bytes = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    log::info!("[export_query] Exported {} rows ({} bytes) to {}", rows, bytes, path);
+
+    Ok(ExportSummary {
+        path: path.to_string(),
+        rows,
+        bytes,
+        duration_ms: start.elapsed().as_millis() as u64,
+    })
+}
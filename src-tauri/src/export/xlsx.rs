@@ -0,0 +1,248 @@
+use std::io::{Seek, Write};
+use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime};
+use rust_xlsxwriter::{Format, Workbook, Worksheet, XlsxError};
+use crate::database::dialect::{self, ColumnKind};
+use crate::database::{temporal, RowSink};
+use crate::models::*;
+use super::value_to_text;
+
+/// Rows per worksheet, including the header row.
+const MAX_SHEET_ROWS: u32 = 1_048_576;
+/// Excel refuses longer strings in a single cell.
+const MAX_CELL_CHARS: usize = 32_767;
+/// Integers beyond this lose precision as Excel numbers and are written as text.
+const MAX_EXACT_INTEGER: u64 = 1 << 53;
+/// Upper bound for automatic column widths, in characters.
+const MAX_COLUMN_WIDTH: usize = 60;
+
+/// Excel workbook output with native cell types. Each result set gets its own
+/// worksheet; results larger than a sheet continue on the next one. Sheets
+/// are in constant-memory mode: each finished row goes to a temporary file.
+pub struct XlsxSink<W: Write + Seek + Send> {
+    out: Option<W>,
+    workbook: Workbook,
+    sheet_name: Option<String>,
+    columns: Vec<ResultColumn>,
+    kinds: Vec<ColumnKind>,
+    result_sets: usize,
+    continuation: usize,
+    sheet: Option<usize>,
+    sheets: usize,
+    row: u32,
+    /// Widest rendered value per column on the current sheet, in characters
+    widths: Vec<usize>,
+    header_format: Format,
+    date_format: Format,
+    datetime_format: Format,
+    time_format: Format,
+}
+
+impl<W: Write + Seek + Send> XlsxSink<W> {
+    pub fn new(out: W, options: &ExportOptions) -> Self {
+        Self {
+            out: Some(out),
+            workbook: Workbook::new(),
+            sheet_name: options.sheet_name.clone(),
+            columns: Vec::new(),
+            kinds: Vec::new(),
+            result_sets: 0,
+            continuation: 0,
+            sheet: None,
+            sheets: 0,
+            row: 0,
+            widths: Vec::new(),
+            header_format: Format::new().set_bold(),
+            date_format: Format::new().set_num_format("yyyy-mm-dd"),
+            datetime_format: Format::new().set_num_format("yyyy-mm-dd hh:mm:ss.000"),
+            time_format: Format::new().set_num_format("hh:mm:ss.000"),
+        }
+    }
+
+    fn worksheet(&mut self) -> Result<&mut Worksheet, String> {
+        let index = self.sheet.ok_or_else(|| "No worksheet started".to_string())?;
+        self.workbook.worksheet_from_index(index).map_err(xlsx_error)
+    }
+
+    /// Add a worksheet for the current result set and write its header.
+    fn start_sheet(&mut self) -> Result<(), String> {
+        self.close_sheet()?;
+
+        let base = match (&self.sheet_name, self.result_sets) {
+            (Some(name), 1) => name.clone(),
+            (Some(name), n) => format!("{} {}", name, n),
+            (None, n) => format!("Result {}", n),
+        };
+        let name = match self.continuation {
+            0 => sheet_name(&base, ""),
+            n => sheet_name(&base, &format!(" ({})", n + 1)),
+        };
+
+        let header_format = self.header_format.clone();
+        let columns = self.columns.clone();
+
+        let sheet = self.workbook.add_worksheet_with_constant_memory();
+        sheet.set_name(name).map_err(xlsx_error)?;
+        for (col, column) in columns.iter().enumerate() {
+            sheet
+                .write_string_with_format(0, col as u16, &column.name, &header_format)
+                .map_err(xlsx_error)?;
+        }
+        sheet.set_freeze_panes(1, 0).map_err(xlsx_error)?;
+
+        self.sheet = Some(self.sheets);
+        self.sheets += 1;
+        self.row = 1;
+        // Bold header text is a little wider than body text
+        self.widths = columns.iter().map(|c| c.name.chars().count() + 2).collect();
+        Ok(())
+    }
+
+    /// Size the columns of the finished sheet to their widest value.
+    fn close_sheet(&mut self) -> Result<(), String> {
+        if self.sheet.is_none() {
+            return Ok(());
+        }
+        let widths = std::mem::take(&mut self.widths);
+        let sheet = self.worksheet()?;
+        for (col, width) in widths.into_iter().enumerate() {
+            let width = (width + 1).min(MAX_COLUMN_WIDTH);
+            sheet.set_column_width(col as u16, width as f64).map_err(xlsx_error)?;
+        }
+        Ok(())
+    }
+
+    /// Write one cell as its native Excel type. Returns the rendered width in characters.
+    fn write_cell(&mut self, col: u16, kind: ColumnKind, value: &serde_json::Value) -> Result<usize, String> {
+        let row = self.row;
+        let date_format = self.date_format.clone();
+        let datetime_format = self.datetime_format.clone();
+        let time_format = self.time_format.clone();
+        let sheet = self.worksheet()?;
+
+        // Date formats render at a fixed width: yyyy-mm-dd, hh:mm:ss.000, both
+        let (result, width) = match (kind, value) {
+            (_, serde_json::Value::Null) => return Ok(0),
+            (_, serde_json::Value::Bool(b)) => (sheet.write_boolean(row, col, *b), 5),
+            (ColumnKind::Boolean, serde_json::Value::Number(n)) => {
+                (sheet.write_boolean(row, col, n.as_f64().unwrap_or(0.0) != 0.0), 5)
+            }
+            (_, serde_json::Value::Number(n)) => {
+                let exact = n.as_i64().is_none_or(|i| i.unsigned_abs() <= MAX_EXACT_INTEGER)
+                    && n.as_u64().is_none_or(|u| u <= MAX_EXACT_INTEGER);
+                let text = n.to_string();
+                let width = text.len();
+                match n.as_f64() {
+                    Some(f) if exact => (sheet.write_number(row, col, f), width),
+                    _ => (sheet.write_string(row, col, text), width),
+                }
+            }
+            (ColumnKind::Decimal | ColumnKind::Float, serde_json::Value::String(s)) => match s.parse::<f64>() {
+                Ok(f) if f.is_finite() => (sheet.write_number(row, col, f), s.len()),
+                _ => (sheet.write_string(row, col, s), s.chars().count()),
+            },
+            (ColumnKind::Date, serde_json::Value::String(s)) => match parse_date(s) {
+                Some(d) => (sheet.write_datetime_with_format(row, col, d, &date_format), 10),
+                None => (sheet.write_string(row, col, s), s.chars().count()),
+            },
+            (ColumnKind::Time, serde_json::Value::String(s)) => {
+                match NaiveTime::parse_from_str(s, "%H:%M:%S%.f") {
+                    Ok(t) => (sheet.write_datetime_with_format(row, col, t, &time_format), 12),
+                    Err(_) => (sheet.write_string(row, col, s), s.chars().count()),
+                }
+            }
+            // Excel has no timezones: instants are written as the wall-clock time shown in the grid
+            (ColumnKind::DateTime | ColumnKind::Timestamp, serde_json::Value::String(s)) => match parse_datetime(s) {
+                Some(dt) => (sheet.write_datetime_with_format(row, col, dt, &datetime_format), 23),
+                None => (sheet.write_string(row, col, s), s.chars().count()),
+            },
+            (_, value) => {
+                let text = value_to_text(value);
+                let text = match text.char_indices().nth(MAX_CELL_CHARS) {
+                    Some((end, _)) => &text[..end],
+                    None => &text,
+                };
+                let width = text.lines().map(|l| l.chars().count()).max().unwrap_or(0);
+                (sheet.write_string(row, col, text), width)
+            }
+        };
+
+        result.map_err(xlsx_error)?;
+        Ok(width)
+    }
+}
+
+impl<W: Write + Seek + Send> RowSink for XlsxSink<W> {
+    fn begin(&mut self, columns: &[ResultColumn]) -> Result<(), String> {
+        if columns.is_empty() {
+            return Ok(());
+        }
+        self.columns = columns.to_vec();
+        self.kinds = columns.iter().map(|c| dialect::column_kind(&c.type_name)).collect();
+        self.result_sets += 1;
+        self.continuation = 0;
+        self.start_sheet()
+    }
+
+    fn write_row(&mut self, values: &[serde_json::Value]) -> Result<(), String> {
+        if self.row >= MAX_SHEET_ROWS {
+            self.continuation += 1;
+            self.start_sheet()?;
+        }
+
+        for (col, value) in values.iter().enumerate() {
+            let kind = self.kinds.get(col).copied().unwrap_or(ColumnKind::Text);
+            let width = self.write_cell(col as u16, kind, value)?;
+            if let Some(max) = self.widths.get_mut(col) {
+                *max = (*max).max(width);
+            }
+        }
+        self.row += 1;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), String> {
+        if self.sheets == 0 {
+            // A workbook needs at least one sheet
+            self.workbook.add_worksheet_with_constant_memory();
+        }
+        self.close_sheet()?;
+
+        let out = self.out.take().ok_or_else(|| "Export file already closed".to_string())?;
+        self.workbook.save_to_writer(out).map_err(xlsx_error)
+    }
+}
+
+fn xlsx_error(e: XlsxError) -> String {
+    format!("Failed to write workbook: {}", e)
+}
+
+/// Worksheet names are limited to 31 characters and may not contain `[]:*?/\`.
+fn sheet_name(base: &str, suffix: &str) -> String {
+    let cleaned: String = base
+        .chars()
+        .map(|c| if "[]:*?/\\".contains(c) { '_' } else { c })
+        .collect();
+    let cleaned = cleaned.trim_matches('\'');
+    let keep = 31 - suffix.chars().count();
+    let mut name: String = cleaned.chars().take(keep).collect();
+    if name.is_empty() {
+        name = "Sheet".to_string();
+    }
+    name.push_str(suffix);
+    name
+}
+
+/// Excel dates start in 1900; earlier values are written as text.
+fn parse_date(s: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .ok()
+        .filter(|d| d.year() >= 1900)
+}
+
+fn parse_datetime(s: &str) -> Option<NaiveDateTime> {
+    ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(s, format).ok())
+        .or_else(|| temporal::parse_offset_datetime(s).map(|dt| dt.naive_local()))
+        .filter(|dt| dt.year() >= 1900)
+}
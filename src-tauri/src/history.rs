@@ -0,0 +1,301 @@
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use sqlx::{QueryBuilder, Row, Sqlite, SqlitePool};
+use std::path::Path;
+use crate::models::*;
+
+const SCHEMA: &str = r#"
+    CREATE TABLE IF NOT EXISTS query_history (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        connection_name TEXT NOT NULL,
+        database_name TEXT NOT NULL DEFAULT '',
+        sql TEXT NOT NULL,
+        executed_at TEXT NOT NULL,
+        duration_ms INTEGER,
+        row_count INTEGER,
+        error TEXT,
+        pinned INTEGER NOT NULL DEFAULT 0,
+        title TEXT
+    );
+
+    CREATE INDEX IF NOT EXISTS idx_query_history_connection
+        ON query_history (connection_name, executed_at);
+
+    CREATE INDEX IF NOT EXISTS idx_query_history_executed_at
+        ON query_history (executed_at);
+
+    CREATE VIRTUAL TABLE IF NOT EXISTS query_history_fts
+        USING fts5(sql, title, content='query_history', content_rowid='id');
+
+    CREATE TRIGGER IF NOT EXISTS query_history_ai AFTER INSERT ON query_history BEGIN
+        INSERT INTO query_history_fts (rowid, sql, title) VALUES (new.id, new.sql, new.title);
+    END;
+
+    CREATE TRIGGER IF NOT EXISTS query_history_ad AFTER DELETE ON query_history BEGIN
+        INSERT INTO query_history_fts (query_history_fts, rowid, sql, title)
+        VALUES ('delete', old.id, old.sql, old.title);
+    END;
+
+    CREATE TRIGGER IF NOT EXISTS query_history_au AFTER UPDATE ON query_history BEGIN
+        INSERT INTO query_history_fts (query_history_fts, rowid, sql, title)
+        VALUES ('delete', old.id, old.sql, old.title);
+        INSERT INTO query_history_fts (rowid, sql, title) VALUES (new.id, new.sql, new.title);
+    END;
+"#;
+
+/// Query history and saved (pinned) queries, kept in an SQLite file in the
+/// app data directory.
+pub struct HistoryStore {
+    pool: SqlitePool,
+}
+
+impl HistoryStore {
+    pub async fn open(path: &Path) -> Result<Self, String> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create history directory: {}", e))?;
+        }
+
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true);
+
+        let store = Self::connect(options, SqlitePoolOptions::new().max_connections(2)).await?;
+        log::info!("[HistoryStore] Opened {}", path.display());
+        Ok(store)
+    }
+
+    /// Open the history file, or start a new one when it cannot be used: the
+    /// old file is kept beside it under a `.broken` suffix. When even that
+    /// fails, history is kept in memory for this session.
+    pub async fn open_or_recover(path: &Path) -> Self {
+        let error = match Self::open(path).await {
+            Ok(store) => return store,
+            Err(e) => e,
+        };
+        log::error!("[HistoryStore] {}", error);
+
+        let broken = path.with_extension(format!("db.broken-{}", chrono::Utc::now().format("%Y%m%d%H%M%S")));
+        match std::fs::rename(path, &broken) {
+            Ok(()) => {
+                log::warn!("[HistoryStore] Moved {} to {}", path.display(), broken.display());
+                match Self::open(path).await {
+                    Ok(store) => return store,
+                    Err(e) => log::error!("[HistoryStore] {}", e),
+                }
+            }
+            Err(e) => log::error!("[HistoryStore] Failed to move {} aside: {}", path.display(), e),
+        }
+
+        log::warn!("[HistoryStore] Keeping history in memory for this session");
+        // One connection that is never closed: each connection to
+        // `:memory:` is a database of its own
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .min_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None);
+        Self::connect(SqliteConnectOptions::new().in_memory(true), pool)
+            .await
+            .expect("in-memory SQLite database")
+    }
+
+    async fn connect(options: SqliteConnectOptions, pool: SqlitePoolOptions) -> Result<Self, String> {
+        let pool = pool
+            .connect_with(options)
+            .await
+            .map_err(|e| format!("Failed to open history database: {}", e))?;
+
+        sqlx::raw_sql(SCHEMA)
+            .execute(&pool)
+            .await
+            .map_err(|e| format!("Failed to initialize history database: {}", e))?;
+
+        Ok(Self { pool })
+    }
+
+    /// Record an executed statement. Failures are logged, never surfaced to the caller.
+    pub async fn record(&self, entry: NewHistoryEntry) {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO query_history
+                (connection_name, database_name, sql, executed_at, duration_ms, row_count, error)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&entry.connection_name)
+        .bind(&entry.database_name)
+        .bind(&entry.sql)
+        .bind(now_timestamp())
+        .bind(entry.duration_ms as i64)
+        .bind(entry.row_count.map(|v| v as i64))
+        .bind(&entry.error)
+        .execute(&self.pool)
+        .await;
+
+        if let Err(e) = result {
+            log::warn!("[HistoryStore] Failed to record query: {}", e);
+        }
+    }
+
+    /// Store a query without running it, pinned under the given title.
+    pub async fn save_query(
+        &self,
+        connection_name: &str,
+        title: &str,
+        sql: &str,
+    ) -> Result<HistoryEntry, String> {
+        let id: i64 = sqlx::query_scalar(
+            r#"
+            INSERT INTO query_history (connection_name, sql, executed_at, pinned, title)
+            VALUES (?, ?, ?, 1, ?)
+            RETURNING id
+            "#,
+        )
+        .bind(connection_name)
+        .bind(sql)
+        .bind(now_timestamp())
+        .bind(title)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to save query: {}", e))?;
+
+        self.get(id).await
+    }
+
+    pub async fn get(&self, id: i64) -> Result<HistoryEntry, String> {
+        let row = sqlx::query("SELECT * FROM query_history WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to read history: {}", e))?
+            .ok_or_else(|| "History entry not found".to_string())?;
+
+        Ok(entry_from_row(&row))
+    }
+
+    pub async fn search(&self, filter: &HistoryFilter) -> Result<Vec<HistoryEntry>, String> {
+        let mut query: QueryBuilder<Sqlite> = QueryBuilder::new("SELECT h.* FROM query_history h");
+
+        let fts = filter.search.as_deref().map(fts_query).filter(|q| !q.is_empty());
+        if fts.is_some() {
+            query.push(" JOIN query_history_fts f ON f.rowid = h.id");
+        }
+
+        query.push(" WHERE 1 = 1");
+
+        if let Some(fts) = fts {
+            query.push(" AND query_history_fts MATCH ").push_bind(fts);
+        }
+        if let Some(name) = &filter.connection_name {
+            query.push(" AND h.connection_name = ").push_bind(name.clone());
+        }
+        if let Some(from) = &filter.from {
+            query.push(" AND h.executed_at >= ").push_bind(normalize_timestamp(from, false)?);
+        }
+        if let Some(to) = &filter.to {
+            query.push(" AND h.executed_at <= ").push_bind(normalize_timestamp(to, true)?);
+        }
+        if filter.pinned_only {
+            query.push(" AND h.pinned = 1");
+        }
+        if filter.errors_only {
+            query.push(" AND h.error IS NOT NULL");
+        }
+
+        query.push(" ORDER BY h.executed_at DESC, h.id DESC");
+        query.push(" LIMIT ").push_bind(filter.limit.unwrap_or(200) as i64);
+        query.push(" OFFSET ").push_bind(filter.offset.unwrap_or(0) as i64);
+
+        let rows = query
+            .build()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to search history: {}", e))?;
+
+        Ok(rows.iter().map(entry_from_row).collect())
+    }
+
+    pub async fn set_pinned(&self, id: i64, pinned: bool, title: Option<String>) -> Result<HistoryEntry, String> {
+        let result = sqlx::query("UPDATE query_history SET pinned = ?, title = COALESCE(?, title) WHERE id = ?")
+            .bind(pinned)
+            .bind(title)
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to update history: {}", e))?;
+
+        if result.rows_affected() == 0 {
+            return Err("History entry not found".to_string());
+        }
+
+        self.get(id).await
+    }
+
+    pub async fn delete(&self, id: i64) -> Result<bool, String> {
+        let result = sqlx::query("DELETE FROM query_history WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to delete history entry: {}", e))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Delete history, optionally for one connection only. Pinned entries are kept.
+    pub async fn clear(&self, connection_name: Option<&str>) -> Result<u64, String> {
+        let result = sqlx::query(
+            "DELETE FROM query_history WHERE pinned = 0 AND (? IS NULL OR connection_name = ?)",
+        )
+        .bind(connection_name)
+        .bind(connection_name)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to clear history: {}", e))?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+fn entry_from_row(row: &sqlx::sqlite::SqliteRow) -> HistoryEntry {
+    HistoryEntry {
+        id: row.get("id"),
+        connection_name: row.get("connection_name"),
+        database_name: row.get("database_name"),
+        sql: row.get("sql"),
+        executed_at: row.get("executed_at"),
+        duration_ms: row.get::<Option<i64>, _>("duration_ms").map(|v| v as u64),
+        row_count: row.get::<Option<i64>, _>("row_count").map(|v| v as u64),
+        error: row.get("error"),
+        pinned: row.get("pinned"),
+        title: row.get("title"),
+    }
+}
+
+/// Timestamps are stored as UTC RFC 3339 text so they sort and compare lexically.
+fn now_timestamp() -> String {
+    chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+}
+
+/// Accepts RFC 3339 or a plain date; a plain date used as an upper bound covers the whole day.
+fn normalize_timestamp(value: &str, end_of_day: bool) -> Result<String, String> {
+    if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Ok(dt
+            .with_timezone(&chrono::Utc)
+            .to_rfc3339_opts(chrono::SecondsFormat::Millis, true));
+    }
+    if let Ok(date) = chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        let time = if end_of_day { "23:59:59.999" } else { "00:00:00.000" };
+        return Ok(format!("{}T{}Z", date.format("%Y-%m-%d"), time));
+    }
+    Err(format!("Invalid date: {}", value))
+}
+
+/// Turn free text into an FTS5 query that matches every word as a prefix,
+/// so user input never produces FTS syntax errors.
+fn fts_query(search: &str) -> String {
+    search
+        .split_whitespace()
+        .map(|term| format!("\"{}\"*", term.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -0,0 +1,324 @@
+use std::fs::File;
+use std::io::Read;
+use encoding_rs::Encoding;
+use crate::database::dialect::ColumnKind;
+use crate::models::*;
+use super::infer::{self, KindTracker};
+use super::{convert_text_fields, DecodingReader, ImportTarget, RowSource, SourceRow};
+
+/// Bytes looked at when detecting the encoding, delimiter and header.
+const SNIFF_BYTES: usize = 64 * 1024;
+/// Records looked at when detecting the delimiter and header.
+const SNIFF_RECORDS: usize = 50;
+const CANDIDATE_DELIMITERS: [u8; 4] = [b',', b';', b'\t', b'|'];
+
+type CsvReader = csv::Reader<DecodingReader<File>>;
+
+/// Settings a CSV file is read with, after detection.
+#[derive(Debug, Clone)]
+pub struct CsvFormat {
+    pub encoding: &'static Encoding,
+    pub delimiter: u8,
+    pub quote: u8,
+    pub has_header: bool,
+}
+
+/// Work out how to read the file, honouring whatever the options pin down.
+pub fn detect_format(path: &str, options: &CsvReadOptions) -> Result<CsvFormat, String> {
+    let quote = ascii_byte(options.quote, "Quote")?;
+
+    let mut sample = Vec::with_capacity(SNIFF_BYTES);
+    open_file(path)?
+        .take(SNIFF_BYTES as u64)
+        .read_to_end(&mut sample)
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+
+    let encoding = detect_encoding(&sample, options.encoding.as_deref())?;
+    let (text, _) = encoding.decode_with_bom_removal(&sample);
+    // Drop the last line, which is probably cut short
+    let text = match (sample.len() == SNIFF_BYTES, text.rfind('\n')) {
+        (true, Some(end)) => &text[..end],
+        _ => &text[..],
+    };
+
+    let delimiter = match options.delimiter {
+        Some(c) => ascii_byte(c, "Delimiter")?,
+        None => detect_delimiter(text, quote),
+    };
+    let has_header = match options.has_header {
+        Some(has_header) => has_header,
+        None => detect_header(&sample_records(text, delimiter, quote)),
+    };
+
+    Ok(CsvFormat { encoding, delimiter, quote, has_header })
+}
+
+fn open_file(path: &str) -> Result<File, String> {
+    File::open(path).map_err(|e| format!("Failed to open file: {}", e))
+}
+
+fn ascii_byte(c: char, what: &str) -> Result<u8, String> {
+    u8::try_from(c)
+        .ok()
+        .filter(u8::is_ascii)
+        .ok_or_else(|| format!("{} must be an ASCII character", what))
+}
+
+/// A byte order mark wins, then an explicit label. Otherwise the file is
+/// UTF-8 if it decodes as such, and Windows-1252 if not.
+fn detect_encoding(sample: &[u8], label: Option<&str>) -> Result<&'static Encoding, String> {
+    if let Some((encoding, _)) = Encoding::for_bom(sample) {
+        return Ok(encoding);
+    }
+    if let Some(label) = label {
+        return Encoding::for_label(label.trim().as_bytes())
+            .ok_or_else(|| format!("Unknown encoding: {}", label));
+    }
+
+    match std::str::from_utf8(sample) {
+        Ok(_) => Ok(encoding_rs::UTF_8),
+        // A multi-byte character cut off at the end of the sample
+        Err(e) if e.error_len().is_none() => Ok(encoding_rs::UTF_8),
+        Err(_) => Ok(encoding_rs::WINDOWS_1252),
+    }
+}
+
+fn sample_records(text: &str, delimiter: u8, quote: u8) -> Vec<Vec<String>> {
+    csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .delimiter(delimiter)
+        .quote(quote)
+        .from_reader(text.as_bytes())
+        .records()
+        .take(SNIFF_RECORDS)
+        .map_while(Result::ok)
+        .map(|record| record.iter().map(str::to_string).collect())
+        .collect()
+}
+
+/// Pick the candidate that splits every sampled record into the same number
+/// of fields, preferring more fields. Falls back to a comma.
+fn detect_delimiter(text: &str, quote: u8) -> u8 {
+    CANDIDATE_DELIMITERS
+        .iter()
+        .filter_map(|&delimiter| {
+            let records = sample_records(text, delimiter, quote);
+            let width = records.first()?.len();
+            let consistent = records.iter().all(|r| r.len() == width);
+            (width > 1).then_some((consistent, width, delimiter))
+        })
+        .max_by_key(|&(consistent, width, _)| (consistent, width))
+        .map_or(b',', |(_, _, delimiter)| delimiter)
+}
+
+/// The first record is a header when a typed column (numbers, dates) has a
+/// non-matching value there. When every column is text, a first record of
+/// distinct, non-empty names is taken as a header.
+fn detect_header(records: &[Vec<String>]) -> bool {
+    let Some((first, rest)) = records.split_first() else {
+        return false;
+    };
+    if rest.is_empty() {
+        return first.iter().all(|f| infer::classify(f.trim()) == ColumnKind::Text);
+    }
+
+    let mut typed_columns = 0;
+    for (idx, name) in first.iter().enumerate() {
+        let mut tracker = KindTracker::default();
+        for record in rest {
+            match record.get(idx) {
+                Some(field) => tracker.observe_text(field),
+                None => tracker.observe_null(),
+            }
+        }
+        let kind = tracker.column(name).kind;
+        if kind == ColumnKind::Text {
+            continue;
+        }
+        typed_columns += 1;
+        // A value that fits the column's type means the first record is data
+        if name.trim().is_empty() || infer::parse_field(kind, name).is_ok() {
+            return false;
+        }
+    }
+
+    if typed_columns > 0 {
+        return true;
+    }
+    let mut names: Vec<String> = first.iter().map(|f| f.trim().to_lowercase()).collect();
+    names.sort();
+    names.dedup();
+    names.len() == first.len() && names.iter().all(|n| !n.is_empty())
+}
+
+fn open_reader(path: &str, format: &CsvFormat) -> Result<CsvReader, String> {
+    let decoded = DecodingReader::new(open_file(path)?, format.encoding);
+    Ok(csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .delimiter(format.delimiter)
+        .quote(format.quote)
+        .from_reader(decoded))
+}
+
+fn read_error(e: csv::Error) -> String {
+    format!("Failed to read CSV: {}", e)
+}
+
+/// Column names from the header record, made unique and non-empty, or
+/// `column_1`, `column_2`, ... without a header.
+fn column_names(header: Option<&csv::StringRecord>, width: usize) -> Vec<String> {
+    let mut names: Vec<String> = Vec::with_capacity(width);
+    for idx in 0..width {
+        let base = header
+            .and_then(|h| h.get(idx))
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map_or_else(|| format!("column_{}", idx + 1), str::to_string);
+
+        let mut name = base.clone();
+        let mut suffix = 2;
+        while names.iter().any(|n| n.eq_ignore_ascii_case(&name)) {
+            name = format!("{}_{}", base, suffix);
+            suffix += 1;
+        }
+        names.push(name);
+    }
+    names
+}
+
+/// Read the header (or the first record's width) and return the column names.
+fn read_columns(reader: &mut CsvReader, format: &CsvFormat) -> Result<(Vec<String>, Option<csv::StringRecord>), String> {
+    let mut first = csv::StringRecord::new();
+    if !reader.read_record(&mut first).map_err(read_error)? {
+        return Err("The file is empty".to_string());
+    }
+
+    if format.has_header {
+        Ok((column_names(Some(&first), first.len()), None))
+    } else {
+        Ok((column_names(None, first.len()), Some(first)))
+    }
+}
+
+fn observe(trackers: &mut [KindTracker], record: &csv::StringRecord, null_token: Option<&str>) {
+    for (idx, tracker) in trackers.iter_mut().enumerate() {
+        match record.get(idx) {
+            None => tracker.observe_null(),
+            Some(field) if Some(field) == null_token => tracker.observe_null(),
+            Some(field) => tracker.observe_text(field),
+        }
+    }
+}
+
+/// Detected format, columns with types inferred from the first rows, and those rows.
+pub fn preview(path: &str, options: &CsvReadOptions) -> Result<CsvPreview, String> {
+    let format = detect_format(path, options)?;
+    let mut reader = open_reader(path, &format)?;
+    let (names, first) = read_columns(&mut reader, &format)?;
+    let null_token = options.null_token.as_deref();
+
+    let mut trackers = vec![KindTracker::default(); names.len()];
+    let mut rows = Vec::new();
+    let mut record = csv::StringRecord::new();
+
+    if let Some(first) = first {
+        observe(&mut trackers, &first, null_token);
+        rows.push(first.iter().map(str::to_string).collect());
+    }
+    while rows.len() < options.preview_rows && reader.read_record(&mut record).map_err(read_error)? {
+        observe(&mut trackers, &record, null_token);
+        rows.push(record.iter().map(str::to_string).collect());
+    }
+
+    Ok(CsvPreview {
+        encoding: format.encoding.name().to_string(),
+        delimiter: format.delimiter as char,
+        has_header: format.has_header,
+        columns: names.iter().zip(&trackers).map(|(name, t)| t.column(name)).collect(),
+        rows,
+    })
+}
+
+/// Infer column types from every record in the file.
+pub fn infer_columns(path: &str, format: &CsvFormat, null_token: Option<&str>) -> Result<Vec<InferredColumn>, String> {
+    let mut reader = open_reader(path, format)?;
+    let (names, first) = read_columns(&mut reader, format)?;
+
+    let mut trackers = vec![KindTracker::default(); names.len()];
+    if let Some(first) = first {
+        observe(&mut trackers, &first, null_token);
+    }
+    let mut record = csv::StringRecord::new();
+    while reader.read_record(&mut record).map_err(read_error)? {
+        observe(&mut trackers, &record, null_token);
+    }
+
+    Ok(names.iter().zip(&trackers).map(|(name, t)| t.column(name)).collect())
+}
+
+/// Records of a CSV file converted to the import target.
+pub struct CsvRows<'a> {
+    reader: CsvReader,
+    target: &'a ImportTarget,
+    width: usize,
+    null_token: Option<String>,
+    pending: Option<csv::StringRecord>,
+    record: csv::StringRecord,
+}
+
+/// An opened CSV file whose columns have not been mapped yet.
+pub struct CsvSource {
+    reader: CsvReader,
+    columns: Vec<String>,
+    /// A first record that turned out to be data rather than a header
+    pending: Option<csv::StringRecord>,
+}
+
+impl CsvSource {
+    pub fn open(path: &str, format: &CsvFormat) -> Result<Self, String> {
+        let mut reader = open_reader(path, format)?;
+        let (columns, pending) = read_columns(&mut reader, format)?;
+        Ok(Self { reader, columns, pending })
+    }
+
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    pub fn into_rows(self, target: &ImportTarget, null_token: Option<String>) -> CsvRows<'_> {
+        CsvRows {
+            reader: self.reader,
+            target,
+            width: self.columns.len(),
+            null_token,
+            pending: self.pending,
+            record: csv::StringRecord::new(),
+        }
+    }
+}
+
+impl RowSource for CsvRows<'_> {
+    fn next_row(&mut self) -> Result<Option<SourceRow>, String> {
+        match self.pending.take() {
+            Some(record) => self.record = record,
+            None => {
+                if !self.reader.read_record(&mut self.record).map_err(read_error)? {
+                    return Ok(None);
+                }
+            }
+        }
+
+        let line = self.record.position().map_or(0, |p| p.line());
+        let values = if self.record.len() > self.width {
+            Err(format!("Expected {} fields but found {}", self.width, self.record.len()))
+        } else {
+            // Missing trailing fields are NULL
+            let fields: Vec<Option<&str>> = (0..self.width).map(|idx| self.record.get(idx)).collect();
+            convert_text_fields(self.target, &fields, self.null_token.as_deref())
+        };
+
+        Ok(Some(SourceRow { line, values }))
+    }
+}
@@ -0,0 +1,241 @@
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use crate::database::dialect::{self, ColumnKind, SqlValue};
+use crate::database::temporal;
+use crate::models::*;
+
+/// Decimals wider than this are inferred as floating point.
+const MAX_DECIMAL_PRECISION: u32 = 38;
+
+const DATE_FORMATS: [&str; 2] = ["%Y-%m-%d", "%Y/%m/%d"];
+const DATETIME_FORMATS: [&str; 4] = [
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y-%m-%d %H:%M",
+    "%Y-%m-%dT%H:%M",
+];
+const TIME_FORMATS: [&str; 2] = ["%H:%M:%S%.f", "%H:%M"];
+
+/// Accumulates what the values of one column looked like, to pick a type
+/// that fits all of them.
+#[derive(Debug, Clone, Default)]
+pub struct KindTracker {
+    kind: Option<ColumnKind>,
+    nullable: bool,
+    max_length: usize,
+    int_digits: u32,
+    scale: u32,
+}
+
+impl KindTracker {
+    pub fn observe_null(&mut self) {
+        self.nullable = true;
+    }
+
+    /// Record a non-NULL field read as text.
+    pub fn observe_text(&mut self, text: &str) {
+        self.max_length = self.max_length.max(text.chars().count());
+        if text.trim().is_empty() {
+            self.nullable = true;
+            return;
+        }
+
+        let kind = classify(text.trim());
+        if let ColumnKind::Integer | ColumnKind::Decimal = kind {
+            let digits = text.trim().trim_start_matches(['-', '+']);
+            let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+            self.int_digits = self.int_digits.max(whole.len() as u32);
+            self.scale = self.scale.max(fraction.len() as u32);
+        }
+        self.observe_kind(kind);
+    }
+
+    /// Record a value from a JSON document.
+    pub fn observe_json(&mut self, value: &serde_json::Value) {
+        match value {
+            serde_json::Value::Null => self.nullable = true,
+            // Numbers go through the text path so decimal digits are tracked
+            serde_json::Value::Number(n) => self.observe_text(&n.to_string()),
+            serde_json::Value::String(s) => {
+                self.max_length = self.max_length.max(s.chars().count());
+                self.observe_kind(classify_json(value).unwrap_or(ColumnKind::Text));
+            }
+            other => {
+                self.max_length = self.max_length.max(other.to_string().chars().count());
+                self.observe_kind(classify_json(other).unwrap_or(ColumnKind::Text));
+            }
+        }
+    }
+
+    fn observe_kind(&mut self, kind: ColumnKind) {
+        self.kind = Some(match self.kind {
+            None => kind,
+            Some(current) => merge(current, kind),
+        });
+        if self.kind == Some(ColumnKind::Decimal) && self.int_digits + self.scale > MAX_DECIMAL_PRECISION {
+            self.kind = Some(ColumnKind::Float);
+        }
+    }
+
+    pub fn column(&self, name: &str) -> InferredColumn {
+        let kind = self.kind.unwrap_or(ColumnKind::Text);
+        let (precision, scale) = match kind {
+            ColumnKind::Decimal => (self.int_digits + self.scale, self.scale),
+            _ => (0, 0),
+        };
+        InferredColumn {
+            name: name.to_string(),
+            kind,
+            nullable: self.nullable || self.kind.is_none(),
+            max_length: self.max_length,
+            precision,
+            scale,
+        }
+    }
+}
+
+/// Narrowest kind a single text value parses as.
+pub fn classify(text: &str) -> ColumnKind {
+    if matches!(text.to_lowercase().as_str(), "true" | "false" | "yes" | "no") {
+        return ColumnKind::Boolean;
+    }
+
+    let unsigned = text.strip_prefix(['-', '+']).unwrap_or(text);
+    let (whole, fraction) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+    let all_digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+    // Leading zeros mark identifiers such as zip codes, which must stay text
+    let leading_zero = whole.len() > 1 && whole.starts_with('0');
+
+    if !whole.is_empty() && all_digits(whole) && !leading_zero {
+        if !unsigned.contains('.') {
+            if text.parse::<i64>().is_ok() {
+                return ColumnKind::Integer;
+            }
+        } else if !fraction.is_empty() && all_digits(fraction) {
+            return ColumnKind::Decimal;
+        }
+    }
+    if text.contains(['e', 'E']) && !leading_zero && text.parse::<f64>().is_ok_and(f64::is_finite) {
+        return ColumnKind::Float;
+    }
+
+    if parse_date(text).is_some() {
+        ColumnKind::Date
+    } else if parse_datetime(text).is_some() {
+        ColumnKind::DateTime
+    } else if temporal::parse_offset_datetime(text).is_some() {
+        ColumnKind::Timestamp
+    } else if parse_time(text).is_some() {
+        ColumnKind::Time
+    } else {
+        ColumnKind::Text
+    }
+}
+
+/// Widen two kinds to one that holds values of both.
+fn merge(a: ColumnKind, b: ColumnKind) -> ColumnKind {
+    use ColumnKind::*;
+    match (a, b) {
+        (a, b) if a == b => a,
+        (Integer, Decimal) | (Decimal, Integer) => Decimal,
+        (Integer | Decimal, Float) | (Float, Integer | Decimal) => Float,
+        (Date, DateTime) | (DateTime, Date) => DateTime,
+        _ => Text,
+    }
+}
+
+/// Convert a non-NULL text field into a value of the target column's kind.
+pub fn parse_field(kind: ColumnKind, text: &str) -> Result<SqlValue, String> {
+    let trimmed = text.trim();
+    let invalid = |what: &str| format!("'{}' is not a valid {}", text, what);
+
+    match kind {
+        ColumnKind::Boolean => match trimmed.to_lowercase().as_str() {
+            "true" | "t" | "yes" | "y" | "on" | "1" => Ok(SqlValue::Bool(true)),
+            "false" | "f" | "no" | "n" | "off" | "0" => Ok(SqlValue::Bool(false)),
+            _ => Err(invalid("boolean")),
+        },
+        ColumnKind::Integer => trimmed.parse().map(SqlValue::Int).map_err(|_| invalid("integer")),
+        ColumnKind::Float => trimmed.parse().map(SqlValue::Float).map_err(|_| invalid("number")),
+        ColumnKind::Decimal => {
+            let valid = match trimmed.contains(['e', 'E']) {
+                true => trimmed.parse::<f64>().is_ok_and(f64::is_finite),
+                false => {
+                    let scale = trimmed.split_once('.').map_or(0, |(_, f)| f.len() as u32);
+                    dialect::parse_decimal(trimmed, scale).is_some()
+                }
+            };
+            match valid {
+                true => Ok(SqlValue::Decimal(trimmed.trim_start_matches('+').to_string())),
+                false => Err(invalid("decimal")),
+            }
+        }
+        ColumnKind::Date => parse_date(trimmed).map(SqlValue::Date).ok_or_else(|| invalid("date")),
+        ColumnKind::Time => parse_time(trimmed).map(SqlValue::Time).ok_or_else(|| invalid("time")),
+        ColumnKind::DateTime => parse_datetime(trimmed)
+            .or_else(|| parse_date(trimmed).and_then(|d| d.and_hms_opt(0, 0, 0)))
+            .map(SqlValue::DateTime)
+            .ok_or_else(|| invalid("date and time")),
+        // Values without an offset are taken as UTC
+        ColumnKind::Timestamp => temporal::parse_offset_datetime(trimmed)
+            .or_else(|| parse_datetime(trimmed).map(|dt| dt.and_utc().fixed_offset()))
+            .map(SqlValue::Timestamp)
+            .ok_or_else(|| invalid("timestamp")),
+        ColumnKind::Binary => Ok(SqlValue::Bytes(
+            dialect::decode_hex_cell(trimmed).unwrap_or_else(|| text.as_bytes().to_vec()),
+        )),
+        ColumnKind::Json => serde_json::from_str(trimmed).map(SqlValue::Json).map_err(|_| invalid("JSON document")),
+        ColumnKind::Text => Ok(SqlValue::Text(text.to_string())),
+    }
+}
+
+/// Convert a JSON value into a value of the target column's kind.
+pub fn parse_json_value(kind: ColumnKind, value: &serde_json::Value) -> Result<SqlValue, String> {
+    use serde_json::Value;
+
+    match (kind, value) {
+        (_, Value::Null) => Ok(SqlValue::Null),
+        (ColumnKind::Json, value) => Ok(SqlValue::Json(value.clone())),
+        (ColumnKind::Text, Value::String(s)) => Ok(SqlValue::Text(s.clone())),
+        (ColumnKind::Text, other) => Ok(SqlValue::Text(other.to_string())),
+        (_, Value::String(s)) => parse_field(kind, s),
+        (ColumnKind::Boolean, Value::Bool(b)) => Ok(SqlValue::Bool(*b)),
+        (ColumnKind::Boolean, Value::Number(n)) => Ok(SqlValue::Bool(n.as_f64() != Some(0.0))),
+        (ColumnKind::Integer, Value::Bool(b)) => Ok(SqlValue::Int(*b as i64)),
+        (ColumnKind::Integer, Value::Number(n)) => n
+            .as_i64()
+            .map(SqlValue::Int)
+            .ok_or_else(|| format!("{} is not a valid integer", n)),
+        (ColumnKind::Float, Value::Number(n)) => Ok(SqlValue::Float(n.as_f64().unwrap_or_default())),
+        (ColumnKind::Decimal, Value::Number(n)) => Ok(SqlValue::Decimal(n.to_string())),
+        (kind, other) => Err(format!("{} does not fit a {:?} column", other, kind)),
+    }
+}
+
+/// Kind of a single JSON value. Strings are only checked for dates and
+/// times; numeric-looking strings stay text, as the producer chose them.
+pub fn classify_json(value: &serde_json::Value) -> Option<ColumnKind> {
+    use serde_json::Value;
+
+    match value {
+        Value::Null => None,
+        Value::Bool(_) => Some(ColumnKind::Boolean),
+        Value::Number(n) => Some(classify(&n.to_string())),
+        Value::String(s) => Some(match classify(s.trim()) {
+            ColumnKind::Boolean | ColumnKind::Integer | ColumnKind::Decimal | ColumnKind::Float => ColumnKind::Text,
+            kind => kind,
+        }),
+        Value::Array(_) | Value::Object(_) => Some(ColumnKind::Json),
+    }
+}
+
+fn parse_date(text: &str) -> Option<NaiveDate> {
+    DATE_FORMATS.iter().find_map(|f| NaiveDate::parse_from_str(text, f).ok())
+}
+
+fn parse_datetime(text: &str) -> Option<NaiveDateTime> {
+    DATETIME_FORMATS.iter().find_map(|f| NaiveDateTime::parse_from_str(text, f).ok())
+}
+
+fn parse_time(text: &str) -> Option<NaiveTime> {
+    TIME_FORMATS.iter().find_map(|f| NaiveTime::parse_from_str(text, f).ok())
+}
@@ -0,0 +1,441 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use encoding_rs::Encoding;
+use serde::de::{Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use crate::database::dialect::SqlValue;
+use crate::models::*;
+use super::infer::{self, KindTracker};
+use super::{DecodingReader, ImportTarget, RowSource, SourceRow};
+
+type TextReader = BufReader<DecodingReader<File>>;
+/// A record's line or position, and the record unless it failed to parse.
+type Record = (u64, Result<OrderedValue, String>);
+
+/// A JSON value whose objects keep their keys in document order, so that
+/// columns come out in the order the file lists them.
+enum OrderedValue {
+    Object(Vec<(String, OrderedValue)>),
+    Other(serde_json::Value),
+}
+
+impl OrderedValue {
+    fn into_value(self) -> serde_json::Value {
+        match self {
+            OrderedValue::Object(entries) => serde_json::Value::Object(
+                entries.into_iter().map(|(k, v)| (k, v.into_value())).collect(),
+            ),
+            OrderedValue::Other(value) => value,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for OrderedValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(OrderedValueVisitor)
+    }
+}
+
+struct OrderedValueVisitor;
+
+impl<'de> Visitor<'de> for OrderedValueVisitor {
+    type Value = OrderedValue;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a JSON value")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<OrderedValue, E> {
+        Ok(OrderedValue::Other(v.into()))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<OrderedValue, E> {
+        Ok(OrderedValue::Other(v.into()))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<OrderedValue, E> {
+        Ok(OrderedValue::Other(v.into()))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<OrderedValue, E> {
+        Ok(OrderedValue::Other(v.into()))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<OrderedValue, E> {
+        Ok(OrderedValue::Other(v.into()))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<OrderedValue, E> {
+        Ok(OrderedValue::Other(v.into()))
+    }
+
+    fn visit_unit<E>(self) -> Result<OrderedValue, E> {
+        Ok(OrderedValue::Other(serde_json::Value::Null))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<OrderedValue, A::Error> {
+        let mut items = Vec::new();
+        while let Some(item) = seq.next_element::<serde_json::Value>()? {
+            items.push(item);
+        }
+        Ok(OrderedValue::Other(serde_json::Value::Array(items)))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<OrderedValue, A::Error> {
+        let mut entries = Vec::new();
+        while let Some((key, value)) = map.next_entry::<String, OrderedValue>()? {
+            entries.push((key, value));
+        }
+        Ok(OrderedValue::Object(entries))
+    }
+}
+
+/// Reads the records of a JSON array or NDJSON file one at a time.
+enum RecordReader {
+    Ndjson { reader: TextReader, line: u64, buffer: String },
+    Array { reader: TextReader, index: u64, started: bool, done: bool },
+}
+
+impl RecordReader {
+    fn open(path: &str, layout: JsonLayout) -> Result<Self, String> {
+        let reader = open_text(path)?;
+        Ok(match layout {
+            JsonLayout::Ndjson => RecordReader::Ndjson { reader, line: 0, buffer: String::new() },
+            JsonLayout::Array => RecordReader::Array { reader, index: 0, started: false, done: false },
+        })
+    }
+
+    /// The next record with its line (NDJSON) or position (array). A record
+    /// that does not parse is returned as an error for that record alone.
+    fn next(&mut self) -> Result<Option<Record>, String> {
+        match self {
+            RecordReader::Ndjson { reader, line, buffer } => loop {
+                buffer.clear();
+                if reader.read_line(buffer).map_err(read_error)? == 0 {
+                    return Ok(None);
+                }
+                *line += 1;
+                if buffer.trim().is_empty() {
+                    continue;
+                }
+                let record = serde_json::from_str(buffer).map_err(|e| format!("Invalid JSON: {}", e));
+                return Ok(Some((*line, record)));
+            },
+            RecordReader::Array { reader, index, started, done } => {
+                if *done {
+                    return Ok(None);
+                }
+                if !*started {
+                    skip_whitespace(reader)?;
+                    if next_byte(reader)? != Some(b'[') {
+                        return Err("Expected the file to contain a JSON array".to_string());
+                    }
+                    *started = true;
+                }
+
+                let (element, last) = read_element(reader)?;
+                *done = last;
+                if last && element.iter().all(u8::is_ascii_whitespace) {
+                    return Ok(None);
+                }
+                *index += 1;
+                let record = serde_json::from_slice(&element).map_err(|e| format!("Invalid JSON: {}", e));
+                Ok(Some((*index, record)))
+            }
+        }
+    }
+}
+
+fn read_error(e: std::io::Error) -> String {
+    format!("Failed to read file: {}", e)
+}
+
+/// Open a JSON file as UTF-8 text; a byte order mark selects UTF-16.
+fn open_text(path: &str) -> Result<TextReader, String> {
+    let mut file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+    let mut bom = [0u8; 3];
+    let read = file.read(&mut bom).map_err(read_error)?;
+    let encoding = Encoding::for_bom(&bom[..read]).map_or(encoding_rs::UTF_8, |(encoding, _)| encoding);
+
+    let file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+    Ok(BufReader::new(DecodingReader::new(file, encoding)))
+}
+
+fn next_byte(reader: &mut TextReader) -> Result<Option<u8>, String> {
+    let byte = reader.fill_buf().map_err(read_error)?.first().copied();
+    if byte.is_some() {
+        reader.consume(1);
+    }
+    Ok(byte)
+}
+
+fn skip_whitespace(reader: &mut TextReader) -> Result<(), String> {
+    loop {
+        let buffer = reader.fill_buf().map_err(read_error)?;
+        if buffer.is_empty() {
+            return Ok(());
+        }
+        let skip = buffer.iter().take_while(|b| b.is_ascii_whitespace()).count();
+        let more = skip == buffer.len();
+        reader.consume(skip);
+        if !more {
+            return Ok(());
+        }
+    }
+}
+
+/// Collect the bytes of the next array element, up to the `,` or `]` that
+/// ends it. Returns whether the array ended.
+fn read_element(reader: &mut TextReader) -> Result<(Vec<u8>, bool), String> {
+    let mut element = Vec::new();
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    loop {
+        let buffer = reader.fill_buf().map_err(read_error)?;
+        if buffer.is_empty() {
+            return Err("Unexpected end of file inside the JSON array".to_string());
+        }
+
+        let mut end = None;
+        for (i, &byte) in buffer.iter().enumerate() {
+            if in_string {
+                match byte {
+                    _ if escaped => escaped = false,
+                    b'\\' => escaped = true,
+                    b'"' => in_string = false,
+                    _ => {}
+                }
+                continue;
+            }
+            match byte {
+                b'"' => in_string = true,
+                b'{' | b'[' => depth += 1,
+                b'}' | b']' if depth > 0 => depth -= 1,
+                b']' => {
+                    end = Some((i, true));
+                    break;
+                }
+                b',' if depth == 0 => {
+                    end = Some((i, false));
+                    break;
+                }
+                _ => {}
+            }
+        }
+
+        match end {
+            Some((i, last)) => {
+                element.extend_from_slice(&buffer[..i]);
+                reader.consume(i + 1);
+                return Ok((element, last));
+            }
+            None => {
+                let len = buffer.len();
+                element.extend_from_slice(buffer);
+                reader.consume(len);
+            }
+        }
+    }
+}
+
+/// An array file starts with `[`; anything else is read as NDJSON.
+pub fn detect_layout(path: &str) -> Result<JsonLayout, String> {
+    let mut reader = open_text(path)?;
+    skip_whitespace(&mut reader)?;
+    Ok(match reader.fill_buf().map_err(read_error)?.first() {
+        Some(b'[') => JsonLayout::Array,
+        _ => JsonLayout::Ndjson,
+    })
+}
+
+/// Turn a record into `(column, value)` pairs, flattening nested objects
+/// into `parent<separator>child` columns as configured.
+fn flatten_record(record: OrderedValue, options: &JsonReadOptions) -> Result<Vec<(String, serde_json::Value)>, String> {
+    let OrderedValue::Object(entries) = record else {
+        return Err("Record is not a JSON object".to_string());
+    };
+    let mut columns = Vec::with_capacity(entries.len());
+    for (key, value) in entries {
+        flatten_into(key, value, 1, options, &mut columns);
+    }
+    Ok(columns)
+}
+
+fn flatten_into(
+    path: String,
+    value: OrderedValue,
+    depth: usize,
+    options: &JsonReadOptions,
+    out: &mut Vec<(String, serde_json::Value)>,
+) {
+    match value {
+        OrderedValue::Object(entries)
+            if matches!(options.nested, NestedMode::Flatten)
+                && !entries.is_empty()
+                && options.max_depth.is_none_or(|max| depth <= max) =>
+        {
+            for (key, value) in entries {
+                flatten_into(format!("{}{}{}", path, options.separator, key), value, depth + 1, options, out);
+            }
+        }
+        other => out.push((path, other.into_value())),
+    }
+}
+
+/// Columns seen across records, in order of first appearance.
+#[derive(Default)]
+struct ColumnSet {
+    names: Vec<String>,
+    index: HashMap<String, usize>,
+    trackers: Vec<KindTracker>,
+    /// Records each column appeared in
+    present: Vec<u64>,
+}
+
+impl ColumnSet {
+    fn observe(&mut self, fields: &[(String, serde_json::Value)]) {
+        for (name, value) in fields {
+            let idx = *self.index.entry(name.clone()).or_insert_with(|| {
+                self.names.push(name.clone());
+                self.trackers.push(KindTracker::default());
+                self.present.push(0);
+                self.names.len() - 1
+            });
+            self.trackers[idx].observe_json(value);
+            self.present[idx] += 1;
+        }
+    }
+
+    fn columns(&self, records: u64) -> Vec<InferredColumn> {
+        self.names
+            .iter()
+            .enumerate()
+            .map(|(idx, name)| {
+                let mut column = self.trackers[idx].column(name);
+                column.nullable |= self.present[idx] < records;
+                column
+            })
+            .collect()
+    }
+}
+
+/// Detected layout, columns with types inferred from the first records, and
+/// those records in column order.
+pub fn preview(path: &str, options: &JsonReadOptions) -> Result<JsonPreview, String> {
+    let layout = match options.layout {
+        Some(layout) => layout,
+        None => detect_layout(path)?,
+    };
+    let mut reader = RecordReader::open(path, layout)?;
+
+    let mut set = ColumnSet::default();
+    let mut records = Vec::new();
+    while records.len() < options.preview_rows {
+        let Some((_, record)) = reader.next()? else {
+            break;
+        };
+        let Ok(fields) = record.and_then(|r| flatten_record(r, options)) else {
+            continue;
+        };
+        set.observe(&fields);
+        records.push(fields);
+    }
+
+    let rows = records
+        .into_iter()
+        .map(|fields| {
+            let mut row = vec![serde_json::Value::Null; set.names.len()];
+            for (name, value) in fields {
+                row[set.index[&name]] = value;
+            }
+            row
+        })
+        .collect();
+
+    Ok(JsonPreview {
+        layout,
+        columns: set.columns(set.present.iter().copied().max().unwrap_or(0)),
+        rows,
+    })
+}
+
+/// Collect the columns of every record and infer their types.
+pub fn scan_columns(path: &str, layout: JsonLayout, options: &JsonReadOptions) -> Result<Vec<InferredColumn>, String> {
+    let mut reader = RecordReader::open(path, layout)?;
+    let mut set = ColumnSet::default();
+    let mut records = 0;
+
+    while let Some((_, record)) = reader.next()? {
+        // Broken records are reported during the import itself
+        if let Ok(fields) = record.and_then(|r| flatten_record(r, options)) {
+            set.observe(&fields);
+            records += 1;
+        }
+    }
+
+    if set.names.is_empty() {
+        return Err("The file contains no JSON objects".to_string());
+    }
+    Ok(set.columns(records))
+}
+
+/// Records of a JSON or NDJSON file converted to the import target.
+pub struct JsonRows<'a> {
+    reader: RecordReader,
+    target: &'a ImportTarget,
+    options: &'a JsonReadOptions,
+    /// Source column name to its index in the scanned columns
+    source_index: HashMap<String, usize>,
+}
+
+impl<'a> JsonRows<'a> {
+    pub fn open(
+        path: &str,
+        layout: JsonLayout,
+        columns: &[String],
+        target: &'a ImportTarget,
+        options: &'a JsonReadOptions,
+    ) -> Result<Self, String> {
+        Ok(Self {
+            reader: RecordReader::open(path, layout)?,
+            target,
+            options,
+            source_index: columns.iter().enumerate().map(|(i, name)| (name.clone(), i)).collect(),
+        })
+    }
+
+    fn convert(&self, record: OrderedValue) -> Result<Vec<SqlValue>, String> {
+        let fields = flatten_record(record, self.options)?;
+        let mut by_source: Vec<Option<serde_json::Value>> = vec![None; self.source_index.len()];
+        for (name, value) in fields {
+            if let Some(&idx) = self.source_index.get(&name) {
+                by_source[idx] = Some(value);
+            }
+        }
+
+        self.target
+            .sources
+            .iter()
+            .zip(&self.target.columns)
+            .zip(&self.target.kinds)
+            .map(|((&idx, column), &kind)| match &by_source[idx] {
+                None => Ok(SqlValue::Null),
+                Some(value) => infer::parse_json_value(kind, value)
+                    .map_err(|e| format!("Column \"{}\": {}", column, e)),
+            })
+            .collect()
+    }
+}
+
+impl RowSource for JsonRows<'_> {
+    fn next_row(&mut self) -> Result<Option<SourceRow>, String> {
+        let Some((line, record)) = self.reader.next()? else {
+            return Ok(None);
+        };
+        let values = record.and_then(|r| self.convert(r));
+        Ok(Some(SourceRow { line, values }))
+    }
+}
@@ -0,0 +1,429 @@
+pub mod delimited;
+pub mod infer;
+pub mod json;
+
+use std::io::Read;
+use std::time::Instant;
+use encoding_rs::{CoderResult, Decoder, Encoding};
+use tokio_util::sync::CancellationToken;
+use crate::database::dialect::{self, ColumnKind, SqlValue};
+use crate::database::{DatabaseConnection, DatabaseSession};
+use crate::models::*;
+
+/// Failed rows kept in the summary; further failures are only counted.
+const MAX_REPORTED_ERRORS: usize = 1000;
+/// Bytes read from the source file per decode step.
+const DECODE_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Wraps a reader in another encoding and yields UTF-8.
+pub struct DecodingReader<R: Read> {
+    inner: R,
+    decoder: Decoder,
+    input: Vec<u8>,
+    output: Vec<u8>,
+    position: usize,
+    eof: bool,
+}
+
+impl<R: Read> DecodingReader<R> {
+    /// A leading byte order mark overrides `encoding` and is dropped.
+    pub fn new(inner: R, encoding: &'static Encoding) -> Self {
+        Self {
+            inner,
+            decoder: encoding.new_decoder_with_bom_removal(),
+            input: vec![0; DECODE_CHUNK_SIZE],
+            output: Vec::new(),
+            position: 0,
+            eof: false,
+        }
+    }
+}
+
+impl<R: Read> Read for DecodingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.position >= self.output.len() {
+            if self.eof {
+                return Ok(0);
+            }
+
+            let read = self.inner.read(&mut self.input)?;
+            self.eof = read == 0;
+
+            let capacity = self
+                .decoder
+                .max_utf8_buffer_length(read)
+                .unwrap_or(DECODE_CHUNK_SIZE * 3);
+            self.output.clear();
+            self.output.resize(capacity, 0);
+            self.position = 0;
+
+            let (result, _, written, _) =
+                self.decoder
+                    .decode_to_utf8(&self.input[..read], &mut self.output, self.eof);
+            debug_assert!(matches!(result, CoderResult::InputEmpty));
+            self.output.truncate(written);
+        }
+
+        let n = buf.len().min(self.output.len() - self.position);
+        buf[..n].copy_from_slice(&self.output[self.position..self.position + n]);
+        self.position += n;
+        Ok(n)
+    }
+}
+
+/// One record read from an import file.
+pub struct SourceRow {
+    pub line: u64,
+    /// Values in target column order, or why this record cannot be imported
+    pub values: Result<Vec<SqlValue>, String>,
+}
+
+/// Records of an import file, already converted to the target columns.
+pub trait RowSource: Send {
+    /// The next record, `None` at the end of the file. An `Err` ends the
+    /// import; problems with a single record go into `SourceRow::values`.
+    fn next_row(&mut self) -> Result<Option<SourceRow>, String>;
+}
+
+/// Where the columns of an import file end up.
+pub struct ImportTarget {
+    pub table: String,
+    /// Target column names
+    pub columns: Vec<String>,
+    /// Index of the source column feeding each target column
+    pub sources: Vec<usize>,
+    /// Kind each source value is parsed as
+    pub kinds: Vec<ColumnKind>,
+    /// `CREATE TABLE` to run before importing, if the table is missing
+    pub create_sql: Option<String>,
+    /// Delete the rows already in the table, inside the import transaction
+    pub truncate: bool,
+}
+
+/// Match source columns to the target table, or plan its creation.
+/// `infer` is only called when the table has to be created.
+pub async fn resolve_target<F>(
+    conn: &DatabaseConnection,
+    table: &str,
+    source: &[String],
+    options: &ImportOptions,
+    infer: F,
+) -> Result<ImportTarget, String>
+where
+    F: FnOnce() -> Result<Vec<InferredColumn>, String>,
+{
+    let db_type = conn.db_type();
+    let find_source = |name: &str| {
+        source
+            .iter()
+            .position(|s| s.eq_ignore_ascii_case(name))
+            .ok_or_else(|| format!("Source column \"{}\" not found", name))
+    };
+
+    let structure = crate::database::get_table_structure_info(conn, table).await?;
+
+    if structure.columns.is_empty() {
+        if !options.create_table {
+            return Err(format!("Table {} does not exist", table));
+        }
+
+        let inferred = infer()?;
+        let mapping: Vec<ColumnMapping> = match &options.mapping {
+            Some(mapping) => mapping.clone(),
+            None => source
+                .iter()
+                .map(|name| ColumnMapping { source: name.clone(), target: name.clone(), sql_type: None })
+                .collect(),
+        };
+
+        let mut target = ImportTarget {
+            table: table.to_string(),
+            columns: Vec::new(),
+            sources: Vec::new(),
+            kinds: Vec::new(),
+            create_sql: None,
+            truncate: false,
+        };
+        let mut definitions = Vec::new();
+        for pair in &mapping {
+            let idx = find_source(&pair.source)?;
+            let column = &inferred[idx];
+            let sql_type = pair.sql_type.clone().unwrap_or_else(|| {
+                dialect::column_type_sql(&db_type, column.kind, column.max_length, column.precision, column.scale)
+            });
+            let null = if column.nullable { "" } else { " NOT NULL" };
+            definitions.push(format!("{} {}{}", dialect::quote_ident(&db_type, &pair.target), sql_type, null));
+
+            target.columns.push(pair.target.clone());
+            target.sources.push(idx);
+            target.kinds.push(match &pair.sql_type {
+                Some(sql_type) => dialect::column_kind(sql_type),
+                None => column.kind,
+            });
+        }
+        if definitions.is_empty() {
+            return Err("No columns to import".to_string());
+        }
+
+        target.create_sql = Some(format!(
+            "CREATE TABLE {} (\n    {}\n)",
+            dialect::quote_table(&db_type, table),
+            definitions.join(",\n    "),
+        ));
+        return Ok(target);
+    }
+
+    let find_target = |name: &str| {
+        structure
+            .columns
+            .iter()
+            .find(|c| c.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| format!("Column \"{}\" not found in {}", name, table))
+    };
+
+    let mut target = ImportTarget {
+        table: table.to_string(),
+        columns: Vec::new(),
+        sources: Vec::new(),
+        kinds: Vec::new(),
+        create_sql: None,
+        truncate: false,
+    };
+    match &options.mapping {
+        Some(mapping) => {
+            for pair in mapping {
+                let idx = find_source(&pair.source)?;
+                let column = find_target(&pair.target)?;
+                target.columns.push(column.name.clone());
+                target.sources.push(idx);
+                target.kinds.push(dialect::column_kind(&column.data_type));
+            }
+        }
+        None => {
+            for (idx, name) in source.iter().enumerate() {
+                if let Ok(column) = find_target(name) {
+                    target.columns.push(column.name.clone());
+                    target.sources.push(idx);
+                    target.kinds.push(dialect::column_kind(&column.data_type));
+                }
+            }
+        }
+    }
+
+    if target.columns.is_empty() {
+        return Err(format!("No source columns match the columns of {}", table));
+    }
+    Ok(target)
+}
+
+/// Insert every row of `source` into the target inside one transaction,
+/// batch by batch. In skip mode a failing batch is retried row by row under
+/// savepoints so that only the offending rows are left out.
+pub async fn run_import(
+    conn: &DatabaseConnection,
+    target: &ImportTarget,
+    source: &mut dyn RowSource,
+    options: &ImportOptions,
+    cancel: CancellationToken,
+    progress: &mut (dyn FnMut(u64) + Send),
+) -> Result<ImportSummary, String> {
+    let start = Instant::now();
+    let mut session = conn.session().await?;
+
+    // Created outside the transaction: MySQL commits implicitly on DDL
+    if let Some(sql) = &target.create_sql {
+        log::info!("[import] Creating table {}", target.table);
+        session
+            .execute(sql)
+            .await
+            .map_err(|e| format!("Failed to create table: {}", e))?;
+    }
+
+    let mut import = Import {
+        target,
+        options,
+        summary: ImportSummary {
+            table: target.table.clone(),
+            created_table: target.create_sql.clone(),
+            rows_read: 0,
+            rows_imported: 0,
+            rows_failed: 0,
+            errors: Vec::new(),
+            duration_ms: 0,
+        },
+    };
+
+    if let Err(e) = session.begin().await {
+        import.drop_created_table(&mut session).await;
+        return Err(e);
+    }
+
+    match import.clear_and_load(&mut session, source, &cancel, progress).await {
+        Ok(()) => {
+            session.commit().await?;
+        }
+        Err(e) => {
+            if let Err(rollback_err) = session.rollback().await {
+                log::error!("[import] Rollback failed: {}", rollback_err);
+            }
+            import.drop_created_table(&mut session).await;
+            return Err(e);
+        }
+    }
+
+    let mut summary = import.summary;
+    summary.duration_ms = start.elapsed().as_millis() as u64;
+    log::info!(
+        "[import] Imported {} of {} rows into {}",
+        summary.rows_imported,
+        summary.rows_read,
+        summary.table
+    );
+    Ok(summary)
+}
+
+struct Import<'a> {
+    target: &'a ImportTarget,
+    options: &'a ImportOptions,
+    summary: ImportSummary,
+}
+
+impl Import<'_> {
+    async fn clear_and_load(
+        &mut self,
+        session: &mut DatabaseSession<'_>,
+        source: &mut dyn RowSource,
+        cancel: &CancellationToken,
+        progress: &mut (dyn FnMut(u64) + Send),
+    ) -> Result<(), String> {
+        if self.target.truncate {
+            let sql = format!("DELETE FROM {}", dialect::quote_table(&session.db_type(), &self.target.table));
+            session
+                .execute(&sql)
+                .await
+                .map_err(|e| format!("Failed to clear table: {}", e))?;
+        }
+        self.load(session, source, cancel, progress).await
+    }
+
+    async fn load(
+        &mut self,
+        session: &mut DatabaseSession<'_>,
+        source: &mut dyn RowSource,
+        cancel: &CancellationToken,
+        progress: &mut (dyn FnMut(u64) + Send),
+    ) -> Result<(), String> {
+        let batch_size = self.options.batch_size.max(1);
+        let mut batch: Vec<(u64, Vec<SqlValue>)> = Vec::with_capacity(batch_size);
+
+        while let Some(row) = source.next_row()? {
+            if cancel.is_cancelled() {
+                return Err("Import cancelled".to_string());
+            }
+            self.summary.rows_read += 1;
+
+            match row.values {
+                Ok(values) => batch.push((row.line, values)),
+                Err(e) => self.fail_row(row.line, e)?,
+            }
+
+            if batch.len() >= batch_size {
+                self.flush(session, &mut batch).await?;
+                progress(self.summary.rows_read);
+            }
+        }
+
+        self.flush(session, &mut batch).await?;
+        progress(self.summary.rows_read);
+        Ok(())
+    }
+
+    async fn flush(&mut self, session: &mut DatabaseSession<'_>, batch: &mut Vec<(u64, Vec<SqlValue>)>) -> Result<(), String> {
+        if batch.is_empty() {
+            return Ok(());
+        }
+        let rows: Vec<Vec<SqlValue>> = batch.iter().map(|(_, values)| values.clone()).collect();
+        let table = &self.target.table;
+        let columns = &self.target.columns;
+
+        if let ImportErrorMode::Abort = self.options.on_error {
+            session.insert_rows(table, columns, &rows).await.map_err(|e| {
+                format!("Failed to insert rows (lines {}-{}): {}", batch[0].0, batch[batch.len() - 1].0, e)
+            })?;
+            self.summary.rows_imported += rows.len() as u64;
+            batch.clear();
+            return Ok(());
+        }
+
+        session.savepoint("import_batch").await?;
+        match session.insert_rows(table, columns, &rows).await {
+            Ok(()) => {
+                self.summary.rows_imported += rows.len() as u64;
+            }
+            Err(e) => {
+                log::warn!("[import] Batch failed, retrying row by row: {}", e);
+                session.rollback_to_savepoint("import_batch").await?;
+
+                for (line, values) in batch.iter() {
+                    session.savepoint("import_row").await?;
+                    match session.insert_statements(table, columns, std::slice::from_ref(values)).await {
+                        Ok(()) => self.summary.rows_imported += 1,
+                        Err(e) => {
+                            session.rollback_to_savepoint("import_row").await?;
+                            self.fail_row(*line, e)?;
+                        }
+                    }
+                }
+            }
+        }
+
+        batch.clear();
+        Ok(())
+    }
+
+    /// Record a failed row, or end the import when errors are not skipped.
+    fn fail_row(&mut self, line: u64, message: String) -> Result<(), String> {
+        if let ImportErrorMode::Abort = self.options.on_error {
+            return Err(format!("Line {}: {}", line, message));
+        }
+        self.summary.rows_failed += 1;
+        if self.summary.errors.len() < MAX_REPORTED_ERRORS {
+            self.summary.errors.push(ImportRowError { line, message });
+        }
+        Ok(())
+    }
+
+    /// Undo `CREATE TABLE` after a failed import so a retry starts clean.
+    async fn drop_created_table(&self, session: &mut DatabaseSession<'_>) {
+        if self.target.create_sql.is_none() {
+            return;
+        }
+        let sql = format!("DROP TABLE {}", dialect::quote_table(&session.db_type(), &self.target.table));
+        if let Err(e) = session.execute(&sql).await {
+            log::warn!("[import] Could not drop table {}: {}", self.target.table, e);
+        }
+    }
+}
+
+/// Convert the raw text fields of one record to the target columns.
+pub fn convert_text_fields(
+    target: &ImportTarget,
+    fields: &[Option<&str>],
+    null_token: Option<&str>,
+) -> Result<Vec<SqlValue>, String> {
+    target
+        .sources
+        .iter()
+        .zip(&target.columns)
+        .zip(&target.kinds)
+        .map(|((&idx, column), &kind)| {
+            let field = fields.get(idx).copied().flatten();
+            match field {
+                None => Ok(SqlValue::Null),
+                Some(text) if Some(text) == null_token => Ok(SqlValue::Null),
+                Some(text) if text.trim().is_empty() && kind != ColumnKind::Text => Ok(SqlValue::Null),
+                Some(text) => infer::parse_field(kind, text).map_err(|e| format!("Column \"{}\": {}", column, e)),
+            }
+        })
+        .collect()
+}
@@ -1,8 +1,18 @@
 pub mod database;
 pub mod commands;
+pub mod compare;
 pub mod models;
+pub mod history;
+pub mod secrets;
+pub mod profiles;
+pub mod export;
+pub mod import;
+pub mod script;
+pub mod transfer;
+pub mod tasks;
 
 use commands::*;
+use tauri::{Emitter, Manager};
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -10,16 +20,84 @@ pub fn run() {
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
         .manage(database::ConnectionManager::new())
+        .manage(tasks::TaskManager::new())
+        .setup(|app| {
+            let data_dir = app.path().app_data_dir()?;
+            let history = tauri::async_runtime::block_on(
+                history::HistoryStore::open_or_recover(&data_dir.join("history.db")),
+            );
+            app.manage(history);
+            app.manage(secrets::SecretStore::new(&data_dir));
+            let config_dir = app.path().app_config_dir()?;
+            app.manage(profiles::ProfileStore::open(&config_dir.join("profiles.json")));
+
+            let handle = app.handle().clone();
+            app.state::<database::ConnectionManager>().on_status(Box::new(move |status| {
+                if let Err(e) = handle.emit("connection-status", status) {
+                    log::warn!("[ConnectionManager] Failed to emit status: {}", e);
+                }
+            }));
+            let handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                loop {
+                    tokio::time::sleep(database::health::HEALTH_CHECK_INTERVAL).await;
+                    handle.state::<database::ConnectionManager>().check_health().await;
+                }
+            });
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             test_connection,
             connect_database,
             disconnect_database,
             execute_query,
+            explain_query,
             get_tables,
             get_table_structure,
             get_table_data,
-            list_databases
+            list_databases,
+            get_connection_stats,
+            download_cell,
+            upload_cell,
+            export_query,
+            export_table,
+            dump_database,
+            preview_csv,
+            import_csv,
+            preview_json,
+            import_json,
+            execute_sql_file,
+            copy_table,
+            compare_schemas,
+            compare_table_data,
+            parse_connection_url,
+            format_connection_url,
+            save_credentials,
+            delete_credentials,
+            get_secret_store_status,
+            unlock_secret_store,
+            lock_secret_store,
+            list_profiles,
+            profile_load_error,
+            save_profile,
+            delete_profile,
+            export_profiles,
+            import_profiles,
+            import_client_profiles,
+            cancel_task,
+            search_history,
+            save_query,
+            set_history_pinned,
+            delete_history_entry,
+            clear_history
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            // SSH tunnels are child processes and would outlive the app
+            if let tauri::RunEvent::Exit = event {
+                let connections = app.state::<database::ConnectionManager>();
+                tauri::async_runtime::block_on(connections.close_all());
+            }
+        });
 }
@@ -1,6 +1,9 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
 use serde::{Deserialize, Serialize};
+use crate::database::dialect::ColumnKind;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum DatabaseType {
     PostgreSQL,
     MySQL,
@@ -17,6 +20,229 @@ pub struct ConnectionConfig {
     pub database: String,
     pub username: String,
     pub password: String,
+    /// Timezone used to display timestamps with a timezone (`UTC`, `local`,
+    /// `+05:30` or an IANA name). When unset the server session timezone is kept.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_timezone: Option<String>,
+    /// Reach the server through an SSH bastion
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssh_tunnel: Option<SshTunnelConfig>,
+    #[serde(default)]
+    pub tls: TlsConfig,
+    /// Connection URL or DSN as entered; when set it decides host, port,
+    /// credentials, TLS and parameters (see `database::dsn`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Unix socket path, used instead of host and port (PostgreSQL and MySQL)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub socket: Option<String>,
+    /// Extra driver parameters such as `application_name` or `options`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub parameters: BTreeMap<String, String>,
+    /// Refuse writes: the session is opened read-only where the engine
+    /// allows it and statements that change data or schema are rejected
+    #[serde(default)]
+    pub read_only: bool,
+    /// Ask for confirmation before destructive statements such as `DROP`
+    /// or a `DELETE` without `WHERE`
+    #[serde(default)]
+    pub protected: bool,
+    #[serde(default, skip_serializing_if = "PoolConfig::is_default")]
+    pub pool: PoolConfig,
+    /// SQL run on every new connection of the pool, such as
+    /// `SET search_path TO app` or `PRAGMA foreign_keys = ON`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub init_sql: Option<String>,
+    /// Entry in the secret store holding the password, SSH secrets and key;
+    /// filled back in when connecting
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credential_id: Option<String>,
+}
+
+/// How the connection is encrypted and how far the server certificate is trusted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TlsMode {
+    Disable,
+    /// Encrypt when the server supports it, without checking its certificate
+    #[default]
+    Prefer,
+    /// Always encrypt, without checking the certificate
+    Require,
+    /// Check the certificate was issued by a trusted CA
+    VerifyCa,
+    /// Also check the certificate names the server
+    VerifyFull,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TlsConfig {
+    pub mode: TlsMode,
+    /// PEM bundle trusted in addition to the system roots
+    pub ca_cert_path: Option<String>,
+    pub client_cert_path: Option<String>,
+    pub client_key_path: Option<String>,
+    /// Name the certificate is checked against instead of the host, for
+    /// SQL Server; through an SSH tunnel it defaults to the host. PostgreSQL
+    /// and MySQL always check the host, so they refuse `VerifyFull` through
+    /// a tunnel.
+    pub server_name: Option<String>,
+}
+
+/// Connection pool limits; unset ones keep the engine's defaults.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PoolConfig {
+    pub max_connections: Option<u32>,
+    /// Connections kept open even when unused
+    pub min_connections: Option<u32>,
+    /// Seconds to wait for a free connection before giving up
+    pub acquire_timeout_secs: Option<u64>,
+    /// Seconds before an unused connection is closed; 0 keeps it
+    pub idle_timeout_secs: Option<u64>,
+    /// Seconds before a connection is replaced, however busy; 0 keeps it
+    pub max_lifetime_secs: Option<u64>,
+}
+
+/// `PoolConfig` with the engine's defaults filled in.
+#[derive(Debug, Clone)]
+pub struct PoolSettings {
+    pub max_connections: u32,
+    pub min_connections: u32,
+    pub acquire_timeout: Duration,
+    pub idle_timeout: Option<Duration>,
+    pub max_lifetime: Option<Duration>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SshAuthMethod {
+    Password,
+    PrivateKey,
+    /// Keys held by the running SSH agent
+    Agent,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SshTunnelConfig {
+    pub host: String,
+    pub port: u16,
+    pub username: String,
+    pub auth: SshAuthMethod,
+    pub password: Option<String>,
+    pub private_key_path: Option<String>,
+    /// Private key in OpenSSH or PEM form, used instead of `private_key_path`
+    pub private_key: Option<String>,
+    /// Passphrase of an encrypted private key
+    pub passphrase: Option<String>,
+    /// `known_hosts` file checked for the bastion's key; the user's own when unset
+    pub known_hosts_path: Option<String>,
+    /// Trust and remember a host seen for the first time. A changed key is always rejected.
+    pub accept_new_host_key: bool,
+}
+
+impl Default for SshTunnelConfig {
+    fn default() -> Self {
+        Self {
+            host: String::new(),
+            port: 22,
+            username: String::new(),
+            auth: SshAuthMethod::Agent,
+            password: None,
+            private_key_path: None,
+            private_key: None,
+            passphrase: None,
+            known_hosts_path: None,
+            accept_new_host_key: false,
+        }
+    }
+}
+
+/// Secrets of a saved connection, kept in the secret store rather than in
+/// the profile.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConnectionSecrets {
+    pub password: Option<String>,
+    pub ssh_password: Option<String>,
+    pub ssh_passphrase: Option<String>,
+    pub ssh_private_key: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SecretBackend {
+    /// Secret Service, macOS Keychain or Windows Credential Manager
+    Keychain,
+    /// File encrypted with a master password, where no keychain is available
+    EncryptedFile,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecretStoreStatus {
+    pub backend: SecretBackend,
+    /// The encrypted file has been created
+    pub initialized: bool,
+    /// Secrets can be read without asking for the master password
+    pub unlocked: bool,
+    /// Why the encrypted file is used although a keychain was expected
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+}
+
+/// Deployment stage a profile is labelled with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProfileEnvironment {
+    Dev,
+    Staging,
+    Prod,
+}
+
+/// A saved connection and where it sits in the connection list. Profiles
+/// never hold secrets; those are in the secret store under `credential_id`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectionProfile {
+    #[serde(default)]
+    pub id: String,
+    pub config: ConnectionConfig,
+    /// Folder path such as `Clients/Acme`; top level when empty
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub folder: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// CSS colour of the profile's label
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<ProfileEnvironment>,
+    /// Keep the password and SSH secrets in the secret store
+    #[serde(default = "default_save_password")]
+    pub save_password: bool,
+    #[serde(default)]
+    pub created_at: String,
+    #[serde(default)]
+    pub updated_at: String,
+}
+
+fn default_save_password() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProfileFormat {
+    Json,
+    Toml,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileImportResult {
+    pub imported: Vec<ConnectionProfile>,
+    /// Entries left out or imported only in part, with the reason
+    pub skipped: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -50,8 +276,14 @@ pub struct ColumnInfo {
     pub foreign_key_column: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub is_auto_increment: Option<bool>,
+    /// Declared length of character and binary columns; unset when unlimited
     #[serde(skip_serializing_if = "Option::is_none")]
     pub max_length: Option<i32>,
+    /// Declared precision and scale of decimal columns
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub numeric_precision: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub numeric_scale: Option<i32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub check_constraint: Option<String>,
 }
@@ -66,6 +298,28 @@ pub struct TableStructure {
 pub struct ResultColumn {
     pub name: String,
     pub type_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nullable: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub precision: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scale: Option<u32>,
+    /// Table the column was read from, when the driver reports it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_table: Option<String>,
+}
+
+impl ResultColumn {
+    pub fn new(name: impl Into<String>, type_name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            type_name: type_name.into(),
+            nullable: None,
+            precision: None,
+            scale: None,
+            source_table: None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -76,6 +330,103 @@ pub struct QueryResult {
     pub execution_time_ms: u64,
     #[serde(default)] // Default to false if missing in JSON (backwards compat)
     pub truncated: bool,
+    /// Set instead of running the query when a protected connection needs
+    /// the user to confirm it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confirmation_required: Option<ConfirmationRequired>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DestructiveKind {
+    DeleteWithoutWhere,
+    UpdateWithoutWhere,
+    Drop,
+    Truncate,
+    AlterDrop,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RowEstimateSource {
+    /// Exact, from `COUNT(*)`
+    Count,
+    /// The planner's estimate
+    Explain,
+}
+
+/// What one destructive statement of a query would do.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatementImpact {
+    pub kind: DestructiveKind,
+    pub statement: String,
+    /// e.g. "Deletes every row of orders"
+    pub description: String,
+    pub tables: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estimated_rows: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estimate_source: Option<RowEstimateSource>,
+}
+
+/// Run the query again with `token` to go ahead.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfirmationRequired {
+    pub token: String,
+    pub impacts: Vec<StatementImpact>,
+}
+
+/// One operator in an execution plan, normalized across engines.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanNode {
+    pub node_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub relation: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub startup_cost: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_cost: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estimated_rows: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actual_rows: Option<f64>,
+    /// Actual time spent in this node per loop, in milliseconds (ANALYZE only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actual_time_ms: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub loops: Option<u64>,
+    /// Engine-specific properties that have no common equivalent
+    pub details: serde_json::Map<String, serde_json::Value>,
+    pub children: Vec<PlanNode>,
+}
+
+impl PlanNode {
+    pub fn new(node_type: impl Into<String>) -> Self {
+        Self {
+            node_type: node_type.into(),
+            relation: None,
+            startup_cost: None,
+            total_cost: None,
+            estimated_rows: None,
+            actual_rows: None,
+            actual_time_ms: None,
+            loops: None,
+            details: serde_json::Map::new(),
+            children: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryPlan {
+    pub root: PlanNode,
+    /// Plan exactly as returned by the engine (JSON, text or XML)
+    pub raw: String,
+    pub analyzed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub planning_time_ms: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub execution_time_ms: Option<f64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -85,36 +436,946 @@ pub struct TestConnectionResult {
     pub version: Option<String>,
 }
 
+/// A statement to add to the query history.
+#[derive(Debug, Clone)]
+pub struct NewHistoryEntry {
+    pub connection_name: String,
+    pub database_name: String,
+    pub sql: String,
+    pub duration_ms: u64,
+    pub row_count: Option<u64>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub id: i64,
+    pub connection_name: String,
+    pub database_name: String,
+    pub sql: String,
+    pub executed_at: String,
+    pub duration_ms: Option<u64>,
+    pub row_count: Option<u64>,
+    pub error: Option<String>,
+    pub pinned: bool,
+    pub title: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HistoryFilter {
+    /// Full-text search over the SQL and title
+    pub search: Option<String>,
+    pub connection_name: Option<String>,
+    /// Inclusive bounds, RFC 3339 or `YYYY-MM-DD`
+    pub from: Option<String>,
+    pub to: Option<String>,
+    #[serde(default)]
+    pub pinned_only: bool,
+    #[serde(default)]
+    pub errors_only: bool,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    Csv,
+    Tsv,
+    /// One JSON object per line
+    Ndjson,
+    JsonArray,
+    /// `INSERT` statements for `ExportOptions::target_dialect`
+    SqlInsert,
+    Parquet,
+    /// Arrow IPC file format, also known as Feather v2
+    ArrowIpc,
+    Xlsx,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParquetCompression {
+    None,
+    #[default]
+    Snappy,
+    Gzip,
+    Lz4,
+    Zstd,
+    Brotli,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CsvQuoteStyle {
+    /// Quote only fields containing the delimiter, quotes or line breaks
+    #[default]
+    Necessary,
+    Always,
+    NonNumeric,
+    Never,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExportOptions {
+    /// Field delimiter for CSV; TSV always uses a tab
+    pub delimiter: char,
+    pub quote: char,
+    pub quote_style: CsvQuoteStyle,
+    pub header: bool,
+    /// Text written for NULL in CSV/TSV
+    pub null_token: String,
+    /// Engine the `INSERT` statements are written for; defaults to the source engine
+    pub target_dialect: Option<DatabaseType>,
+    /// Table named in `INSERT` statements; defaults to the source table when
+    /// every column comes from the same one
+    pub table_name: Option<String>,
+    pub rows_per_statement: usize,
+    pub compression: ParquetCompression,
+    /// Codec-specific level for gzip, zstd and brotli
+    pub compression_level: Option<u32>,
+    /// Maximum rows per Parquet row group; the writer default when unset
+    pub row_group_size: Option<usize>,
+    /// Worksheet name for XLSX; further result sets get a numbered suffix
+    pub sheet_name: Option<String>,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            quote: '"',
+            quote_style: CsvQuoteStyle::Necessary,
+            header: true,
+            null_token: String::new(),
+            target_dialect: None,
+            table_name: None,
+            rows_per_statement: 100,
+            compression: ParquetCompression::Snappy,
+            compression_level: None,
+            row_group_size: None,
+            sheet_name: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportSummary {
+    pub path: String,
+    pub rows: u64,
+    pub bytes: u64,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionState {
+    Connected,
+    /// Answering, but slowly
+    Degraded,
+    /// The pool is being opened again
+    Reconnecting,
+    /// Could not be opened again; retried on the next health check
+    Lost,
+}
+
+/// Payload of the `connection-status` event, sent when a connection's state changes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectionStatus {
+    pub connection_id: String,
+    pub state: ConnectionState,
+    /// The error that caused the change, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// What `get_connection_stats` reports, to tell why queries wait.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectionStats {
+    pub state: ConnectionState,
+    pub pool: PoolStats,
+    /// Left out when no connection was free to ask
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session: Option<SessionInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoolStats {
+    /// Open connections, idle or in use
+    pub size: u32,
+    pub idle: u32,
+    pub in_use: u32,
+    pub max_connections: u32,
+    pub min_connections: u32,
+    /// How long getting a connection took for this report; left out when
+    /// none was free within a second
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub acquire_ms: Option<f64>,
+    /// Since the pool was opened; only SQL Server's pool keeps these
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub waited: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timed_out: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_wait_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub connections_created: Option<u64>,
+}
+
+/// The server's view of the session that answered; other connections of
+/// the pool may differ in PID and in what init SQL or `SET` changed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SessionInfo {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backend_pid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub database: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schema: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server_version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+}
+
+/// Payload of the `task-progress` event emitted by long-running operations.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskProgress {
+    pub task_id: String,
+    /// Rows (or statements) handled so far
+    pub processed: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<u64>,
+    /// Bytes of the input file read so far, for tasks that stream one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bytes_read: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bytes_total: Option<u64>,
+}
+
+/// How a CSV file is read. Settings left unset are detected from the file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CsvReadOptions {
+    pub delimiter: Option<char>,
+    pub quote: char,
+    /// Encoding label such as `utf-8`, `windows-1252` or `utf-16le`
+    pub encoding: Option<String>,
+    pub has_header: Option<bool>,
+    /// Fields equal to this are NULL. Empty fields are always NULL outside text columns.
+    pub null_token: Option<String>,
+    /// Data rows returned by a preview
+    pub preview_rows: usize,
+}
+
+impl Default for CsvReadOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: None,
+            quote: '"',
+            encoding: None,
+            has_header: None,
+            null_token: None,
+            preview_rows: 100,
+        }
+    }
+}
+
+/// Column of an import file, with the type inferred from its values.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InferredColumn {
+    pub name: String,
+    pub kind: ColumnKind,
+    pub nullable: bool,
+    /// Longest value, in characters
+    pub max_length: usize,
+    /// Total and fractional digits, for decimals
+    pub precision: u32,
+    pub scale: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CsvPreview {
+    pub encoding: String,
+    pub delimiter: char,
+    pub has_header: bool,
+    pub columns: Vec<InferredColumn>,
+    pub rows: Vec<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JsonLayout {
+    /// One top-level array of objects
+    Array,
+    /// One object per line
+    Ndjson,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NestedMode {
+    /// Nested objects become columns named by their path, e.g. `address_city`
+    #[default]
+    Flatten,
+    /// Nested objects are stored whole in JSON columns
+    Json,
+}
+
+/// How a JSON or NDJSON file is read. Arrays are always stored as JSON.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JsonReadOptions {
+    /// Detected from the first character of the file when unset
+    pub layout: Option<JsonLayout>,
+    pub nested: NestedMode,
+    /// Joins the keys of a flattened path
+    pub separator: String,
+    /// Levels of nesting flattened before the rest is stored as JSON
+    pub max_depth: Option<usize>,
+    /// Records returned by a preview
+    pub preview_rows: usize,
+}
+
+impl Default for JsonReadOptions {
+    fn default() -> Self {
+        Self {
+            layout: None,
+            nested: NestedMode::Flatten,
+            separator: "_".to_string(),
+            max_depth: None,
+            preview_rows: 100,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonPreview {
+    pub layout: JsonLayout,
+    pub columns: Vec<InferredColumn>,
+    pub rows: Vec<Vec<serde_json::Value>>,
+}
+
+/// Routes one source column to a target column.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColumnMapping {
+    pub source: String,
+    pub target: String,
+    /// Column type used instead of the inferred one when the table is created
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sql_type: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportErrorMode {
+    /// Roll back the whole import on the first bad row
+    #[default]
+    Abort,
+    /// Leave bad rows out and report them
+    Skip,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImportOptions {
+    pub csv: CsvReadOptions,
+    pub json: JsonReadOptions,
+    /// Source to target columns. By default, columns are matched by name.
+    pub mapping: Option<Vec<ColumnMapping>>,
+    /// Create the table from the inferred column types if it does not exist
+    pub create_table: bool,
+    /// Rows per insert batch
+    pub batch_size: usize,
+    pub on_error: ImportErrorMode,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        Self {
+            csv: CsvReadOptions::default(),
+            json: JsonReadOptions::default(),
+            mapping: None,
+            create_table: false,
+            batch_size: 1000,
+            on_error: ImportErrorMode::Abort,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportRowError {
+    /// Line number in CSV and NDJSON files, record number in JSON arrays,
+    /// row number in table copies
+    pub line: u64,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportSummary {
+    pub table: String,
+    /// `CREATE TABLE` statement, when the table was created by the import
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_table: Option<String>,
+    pub rows_read: u64,
+    pub rows_imported: u64,
+    pub rows_failed: u64,
+    /// The first failed rows; `rows_failed` has the full count
+    pub errors: Vec<ImportRowError>,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CopyTableOptions {
+    /// Source to target columns. By default, columns are matched by name.
+    pub mapping: Option<Vec<ColumnMapping>>,
+    /// Create the target table from the source columns if it does not exist
+    pub create_table: bool,
+    /// Delete the rows already in the target table first
+    pub truncate: bool,
+    /// Condition limiting the source rows, written in the source dialect
+    pub filter: Option<String>,
+    /// Rows per insert batch
+    pub batch_size: usize,
+    pub on_error: ImportErrorMode,
+}
+
+impl Default for CopyTableOptions {
+    fn default() -> Self {
+        Self {
+            mapping: None,
+            create_table: true,
+            truncate: false,
+            filter: None,
+            batch_size: 1000,
+            on_error: ImportErrorMode::Abort,
+        }
+    }
+}
+
+/// A column that could not be copied as is.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColumnMismatch {
+    pub column: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_type: Option<String>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CopyTableSummary {
+    pub source_table: String,
+    pub target_table: String,
+    /// `CREATE TABLE` statement, when the target was created by the copy
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_table: Option<String>,
+    pub rows_read: u64,
+    pub rows_copied: u64,
+    pub rows_failed: u64,
+    /// The first failed rows; `rows_failed` has the full count
+    pub errors: Vec<ImportRowError>,
+    pub mismatches: Vec<ColumnMismatch>,
+    pub duration_ms: u64,
+}
+
+/// Tables, views and sequences of a database, as read for dumps and comparisons.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SchemaSnapshot {
+    pub tables: Vec<TableDefinition>,
+    pub views: Vec<ViewDefinition>,
+    pub sequences: Vec<SequenceDefinition>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TableDefinition {
+    /// `None` on engines with a single namespace (MySQL, SQLite)
+    pub schema: Option<String>,
+    pub name: String,
+    pub columns: Vec<ColumnDefinition>,
+    pub primary_key: Option<KeyConstraint>,
+    pub unique_constraints: Vec<KeyConstraint>,
+    pub foreign_keys: Vec<ForeignKeyDefinition>,
+    pub checks: Vec<CheckConstraint>,
+    /// Indexes not backing a primary key or unique constraint
+    pub indexes: Vec<IndexDefinition>,
+    /// `CREATE TABLE` statement as the engine reports it (MySQL, SQLite)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub definition: Option<String>,
+}
+
+impl TableDefinition {
+    /// `schema.table`, or the bare name without a schema.
+    pub fn qualified_name(&self) -> String {
+        qualify(self.schema.as_deref(), &self.name)
+    }
+}
+
+pub fn qualify(schema: Option<&str>, name: &str) -> String {
+    match schema {
+        Some(schema) => format!("{}.{}", schema, name),
+        None => name.to_string(),
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColumnDefinition {
+    pub name: String,
+    /// Type as declared, with length, precision and scale
+    pub data_type: String,
+    pub nullable: bool,
+    /// Default expression in the engine's own syntax
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+    /// Identity clause, e.g. `GENERATED BY DEFAULT AS IDENTITY`, `AUTO_INCREMENT` or `IDENTITY(1,1)`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub identity: Option<String>,
+    /// Expression of a generated (computed) column; empty on SQLite, which does not report it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub generated: Option<String>,
+    /// Whether a generated column is stored rather than computed on read
+    #[serde(default)]
+    pub generated_stored: bool,
+}
+
+/// Primary key or unique constraint.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyConstraint {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub columns: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForeignKeyDefinition {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub columns: Vec<String>,
+    /// Qualified like `TableDefinition::qualified_name`
+    pub referenced_table: String,
+    pub referenced_columns: Vec<String>,
+    /// Referential actions such as `CASCADE`; `None` means `NO ACTION`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub on_update: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub on_delete: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckConstraint {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub expression: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexDefinition {
+    pub name: String,
+    /// Key columns (quoted) or expressions, with ` DESC` where descending
+    pub columns: Vec<String>,
+    pub unique: bool,
+    /// `CREATE INDEX` statement as the engine reports it, when it does
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub definition: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ViewDefinition {
+    pub schema: Option<String>,
+    pub name: String,
+    /// Complete `CREATE VIEW` statement
+    pub definition: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SequenceDefinition {
+    pub schema: Option<String>,
+    pub name: String,
+    pub data_type: String,
+    pub start: i64,
+    pub increment: i64,
+    pub min_value: i64,
+    pub max_value: i64,
+    pub cycle: bool,
+    /// Last value handed out, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_value: Option<i64>,
+    /// Column the sequence belongs to, as `(qualified table, column)`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owned_by: Option<(String, String)>,
+    /// Backs an identity column and is created along with it
+    #[serde(default)]
+    pub identity: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DumpContent {
+    #[default]
+    SchemaAndData,
+    SchemaOnly,
+    DataOnly,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DumpDataFormat {
+    #[default]
+    Insert,
+    /// `COPY ... FROM stdin` blocks; PostgreSQL only, other engines use `INSERT`
+    Copy,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DumpOptions {
+    pub content: DumpContent,
+    pub data_format: DumpDataFormat,
+    /// Only these tables (`table` or `schema.table`). Views and unowned
+    /// sequences are left out when set.
+    pub tables: Option<Vec<String>>,
+    pub exclude_tables: Vec<String>,
+    /// Drop each object before creating it
+    pub drop_existing: bool,
+    pub rows_per_statement: usize,
+}
+
+impl Default for DumpOptions {
+    fn default() -> Self {
+        Self {
+            content: DumpContent::SchemaAndData,
+            data_format: DumpDataFormat::Insert,
+            tables: None,
+            exclude_tables: Vec::new(),
+            drop_existing: false,
+            rows_per_statement: 100,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DumpSummary {
+    pub path: String,
+    pub tables: usize,
+    pub views: usize,
+    pub sequences: usize,
+    pub rows: u64,
+    pub bytes: u64,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SchemaCompareOptions {
+    /// Generate statements that bring the target in line with the source
+    pub generate_script: bool,
+    /// Drop objects that only exist in the target; otherwise those statements are commented out
+    pub include_drops: bool,
+    /// Match objects by name alone, for databases that keep them in different schemas
+    pub ignore_schema: bool,
+    /// Tables to compare, by name or `schema.table`; all when empty
+    pub tables: Vec<String>,
+    pub exclude_tables: Vec<String>,
+}
+
+impl Default for SchemaCompareOptions {
+    fn default() -> Self {
+        Self {
+            generate_script: true,
+            include_drops: false,
+            ignore_schema: false,
+            tables: Vec::new(),
+            exclude_tables: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SchemaObjectType {
+    Table,
+    Column,
+    PrimaryKey,
+    UniqueConstraint,
+    ForeignKey,
+    CheckConstraint,
+    Index,
+    View,
+    Sequence,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SchemaChange {
+    /// Only in the source
+    Missing,
+    /// Only in the target
+    Extra,
+    Changed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchemaDifference {
+    pub object_type: SchemaObjectType,
+    pub change: SchemaChange,
+    /// Table the column, constraint or index belongs to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub table: Option<String>,
+    pub name: String,
+    /// What differs, e.g. `type: integer -> bigint`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub details: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchemaComparison {
+    pub source_type: DatabaseType,
+    pub target_type: DatabaseType,
+    pub tables_compared: usize,
+    pub views_compared: usize,
+    pub sequences_compared: usize,
+    pub differences: Vec<SchemaDifference>,
+    /// What the comparison skipped and changes the script cannot make
+    pub warnings: Vec<String>,
+    /// Migration script in the target dialect
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub script: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DataCompareOptions {
+    /// Columns to compare besides the key; by default every column both tables have
+    pub columns: Option<Vec<String>>,
+    pub ignore_columns: Vec<String>,
+    /// Rows per chunk compared by hash before any row is read; 0 reads every row
+    pub chunk_size: usize,
+    /// Differing rows listed in the result; the counts include all of them
+    pub max_differences: usize,
+    /// Generate statements that make the target rows match the source
+    pub generate_script: bool,
+}
+
+impl Default for DataCompareOptions {
+    fn default() -> Self {
+        Self {
+            columns: None,
+            ignore_columns: Vec::new(),
+            chunk_size: 10_000,
+            max_differences: 1000,
+            generate_script: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RowChange {
+    /// Only in the source
+    Missing,
+    /// Only in the target
+    Extra,
+    Changed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColumnValueDifference {
+    pub column: String,
+    pub source: serde_json::Value,
+    pub target: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RowDifference {
+    pub change: RowChange,
+    pub key: serde_json::Map<String, serde_json::Value>,
+    /// Columns whose values differ, for changed rows
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub columns: Vec<ColumnValueDifference>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DataComparison {
+    pub key_columns: Vec<String>,
+    pub columns: Vec<String>,
+    pub source_rows: u64,
+    pub target_rows: u64,
+    pub rows_missing: u64,
+    pub rows_extra: u64,
+    pub rows_changed: u64,
+    pub rows_identical: u64,
+    pub chunks: u64,
+    /// Chunks whose hashes matched, so their rows were not read
+    pub chunks_skipped: u64,
+    /// The first differing rows; the counts above include all of them
+    pub differences: Vec<RowDifference>,
+    pub warnings: Vec<String>,
+    /// `INSERT`, `UPDATE` and `DELETE` statements in the target dialect
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub script: Option<String>,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScriptErrorMode {
+    /// Stop at the first failing statement; a script transaction is rolled back
+    #[default]
+    Stop,
+    /// Leave out failing statements and keep the rest
+    Skip,
+    /// Run every statement to report all failures, but roll back a script
+    /// transaction if any statement failed
+    Continue,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScriptOptions {
+    /// Run the whole script in one transaction. DDL still commits
+    /// implicitly on MySQL.
+    pub transaction: bool,
+    pub on_error: ScriptErrorMode,
+    /// Encoding label; a byte order mark wins, UTF-8 otherwise
+    pub encoding: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptStatementError {
+    /// Position of the statement in the script, counting from 1
+    pub statement: u64,
+    pub line: u64,
+    /// Start of the statement text
+    pub sql: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptSummary {
+    pub statements_executed: u64,
+    pub statements_failed: u64,
+    /// The first failed statements; `statements_failed` has the full count
+    pub errors: Vec<ScriptStatementError>,
+    pub bytes_read: u64,
+    /// False when a script transaction was rolled back
+    pub committed: bool,
+    pub duration_ms: u64,
+}
+
+impl TlsConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        let set = |path: &Option<String>| path.as_deref().is_some_and(|p| !p.is_empty());
+        if set(&self.client_cert_path) != set(&self.client_key_path) {
+            return Err("A client certificate and its key must be given together".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl PoolConfig {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The limits to open the pool with, after checking they make sense.
+    pub fn settings(&self, db_type: &DatabaseType) -> Result<PoolSettings, String> {
+        // What each engine used before pools were configurable
+        let (max_connections, min_connections, acquire_timeout, idle_timeout) = match db_type {
+            DatabaseType::PostgreSQL => (20, 0, 30, 600),
+            DatabaseType::MySQL | DatabaseType::SQLite => (5, 0, 10, 600),
+            DatabaseType::SQLServer => (10, 1, 10, 60),
+        };
+        let max_connections = self.max_connections.unwrap_or(max_connections);
+        let min_connections = self.min_connections.unwrap_or(min_connections);
+        if max_connections == 0 {
+            return Err("The pool needs at least one connection".to_string());
+        }
+        if min_connections > max_connections {
+            return Err(format!(
+                "The pool's minimum of {} connections is above its maximum of {}",
+                min_connections, max_connections
+            ));
+        }
+        let seconds = |secs: u64| (secs > 0).then(|| Duration::from_secs(secs));
+        Ok(PoolSettings {
+            max_connections,
+            min_connections,
+            acquire_timeout: Duration::from_secs(self.acquire_timeout_secs.unwrap_or(acquire_timeout).max(1)),
+            idle_timeout: seconds(self.idle_timeout_secs.unwrap_or(idle_timeout)),
+            max_lifetime: seconds(self.max_lifetime_secs.unwrap_or(30 * 60)),
+        })
+    }
+}
+
 impl ConnectionConfig {
     pub fn connection_string(&self) -> String {
         // URL-encode username and password for special characters
         let encoded_username = urlencoding::encode(&self.username);
         let encoded_password = urlencoding::encode(&self.password);
+        let query: Vec<String> = self
+            .parameters
+            .iter()
+            .map(|(key, value)| format!("{}={}", urlencoding::encode(key), urlencoding::encode(value)))
+            .collect();
+        let query = match query.is_empty() {
+            true => String::new(),
+            false => format!("?{}", query.join("&")),
+        };
+        // IPv6 addresses are bracketed in URLs
+        let host = match self.host.contains(':') && !self.host.starts_with('[') {
+            true => format!("[{}]", self.host),
+            false => self.host.clone(),
+        };
+        let database = urlencoding::encode(&self.database);
         
         match self.db_type {
             DatabaseType::PostgreSQL => {
                 format!(
-                    "postgres://{}:{}@{}:{}/{}",
-                    encoded_username, encoded_password, self.host, self.port, self.database
+                    "postgres://{}:{}@{}:{}/{}{}",
+                    encoded_username, encoded_password, host, self.port, database, query
                 )
             }
             DatabaseType::MySQL => {
                 format!(
-                    "mysql://{}:{}@{}:{}/{}",
-                    encoded_username, encoded_password, self.host, self.port, self.database
+                    "mysql://{}:{}@{}:{}/{}{}",
+                    encoded_username, encoded_password, host, self.port, database, query
                 )
             }
             DatabaseType::SQLite => {
                 // SQLite uses file path as database with proper URI format
-                // This allows creating the file if it doesn't exist
-                format!("sqlite:{}?mode=rwc", self.database)
+                // This allows creating the file if it doesn't exist, unless
+                // the parameters ask for another mode or the connection is read-only
+                let mode = match self.read_only {
+                    true => "ro",
+                    false => self.parameters.get("mode").map_or("rwc", |m| m.as_str()),
+                };
+                let mut params = vec![format!("mode={}", mode)];
+                params.extend(
+                    self.parameters
+                        .iter()
+                        .filter(|(key, _)| key.as_str() != "mode")
+                        .map(|(key, value)| format!("{}={}", urlencoding::encode(key), urlencoding::encode(value))),
+                );
+                format!("sqlite:{}?{}", crate::database::dsn::encode_path(&self.database), params.join("&"))
             }
             DatabaseType::SQLServer => {
                 // SQL Server connection string format for Tiberius
                 // Format: server=host;port=port;database=db;user=user;password=pass
+                let (encrypt, trust) = match self.tls.mode {
+                    TlsMode::Disable => ("false", true),
+                    TlsMode::Prefer | TlsMode::Require => ("true", true),
+                    TlsMode::VerifyCa | TlsMode::VerifyFull => ("true", false),
+                };
                 format!(
-                    "server=tcp:{},{};database={};user={};password={};TrustServerCertificate=true",
-                    self.host, self.port, self.database, self.username, self.password
+                    "server=tcp:{},{};database={};user={};password={};Encrypt={};TrustServerCertificate={}",
+                    self.host, self.port, self.database, self.username, self.password, encrypt, trust
                 )
             }
         }
@@ -0,0 +1,438 @@
+//! Connections already configured for the PostgreSQL and MySQL command-line
+//! clients: `~/.pgpass`, `pg_service.conf` and `~/.my.cnf`.
+
+use std::path::{Path, PathBuf};
+use crate::database::dsn;
+use crate::models::*;
+
+/// A `.pgpass` line; `*` matches anything.
+struct PgPassEntry {
+    host: String,
+    port: String,
+    database: String,
+    username: String,
+    password: String,
+}
+
+impl PgPassEntry {
+    fn matches(&self, config: &ConnectionConfig) -> bool {
+        let field = |pattern: &str, value: &str| pattern == "*" || pattern == value;
+        let host = config.socket.as_deref().unwrap_or("localhost");
+        field(&self.host, if config.host.is_empty() { host } else { &config.host })
+            && field(&self.port, &config.port.to_string())
+            && field(&self.database, &config.database)
+            && field(&self.username, &config.username)
+    }
+}
+
+/// Profiles for every service in `pg_service.conf`, every `.pgpass` line
+/// no service claims and the client groups of `~/.my.cnf`, with passwords.
+/// Also returns what could not be read.
+pub fn discover(home: &Path) -> (Vec<ConnectionProfile>, Vec<String>) {
+    let mut profiles = Vec::new();
+    let mut skipped = Vec::new();
+
+    let pgpass = match read(&pgpass_path(home)) {
+        Some((path, text)) => parse_pgpass(&text, &path, &mut skipped),
+        None => Vec::new(),
+    };
+    let mut used = vec![false; pgpass.len()];
+
+    for path in pg_service_paths(home) {
+        let Some((path, text)) = read(&path) else {
+            continue;
+        };
+        for (service, pairs) in ini_sections(&text) {
+            match dsn::postgres_keywords(pairs) {
+                Ok(mut config) => {
+                    config.name = service.clone();
+                    // libpq looks the password up in .pgpass when the service has none
+                    if config.password.is_empty() {
+                        if let Some(idx) = pgpass.iter().position(|e| e.matches(&config)) {
+                            config.password = pgpass[idx].password.clone();
+                            used[idx] = true;
+                        }
+                    }
+                    if profiles.iter().all(|p: &ConnectionProfile| p.config.name != config.name) {
+                        profiles.push(profile(config, "pg_service"));
+                    }
+                }
+                Err(e) => skipped.push(format!("{} [{}]: {}", path.display(), service, e)),
+            }
+        }
+    }
+
+    for (entry, _) in pgpass.iter().zip(&used).filter(|(_, used)| !**used) {
+        let mut config = dsn::postgres_keywords([
+            ("user".to_string(), entry.username.clone()),
+            ("password".to_string(), entry.password.clone()),
+        ])
+        .expect("user and password are always valid");
+        if entry.host != "*" {
+            if entry.host.starts_with('/') {
+                config.socket = Some(entry.host.clone());
+            } else {
+                config.host = entry.host.clone();
+            }
+        }
+        if let Ok(port) = entry.port.parse() {
+            config.port = port;
+        }
+        if entry.database != "*" {
+            config.database = entry.database.clone();
+        }
+        config.name = format!(
+            "{}@{}:{}{}",
+            entry.username,
+            config.socket.as_deref().unwrap_or(&config.host),
+            config.port,
+            match config.database.is_empty() {
+                true => String::new(),
+                false => format!("/{}", config.database),
+            }
+        );
+        profiles.push(profile(config, "pgpass"));
+    }
+
+    if let Some((path, text)) = read(&home.join(".my.cnf")) {
+        profiles.extend(parse_my_cnf(&text, &path, &mut skipped));
+    }
+
+    (profiles, skipped)
+}
+
+fn profile(config: ConnectionConfig, source: &str) -> ConnectionProfile {
+    ConnectionProfile {
+        id: String::new(),
+        config,
+        folder: String::new(),
+        tags: vec![source.to_string()],
+        color: None,
+        environment: None,
+        save_password: true,
+        created_at: String::new(),
+        updated_at: String::new(),
+    }
+}
+
+fn read(path: &Path) -> Option<(PathBuf, String)> {
+    match std::fs::read_to_string(path) {
+        Ok(text) => Some((path.to_path_buf(), text)),
+        Err(e) => {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                log::warn!("[ProfileStore] Could not read {}: {}", path.display(), e);
+            }
+            None
+        }
+    }
+}
+
+fn pgpass_path(home: &Path) -> PathBuf {
+    if let Some(path) = std::env::var_os("PGPASSFILE") {
+        return PathBuf::from(path);
+    }
+    #[cfg(windows)]
+    if let Some(appdata) = std::env::var_os("APPDATA") {
+        return PathBuf::from(appdata).join("postgresql").join("pgpass.conf");
+    }
+    home.join(".pgpass")
+}
+
+/// The user's service file first; its services win over the system ones.
+fn pg_service_paths(home: &Path) -> Vec<PathBuf> {
+    let user = match std::env::var_os("PGSERVICEFILE") {
+        Some(path) => PathBuf::from(path),
+        #[cfg(windows)]
+        None => std::env::var_os("APPDATA")
+            .map(|appdata| PathBuf::from(appdata).join("postgresql").join(".pg_service.conf"))
+            .unwrap_or_else(|| home.join(".pg_service.conf")),
+        #[cfg(not(windows))]
+        None => home.join(".pg_service.conf"),
+    };
+    let system = match std::env::var_os("PGSYSCONFDIR") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from("/etc/postgresql-common"),
+    };
+    vec![user, system.join("pg_service.conf")]
+}
+
+/// `host:port:database:user:password` lines, where `\:` and `\\` escape.
+fn parse_pgpass(text: &str, path: &Path, skipped: &mut Vec<String>) -> Vec<PgPassEntry> {
+    let mut entries = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+
+        let mut fields = vec![String::new()];
+        let mut chars = line.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => fields.last_mut().unwrap().extend(chars.next()),
+                ':' if fields.len() < 5 => fields.push(String::new()),
+                c => fields.last_mut().unwrap().push(c),
+            }
+        }
+        let Ok([host, port, database, username, password]) = <[String; 5]>::try_from(fields) else {
+            skipped.push(format!("{} line {}: expected host:port:database:user:password", path.display(), number + 1));
+            continue;
+        };
+        if username == "*" {
+            skipped.push(format!("{} line {}: no user name", path.display(), number + 1));
+            continue;
+        }
+        entries.push(PgPassEntry { host, port, database, username, password });
+    }
+    entries
+}
+
+/// `[section]` headers followed by `key=value` lines; `#` and `;` start comments.
+fn ini_sections(text: &str) -> Vec<(String, Vec<(String, String)>)> {
+    let mut sections: Vec<(String, Vec<(String, String)>)> = Vec::new();
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') || line.starts_with('!') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            sections.push((name.trim().to_string(), Vec::new()));
+            continue;
+        }
+        let Some((_, pairs)) = sections.last_mut() else {
+            continue;
+        };
+        let (key, value) = line.split_once('=').unwrap_or((line, ""));
+        let value = value.trim();
+        let value = match value.len() >= 2 && (value.starts_with('"') && value.ends_with('"') || value.starts_with('\'') && value.ends_with('\'')) {
+            true => &value[1..value.len() - 1],
+            false => value,
+        };
+        pairs.push((key.trim().to_string(), value.to_string()));
+    }
+    sections
+}
+
+/// `[client]` and `[mysql]` make one profile; `[client_<suffix>]` and
+/// `[mysql_<suffix>]`, read by `--defaults-group-suffix`, make one more each.
+fn parse_my_cnf(text: &str, path: &Path, skipped: &mut Vec<String>) -> Vec<ConnectionProfile> {
+    let sections = ini_sections(text);
+    // In file order, as the client reads them: a later value wins
+    let group = |suffix: &str| -> Vec<(String, String)> {
+        sections
+            .iter()
+            .filter(|(name, _)| {
+                ["client", "mysql"].iter().any(|base| {
+                    name.strip_prefix(base).is_some_and(|rest| rest.is_empty() || rest == suffix)
+                })
+            })
+            .flat_map(|(_, pairs)| pairs.iter().cloned())
+            .collect()
+    };
+
+    // Other programs' groups such as `[mysqld]` or `[mysqldump]` are not suffixes
+    let mut suffixes = vec![String::new()];
+    for (name, _) in &sections {
+        for base in ["client", "mysql"] {
+            if let Some(rest) = name.strip_prefix(base).filter(|r| r.starts_with(['_', '-'])) {
+                if !suffixes.iter().any(|s| s == rest) {
+                    suffixes.push(rest.to_string());
+                }
+            }
+        }
+    }
+
+    let mut profiles = Vec::new();
+    for suffix in suffixes {
+        let pairs = group(&suffix);
+        if pairs.is_empty() {
+            continue;
+        }
+        let mut config = dsn::parse("mysql://localhost").expect("a fixed URL parses");
+        for (key, value) in pairs {
+            match key.replace('_', "-").as_str() {
+                "host" => config.host = value,
+                "port" => match value.parse() {
+                    Ok(port) => config.port = port,
+                    Err(_) => skipped.push(format!("{}: invalid port {}", path.display(), value)),
+                },
+                "user" => config.username = value,
+                "password" => config.password = value,
+                "database" => config.database = value,
+                "socket" => config.socket = Some(value),
+                "ssl-ca" => config.tls.ca_cert_path = Some(value),
+                "ssl-cert" => config.tls.client_cert_path = Some(value),
+                "ssl-key" => config.tls.client_key_path = Some(value),
+                "ssl-mode" => {
+                    config.tls.mode = match value.to_uppercase().as_str() {
+                        "DISABLED" => TlsMode::Disable,
+                        "REQUIRED" => TlsMode::Require,
+                        "VERIFY_CA" => TlsMode::VerifyCa,
+                        "VERIFY_IDENTITY" => TlsMode::VerifyFull,
+                        _ => TlsMode::Prefer,
+                    }
+                }
+                _ => {}
+            }
+        }
+        if config.username.is_empty() && config.password.is_empty() && config.database.is_empty() {
+            continue;
+        }
+        config.name = match suffix.is_empty() {
+            true => format!("my.cnf ({}@{})", config.username, config.host),
+            false => format!("my.cnf {}", suffix.trim_start_matches(['_', '-'])),
+        };
+        profiles.push(profile(config, "my.cnf"));
+    }
+    profiles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pgpass(text: &str) -> (Vec<PgPassEntry>, Vec<String>) {
+        let mut skipped = Vec::new();
+        let entries = parse_pgpass(text, Path::new(".pgpass"), &mut skipped);
+        (entries, skipped)
+    }
+
+    fn my_cnf(text: &str) -> Vec<ConnectionConfig> {
+        let mut skipped = Vec::new();
+        let profiles = parse_my_cnf(text, Path::new(".my.cnf"), &mut skipped);
+        assert!(skipped.is_empty(), "{:?}", skipped);
+        profiles.into_iter().map(|p| p.config).collect()
+    }
+
+    #[test]
+    fn pgpass_fields() {
+        let cases = [
+            ("db:5432:app:me:secret", ["db", "5432", "app", "me", "secret"]),
+            ("*:*:*:me:secret", ["*", "*", "*", "me", "secret"]),
+            // Escaped separators and backslashes
+            (r"db\:1:5432:a\\b:me:p\:w", ["db:1", "5432", r"a\b", "me", "p:w"]),
+            // The password takes the rest of the line
+            ("db:5432:app:me:a:b:c", ["db", "5432", "app", "me", "a:b:c"]),
+            ("/var/run/postgresql:5432:app:me:", ["/var/run/postgresql", "5432", "app", "me", ""]),
+            (r"db:5432:app:me:ends\\", ["db", "5432", "app", "me", r"ends\"]),
+        ];
+        for (line, expected) in cases {
+            let (entries, skipped) = pgpass(line);
+            assert!(skipped.is_empty(), "{}: {:?}", line, skipped);
+            let entry = &entries[0];
+            assert_eq!(
+                [&entry.host, &entry.port, &entry.database, &entry.username, &entry.password],
+                expected,
+                "{}",
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn pgpass_skips_comments_and_bad_lines() {
+        let (entries, skipped) = pgpass("# comment\n\n  # indented\ndb:5432:app\n*:*:*:*:secret\ndb:5432:app:me:pw\n");
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].username, "me");
+        assert_eq!(
+            skipped,
+            [
+                ".pgpass line 4: expected host:port:database:user:password",
+                ".pgpass line 5: no user name"
+            ]
+        );
+    }
+
+    #[test]
+    fn pgpass_wildcards_match() {
+        let (entries, _) = pgpass("*:5432:*:me:pw\nlocalhost:*:app:other:pw2");
+        let mut config = dsn::parse("postgres://me@db.example.com:5432/app").unwrap();
+        assert!(entries[0].matches(&config));
+        assert!(!entries[1].matches(&config));
+        config.port = 5433;
+        assert!(!entries[0].matches(&config));
+
+        // No host means the local socket, which .pgpass calls localhost
+        let mut local = dsn::parse("postgres://other@localhost/app").unwrap();
+        local.host = String::new();
+        assert!(entries[1].matches(&local));
+    }
+
+    #[test]
+    fn ini_section_values() {
+        let text = "\
+key=outside
+# comment
+; also a comment
+[first]
+host = db
+quoted = \"a b \"
+single='x=y'
+flag
+!include /etc/other.cnf
+!includedir /etc/conf.d
+[ second ]
+empty=
+unbalanced=\"abc
+";
+        let sections = ini_sections(text);
+        let pairs = |values: &[(&str, &str)]| -> Vec<(String, String)> {
+            values.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+        };
+        assert_eq!(
+            sections,
+            [
+                (
+                    "first".to_string(),
+                    pairs(&[("host", "db"), ("quoted", "a b "), ("single", "x=y"), ("flag", "")])
+                ),
+                ("second".to_string(), pairs(&[("empty", ""), ("unbalanced", "\"abc")])),
+            ]
+        );
+    }
+
+    #[test]
+    fn my_cnf_groups() {
+        // [mysql] is read after [client] and wins where both set a value
+        let configs = my_cnf("[mysql]\nuser=reader\n[client]\nuser=me\npassword=\"p#w\"\nport=3307\n[mysql]\ndatabase=shop\n");
+        assert_eq!(configs.len(), 1);
+        assert_eq!(configs[0].username, "me");
+        assert_eq!(configs[0].password, "p#w");
+        assert_eq!(configs[0].port, 3307);
+        assert_eq!(configs[0].database, "shop");
+        assert_eq!(configs[0].name, "my.cnf (me@localhost)");
+
+        let configs = my_cnf("[client]\nuser=me\n[mysql]\nuser=reader\n");
+        assert_eq!(configs[0].username, "reader");
+    }
+
+    #[test]
+    fn my_cnf_suffixes() {
+        let text = "\
+[client]
+user=me
+host=db
+[client_staging]
+host=staging-db
+ssl_mode=VERIFY_IDENTITY
+[mysqld]
+user=mysql
+[mysqldump]
+user=backup
+";
+        let configs = my_cnf(text);
+        assert_eq!(configs.len(), 2);
+        assert_eq!((configs[0].username.as_str(), configs[0].host.as_str()), ("me", "db"));
+        assert_eq!(configs[1].name, "my.cnf staging");
+        assert_eq!((configs[1].username.as_str(), configs[1].host.as_str()), ("me", "staging-db"));
+        assert_eq!(configs[1].tls.mode, TlsMode::VerifyFull);
+    }
+
+    #[test]
+    fn my_cnf_without_credentials() {
+        assert!(my_cnf("[client]\nhost=db\n[mysqld]\nuser=mysql\n").is_empty());
+
+        let mut skipped = Vec::new();
+        let profiles = parse_my_cnf("[client]\nuser=me\nport=abc\n", Path::new(".my.cnf"), &mut skipped);
+        assert_eq!(profiles[0].config.port, 3306);
+        assert_eq!(skipped, [".my.cnf: invalid port abc"]);
+    }
+}
@@ -0,0 +1,254 @@
+pub mod client;
+
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use crate::database::dsn;
+use crate::models::*;
+use crate::secrets::SecretStore;
+
+const VERSION: u32 = 1;
+
+/// Layout of `profiles.json` and of exported files.
+#[derive(Serialize, Deserialize)]
+struct ProfileFile {
+    version: u32,
+    #[serde(default)]
+    profiles: Vec<ConnectionProfile>,
+}
+
+/// Saved connection profiles, kept in a JSON file in the app config
+/// directory. Secrets are handed to the [`SecretStore`] on the way in, so
+/// the file can be shared or put under version control.
+pub struct ProfileStore {
+    path: PathBuf,
+    profiles: Mutex<Vec<ConnectionProfile>>,
+    /// Why the file could not be loaded, for the UI to show
+    load_error: Option<String>,
+    /// False when an unreadable file could not be moved aside, so that
+    /// saving does not replace it
+    writable: bool,
+}
+
+impl ProfileStore {
+    /// Load the profiles file. One that cannot be read is moved aside under a
+    /// `.broken` suffix and the store starts empty.
+    pub fn open(path: &Path) -> Self {
+        let loaded = match std::fs::read_to_string(path) {
+            Ok(text) => parse(&text, ProfileFormat::Json),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(format!("Failed to read connection profiles: {}", e)),
+        };
+        let (profiles, load_error, writable) = match loaded {
+            Ok(profiles) => {
+                log::info!("[ProfileStore] Loaded {} profiles from {}", profiles.len(), path.display());
+                (profiles, None, true)
+            }
+            Err(e) => {
+                log::error!("[ProfileStore] {}", e);
+                let broken = path.with_extension(format!("json.broken-{}", chrono::Utc::now().format("%Y%m%d%H%M%S")));
+                match std::fs::rename(path, &broken) {
+                    Ok(()) => (Vec::new(), Some(format!("{}; the file was moved to {}", e, broken.display())), true),
+                    Err(rename) => {
+                        log::error!("[ProfileStore] Failed to move {} aside: {}", path.display(), rename);
+                        (Vec::new(), Some(format!("{}; profiles cannot be saved until {} is fixed or removed", e, path.display())), false)
+                    }
+                }
+            }
+        };
+        Self {
+            path: path.to_path_buf(),
+            profiles: Mutex::new(profiles),
+            load_error,
+            writable,
+        }
+    }
+
+    /// Why the profiles file could not be loaded at startup.
+    pub fn load_error(&self) -> Option<String> {
+        self.load_error.clone()
+    }
+
+    pub async fn list(&self) -> Vec<ConnectionProfile> {
+        self.profiles.lock().await.clone()
+    }
+
+    /// Add a profile, or replace the one with the same id or, failing
+    /// that, the same name. Its secrets go to the secret store unless the
+    /// profile asks not to keep them.
+    pub async fn save(&self, mut profile: ConnectionProfile, secrets: &SecretStore) -> Result<ConnectionProfile, String> {
+        let existing = {
+            let profiles = self.profiles.lock().await;
+            find(&profiles, &profile).map(|idx| profiles[idx].clone())
+        };
+        if let Some(existing) = &existing {
+            profile.id = existing.id.clone();
+            profile.created_at = existing.created_at.clone();
+            if profile.config.credential_id.is_none() {
+                profile.config.credential_id = existing.config.credential_id.clone();
+            }
+        }
+
+        if profile.save_password {
+            profile.config = secrets.store(profile.config).await?;
+        } else {
+            if let Some(id) = profile.config.credential_id.take() {
+                secrets.delete(&id).await?;
+            }
+            profile.config = without_secrets(profile.config);
+        }
+
+        let now = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+        if profile.id.is_empty() {
+            profile.id = uuid::Uuid::new_v4().to_string();
+        }
+        if profile.created_at.is_empty() {
+            profile.created_at = now.clone();
+        }
+        profile.updated_at = now;
+
+        let mut profiles = self.profiles.lock().await;
+        match find(&profiles, &profile) {
+            Some(idx) => profiles[idx] = profile.clone(),
+            None => profiles.push(profile.clone()),
+        }
+        self.write(&profiles)?;
+        Ok(profile)
+    }
+
+    /// Remove a profile and its stored secrets.
+    pub async fn delete(&self, id: &str, secrets: &SecretStore) -> Result<bool, String> {
+        let removed = {
+            let mut profiles = self.profiles.lock().await;
+            let Some(idx) = profiles.iter().position(|p| p.id == id) else {
+                return Ok(false);
+            };
+            let removed = profiles.remove(idx);
+            self.write(&profiles)?;
+            removed
+        };
+        if let Some(credential_id) = &removed.config.credential_id {
+            secrets.delete(credential_id).await?;
+        }
+        Ok(true)
+    }
+
+    /// Save imported profiles one by one; a failure leaves the others in place.
+    /// Profiles are added, never replaced: one whose name is taken is skipped.
+    pub async fn import(&self, profiles: Vec<ConnectionProfile>, secrets: &SecretStore) -> ProfileImportResult {
+        let mut result = ProfileImportResult {
+            imported: Vec::new(),
+            skipped: Vec::new(),
+        };
+        for mut profile in profiles {
+            let name = profile.config.name.clone();
+            {
+                let existing = self.profiles.lock().await;
+                if existing.iter().any(|p| p.config.name == name) {
+                    result.skipped.push(format!("{}: a profile with this name already exists", name));
+                    continue;
+                }
+                if existing.iter().any(|p| p.id == profile.id) {
+                    profile.id.clear();
+                }
+            }
+            // A profile from another machine cannot use its secret store entry
+            profile.config.credential_id = None;
+            match self.save(profile.clone(), secrets).await {
+                Ok(saved) => result.imported.push(saved),
+                Err(e) if profile.save_password => {
+                    // Most likely a locked store; keep the profile without its password
+                    profile.save_password = false;
+                    match self.save(profile, secrets).await {
+                        Ok(saved) => {
+                            result.skipped.push(format!("{}: saved without its password ({})", name, e));
+                            result.imported.push(saved);
+                        }
+                        Err(e) => result.skipped.push(format!("{}: {}", name, e)),
+                    }
+                }
+                Err(e) => result.skipped.push(format!("{}: {}", name, e)),
+            }
+        }
+        result
+    }
+
+    /// Replace the file in one step so a crash cannot leave it half written.
+    fn write(&self, profiles: &[ConnectionProfile]) -> Result<(), String> {
+        if !self.writable {
+            return Err(format!("Connection profiles cannot be saved until {} is fixed or removed", self.path.display()));
+        }
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create the profile directory: {}", e))?;
+        }
+        // Unlike an export, the file keeps the references to stored secrets
+        let file = ProfileFile {
+            version: VERSION,
+            profiles: profiles.to_vec(),
+        };
+        let text = serde_json::to_string_pretty(&file).map_err(|e| format!("Failed to save connection profiles: {}", e))?;
+        let temp = self.path.with_extension("tmp");
+        std::fs::write(&temp, text).map_err(|e| format!("Failed to save connection profiles: {}", e))?;
+        std::fs::rename(&temp, &self.path).map_err(|e| format!("Failed to save connection profiles: {}", e))
+    }
+}
+
+/// Profiles as a JSON or TOML document, without any secrets or references to them.
+pub fn export(profiles: &[ConnectionProfile], format: ProfileFormat) -> Result<String, String> {
+    let file = ProfileFile {
+        version: VERSION,
+        profiles: profiles
+            .iter()
+            .cloned()
+            .map(|mut profile| {
+                profile.config = without_secrets(profile.config);
+                profile
+            })
+            .collect(),
+    };
+    match format {
+        ProfileFormat::Json => {
+            serde_json::to_string_pretty(&file).map_err(|e| format!("Failed to export profiles: {}", e))
+        }
+        ProfileFormat::Toml => toml::to_string_pretty(&file).map_err(|e| format!("Failed to export profiles: {}", e)),
+    }
+}
+
+/// Read profiles written by [`export`]. A bare list of profiles is accepted too.
+pub fn parse(text: &str, format: ProfileFormat) -> Result<Vec<ConnectionProfile>, String> {
+    let file: ProfileFile = match format {
+        ProfileFormat::Json => match serde_json::from_str::<ProfileFile>(text) {
+            Ok(file) => file,
+            Err(e) => match serde_json::from_str::<Vec<ConnectionProfile>>(text) {
+                Ok(profiles) => ProfileFile { version: VERSION, profiles },
+                Err(_) => return Err(format!("Failed to read profiles: {}", e)),
+            },
+        },
+        ProfileFormat::Toml => toml::from_str(text).map_err(|e| format!("Failed to read profiles: {}", e))?,
+    };
+    if file.version > VERSION {
+        return Err(format!("Profiles were written by a newer version (format {})", file.version));
+    }
+    Ok(file.profiles)
+}
+
+fn find(profiles: &[ConnectionProfile], profile: &ConnectionProfile) -> Option<usize> {
+    profiles
+        .iter()
+        .position(|p| !profile.id.is_empty() && p.id == profile.id)
+        .or_else(|| profiles.iter().position(|p| p.config.name == profile.config.name))
+}
+
+fn without_secrets(mut config: ConnectionConfig) -> ConnectionConfig {
+    config.password.clear();
+    config.credential_id = None;
+    if let Some((url, _)) = config.url.as_deref().and_then(dsn::split_password) {
+        config.url = Some(url);
+    }
+    if let Some(ssh) = config.ssh_tunnel.as_mut() {
+        ssh.password = None;
+        ssh.passphrase = None;
+        ssh.private_key = None;
+    }
+    config
+}
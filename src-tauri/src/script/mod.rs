@@ -0,0 +1,229 @@
+pub mod splitter;
+
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+use encoding_rs::Encoding;
+use tokio_util::sync::CancellationToken;
+use crate::database::{guard, DatabaseConnection, DatabaseSession};
+use crate::import::DecodingReader;
+use crate::models::*;
+use splitter::{ScriptStatement, StatementSplitter};
+
+/// Failed statements kept in the summary; further failures are only counted.
+const MAX_REPORTED_ERRORS: usize = 1000;
+/// Characters of statement text kept with each reported error.
+const ERROR_SQL_PREVIEW: usize = 200;
+const SAVEPOINT: &str = "script_statement";
+
+/// Counts the bytes taken from the file, before any decoding.
+struct CountingReader<R: Read> {
+    inner: R,
+    count: Arc<AtomicU64>,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count.fetch_add(n as u64, Ordering::Relaxed);
+        Ok(n)
+    }
+}
+
+fn encoding(options: &ScriptOptions) -> Result<&'static Encoding, String> {
+    match &options.encoding {
+        Some(label) => Encoding::for_label(label.trim().as_bytes())
+            .ok_or_else(|| format!("Unknown encoding: {}", label)),
+        None => Ok(encoding_rs::UTF_8),
+    }
+}
+
+/// Read through a script without running it, refusing the first
+/// destructive statement, for a protected connection.
+fn check_protected(script: &[u8], options: &ScriptOptions, db_type: &DatabaseType) -> Result<(), String> {
+    let reader = BufReader::new(DecodingReader::new(script, encoding(options)?));
+    let mut splitter = StatementSplitter::new(reader, db_type.clone());
+    while let Some(statement) = splitter.next_statement()? {
+        guard::check_protected(db_type, &statement.sql)
+            .map_err(|e| format!("Line {}: {}", statement.line, e))?;
+    }
+    Ok(())
+}
+
+/// Run the statements of a SQL file one by one on a single connection.
+/// `progress` receives the number of statements run and bytes read.
+///
+/// A script has no confirmation step, so on a `protected` connection it is
+/// refused before any of it runs if it holds a destructive statement. The
+/// file is then read once into memory, so the statements run are the ones
+/// that were checked even if the file changes in between.
+pub async fn run_script(
+    conn: &DatabaseConnection,
+    path: &str,
+    options: &ScriptOptions,
+    protected: bool,
+    cancel: CancellationToken,
+    progress: &mut (dyn FnMut(u64, u64) + Send),
+) -> Result<ScriptSummary, String> {
+    let mut file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path, e))?;
+    if !protected {
+        return run_source(conn, path, file, options, cancel, progress).await;
+    }
+
+    let mut script = Vec::new();
+    file.read_to_end(&mut script)
+        .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    check_protected(&script, options, &conn.db_type())?;
+    run_source(conn, path, script.as_slice(), options, cancel, progress).await
+}
+
+async fn run_source<R: Read + Send>(
+    conn: &DatabaseConnection,
+    path: &str,
+    source: R,
+    options: &ScriptOptions,
+    cancel: CancellationToken,
+    progress: &mut (dyn FnMut(u64, u64) + Send),
+) -> Result<ScriptSummary, String> {
+    let start = Instant::now();
+    let encoding = encoding(options)?;
+    let bytes_read = Arc::new(AtomicU64::new(0));
+    let reader = CountingReader { inner: source, count: bytes_read.clone() };
+    let mut splitter = StatementSplitter::new(BufReader::new(DecodingReader::new(reader, encoding)), conn.db_type());
+
+    let mut session = conn.session().await?;
+    if options.transaction {
+        session.begin().await?;
+    }
+
+    let mut script = Script {
+        options,
+        statements: 0,
+        summary: ScriptSummary {
+            statements_executed: 0,
+            statements_failed: 0,
+            errors: Vec::new(),
+            bytes_read: 0,
+            committed: true,
+            duration_ms: 0,
+        },
+    };
+
+    let result = script
+        .run(&mut session, &mut splitter, &cancel, &mut |statements| {
+            progress(statements, bytes_read.load(Ordering::Relaxed))
+        })
+        .await;
+
+    if options.transaction {
+        let keep = result.is_ok()
+            && !(matches!(options.on_error, ScriptErrorMode::Continue) && script.summary.statements_failed > 0);
+        if keep {
+            session.commit().await?;
+        } else {
+            if let Err(e) = session.rollback().await {
+                log::error!("[script] Rollback failed: {}", e);
+            }
+            script.summary.committed = false;
+        }
+    }
+    result?;
+
+    let mut summary = script.summary;
+    summary.bytes_read = bytes_read.load(Ordering::Relaxed);
+    summary.duration_ms = start.elapsed().as_millis() as u64;
+    log::info!(
+        "[script] Ran {} statements from {}, {} failed",
+        summary.statements_executed + summary.statements_failed,
+        path,
+        summary.statements_failed
+    );
+    Ok(summary)
+}
+
+struct Script<'a> {
+    options: &'a ScriptOptions,
+    statements: u64,
+    summary: ScriptSummary,
+}
+
+impl Script<'_> {
+    async fn run<R: std::io::BufRead + Send>(
+        &mut self,
+        session: &mut DatabaseSession<'_>,
+        splitter: &mut StatementSplitter<R>,
+        cancel: &CancellationToken,
+        progress: &mut (dyn FnMut(u64) + Send),
+    ) -> Result<(), String> {
+        // Inside a transaction a failed statement is undone through a
+        // savepoint; PostgreSQL would otherwise reject everything after it
+        let isolate = self.options.transaction && !matches!(self.options.on_error, ScriptErrorMode::Stop);
+
+        while let Some(statement) = splitter.next_statement()? {
+            if cancel.is_cancelled() {
+                return Err("Script cancelled".to_string());
+            }
+            self.statements += 1;
+
+            if isolate {
+                session.savepoint(SAVEPOINT).await?;
+            }
+            match execute(session, &statement, splitter).await {
+                Ok(()) => self.summary.statements_executed += 1,
+                Err(e) => {
+                    if isolate {
+                        session.rollback_to_savepoint(SAVEPOINT).await?;
+                    }
+                    self.fail_statement(&statement, e)?;
+                }
+            }
+            if isolate {
+                // MySQL has already dropped it if the statement was DDL
+                if let Err(e) = session.release_savepoint(SAVEPOINT).await {
+                    log::warn!("[script] {}", e);
+                }
+            }
+
+            progress(self.statements);
+        }
+
+        Ok(())
+    }
+
+    /// Record a failed statement, or end the script when errors stop it.
+    fn fail_statement(&mut self, statement: &ScriptStatement, message: String) -> Result<(), String> {
+        if let ScriptErrorMode::Stop = self.options.on_error {
+            return Err(format!("Statement {} (line {}): {}", self.statements, statement.line, message));
+        }
+        log::warn!("[script] Statement {} (line {}) failed: {}", self.statements, statement.line, message);
+        self.summary.statements_failed += 1;
+        if self.summary.errors.len() < MAX_REPORTED_ERRORS {
+            self.summary.errors.push(ScriptStatementError {
+                statement: self.statements,
+                line: statement.line,
+                sql: statement.sql.chars().take(ERROR_SQL_PREVIEW).collect(),
+                message,
+            });
+        }
+        Ok(())
+    }
+}
+
+async fn execute<R: std::io::BufRead + Send>(
+    session: &mut DatabaseSession<'_>,
+    statement: &ScriptStatement,
+    splitter: &mut StatementSplitter<R>,
+) -> Result<(), String> {
+    if statement.copy_from_stdin {
+        session
+            .copy_from_stdin(&statement.sql, &mut || splitter.copy_data())
+            .await?;
+        return Ok(());
+    }
+    for _ in 0..statement.repeat.max(1) {
+        session.execute(&statement.sql).await?;
+    }
+    Ok(())
+}
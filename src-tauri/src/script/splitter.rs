@@ -0,0 +1,487 @@
+use std::io::BufRead;
+use crate::models::DatabaseType;
+
+/// Rows of a `COPY ... FROM stdin` block handed out per `copy_data` call.
+const COPY_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// One statement of a script, without its terminator.
+#[derive(Debug)]
+pub struct ScriptStatement {
+    pub sql: String,
+    /// Line the statement starts on, counting from 1
+    pub line: u64,
+    /// Times to run the statement; `GO 5` repeats a SQL Server batch
+    pub repeat: u32,
+    /// A PostgreSQL `COPY ... FROM stdin` whose rows follow in the script,
+    /// read with `copy_data`
+    pub copy_from_stdin: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum State {
+    Code,
+    Quoted { quote: u8, backslash: bool },
+    LineComment,
+    BlockComment(u32),
+    /// Inside a PostgreSQL dollar-quoted string, holding its `$tag$`
+    DollarQuoted(String),
+}
+
+/// Reads a script line by line and yields its statements the way each
+/// engine's own client splits them: on `;` outside strings and comments,
+/// on `DELIMITER` changes for MySQL, on `GO` lines only for SQL Server, and
+/// on the `END;` of a trigger body for SQLite.
+pub struct StatementSplitter<R: BufRead> {
+    reader: R,
+    db_type: DatabaseType,
+    delimiter: String,
+    state: State,
+    line: String,
+    /// Byte offset in `line` where scanning resumes
+    position: usize,
+    line_number: u64,
+    /// Text of the current statement, including comments before its first token
+    statement: String,
+    /// Offset in `statement` of the first token, unset while it is blank
+    code_start: Option<usize>,
+    start_line: u64,
+    in_copy: bool,
+}
+
+impl<R: BufRead> StatementSplitter<R> {
+    pub fn new(reader: R, db_type: DatabaseType) -> Self {
+        Self {
+            reader,
+            db_type,
+            delimiter: ";".to_string(),
+            state: State::Code,
+            line: String::new(),
+            position: 0,
+            line_number: 0,
+            statement: String::new(),
+            code_start: None,
+            start_line: 0,
+            in_copy: false,
+        }
+    }
+
+    /// The next statement, `None` at the end of the script. Rows of a
+    /// `COPY ... FROM stdin` that were not read with `copy_data` are skipped.
+    pub fn next_statement(&mut self) -> Result<Option<ScriptStatement>, String> {
+        while self.copy_data()?.is_some() {}
+
+        loop {
+            if self.position >= self.line.len() {
+                if !self.read_line()? {
+                    return Ok(self.take_statement(1));
+                }
+                if self.state == State::Code {
+                    if let Some(repeat) = self.batch_separator() {
+                        self.position = self.line.len();
+                        match self.take_statement(repeat) {
+                            Some(statement) => return Ok(Some(statement)),
+                            None => continue,
+                        }
+                    }
+                    if self.code_start.is_none() && self.delimiter_command() {
+                        self.statement.clear();
+                        self.position = self.line.len();
+                        continue;
+                    }
+                }
+            }
+
+            if let Some(statement) = self.scan_line() {
+                return Ok(Some(statement));
+            }
+        }
+    }
+
+    /// The next chunk of rows of the `COPY ... FROM stdin` just returned,
+    /// `None` once its closing `\.` line has been read.
+    pub fn copy_data(&mut self) -> Result<Option<String>, String> {
+        let mut chunk = String::new();
+        while self.in_copy && chunk.len() < COPY_CHUNK_SIZE {
+            if !self.read_line()? {
+                self.in_copy = false;
+                break;
+            }
+            self.position = self.line.len();
+            if self.line.trim_end_matches(['\r', '\n']) == "\\." {
+                self.in_copy = false;
+                break;
+            }
+            chunk.push_str(&self.line);
+        }
+        Ok(if chunk.is_empty() { None } else { Some(chunk) })
+    }
+
+    fn read_line(&mut self) -> Result<bool, String> {
+        self.line.clear();
+        self.position = 0;
+        let read = self
+            .reader
+            .read_line(&mut self.line)
+            .map_err(|e| format!("Failed to read script: {}", e))?;
+        if read == 0 {
+            return Ok(false);
+        }
+        self.line_number += 1;
+        Ok(true)
+    }
+
+    /// `GO` or `GO <count>` on a line of its own ends a SQL Server batch.
+    fn batch_separator(&self) -> Option<u32> {
+        if !matches!(self.db_type, DatabaseType::SQLServer) {
+            return None;
+        }
+        let text = match self.line.find("--") {
+            Some(idx) => &self.line[..idx],
+            None => &self.line,
+        };
+        let mut words = text.split_whitespace();
+        if !words.next()?.eq_ignore_ascii_case("GO") {
+            return None;
+        }
+        match (words.next(), words.next()) {
+            (None, _) => Some(1),
+            (Some(count), None) => count.parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// Apply a MySQL client `DELIMITER` command on the current line.
+    fn delimiter_command(&mut self) -> bool {
+        if !matches!(self.db_type, DatabaseType::MySQL) {
+            return false;
+        }
+        let mut words = self.line.split_whitespace();
+        match (words.next(), words.next()) {
+            (Some(command), Some(delimiter)) if command.eq_ignore_ascii_case("DELIMITER") => {
+                self.delimiter = delimiter.to_string();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Scan the rest of the current line, returning a statement if one ends on it.
+    fn scan_line(&mut self) -> Option<ScriptStatement> {
+        loop {
+            let line = std::mem::take(&mut self.line);
+            let terminated = self.scan_to_delimiter(&line);
+            self.line = line;
+            if !terminated {
+                return None;
+            }
+            if let Some(statement) = self.take_statement(1) {
+                return Some(statement);
+            }
+        }
+    }
+
+    /// Move text up to the next delimiter into the statement, or the whole
+    /// rest of the line if there is none. Returns whether a delimiter ended it.
+    fn scan_to_delimiter(&mut self, line: &str) -> bool {
+        let bytes = line.as_bytes();
+        let mut i = self.position;
+
+        while i < bytes.len() {
+            let c = bytes[i];
+            let next = bytes.get(i + 1).copied();
+            match &self.state {
+                State::Code => {
+                    if !matches!(self.db_type, DatabaseType::SQLServer)
+                        && line[i..].starts_with(self.delimiter.as_str())
+                        && !self.inside_trigger_body(&line[self.position..i])
+                    {
+                        self.statement.push_str(&line[self.position..i]);
+                        self.position = i + self.delimiter.len();
+                        return true;
+                    }
+
+                    match c {
+                        b'-' if next == Some(b'-') => {
+                            // MySQL needs whitespace after `--`, as in `SELECT 1--1`
+                            let comment = !matches!(self.db_type, DatabaseType::MySQL)
+                                || bytes.get(i + 2).is_none_or(|b| b.is_ascii_whitespace());
+                            if comment {
+                                self.state = State::LineComment;
+                                i += 1;
+                            } else {
+                                self.mark_code(i);
+                            }
+                        }
+                        b'#' if matches!(self.db_type, DatabaseType::MySQL) => self.state = State::LineComment,
+                        b'/' if next == Some(b'*') => {
+                            // MySQL runs the contents of `/*! ... */`
+                            if matches!(self.db_type, DatabaseType::MySQL) && bytes.get(i + 2) == Some(&b'!') {
+                                self.mark_code(i);
+                            }
+                            self.state = State::BlockComment(1);
+                            i += 1;
+                        }
+                        b'\'' => {
+                            let backslash = match self.db_type {
+                                DatabaseType::MySQL => true,
+                                // E'...' escape strings
+                                DatabaseType::PostgreSQL => {
+                                    i > 0
+                                        && matches!(bytes[i - 1], b'E' | b'e')
+                                        && (i < 2 || !is_identifier_byte(bytes[i - 2]))
+                                }
+                                _ => false,
+                            };
+                            self.mark_code(i);
+                            self.state = State::Quoted { quote: b'\'', backslash };
+                        }
+                        b'"' => {
+                            self.mark_code(i);
+                            let backslash = matches!(self.db_type, DatabaseType::MySQL);
+                            self.state = State::Quoted { quote: b'"', backslash };
+                        }
+                        b'`' if matches!(self.db_type, DatabaseType::MySQL | DatabaseType::SQLite) => {
+                            self.mark_code(i);
+                            self.state = State::Quoted { quote: b'`', backslash: false };
+                        }
+                        b'[' if matches!(self.db_type, DatabaseType::SQLServer | DatabaseType::SQLite) => {
+                            self.mark_code(i);
+                            self.state = State::Quoted { quote: b']', backslash: false };
+                        }
+                        b'$' if matches!(self.db_type, DatabaseType::PostgreSQL)
+                            && (i == 0 || !is_identifier_byte(bytes[i - 1])) =>
+                        {
+                            self.mark_code(i);
+                            if let Some(tag) = dollar_tag(&line[i..]) {
+                                i += tag.len() - 1;
+                                self.state = State::DollarQuoted(tag.to_string());
+                            }
+                        }
+                        c if c.is_ascii_whitespace() => {}
+                        _ => self.mark_code(i),
+                    }
+                }
+                State::Quoted { quote, backslash } => {
+                    if *backslash && c == b'\\' {
+                        i += 1;
+                    } else if c == *quote {
+                        // A doubled quote reopens the string on the next byte
+                        self.state = State::Code;
+                    }
+                }
+                State::LineComment => {
+                    if c == b'\n' {
+                        self.state = State::Code;
+                    }
+                }
+                State::BlockComment(depth) => {
+                    let depth = *depth;
+                    if c == b'*' && next == Some(b'/') {
+                        self.state = if depth == 1 { State::Code } else { State::BlockComment(depth - 1) };
+                        i += 1;
+                    } else if c == b'/' && next == Some(b'*') && matches!(self.db_type, DatabaseType::PostgreSQL) {
+                        // Only PostgreSQL nests block comments
+                        self.state = State::BlockComment(depth + 1);
+                        i += 1;
+                    }
+                }
+                State::DollarQuoted(tag) => {
+                    if line[i..].starts_with(tag.as_str()) {
+                        i += tag.len() - 1;
+                        self.state = State::Code;
+                    }
+                }
+            }
+            i += 1;
+        }
+
+        self.statement.push_str(&line[self.position..]);
+        self.position = line.len();
+        false
+    }
+
+    fn mark_code(&mut self, line_offset: usize) {
+        if self.code_start.is_none() {
+            self.code_start = Some(self.statement.len() + line_offset - self.position);
+            self.start_line = self.line_number;
+        }
+    }
+
+    /// SQLite trigger bodies hold `;`-terminated statements, so like sqlite3
+    /// itself only a `;` after an `END` that follows another `;` ends them.
+    /// `pending` is the text of the current line not yet moved into the statement.
+    fn inside_trigger_body(&self, pending: &str) -> bool {
+        if !matches!(self.db_type, DatabaseType::SQLite) {
+            return false;
+        }
+        let Some(start) = self.code_start else {
+            return false;
+        };
+        let (written, pending) = match self.statement.get(start..) {
+            Some(written) => (written, pending),
+            None => ("", &pending[start - self.statement.len()..]),
+        };
+
+        let head: String = written.chars().chain(pending.chars()).take(64).collect();
+        let mut words = head.split_whitespace().map(str::to_ascii_uppercase);
+        let is_trigger = matches!(
+            (words.next().as_deref(), words.next().as_deref(), words.next().as_deref()),
+            (Some("CREATE"), Some("TRIGGER"), _) | (Some("CREATE"), Some("TEMP" | "TEMPORARY"), Some("TRIGGER"))
+        );
+        if !is_trigger {
+            return false;
+        }
+
+        let text = format!("{}{}", written, pending);
+        let text = text.trim_end();
+        let closed = text
+            .len()
+            .checked_sub(3)
+            .and_then(|end| Some((text.get(end..)?, &text[..end])))
+            .is_some_and(|(last, before)| last.eq_ignore_ascii_case("END") && before.trim_end().ends_with(';'));
+        !closed
+    }
+
+    fn take_statement(&mut self, repeat: u32) -> Option<ScriptStatement> {
+        let mut text = std::mem::take(&mut self.statement);
+        let start = self.code_start.take()?;
+        text.truncate(text.trim_end().len());
+        text.drain(..start);
+
+        let copy_from_stdin = matches!(self.db_type, DatabaseType::PostgreSQL) && is_copy_from_stdin(&text);
+        if copy_from_stdin {
+            // Rows start on the next line
+            self.in_copy = true;
+            self.position = self.line.len();
+        }
+        Some(ScriptStatement {
+            sql: text,
+            line: self.start_line,
+            repeat,
+            copy_from_stdin,
+        })
+    }
+}
+
+fn is_identifier_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_' || b == b'$' || b >= 0x80
+}
+
+/// The `$tag$` opening a dollar-quoted string at the start of `text`.
+fn dollar_tag(text: &str) -> Option<&str> {
+    let rest = &text[1..];
+    let end = rest.find('$')?;
+    let tag = &rest[..end];
+    let valid = tag.chars().next().is_none_or(|c| !c.is_ascii_digit())
+        && tag.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_' || b >= 0x80);
+    valid.then(|| &text[..end + 2])
+}
+
+fn is_copy_from_stdin(sql: &str) -> bool {
+    let words: Vec<String> = sql.split_whitespace().map(str::to_ascii_uppercase).collect();
+    words.first().is_some_and(|w| w == "COPY")
+        && words.windows(2).any(|pair| pair[0] == "FROM" && pair[1] == "STDIN")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Statements of `script` with the line each starts on and its repeat count.
+    fn split(db_type: DatabaseType, script: &str) -> Vec<(String, u64, u32)> {
+        let mut splitter = StatementSplitter::new(script.as_bytes(), db_type);
+        let mut statements = Vec::new();
+        while let Some(statement) = splitter.next_statement().unwrap() {
+            statements.push((statement.sql, statement.line, statement.repeat));
+        }
+        statements
+    }
+
+    fn sql(db_type: DatabaseType, script: &str) -> Vec<String> {
+        split(db_type, script).into_iter().map(|(sql, _, _)| sql).collect()
+    }
+
+    #[test]
+    fn go_ends_sql_server_batches() {
+        let script = "SELECT 1;\nSELECT 2\nGO\nPRINT 'a;b'\n  go 3 -- three times\n/*\nGO\n*/ SELECT 4\n";
+        assert_eq!(
+            split(DatabaseType::SQLServer, script),
+            [
+                ("SELECT 1;\nSELECT 2".to_string(), 1, 1),
+                ("PRINT 'a;b'".to_string(), 4, 3),
+                ("SELECT 4".to_string(), 8, 1),
+            ]
+        );
+        // Only a line of its own separates
+        assert_eq!(sql(DatabaseType::SQLServer, "SELECT 1 AS go\nGO"), ["SELECT 1 AS go"]);
+    }
+
+    #[test]
+    fn mysql_delimiter_changes_the_terminator() {
+        let script = "DELIMITER //\nCREATE PROCEDURE p()\nBEGIN\n  SELECT 1;\n  SELECT 2;\nEND//\nDELIMITER ;\nSELECT 3;\n";
+        assert_eq!(
+            split(DatabaseType::MySQL, script),
+            [
+                ("CREATE PROCEDURE p()\nBEGIN\n  SELECT 1;\n  SELECT 2;\nEND".to_string(), 2, 1),
+                ("SELECT 3".to_string(), 8, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn postgres_dollar_quoted_bodies_keep_their_semicolons() {
+        let script = "CREATE FUNCTION f() RETURNS int AS $$ SELECT 1; $$ LANGUAGE sql;\n\
+                      DO $body$ BEGIN RAISE NOTICE ';'; END $body$;\nSELECT $1;";
+        assert_eq!(
+            sql(DatabaseType::PostgreSQL, script),
+            [
+                "CREATE FUNCTION f() RETURNS int AS $$ SELECT 1; $$ LANGUAGE sql",
+                "DO $body$ BEGIN RAISE NOTICE ';'; END $body$",
+                "SELECT $1",
+            ]
+        );
+    }
+
+    #[test]
+    fn comments_hide_semicolons() {
+        // PostgreSQL block comments nest; the others end at the first `*/`.
+        // Comments before a statement are left out of it
+        assert_eq!(
+            sql(DatabaseType::PostgreSQL, "/* a /* b; */ c; */ SELECT 1; -- d;\nSELECT 2;"),
+            ["SELECT 1", "SELECT 2"]
+        );
+        assert_eq!(sql(DatabaseType::SQLite, "/* a /* b */ SELECT 1; */"), ["SELECT 1", "*/"]);
+        assert_eq!(sql(DatabaseType::MySQL, "# a;\nSELECT 1--1;\nSELECT 2;"), ["SELECT 1--1", "SELECT 2"]);
+    }
+
+    #[test]
+    fn strings_hide_semicolons() {
+        assert_eq!(
+            sql(DatabaseType::PostgreSQL, "SELECT 'a;b', \"c;d\", E'e\\';f', 'g'';h'; SELECT 2"),
+            ["SELECT 'a;b', \"c;d\", E'e\\';f', 'g'';h'", "SELECT 2"]
+        );
+        assert_eq!(sql(DatabaseType::MySQL, "SELECT 'it\\'s;', `x;y`; SELECT 2"), ["SELECT 'it\\'s;', `x;y`", "SELECT 2"]);
+        assert_eq!(sql(DatabaseType::SQLite, "SELECT [a;b], 'c\\'; SELECT 2"), ["SELECT [a;b], 'c\\'", "SELECT 2"]);
+    }
+
+    #[test]
+    fn sqlite_trigger_bodies_end_at_end() {
+        let script = "CREATE TRIGGER tr AFTER INSERT ON t BEGIN\n  UPDATE u SET x = 1;\nEND;\nSELECT 1;";
+        assert_eq!(
+            sql(DatabaseType::SQLite, script),
+            ["CREATE TRIGGER tr AFTER INSERT ON t BEGIN\n  UPDATE u SET x = 1;\nEND", "SELECT 1"]
+        );
+    }
+
+    #[test]
+    fn copy_rows_are_read_separately() {
+        let mut splitter = StatementSplitter::new("COPY t FROM stdin;\n1\t;\n\\.\nSELECT 1;".as_bytes(), DatabaseType::PostgreSQL);
+        let copy = splitter.next_statement().unwrap().unwrap();
+        assert!(copy.copy_from_stdin);
+        assert_eq!(splitter.copy_data().unwrap().as_deref(), Some("1\t;\n"));
+        assert_eq!(splitter.copy_data().unwrap(), None);
+        let next = splitter.next_statement().unwrap().unwrap();
+        assert_eq!((next.sql.as_str(), next.line), ("SELECT 1", 4));
+        assert!(splitter.next_statement().unwrap().is_none());
+    }
+}
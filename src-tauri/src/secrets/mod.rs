@@ -0,0 +1,312 @@
+#[cfg(target_os = "linux")]
+mod vault;
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+#[cfg(target_os = "linux")]
+use std::time::{Duration, Instant};
+#[cfg(target_os = "linux")]
+use tokio::sync::Mutex;
+use crate::database::dsn;
+use crate::models::*;
+
+/// Service name entries are filed under in the OS keychain.
+const SERVICE: &str = "datablaze";
+/// How long a Secret Service that did not answer is left alone before it is
+/// asked again.
+#[cfg(target_os = "linux")]
+const PROBE_RETRY: Duration = Duration::from_secs(30);
+
+/// Passwords, SSH passphrases and private keys of saved connections. They
+/// live in the platform keychain; a profile only keeps the `credential_id`
+/// of its entry. Where Linux has no Secret Service the entries go to a file
+/// encrypted with a master password instead.
+pub struct SecretStore {
+    vault_path: PathBuf,
+    backend: OnceLock<SecretBackend>,
+    /// When the Secret Service last failed to answer, and why
+    #[cfg(target_os = "linux")]
+    unavailable: std::sync::Mutex<Option<(Instant, String)>>,
+    /// The encrypted file once unlocked
+    #[cfg(target_os = "linux")]
+    vault: Mutex<Option<vault::Vault>>,
+}
+
+impl SecretStore {
+    pub fn new(data_dir: &Path) -> Self {
+        Self {
+            vault_path: data_dir.join("secrets.vault"),
+            backend: OnceLock::new(),
+            #[cfg(target_os = "linux")]
+            unavailable: std::sync::Mutex::new(None),
+            #[cfg(target_os = "linux")]
+            vault: Mutex::new(None),
+        }
+    }
+
+    pub async fn status(&self) -> SecretStoreStatus {
+        let backend = self.backend().await;
+        #[cfg(target_os = "linux")]
+        let unlocked = backend == SecretBackend::Keychain || self.vault.lock().await.is_some();
+        #[cfg(not(target_os = "linux"))]
+        let unlocked = true;
+        #[cfg(target_os = "linux")]
+        let warning = match self.unavailable.lock().unwrap().as_ref() {
+            Some((_, reason)) if !self.vault_path.exists() => Some(format!(
+                "The system keychain did not answer ({}). Once a master password is set, passwords are kept in \
+                 the encrypted file and any already saved in the keychain are no longer read",
+                reason
+            )),
+            _ => None,
+        };
+        #[cfg(not(target_os = "linux"))]
+        let warning = None;
+        SecretStoreStatus {
+            backend,
+            initialized: backend == SecretBackend::Keychain || self.vault_path.exists(),
+            unlocked,
+            warning,
+        }
+    }
+
+    /// Open the encrypted file with the master password, creating it with
+    /// that password on first use.
+    #[cfg(target_os = "linux")]
+    pub async fn unlock(&self, master_password: String) -> Result<SecretStoreStatus, String> {
+        if self.backend().await != SecretBackend::EncryptedFile {
+            return Err("Secrets are kept in the system keychain, which needs no master password".to_string());
+        }
+        let path = self.vault_path.clone();
+        // Deriving the key is deliberately slow
+        let vault = tokio::task::spawn_blocking(move || vault::Vault::unlock(&path, &master_password))
+            .await
+            .map_err(|e| format!("Failed to unlock the credential store: {}", e))??;
+        *self.vault.lock().await = Some(vault);
+        Ok(self.status().await)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub async fn unlock(&self, _master_password: String) -> Result<SecretStoreStatus, String> {
+        Err("Secrets are kept in the system keychain, which needs no master password".to_string())
+    }
+
+    /// Forget the key of the encrypted file until the next unlock.
+    pub async fn lock(&self) {
+        #[cfg(target_os = "linux")]
+        self.vault.lock().await.take();
+    }
+
+    /// Move the secrets out of a connection into the store and return the
+    /// connection without them. Secrets the connection leaves empty keep
+    /// their stored value.
+    pub async fn store(&self, mut config: ConnectionConfig) -> Result<ConnectionConfig, String> {
+        let secrets = take_secrets(&mut config);
+        if secrets == ConnectionSecrets::default() {
+            return Ok(config);
+        }
+
+        let id = config
+            .credential_id
+            .get_or_insert_with(|| uuid::Uuid::new_v4().to_string())
+            .clone();
+        let mut stored = self.read(&id).await?.unwrap_or_default();
+        merge(&mut stored, secrets);
+        self.write(&id, stored).await?;
+        Ok(config)
+    }
+
+    /// Put the stored secrets back into a connection about to be opened.
+    /// Values already on the connection win.
+    pub async fn fill(&self, mut config: ConnectionConfig) -> Result<ConnectionConfig, String> {
+        let Some(id) = config.credential_id.clone() else {
+            return Ok(config);
+        };
+        let Some(secrets) = self.read(&id).await? else {
+            log::warn!("[SecretStore] No stored secrets for connection '{}'", config.name);
+            return Ok(config);
+        };
+
+        if config.password.is_empty() {
+            config.password = secrets.password.unwrap_or_default();
+        }
+        if let Some(ssh) = config.ssh_tunnel.as_mut() {
+            fill_empty(&mut ssh.password, secrets.ssh_password);
+            fill_empty(&mut ssh.passphrase, secrets.ssh_passphrase);
+            fill_empty(&mut ssh.private_key, secrets.ssh_private_key);
+        }
+        Ok(config)
+    }
+
+    pub async fn delete(&self, id: &str) -> Result<(), String> {
+        match self.backend().await {
+            SecretBackend::Keychain => {
+                let id = id.to_string();
+                run_blocking(move || match keyring::Entry::new(SERVICE, &id)?.delete_credential() {
+                    Err(keyring::Error::NoEntry) => Ok(()),
+                    other => other,
+                })
+                .await
+                .map_err(|e| format!("Failed to delete credentials: {}", e))
+            }
+            SecretBackend::EncryptedFile => self.with_vault(|vault| vault.remove(id)).await,
+        }
+    }
+
+    async fn read(&self, id: &str) -> Result<Option<ConnectionSecrets>, String> {
+        match self.backend().await {
+            SecretBackend::Keychain => {
+                let id = id.to_string();
+                let text = run_blocking(move || match keyring::Entry::new(SERVICE, &id)?.get_password() {
+                    Ok(text) => Ok(Some(text)),
+                    Err(keyring::Error::NoEntry) => Ok(None),
+                    Err(e) => Err(e),
+                })
+                .await
+                .map_err(|e| format!("Failed to read credentials from the keychain: {}", e))?;
+                text.map(|text| {
+                    serde_json::from_str(&text).map_err(|e| format!("Failed to read stored credentials: {}", e))
+                })
+                .transpose()
+            }
+            SecretBackend::EncryptedFile => self.with_vault(|vault| Ok(vault.get(id))).await,
+        }
+    }
+
+    async fn write(&self, id: &str, secrets: ConnectionSecrets) -> Result<(), String> {
+        match self.backend().await {
+            SecretBackend::Keychain => {
+                let text = serde_json::to_string(&secrets)
+                    .map_err(|e| format!("Failed to store credentials: {}", e))?;
+                let id = id.to_string();
+                run_blocking(move || keyring::Entry::new(SERVICE, &id)?.set_password(&text))
+                    .await
+                    .map_err(|e| format!("Failed to store credentials in the keychain: {}", e))
+            }
+            SecretBackend::EncryptedFile => self.with_vault(|vault| vault.set(id, secrets)).await,
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn with_vault<T>(&self, f: impl FnOnce(&mut vault::Vault) -> Result<T, String>) -> Result<T, String> {
+        match self.vault.lock().await.as_mut() {
+            Some(vault) => f(vault),
+            None => Err("The credential store is locked; enter the master password".to_string()),
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    async fn with_vault<T>(&self, _f: impl FnOnce(&mut NoVault) -> Result<T, String>) -> Result<T, String> {
+        Err("No encrypted credential file on this platform".to_string())
+    }
+
+    /// The keychain, unless Linux has no Secret Service running or the
+    /// encrypted file is already in use. A Secret Service that does not
+    /// answer is asked again later, as it may still be starting; until the
+    /// encrypted file is created nothing is settled.
+    async fn backend(&self) -> SecretBackend {
+        if let Some(backend) = self.backend.get() {
+            return *backend;
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            if self.vault_path.exists() {
+                return *self.backend.get_or_init(|| SecretBackend::EncryptedFile);
+            }
+            if let Some((at, _)) = self.unavailable.lock().unwrap().as_ref() {
+                if at.elapsed() < PROBE_RETRY {
+                    return SecretBackend::EncryptedFile;
+                }
+            }
+            // Looking up a missing entry is enough to reach the service
+            let probe = run_blocking(|| match keyring::Entry::new(SERVICE, "probe")?.get_password() {
+                Ok(_) | Err(keyring::Error::NoEntry) => Ok(()),
+                Err(e) => Err(e),
+            })
+            .await;
+            match probe {
+                Ok(()) => {
+                    self.unavailable.lock().unwrap().take();
+                    *self.backend.get_or_init(|| SecretBackend::Keychain)
+                }
+                Err(e) => {
+                    log::warn!("[SecretStore] Secret Service unavailable ({}), using the encrypted file", e);
+                    *self.unavailable.lock().unwrap() = Some((Instant::now(), e));
+                    SecretBackend::EncryptedFile
+                }
+            }
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            *self.backend.get_or_init(|| SecretBackend::Keychain)
+        }
+    }
+}
+
+/// Stands in for the encrypted file where there is none.
+#[cfg(not(target_os = "linux"))]
+struct NoVault;
+
+#[cfg(not(target_os = "linux"))]
+impl NoVault {
+    fn get(&self, _id: &str) -> Option<ConnectionSecrets> {
+        None
+    }
+
+    fn set(&mut self, _id: &str, _secrets: ConnectionSecrets) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn remove(&mut self, _id: &str) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// Clear the secrets from a connection, returning them.
+fn take_secrets(config: &mut ConnectionConfig) -> ConnectionSecrets {
+    let non_empty = |value: Option<String>| value.filter(|v| !v.is_empty());
+    let mut secrets = ConnectionSecrets {
+        password: non_empty(Some(std::mem::take(&mut config.password))),
+        ..Default::default()
+    };
+    // A password written into the URL counts too
+    if let Some((url, password)) = config.url.as_deref().and_then(dsn::split_password) {
+        config.url = Some(url);
+        secrets.password.get_or_insert(password);
+    }
+    if let Some(ssh) = config.ssh_tunnel.as_mut() {
+        secrets.ssh_password = non_empty(ssh.password.take());
+        secrets.ssh_passphrase = non_empty(ssh.passphrase.take());
+        secrets.ssh_private_key = non_empty(ssh.private_key.take());
+    }
+    secrets
+}
+
+fn merge(stored: &mut ConnectionSecrets, update: ConnectionSecrets) {
+    for (slot, value) in [
+        (&mut stored.password, update.password),
+        (&mut stored.ssh_password, update.ssh_password),
+        (&mut stored.ssh_passphrase, update.ssh_passphrase),
+        (&mut stored.ssh_private_key, update.ssh_private_key),
+    ] {
+        if value.is_some() {
+            *slot = value;
+        }
+    }
+}
+
+fn fill_empty(slot: &mut Option<String>, stored: Option<String>) {
+    if slot.as_deref().unwrap_or_default().is_empty() {
+        *slot = stored.or(slot.take());
+    }
+}
+
+/// Keychain calls block on D-Bus or the platform API.
+async fn run_blocking<T: Send + 'static>(
+    f: impl FnOnce() -> keyring::Result<T> + Send + 'static,
+) -> Result<T, String> {
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,266 @@
+use std::collections::HashMap;
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use crate::models::*;
+
+const VERSION: u32 = 1;
+/// PBKDF2-HMAC-SHA256 rounds for new files, as recommended by OWASP.
+const ITERATIONS: u32 = 600_000;
+const SALT_LEN: usize = 16;
+
+/// On-disk form: the secrets map as JSON, sealed with AES-256-GCM under a
+/// key derived from the master password. The salt and round count are kept
+/// so the key can be derived again.
+#[derive(Serialize, Deserialize)]
+struct VaultFile {
+    version: u32,
+    iterations: u32,
+    salt: String,
+    /// Nonce followed by the ciphertext and tag, hex encoded
+    data: String,
+}
+
+/// The unlocked encrypted file. Every change rewrites it.
+pub struct Vault {
+    path: PathBuf,
+    key: LessSafeKey,
+    salt: Vec<u8>,
+    iterations: u32,
+    entries: HashMap<String, ConnectionSecrets>,
+}
+
+impl Vault {
+    /// Decrypt the file with the master password, or create an empty one
+    /// protected by it.
+    pub fn unlock(path: &Path, master_password: &str) -> Result<Self, String> {
+        if master_password.is_empty() {
+            return Err("The master password cannot be empty".to_string());
+        }
+
+        if !path.exists() {
+            let mut salt = vec![0; SALT_LEN];
+            SystemRandom::new()
+                .fill(&mut salt)
+                .map_err(|_| "Failed to generate a salt".to_string())?;
+            let vault = Self {
+                path: path.to_path_buf(),
+                key: derive_key(master_password, &salt, ITERATIONS)?,
+                salt,
+                iterations: ITERATIONS,
+                entries: HashMap::new(),
+            };
+            vault.save()?;
+            log::info!("[SecretStore] Created encrypted credential file {}", path.display());
+            return Ok(vault);
+        }
+
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read the credential file: {}", e))?;
+        let file: VaultFile = serde_json::from_str(&text)
+            .map_err(|e| format!("Failed to read the credential file: {}", e))?;
+        if file.version != VERSION {
+            return Err(format!("Unsupported credential file version {}", file.version));
+        }
+        let salt = hex::decode(&file.salt).map_err(|e| format!("Damaged credential file: {}", e))?;
+        let mut data = hex::decode(&file.data).map_err(|e| format!("Damaged credential file: {}", e))?;
+        if data.len() < NONCE_LEN {
+            return Err("Damaged credential file".to_string());
+        }
+
+        let key = derive_key(master_password, &salt, file.iterations)?;
+        let mut sealed = data.split_off(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(&data).map_err(|_| "Damaged credential file".to_string())?;
+        // A wrong key and a tampered file look the same to GCM
+        let plain = key
+            .open_in_place(nonce, Aad::from(file.salt.as_bytes()), &mut sealed)
+            .map_err(|_| "Wrong master password".to_string())?;
+        let entries = serde_json::from_slice(plain)
+            .map_err(|e| format!("Failed to read the credential file: {}", e))?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            key,
+            salt,
+            iterations: file.iterations,
+            entries,
+        })
+    }
+
+    pub fn get(&self, id: &str) -> Option<ConnectionSecrets> {
+        self.entries.get(id).cloned()
+    }
+
+    pub fn set(&mut self, id: &str, secrets: ConnectionSecrets) -> Result<(), String> {
+        self.entries.insert(id.to_string(), secrets);
+        self.save()
+    }
+
+    pub fn remove(&mut self, id: &str) -> Result<(), String> {
+        if self.entries.remove(id).is_some() {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let salt = hex::encode(&self.salt);
+        let mut nonce = [0; NONCE_LEN];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .map_err(|_| "Failed to generate a nonce".to_string())?;
+        let mut sealed = serde_json::to_vec(&self.entries)
+            .map_err(|e| format!("Failed to save the credential file: {}", e))?;
+        self.key
+            .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::from(salt.as_bytes()), &mut sealed)
+            .map_err(|_| "Failed to encrypt the credential file".to_string())?;
+
+        let mut data = nonce.to_vec();
+        data.extend_from_slice(&sealed);
+        let file = VaultFile {
+            version: VERSION,
+            iterations: self.iterations,
+            salt,
+            data: hex::encode(data),
+        };
+        let text = serde_json::to_string_pretty(&file)
+            .map_err(|e| format!("Failed to save the credential file: {}", e))?;
+        write_private(&self.path, text.as_bytes())
+    }
+}
+
+fn derive_key(master_password: &str, salt: &[u8], iterations: u32) -> Result<LessSafeKey, String> {
+    let iterations = NonZeroU32::new(iterations).ok_or("Damaged credential file")?;
+    let mut key = [0; 32];
+    pbkdf2::derive(pbkdf2::PBKDF2_HMAC_SHA256, iterations, salt, master_password.as_bytes(), &mut key);
+    let key = UnboundKey::new(&AES_256_GCM, &key).map_err(|_| "Failed to derive the encryption key".to_string())?;
+    Ok(LessSafeKey::new(key))
+}
+
+/// Replace the file in one step, readable by the owner only.
+fn write_private(path: &Path, contents: &[u8]) -> Result<(), String> {
+    use std::io::Write;
+    use std::os::unix::fs::OpenOptionsExt;
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create the credential directory: {}", e))?;
+    }
+    let temp = path.with_extension("tmp");
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(&temp)
+        .map_err(|e| format!("Failed to save the credential file: {}", e))?;
+    file.write_all(contents)
+        .and_then(|_| file.sync_all())
+        .map_err(|e| format!("Failed to save the credential file: {}", e))?;
+    std::fs::rename(&temp, path).map_err(|e| format!("Failed to save the credential file: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh directory for one test's credential file.
+    fn vault_path() -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("datablaze-vault-{}", uuid::Uuid::new_v4()))
+            .join("secrets.vault")
+    }
+
+    fn secrets(password: &str) -> ConnectionSecrets {
+        ConnectionSecrets {
+            password: Some(password.to_string()),
+            ssh_passphrase: Some("unlock me".to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn remove(path: &Path) {
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn secrets_survive_a_round_trip() {
+        let path = vault_path();
+        let mut vault = Vault::unlock(&path, "correct horse").unwrap();
+        vault.set("a", secrets("first")).unwrap();
+        vault.set("b", secrets("second")).unwrap();
+        vault.remove("b").unwrap();
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(!text.contains("first") && !text.contains("unlock me"));
+
+        let vault = Vault::unlock(&path, "correct horse").unwrap();
+        assert_eq!(vault.get("a"), Some(secrets("first")));
+        assert_eq!(vault.get("b"), None);
+        assert_eq!(vault.iterations, ITERATIONS);
+        remove(&path);
+    }
+
+    #[test]
+    fn wrong_master_password_is_rejected() {
+        let path = vault_path();
+        Vault::unlock(&path, "correct horse").unwrap().set("a", secrets("first")).unwrap();
+
+        assert_eq!(Vault::unlock(&path, "battery staple").err(), Some("Wrong master password".to_string()));
+        assert!(Vault::unlock(&path, "").is_err());
+        remove(&path);
+    }
+
+    #[test]
+    fn tampered_file_is_rejected() {
+        let path = vault_path();
+        Vault::unlock(&path, "correct horse").unwrap().set("a", secrets("first")).unwrap();
+        let original = std::fs::read_to_string(&path).unwrap();
+
+        let flip = |hex_text: &str, at: usize| {
+            let mut bytes = hex::decode(hex_text).unwrap();
+            bytes[at] ^= 1;
+            hex::encode(bytes)
+        };
+        let tampered = |edit: &dyn Fn(&mut VaultFile)| {
+            let mut file: VaultFile = serde_json::from_str(&original).unwrap();
+            edit(&mut file);
+            std::fs::write(&path, serde_json::to_string(&file).unwrap()).unwrap();
+            Vault::unlock(&path, "correct horse").err()
+        };
+
+        assert_eq!(tampered(&|file| file.data = flip(&file.data, NONCE_LEN + 2)), Some("Wrong master password".to_string()));
+        assert!(tampered(&|file| file.data = flip(&file.data, 0)).is_some());
+        // The salt is authenticated as well as used to derive the key
+        assert!(tampered(&|file| file.salt = flip(&file.salt, 0)).is_some());
+        assert!(tampered(&|file| file.data.truncate(NONCE_LEN)).is_some());
+        assert!(tampered(&|_| {}).is_none());
+        remove(&path);
+    }
+
+    #[test]
+    fn files_with_fewer_iterations_still_open() {
+        let path = vault_path();
+        let salt = vec![7; SALT_LEN];
+        let vault = Vault {
+            path: path.clone(),
+            key: derive_key("correct horse", &salt, 100_000).unwrap(),
+            salt,
+            iterations: 100_000,
+            entries: HashMap::from([("a".to_string(), secrets("first"))]),
+        };
+        vault.save().unwrap();
+
+        let mut vault = Vault::unlock(&path, "correct horse").unwrap();
+        assert_eq!(vault.get("a"), Some(secrets("first")));
+        vault.set("b", secrets("second")).unwrap();
+
+        // Saving keeps the round count the key was derived with
+        let vault = Vault::unlock(&path, "correct horse").unwrap();
+        assert_eq!(vault.iterations, 100_000);
+        assert_eq!(vault.get("b"), Some(secrets("second")));
+        remove(&path);
+    }
+}
@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+use crate::models::TaskProgress;
+
+/// Minimum time between two progress events for the same task.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Long-running operations (exports, imports, script runs) that the frontend
+/// can cancel by the task id it passed in.
+pub struct TaskManager {
+    tasks: Arc<Mutex<HashMap<String, CancellationToken>>>,
+}
+
+impl TaskManager {
+    pub fn new() -> Self {
+        Self {
+            tasks: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub async fn register(&self, task_id: &str) -> CancellationToken {
+        let token = CancellationToken::new();
+        let mut tasks = self.tasks.lock().await;
+        tasks.insert(task_id.to_string(), token.clone());
+        token
+    }
+
+    pub async fn finish(&self, task_id: &str) {
+        let mut tasks = self.tasks.lock().await;
+        tasks.remove(task_id);
+    }
+
+    pub async fn cancel(&self, task_id: &str) -> bool {
+        let tasks = self.tasks.lock().await;
+        match tasks.get(task_id) {
+            Some(token) => {
+                log::info!("[TaskManager] Cancelling task: {}", task_id);
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl Default for TaskManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Emits throttled `task-progress` events for one task.
+pub struct ProgressReporter {
+    app: AppHandle,
+    task_id: String,
+    last: Option<Instant>,
+}
+
+impl ProgressReporter {
+    pub fn new(app: AppHandle, task_id: &str) -> Self {
+        Self {
+            app,
+            task_id: task_id.to_string(),
+            last: None,
+        }
+    }
+
+    pub fn report(&mut self, processed: u64, total: Option<u64>) {
+        if self.throttled() {
+            return;
+        }
+        self.emit(processed, total);
+    }
+
+    /// Like `report`, for tasks that also track how far they are through a file.
+    pub fn report_bytes(&mut self, processed: u64, bytes_read: u64, bytes_total: Option<u64>) {
+        if self.throttled() {
+            return;
+        }
+        self.emit_bytes(processed, bytes_read, bytes_total);
+    }
+
+    fn throttled(&mut self) -> bool {
+        if self.last.is_some_and(|last| last.elapsed() < PROGRESS_INTERVAL) {
+            return true;
+        }
+        self.last = Some(Instant::now());
+        false
+    }
+
+    /// Emit regardless of throttling, e.g. for the final count.
+    pub fn emit(&self, processed: u64, total: Option<u64>) {
+        self.send(TaskProgress {
+            task_id: self.task_id.clone(),
+            processed,
+            total,
+            bytes_read: None,
+            bytes_total: None,
+        });
+    }
+
+    pub fn emit_bytes(&self, processed: u64, bytes_read: u64, bytes_total: Option<u64>) {
+        self.send(TaskProgress {
+            task_id: self.task_id.clone(),
+            processed,
+            total: None,
+            bytes_read: Some(bytes_read),
+            bytes_total,
+        });
+    }
+
+    fn send(&self, progress: TaskProgress) {
+        if let Err(e) = self.app.emit("task-progress", progress) {
+            log::warn!("[ProgressReporter] Failed to emit progress: {}", e);
+        }
+    }
+}
@@ -0,0 +1,356 @@
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::time::Instant;
+use tokio_util::sync::CancellationToken;
+use crate::database::dialect::{self, ColumnKind, SqlValue};
+use crate::database::temporal::TemporalFormat;
+use crate::database::{self, ddl, guard, DatabaseConnection, RowSink};
+use crate::import::{self, infer, ImportTarget, RowSource, SourceRow};
+use crate::models::*;
+
+/// Converted rows buffered between the source reader and the inserts.
+const CHANNEL_ROWS: usize = 4096;
+
+type RowMessage = Result<Option<SourceRow>, String>;
+
+/// Copy the rows of a table to another connection, possibly on another
+/// engine. Rows are read on a blocking thread and inserted through the import
+/// path, so batching, skip mode and the single transaction behave the same.
+pub async fn copy_table(
+    source: DatabaseConnection,
+    source_table: &str,
+    target: &DatabaseConnection,
+    target_table: &str,
+    options: &CopyTableOptions,
+    cancel: CancellationToken,
+    progress: &mut (dyn FnMut(u64) + Send),
+) -> Result<CopyTableSummary, String> {
+    let start = Instant::now();
+    let source_db = source.db_type();
+    let target_db = target.db_type();
+
+    let source_columns = database::get_table_structure_info(&source, source_table).await?.columns;
+    if source_columns.is_empty() {
+        return Err(format!("Table {} does not exist", source_table));
+    }
+    let names: Vec<String> = source_columns.iter().map(|c| c.name.clone()).collect();
+    let existing = database::get_table_structure_info(target, target_table).await?.columns;
+
+    let mapping = match (&options.mapping, existing.is_empty()) {
+        (Some(mapping), true) => Some(
+            mapping
+                .iter()
+                .map(|pair| {
+                    let sql_type = pair.sql_type.clone().or_else(|| {
+                        source_columns
+                            .iter()
+                            .find(|c| c.name.eq_ignore_ascii_case(&pair.source))
+                            .map(|c| target_type_sql(&target_db, c))
+                    });
+                    ColumnMapping { sql_type, ..pair.clone() }
+                })
+                .collect(),
+        ),
+        (None, true) => Some(
+            source_columns
+                .iter()
+                .map(|c| ColumnMapping {
+                    source: c.name.clone(),
+                    target: c.name.clone(),
+                    sql_type: Some(target_type_sql(&target_db, c)),
+                })
+                .collect(),
+        ),
+        (mapping, false) => mapping.clone(),
+    };
+    let import_options = ImportOptions {
+        mapping,
+        create_table: options.create_table,
+        batch_size: options.batch_size,
+        on_error: options.on_error,
+        ..ImportOptions::default()
+    };
+
+    let mut plan = import::resolve_target(target, target_table, &names, &import_options, || {
+        Ok(source_columns
+            .iter()
+            .map(|c| InferredColumn {
+                name: c.name.clone(),
+                kind: dialect::column_kind(&c.data_type),
+                nullable: c.is_nullable,
+                max_length: 0,
+                precision: 0,
+                scale: 0,
+            })
+            .collect())
+    })
+    .await?;
+    plan.truncate = options.truncate && !existing.is_empty();
+    if plan.create_sql.is_some() {
+        plan.create_sql = Some(create_table_sql(&target_db, &plan, &source_columns, &import_options));
+    }
+
+    let mismatches = find_mismatches(&target_db, &plan, &source_columns, &existing);
+    for mismatch in &mismatches {
+        log::warn!("[copy_table] {}: {}", mismatch.column, mismatch.message);
+    }
+
+    let select = select_sql(&source_db, source_table, &names, options.filter.as_deref());
+    // The filter is pasted after WHERE as typed
+    if guard::statements(&source_db, &select).len() > 1 {
+        return Err("The filter must be a single condition".to_string());
+    }
+    if let Some(reason) = guard::writes(&source_db, &select) {
+        return Err(format!("The filter must only read: {} is not allowed", reason));
+    }
+    let (sender, receiver) = sync_channel::<RowMessage>(CHANNEL_ROWS);
+    let mut sink = ChannelSink {
+        sender,
+        columns: plan.columns.clone(),
+        sources: plan.sources.clone(),
+        kinds: plan.kinds.clone(),
+        rows: 0,
+    };
+
+    // The source is read on its own thread: `RowSink` is synchronous and
+    // blocks while the inserts catch up
+    let runtime = tokio::runtime::Handle::current();
+    let reader = tokio::task::spawn_blocking(move || {
+        let result = runtime.block_on(database::stream_sql_query(&source, &select, &TemporalFormat::default(), &mut sink));
+        let _ = sink.sender.send(result.map(|_| None));
+    });
+
+    let mut rows = ChannelSource { receiver };
+    let result = import::run_import(target, &plan, &mut rows, &import_options, cancel, progress).await;
+    // Unblocks the reader if the import stopped early
+    drop(rows);
+    if let Err(e) = reader.await {
+        log::error!("[copy_table] Source reader failed: {}", e);
+    }
+    let summary = result?;
+
+    log::info!(
+        "[copy_table] Copied {} of {} rows from {} to {}",
+        summary.rows_imported,
+        summary.rows_read,
+        source_table,
+        target_table
+    );
+    Ok(CopyTableSummary {
+        source_table: source_table.to_string(),
+        target_table: summary.table,
+        created_table: summary.created_table,
+        rows_read: summary.rows_read,
+        rows_copied: summary.rows_imported,
+        rows_failed: summary.rows_failed,
+        errors: summary.errors,
+        mismatches,
+        duration_ms: start.elapsed().as_millis() as u64,
+    })
+}
+
+/// Column type on the target engine for a source column.
+fn target_type_sql(db_type: &DatabaseType, column: &ColumnInfo) -> String {
+    dialect::translate_type(
+        db_type,
+        &column.data_type,
+        column.max_length,
+        column.numeric_precision,
+        column.numeric_scale,
+    )
+}
+
+/// `CREATE TABLE` for a new target, keeping the source primary key when all
+/// of its columns are copied.
+fn create_table_sql(db_type: &DatabaseType, plan: &ImportTarget, source: &[ColumnInfo], options: &ImportOptions) -> String {
+    let mapping = options.mapping.as_deref().unwrap_or_default();
+    let columns: Vec<ColumnDefinition> = plan
+        .columns
+        .iter()
+        .zip(&plan.sources)
+        .map(|(name, &idx)| {
+            let column = &source[idx];
+            ColumnDefinition {
+                name: name.clone(),
+                data_type: mapping
+                    .iter()
+                    .find(|pair| pair.target == *name)
+                    .and_then(|pair| pair.sql_type.clone())
+                    .unwrap_or_else(|| target_type_sql(db_type, column)),
+                nullable: column.is_nullable,
+                default: None,
+                identity: None,
+                generated: None,
+                generated_stored: false,
+            }
+        })
+        .collect();
+
+    let key: Vec<String> = source
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| c.is_primary_key)
+        .map(|(idx, _)| plan.sources.iter().position(|&s| s == idx).map(|i| plan.columns[i].clone()))
+        .collect::<Option<_>>()
+        .unwrap_or_default();
+
+    let (schema, name) = match plan.table.split_once('.') {
+        Some((schema, name)) => (Some(schema.to_string()), name.to_string()),
+        None => (None, plan.table.clone()),
+    };
+    let table = TableDefinition {
+        schema,
+        name,
+        columns,
+        primary_key: (!key.is_empty()).then_some(KeyConstraint { name: None, columns: key }),
+        unique_constraints: Vec::new(),
+        foreign_keys: Vec::new(),
+        checks: Vec::new(),
+        indexes: Vec::new(),
+        definition: None,
+    };
+    ddl::create_table(db_type, &table, &[])
+}
+
+/// Columns that are left out, change type or may not fit.
+fn find_mismatches(
+    db_type: &DatabaseType,
+    plan: &ImportTarget,
+    source: &[ColumnInfo],
+    existing: &[ColumnInfo],
+) -> Vec<ColumnMismatch> {
+    let mut mismatches = Vec::new();
+
+    for (idx, column) in source.iter().enumerate() {
+        if !plan.sources.contains(&idx) {
+            mismatches.push(ColumnMismatch {
+                column: column.name.clone(),
+                source_type: Some(column.data_type.clone()),
+                target_type: None,
+                message: "Not mapped to a target column; skipped".to_string(),
+            });
+        }
+    }
+    for column in existing {
+        if !plan.columns.iter().any(|c| c.eq_ignore_ascii_case(&column.name)) {
+            mismatches.push(ColumnMismatch {
+                column: column.name.clone(),
+                source_type: None,
+                target_type: Some(column.data_type.clone()),
+                message: "Not in the source; left to its default".to_string(),
+            });
+        }
+    }
+
+    for ((name, &idx), &kind) in plan.columns.iter().zip(&plan.sources).zip(&plan.kinds) {
+        let from = &source[idx];
+        let from_kind = dialect::column_kind(&from.data_type);
+        let to = existing.iter().find(|c| c.name.eq_ignore_ascii_case(name));
+        let to_type = to.map_or_else(|| target_type_sql(db_type, from), |c| c.data_type.clone());
+
+        let too_long = match (from.max_length, to.and_then(|c| c.max_length)) {
+            (Some(from_length), Some(to_length)) => from_length > to_length,
+            (None, Some(_)) => matches!(kind, ColumnKind::Text | ColumnKind::Binary),
+            _ => false,
+        };
+        let copied_as_text =
+            kind == ColumnKind::Text && (from_kind != ColumnKind::Text || !dialect::is_character_type(&from.data_type));
+
+        let message = if too_long {
+            to.and_then(|c| c.max_length)
+                .map(|length| format!("Values longer than {} characters will not fit", length))
+        } else if copied_as_text && to.is_none() {
+            Some(format!("No matching type on {:?}; copied as text", db_type))
+        } else if from_kind != kind {
+            Some(format!("Values are converted from {:?} to {:?}", from_kind, kind))
+        } else if kind == ColumnKind::Timestamp && matches!(db_type, DatabaseType::MySQL) {
+            Some("MySQL does not keep time zone offsets".to_string())
+        } else {
+            None
+        };
+
+        if let Some(message) = message {
+            mismatches.push(ColumnMismatch {
+                column: name.clone(),
+                source_type: Some(from.data_type.clone()),
+                target_type: Some(to_type),
+                message,
+            });
+        }
+    }
+
+    mismatches
+}
+
+/// PostgreSQL columns are read as text: its typed decoding drops values such
+/// as `numeric` and arrays, while the text form parses into any kind.
+fn select_sql(db_type: &DatabaseType, table: &str, columns: &[String], filter: Option<&str>) -> String {
+    let list: Vec<String> = columns
+        .iter()
+        .map(|c| {
+            let quoted = dialect::quote_ident(db_type, c);
+            match db_type {
+                DatabaseType::PostgreSQL => format!("{}::text AS {}", quoted, quoted),
+                _ => quoted,
+            }
+        })
+        .collect();
+    let mut sql = format!("SELECT {} FROM {}", list.join(", "), dialect::quote_table(db_type, table));
+    if let Some(filter) = filter.map(str::trim).filter(|f| !f.is_empty()) {
+        sql.push_str(&format!(" WHERE {}", filter));
+    }
+    sql
+}
+
+/// Converts streamed source rows to the target kinds and queues them.
+struct ChannelSink {
+    sender: SyncSender<RowMessage>,
+    columns: Vec<String>,
+    sources: Vec<usize>,
+    kinds: Vec<ColumnKind>,
+    rows: u64,
+}
+
+impl RowSink for ChannelSink {
+    fn begin(&mut self, _columns: &[ResultColumn]) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn write_row(&mut self, values: &[serde_json::Value]) -> Result<(), String> {
+        self.rows += 1;
+        let converted: Result<Vec<SqlValue>, String> = self
+            .sources
+            .iter()
+            .zip(&self.columns)
+            .zip(&self.kinds)
+            .map(|((&idx, column), &kind)| {
+                let value = values.get(idx).unwrap_or(&serde_json::Value::Null);
+                // JSON documents arrive as text; keep them from being quoted again
+                let parsed = match (kind, value) {
+                    (ColumnKind::Json, serde_json::Value::String(text)) => serde_json::from_str(text).ok(),
+                    _ => None,
+                };
+                infer::parse_json_value(kind, parsed.as_ref().unwrap_or(value))
+                    .map_err(|e| format!("Column \"{}\": {}", column, e))
+            })
+            .collect();
+        self.sender
+            .send(Ok(Some(SourceRow { line: self.rows, values: converted })))
+            .map_err(|_| "Copy stopped".to_string())
+    }
+
+    fn finish(&mut self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// Rows queued by `ChannelSink`, ending with `Ok(None)` or the read error.
+struct ChannelSource {
+    receiver: Receiver<RowMessage>,
+}
+
+impl RowSource for ChannelSource {
+    fn next_row(&mut self) -> Result<Option<SourceRow>, String> {
+        tokio::task::block_in_place(|| self.receiver.recv())
+            .unwrap_or_else(|_| Err("Source reader stopped unexpectedly".to_string()))
+    }
+}
@@ -18,7 +18,7 @@ import {
     ArrowUpDown
 } from 'lucide-react';
 import { useConnectionStore } from './stores/connectionStore';
-import { executeQuery, getTableData, getTables, connectDatabase } from './lib/tauri';
+import { executeQuery, getTableData, getTables, connectDatabase, getSecretStoreStatus, profileLoadError, SecretStoreStatus, ConfirmationRequired, ConnectionState, onConnectionStatus } from './lib/tauri';
 import { QueryResult } from './lib/tauri';
 import { SettingsManager } from './lib/settingsManager';
 import { SavedConnection } from './lib/storage';
@@ -31,6 +31,8 @@ import { ResultsPanel } from './components/Results/ResultsPanel';
 import { SettingsModal } from './components/Settings/SettingsModal';
 import { SavedQueriesModal } from './components/SavedQueries/SavedQueriesModal';
 import { ManageConnectionsModal } from './components/Connection/ManageConnectionsModal';
+import { UnlockSecretsModal } from './components/Connection/UnlockSecretsModal';
+import { ConfirmDestructiveModal } from './components/Results/ConfirmDestructiveModal';
 import { StructureView } from './components/Structure/StructureView';
 import { TitleBar } from './components/UI/TitleBar';
 import { DatabaseIcon } from './components/UI/DatabaseIcon';
@@ -39,6 +41,13 @@ import './index.css';
 
 const appVersion = '0.2.3';
 
+const CONNECTION_STATE_LABELS: Record<ConnectionState, string> = {
+    connected: 'Connected',
+    degraded: 'Slow connection',
+    reconnecting: 'Reconnecting…',
+    lost: 'Connection lost',
+};
+
 interface FkViewContext {
     id: string;
     tableName: string;
@@ -55,6 +64,7 @@ function App() {
         activeConnectionId,
         setActiveConnection,
         addConnection,
+        setConnectionStatus,
         queryTabs,
         activeTabId,
         addQueryTab,
@@ -88,6 +98,14 @@ function App() {
     const [showSettingsModal, setShowSettingsModal] = useState(false);
     const [showConnectionDropdown, setShowConnectionDropdown] = useState(false);
     const [showSavedQueriesModal, setShowSavedQueriesModal] = useState(false);
+    const [lockedSecrets, setLockedSecrets] = useState<SecretStoreStatus | null>(null);
+    // Destructive query held back by a protected connection
+    const [pendingDestructive, setPendingDestructive] = useState<{
+        tabId: string;
+        connectionId: string;
+        sql: string;
+        confirmation: ConfirmationRequired;
+    } | null>(null);
     const [showSaveQueryDialog, setShowSaveQueryDialog] = useState(false);
     const [saveQueryName, setSaveQueryName] = useState('');
     const [settings, setSettings] = useState(SettingsManager.getSettings());
@@ -125,6 +143,33 @@ function App() {
         loadSavedConnections();
     }, [loadSavedConnections]);
 
+    // An unreadable profiles file is moved aside and the list starts empty
+    useEffect(() => {
+        profileLoadError()
+            .then((message) => message && alert(`Saved connections could not be loaded: ${message}`))
+            .catch((err) => console.error('Failed to check the saved connections:', err));
+    }, []);
+
+    // Without a system keychain, saved passwords wait for the master password
+    useEffect(() => {
+        getSecretStoreStatus()
+            .then((status) => setLockedSecrets(status.unlocked ? null : status))
+            .catch((err) => console.error('Failed to check the secret store:', err));
+    }, []);
+
+    // Health checks report when a connection drops or comes back
+    useEffect(() => {
+        const unlisten = onConnectionStatus((status) => {
+            if (status.message) {
+                console.warn(`[Connection] ${status.connection_id} is ${status.state}: ${status.message}`);
+            }
+            setConnectionStatus(status.connection_id, status.state);
+        });
+        return () => {
+            unlisten.then(f => f());
+        };
+    }, [setConnectionStatus]);
+
     // Load saved queries on startup
     useEffect(() => {
         loadSavedQueries();
@@ -488,6 +533,7 @@ CREATE TABLE new_table (
     }, []);
 
     const activeTab = queryTabs.find((t) => t.id === activeTabId);
+    const activeStatus = connections.find(c => c.id === activeConnectionId)?.status ?? 'connected';
 
     const closeFkSidebar = () => {
         setFkStack([]);
@@ -579,12 +625,35 @@ CREATE TABLE new_table (
 
         try {
             const queryResult = await executeQuery(connectionIdToUse, activeTab.sql);
+            if (queryResult.confirmation_required) {
+                updateTabResult(activeTab.id, null, false, null);
+                setPendingDestructive({
+                    tabId: activeTab.id,
+                    connectionId: connectionIdToUse,
+                    sql: activeTab.sql,
+                    confirmation: queryResult.confirmation_required,
+                });
+                return;
+            }
             updateTabResult(activeTab.id, queryResult, false, null);
         } catch (err) {
             updateTabResult(activeTab.id, null, false, String(err));
         }
     };
 
+    const handleConfirmDestructive = async (token: string) => {
+        if (!pendingDestructive) return;
+        const { tabId, connectionId, sql } = pendingDestructive;
+        setPendingDestructive(null);
+        updateTabResult(tabId, null, true, null);
+        try {
+            const queryResult = await executeQuery(connectionId, sql, token);
+            updateTabResult(tabId, queryResult, false, null);
+        } catch (err) {
+            updateTabResult(tabId, null, false, String(err));
+        }
+    };
+
     const handleTableDataRequest = async (table: string) => {
         if (!activeConnectionId) return;
 
@@ -729,13 +798,13 @@ CREATE TABLE new_table (
                     <div className="connection-selector-content">
                         {activeConnectionId ? (
                             <>
-                                <div className="connection-status connected" />
+                                <div className={`connection-status ${activeStatus}`} />
                                 <DatabaseIcon
                                     dbType={connections.find(c => c.id === activeConnectionId)?.config.db_type || 'PostgreSQL'}
                                     size={20}
                                 />
                                 <div className="connection-info">
-                                    <span className="connection-label">Connected</span>
+                                    <span className="connection-label">{CONNECTION_STATE_LABELS[activeStatus]}</span>
                                     <span className="connection-name">
                                         {connections.find(c => c.id === activeConnectionId)?.config.name || 'Database'}
                                     </span>
@@ -1328,6 +1397,26 @@ CREATE TABLE new_table (
                 </div>
             </Modal>
 
+            {pendingDestructive && (
+                <ConfirmDestructiveModal
+                    confirmation={pendingDestructive.confirmation}
+                    connectionName={connections.find(c => c.id === pendingDestructive.connectionId)?.config.name}
+                    onConfirm={handleConfirmDestructive}
+                    onClose={() => setPendingDestructive(null)}
+                />
+            )}
+
+            {lockedSecrets && (
+                <UnlockSecretsModal
+                    status={lockedSecrets}
+                    onUnlock={() => {
+                        setLockedSecrets(null);
+                        loadSavedConnections();
+                    }}
+                    onClose={() => setLockedSecrets(null)}
+                />
+            )}
+
             <SavedQueriesModal
                 isOpen={showSavedQueriesModal}
                 onClose={() => setShowSavedQueriesModal(false)}
@@ -1,10 +1,18 @@
 import { useState } from 'react';
-import { ConnectionConfig, testConnection, connectDatabase, getTables } from '../../lib/tauri';
+import { ConnectionConfig, PoolConfig, SshTunnelConfig, TlsConfig, testConnection, connectDatabase, getTables, parseConnectionUrl } from '../../lib/tauri';
 import { useConnectionStore } from '../../stores/connectionStore';
-import { Database, Loader2, CheckCircle, XCircle, Server, User, Lock, HelpCircle, FolderOpen } from 'lucide-react';
+import { Database, Loader2, CheckCircle, XCircle, Server, User, Lock, HelpCircle, FolderOpen, Link } from 'lucide-react';
 import { DatabaseIcon } from '../UI/DatabaseIcon';
 import { open } from '@tauri-apps/plugin-dialog';
 
+const POOL_FIELDS: { key: keyof PoolConfig; label: string }[] = [
+    { key: 'max_connections', label: 'Max' },
+    { key: 'min_connections', label: 'Min' },
+    { key: 'acquire_timeout_secs', label: 'Acquire' },
+    { key: 'idle_timeout_secs', label: 'Idle' },
+    { key: 'max_lifetime_secs', label: 'Lifetime' },
+];
+
 interface ConnectionFormProps {
     onConnect?: () => void;
     onCancel?: () => void;
@@ -28,12 +36,17 @@ export const ConnectionForm: React.FC<ConnectionFormProps> = ({ onConnect, onCan
     const [testResult, setTestResult] = useState<{ success: boolean; message: string } | null>(null);
     const [connecting, setConnectingLocal] = useState(false);
     const [showAdvanced, setShowAdvanced] = useState(false);
+    const [urlError, setUrlError] = useState<string | null>(null);
+
+    // Fields a URL decides; editing one of them drops the URL
+    const urlFields = ['db_type', 'host', 'port', 'username'];
 
     const handleChange = (e: React.ChangeEvent<HTMLInputElement | HTMLSelectElement>) => {
         const { name, value } = e.target;
         setConfig((prev) => ({
             ...prev,
             [name]: name === 'port' ? parseInt(value) || 0 : value,
+            url: urlFields.includes(name) ? undefined : prev.url,
         }));
 
         if (name === 'db_type') {
@@ -51,6 +64,74 @@ export const ConnectionForm: React.FC<ConnectionFormProps> = ({ onConnect, onCan
         }
     };
 
+    const updateSsh = (patch: Partial<SshTunnelConfig>) => {
+        setConfig((prev) => ({
+            ...prev,
+            ssh_tunnel: prev.ssh_tunnel && { ...prev.ssh_tunnel, ...patch },
+        }));
+    };
+
+    const toggleSsh = (enabled: boolean) => {
+        setConfig((prev) => ({
+            ...prev,
+            ssh_tunnel: enabled
+                ? { host: '', port: 22, username: '', auth: 'agent' }
+                : undefined,
+        }));
+    };
+
+    const updateTls = (patch: Partial<TlsConfig>) => {
+        setConfig((prev) => ({
+            ...prev,
+            tls: { mode: 'prefer', ...prev.tls, ...patch },
+            url: undefined,
+        }));
+    };
+
+    const handleUrlBlur = async () => {
+        const url = config.url?.trim();
+        setUrlError(null);
+        if (!url) {
+            setConfig((prev) => ({ ...prev, url: undefined }));
+            return;
+        }
+
+        try {
+            const parsed = await parseConnectionUrl(url);
+            setConfig((prev) => ({
+                ...parsed,
+                name: prev.name,
+                display_timezone: prev.display_timezone,
+                ssh_tunnel: prev.ssh_tunnel,
+                read_only: prev.read_only,
+                protected: prev.protected,
+                pool: prev.pool,
+                init_sql: prev.init_sql,
+                password: parsed.password || prev.password,
+                url,
+            }));
+        } catch (err) {
+            setUrlError(String(err));
+        }
+    };
+
+    const updatePool = (key: keyof PoolConfig, value: string) => {
+        setConfig((prev) => {
+            const pool: PoolConfig = { ...prev.pool, [key]: value === '' ? undefined : Math.max(0, parseInt(value, 10) || 0) };
+            const isEmpty = Object.values(pool).every((v) => v === undefined);
+            return { ...prev, pool: isEmpty ? undefined : pool };
+        });
+    };
+
+    const updateParameters = (text: string) => {
+        const parameters: Record<string, string> = {};
+        for (const line of text.split('\n')) {
+            const idx = line.indexOf('=');
+            if (idx > 0) parameters[line.slice(0, idx).trim()] = line.slice(idx + 1).trim();
+        }
+        setConfig((prev) => ({ ...prev, parameters, url: undefined }));
+    };
+
     const handleSelectFile = async () => {
         try {
             const selected = await open({
@@ -169,7 +250,8 @@ export const ConnectionForm: React.FC<ConnectionFormProps> = ({ onConnect, onCan
                             onClick={() => setConfig(prev => ({
                                 ...prev,
                                 db_type: opt.value,
-                                port: opt.port
+                                port: opt.port,
+                                url: undefined
                             }))}
                         >
                             <span className="db-type-icon">
@@ -197,6 +279,27 @@ export const ConnectionForm: React.FC<ConnectionFormProps> = ({ onConnect, onCan
                 />
             </div>
 
+            {/* Connection URL - fills in the fields below */}
+            <div className="form-group">
+                <label className="form-label">
+                    <Link size={14} />
+                    Connection URL
+                    <span className="form-hint optional">
+                        Optional - postgres://, mysql://, sqlite:, key=value DSN or an ADO.NET string
+                    </span>
+                </label>
+                <input
+                    type="text"
+                    name="url"
+                    className="form-input"
+                    placeholder="postgres://user@host:5432/db?sslmode=require"
+                    value={config.url || ''}
+                    onChange={handleChange}
+                    onBlur={handleUrlBlur}
+                />
+                {urlError && <span className="form-hint error">{urlError}</span>}
+            </div>
+
             {/* Server Details - Not needed for SQLite */}
             {config.db_type !== 'SQLite' && (
                 <div className="form-section">
@@ -325,9 +428,295 @@ export const ConnectionForm: React.FC<ConnectionFormProps> = ({ onConnect, onCan
                     <div className="advanced-info">
                         <p>💡 <strong>Tip:</strong> Leave the database field empty to connect to the server and browse available databases.</p>
                     </div>
+                    <div className="form-group">
+                        <label className="form-label">
+                            Display Timezone
+                            <span className="form-hint optional">
+                                UTC, local, +05:30 or America/Lima - leave empty to keep the session timezone
+                            </span>
+                        </label>
+                        <input
+                            type="text"
+                            name="display_timezone"
+                            className="form-input"
+                            placeholder="Session timezone"
+                            value={config.display_timezone || ''}
+                            onChange={handleChange}
+                        />
+                    </div>
+                    {(config.db_type === 'PostgreSQL' || config.db_type === 'MySQL') && (
+                        <div className="form-group">
+                            <label className="form-label">
+                                Unix Socket
+                                <span className="form-hint optional">
+                                    {config.db_type === 'PostgreSQL'
+                                        ? 'Socket directory, used instead of host and port'
+                                        : 'Socket file, used instead of host and port'}
+                                </span>
+                            </label>
+                            <input
+                                type="text"
+                                className="form-input"
+                                placeholder={config.db_type === 'PostgreSQL' ? '/var/run/postgresql' : '/var/run/mysqld/mysqld.sock'}
+                                value={config.socket || ''}
+                                onChange={(e) => setConfig((prev) => ({ ...prev, socket: e.target.value || undefined, url: undefined }))}
+                            />
+                        </div>
+                    )}
+                    <div className="form-group">
+                        <label className="form-label">
+                            Connection Parameters
+                            <span className="form-hint optional">
+                                One key=value per line, e.g. application_name=datablaze
+                            </span>
+                        </label>
+                        <textarea
+                            className="form-input"
+                            rows={3}
+                            defaultValue={Object.entries(config.parameters || {}).map(([k, v]) => `${k}=${v}`).join('\n')}
+                            key={JSON.stringify(config.parameters || {})}
+                            onBlur={(e) => updateParameters(e.target.value)}
+                        />
+                    </div>
+                    <div className="form-group">
+                        <label className="form-label">
+                            Session Init SQL
+                            <span className="form-hint optional">
+                                Run on every new connection, e.g. {config.db_type === 'SQLite' ? 'PRAGMA foreign_keys = ON' : config.db_type === 'MySQL' ? 'SET NAMES utf8mb4' : 'SET search_path TO app'}
+                            </span>
+                        </label>
+                        <textarea
+                            className="form-input"
+                            rows={2}
+                            value={config.init_sql || ''}
+                            onChange={(e) => setConfig((prev) => ({ ...prev, init_sql: e.target.value || undefined }))}
+                        />
+                    </div>
+                    <div className="form-group">
+                        <label className="form-label">
+                            Connection Pool
+                            <span className="form-hint optional">
+                                Leave empty for the defaults; timeouts in seconds, 0 turns idle timeout and lifetime off
+                            </span>
+                        </label>
+                        <div className="form-row">
+                            {POOL_FIELDS.map(({ key, label }) => (
+                                <div key={key} className="form-group">
+                                    <label className="form-label">{label}</label>
+                                    <input
+                                        type="number"
+                                        min={0}
+                                        className="form-input"
+                                        placeholder="Default"
+                                        value={config.pool?.[key] ?? ''}
+                                        onChange={(e) => updatePool(key, e.target.value)}
+                                    />
+                                </div>
+                            ))}
+                        </div>
+                    </div>
+                    {config.db_type !== 'SQLite' && (
+                        <div className="form-group">
+                            <label className="form-label">
+                                <Lock size={14} />
+                                TLS / SSL
+                            </label>
+                            <select
+                                className="form-input"
+                                value={config.tls?.mode || 'prefer'}
+                                onChange={(e) => updateTls({ mode: e.target.value as TlsConfig['mode'] })}
+                            >
+                                <option value="disable">Disable</option>
+                                <option value="prefer">Prefer</option>
+                                <option value="require">Require</option>
+                                <option value="verify_ca">Verify CA</option>
+                                <option value="verify_full">Verify CA and host name</option>
+                            </select>
+                            {(config.tls?.mode === 'verify_ca' || config.tls?.mode === 'verify_full') && (
+                                <input
+                                    type="text"
+                                    className="form-input"
+                                    placeholder="CA certificate file (default: system roots)"
+                                    value={config.tls?.ca_cert_path || ''}
+                                    onChange={(e) => updateTls({ ca_cert_path: e.target.value || undefined })}
+                                />
+                            )}
+                            {config.db_type === 'SQLServer' ? (
+                                <input
+                                    type="text"
+                                    className="form-input"
+                                    placeholder="Certificate host name (default: server host)"
+                                    value={config.tls?.server_name || ''}
+                                    onChange={(e) => updateTls({ server_name: e.target.value || undefined })}
+                                />
+                            ) : (
+                                <div className="form-row">
+                                    <div className="form-group flex-1">
+                                        <input
+                                            type="text"
+                                            className="form-input"
+                                            placeholder="Client certificate file"
+                                            value={config.tls?.client_cert_path || ''}
+                                            onChange={(e) => updateTls({ client_cert_path: e.target.value || undefined })}
+                                        />
+                                    </div>
+                                    <div className="form-group flex-1">
+                                        <input
+                                            type="text"
+                                            className="form-input"
+                                            placeholder="Client key file"
+                                            value={config.tls?.client_key_path || ''}
+                                            onChange={(e) => updateTls({ client_key_path: e.target.value || undefined })}
+                                        />
+                                    </div>
+                                </div>
+                            )}
+                        </div>
+                    )}
+                    {config.db_type !== 'SQLite' && (
+                        <div className="form-group">
+                            <label className="form-label">
+                                <input
+                                    type="checkbox"
+                                    checked={!!config.ssh_tunnel}
+                                    onChange={(e) => toggleSsh(e.target.checked)}
+                                />
+                                SSH Tunnel
+                                <span className="form-hint optional">
+                                    Connect through a bastion host
+                                </span>
+                            </label>
+                            {config.ssh_tunnel && (
+                                <>
+                                    <div className="form-row">
+                                        <div className="form-group flex-2">
+                                            <input
+                                                type="text"
+                                                className="form-input"
+                                                placeholder="SSH host"
+                                                value={config.ssh_tunnel.host}
+                                                onChange={(e) => updateSsh({ host: e.target.value })}
+                                            />
+                                        </div>
+                                        <div className="form-group flex-1">
+                                            <input
+                                                type="number"
+                                                className="form-input"
+                                                placeholder="Port"
+                                                value={config.ssh_tunnel.port}
+                                                onChange={(e) => updateSsh({ port: parseInt(e.target.value) || 0 })}
+                                            />
+                                        </div>
+                                    </div>
+                                    <div className="form-row">
+                                        <div className="form-group flex-1">
+                                            <input
+                                                type="text"
+                                                className="form-input"
+                                                placeholder="SSH user"
+                                                value={config.ssh_tunnel.username}
+                                                onChange={(e) => updateSsh({ username: e.target.value })}
+                                            />
+                                        </div>
+                                        <div className="form-group flex-1">
+                                            <select
+                                                className="form-input"
+                                                value={config.ssh_tunnel.auth}
+                                                onChange={(e) => updateSsh({ auth: e.target.value as SshTunnelConfig['auth'] })}
+                                            >
+                                                <option value="agent">SSH agent</option>
+                                                <option value="private_key">Private key</option>
+                                                <option value="password">Password</option>
+                                            </select>
+                                        </div>
+                                    </div>
+                                    {config.ssh_tunnel.auth === 'password' && (
+                                        <input
+                                            type="password"
+                                            className="form-input"
+                                            placeholder="SSH password"
+                                            value={config.ssh_tunnel.password || ''}
+                                            onChange={(e) => updateSsh({ password: e.target.value })}
+                                        />
+                                    )}
+                                    {config.ssh_tunnel.auth === 'private_key' && (
+                                        <div className="form-row">
+                                            <div className="form-group flex-2">
+                                                <input
+                                                    type="text"
+                                                    className="form-input"
+                                                    placeholder="~/.ssh/id_ed25519"
+                                                    value={config.ssh_tunnel.private_key_path || ''}
+                                                    onChange={(e) => updateSsh({ private_key_path: e.target.value })}
+                                                />
+                                            </div>
+                                            <div className="form-group flex-1">
+                                                <input
+                                                    type="password"
+                                                    className="form-input"
+                                                    placeholder="Passphrase"
+                                                    value={config.ssh_tunnel.passphrase || ''}
+                                                    onChange={(e) => updateSsh({ passphrase: e.target.value })}
+                                                />
+                                            </div>
+                                        </div>
+                                    )}
+                                    {config.ssh_tunnel.auth === 'private_key' && (
+                                        <textarea
+                                            className="form-input"
+                                            rows={3}
+                                            placeholder="Or paste the private key - it is kept in the system keychain"
+                                            value={config.ssh_tunnel.private_key || ''}
+                                            onChange={(e) => updateSsh({ private_key: e.target.value || undefined })}
+                                        />
+                                    )}
+                                    <input
+                                        type="text"
+                                        className="form-input"
+                                        placeholder="known_hosts file (default ~/.ssh/known_hosts)"
+                                        value={config.ssh_tunnel.known_hosts_path || ''}
+                                        onChange={(e) => updateSsh({ known_hosts_path: e.target.value || undefined })}
+                                    />
+                                    <label className="form-label">
+                                        <input
+                                            type="checkbox"
+                                            checked={!!config.ssh_tunnel.accept_new_host_key}
+                                            onChange={(e) => updateSsh({ accept_new_host_key: e.target.checked })}
+                                        />
+                                        Trust the host key on first connection
+                                    </label>
+                                </>
+                            )}
+                        </div>
+                    )}
                 </div>
             )}
 
+            <div className="form-group">
+                <label className="form-label">
+                    <input
+                        type="checkbox"
+                        checked={!!config.read_only}
+                        onChange={(e) => setConfig((prev) => ({ ...prev, read_only: e.target.checked || undefined }))}
+                    />
+                    Read-only
+                    <span className="form-hint optional">
+                        Reject statements that change data or schema
+                    </span>
+                </label>
+                <label className="form-label">
+                    <input
+                        type="checkbox"
+                        checked={!!config.protected}
+                        onChange={(e) => setConfig((prev) => ({ ...prev, protected: e.target.checked || undefined }))}
+                    />
+                    Protected
+                    <span className="form-hint optional">
+                        Confirm DROP, TRUNCATE and DELETE or UPDATE without WHERE before running them
+                    </span>
+                </label>
+            </div>
+
             {/* Test Result */}
             {testResult && (
                 <div className={`connection-test-result ${testResult.success ? 'success' : 'error'}`}>
@@ -1,10 +1,36 @@
 import React, { useState, useEffect } from 'react';
-import { X, Database, Trash2, Edit3, Plus, Check, XCircle, Loader2, Server, ExternalLink, FolderOpen } from 'lucide-react';
+import { X, Database, Trash2, Edit3, Plus, Check, XCircle, Loader2, Server, ExternalLink, FolderOpen, Folder, Tag, Upload, Download, FileInput } from 'lucide-react';
 import { useConnectionStore } from '../../stores/connectionStore';
-import { SavedConnection, updateSavedConnection } from '../../lib/storage';
-import { ConnectionConfig, testConnection, connectDatabase, getTables } from '../../lib/tauri';
+import { SavedConnection, ProfileDetails, updateSavedConnection } from '../../lib/storage';
+import {
+    ConnectionConfig,
+    ProfileEnvironment,
+    ProfileFormat,
+    ProfileImportResult,
+    testConnection,
+    connectDatabase,
+    getTables,
+    exportProfiles,
+    importProfiles,
+    importClientProfiles,
+} from '../../lib/tauri';
 import { DatabaseIcon } from '../UI/DatabaseIcon';
-import { open } from '@tauri-apps/plugin-dialog';
+import { open, save } from '@tauri-apps/plugin-dialog';
+
+const ENVIRONMENT_STYLES: Record<ProfileEnvironment, { label: string; bg: string; color: string }> = {
+    dev: { label: 'Dev', bg: 'rgba(34, 197, 94, 0.15)', color: 'var(--success)' },
+    staging: { label: 'Staging', bg: 'rgba(234, 179, 8, 0.15)', color: '#eab308' },
+    prod: { label: 'Prod', bg: 'rgba(239, 68, 68, 0.15)', color: 'var(--error)' },
+};
+
+const PROFILE_FILE_FILTERS = [
+    { name: 'JSON', extensions: ['json'] },
+    { name: 'TOML', extensions: ['toml'] },
+];
+
+function profileFormat(path: string): ProfileFormat {
+    return path.toLowerCase().endsWith('.toml') ? 'toml' : 'json';
+}
 
 interface ManageConnectionsModalProps {
     isOpen: boolean;
@@ -38,6 +64,9 @@ export const ManageConnectionsModal: React.FC<ManageConnectionsModalProps> = ({
     const [testResult, setTestResult] = useState<{ success: boolean; message: string } | null>(null);
     const [connecting, setConnecting] = useState(false);
     const [deleteConfirm, setDeleteConfirm] = useState<string | null>(null);
+    const [editDetails, setEditDetails] = useState<ProfileDetails>({});
+    const [editTags, setEditTags] = useState('');
+    const [transferResult, setTransferResult] = useState<{ success: boolean; message: string } | null>(null);
 
     useEffect(() => {
         if (isOpen) {
@@ -47,6 +76,7 @@ export const ManageConnectionsModal: React.FC<ManageConnectionsModalProps> = ({
             setEditConfig(null);
             setTestResult(null);
             setDeleteConfirm(null);
+            setTransferResult(null);
         }
     }, [isOpen, loadSavedConnections]);
 
@@ -55,6 +85,8 @@ export const ManageConnectionsModal: React.FC<ManageConnectionsModalProps> = ({
     const handleEditClick = (conn: SavedConnection) => {
         setEditingConnection(conn);
         setEditConfig({ ...conn.config });
+        setEditDetails({ folder: conn.folder, color: conn.color, environment: conn.environment });
+        setEditTags((conn.tags || []).join(', '));
         setViewMode('edit');
         setTestResult(null);
     };
@@ -107,14 +139,58 @@ export const ManageConnectionsModal: React.FC<ManageConnectionsModalProps> = ({
         }
     };
 
-    const handleSaveChanges = () => {
+    const handleSaveChanges = async () => {
         if (!editingConnection || !editConfig) return;
 
-        updateSavedConnection(editingConnection.id, editConfig, true);
+        try {
+            const tags = editTags.split(',').map(t => t.trim()).filter(Boolean);
+            await updateSavedConnection(editingConnection.id, editConfig, editingConnection.save_password, { ...editDetails, tags });
+        } catch (err) {
+            setTestResult({ success: false, message: String(err) });
+            return;
+        }
         loadSavedConnections();
         handleCancelEdit();
     };
 
+    const reportImport = (result: ProfileImportResult) => {
+        const count = `${result.imported.length} connection${result.imported.length !== 1 ? 's' : ''} imported`;
+        setTransferResult({
+            success: result.skipped.length === 0,
+            message: result.skipped.length === 0 ? count : `${count}; ${result.skipped.join('; ')}`,
+        });
+        loadSavedConnections();
+    };
+
+    const handleExport = async () => {
+        try {
+            const path = await save({ defaultPath: 'connections.json', filters: PROFILE_FILE_FILTERS });
+            if (!path) return;
+            const count = await exportProfiles(path, profileFormat(path));
+            setTransferResult({ success: true, message: `${count} connection${count !== 1 ? 's' : ''} exported without passwords` });
+        } catch (err) {
+            setTransferResult({ success: false, message: String(err) });
+        }
+    };
+
+    const handleImport = async () => {
+        try {
+            const path = await open({ multiple: false, filters: PROFILE_FILE_FILTERS });
+            if (!path || typeof path !== 'string') return;
+            reportImport(await importProfiles(path, profileFormat(path)));
+        } catch (err) {
+            setTransferResult({ success: false, message: String(err) });
+        }
+    };
+
+    const handleImportClients = async () => {
+        try {
+            reportImport(await importClientProfiles());
+        } catch (err) {
+            setTransferResult({ success: false, message: String(err) });
+        }
+    };
+
     const handleDelete = (id: string) => {
         // Remove from saved connections
         removeSavedConnectionById(id);
@@ -218,9 +294,26 @@ export const ManageConnectionsModal: React.FC<ManageConnectionsModalProps> = ({
                                     </div>
                                 ) : (
                                     <div style={{ display: 'flex', flexDirection: 'column', gap: '8px' }}>
-                                        {savedConnections.map((conn) => (
+                                        {[...savedConnections]
+                                            .sort((a, b) => (a.folder || '').localeCompare(b.folder || ''))
+                                            .map((conn, i, sorted) => (
+                                            <React.Fragment key={conn.id}>
+                                            {(conn.folder || '') !== (sorted[i - 1]?.folder || '') && (
+                                                <div
+                                                    style={{
+                                                        display: 'flex',
+                                                        alignItems: 'center',
+                                                        gap: '6px',
+                                                        fontSize: '12px',
+                                                        color: 'var(--text-muted)',
+                                                        marginTop: i > 0 ? '8px' : 0,
+                                                    }}
+                                                >
+                                                    <Folder size={14} />
+                                                    {conn.folder}
+                                                </div>
+                                            )}
                                             <div
-                                                key={conn.id}
                                                 className="connection-card"
                                                 style={{
                                                     display: 'flex',
@@ -233,6 +326,7 @@ export const ManageConnectionsModal: React.FC<ManageConnectionsModalProps> = ({
                                                     border: isActive(conn.id)
                                                         ? '1px solid var(--accent-primary)'
                                                         : '1px solid var(--border-color)',
+                                                    borderLeft: conn.color ? `4px solid ${conn.color}` : undefined,
                                                     gap: '12px',
                                                     transition: 'all 0.2s ease',
                                                 }}
@@ -277,6 +371,51 @@ export const ManageConnectionsModal: React.FC<ManageConnectionsModalProps> = ({
                                                             {conn.config.name || 'Unnamed Connection'}
                                                         </span>
                                                         {getDatabaseTypeBadge(conn.config.db_type)}
+                                                        {conn.environment && (
+                                                            <span
+                                                                style={{
+                                                                    padding: '2px 8px',
+                                                                    borderRadius: '4px',
+                                                                    fontSize: '10px',
+                                                                    fontWeight: 600,
+                                                                    background: ENVIRONMENT_STYLES[conn.environment].bg,
+                                                                    color: ENVIRONMENT_STYLES[conn.environment].color,
+                                                                    textTransform: 'uppercase',
+                                                                }}
+                                                            >
+                                                                {ENVIRONMENT_STYLES[conn.environment].label}
+                                                            </span>
+                                                        )}
+                                                        {conn.config.read_only && (
+                                                            <span
+                                                                style={{
+                                                                    padding: '2px 8px',
+                                                                    borderRadius: '4px',
+                                                                    fontSize: '10px',
+                                                                    fontWeight: 600,
+                                                                    background: 'var(--bg-secondary)',
+                                                                    color: 'var(--text-muted)',
+                                                                    textTransform: 'uppercase',
+                                                                }}
+                                                            >
+                                                                Read-only
+                                                            </span>
+                                                        )}
+                                                        {conn.config.protected && (
+                                                            <span
+                                                                style={{
+                                                                    padding: '2px 8px',
+                                                                    borderRadius: '4px',
+                                                                    fontSize: '10px',
+                                                                    fontWeight: 600,
+                                                                    background: 'var(--bg-secondary)',
+                                                                    color: 'var(--text-muted)',
+                                                                    textTransform: 'uppercase',
+                                                                }}
+                                                            >
+                                                                Protected
+                                                            </span>
+                                                        )}
                                                         {isConnected(conn.id) && (
                                                             <span
                                                                 style={{
@@ -304,6 +443,11 @@ export const ManageConnectionsModal: React.FC<ManageConnectionsModalProps> = ({
                                                     >
                                                         {conn.config.host}:{conn.config.port}/{conn.config.database} •{' '}
                                                         {conn.config.username}
+                                                        {conn.tags && conn.tags.length > 0 && (
+                                                            <>
+                                                                {' '}• <Tag size={10} /> {conn.tags.join(', ')}
+                                                            </>
+                                                        )}
                                                     </div>
                                                 </div>
 
@@ -365,32 +509,62 @@ export const ManageConnectionsModal: React.FC<ManageConnectionsModalProps> = ({
                                                     )}
                                                 </div>
                                             </div>
+                                            </React.Fragment>
                                         ))}
                                     </div>
                                 )}
                             </div>
 
-                            {/* Footer */}
-                            {savedConnections.length > 0 && (
+                            {transferResult && (
                                 <div
-                                    style={{
-                                        padding: '12px 16px',
-                                        borderTop: '1px solid var(--border-color)',
-                                        display: 'flex',
-                                        justifyContent: 'space-between',
-                                        alignItems: 'center',
-                                    }}
+                                    className={`connection-test-result ${transferResult.success ? 'success' : 'error'}`}
+                                    style={{ margin: '0 16px 12px' }}
                                 >
-                                    <span style={{ fontSize: '12px', color: 'var(--text-muted)' }}>
-                                        {savedConnections.length} connection{savedConnections.length !== 1 ? 's' : ''}{' '}
-                                        saved
-                                    </span>
+                                    {transferResult.success ? <Check size={18} /> : <XCircle size={18} />}
+                                    <span>{transferResult.message}</span>
+                                </div>
+                            )}
+
+                            {/* Footer */}
+                            <div
+                                style={{
+                                    padding: '12px 16px',
+                                    borderTop: '1px solid var(--border-color)',
+                                    display: 'flex',
+                                    justifyContent: 'space-between',
+                                    alignItems: 'center',
+                                    gap: '8px',
+                                }}
+                            >
+                                <span style={{ fontSize: '12px', color: 'var(--text-muted)', flex: 1 }}>
+                                    {savedConnections.length} connection{savedConnections.length !== 1 ? 's' : ''}{' '}
+                                    saved
+                                </span>
+                                <button
+                                    className="btn btn-ghost btn-icon"
+                                    onClick={handleImportClients}
+                                    title="Import from .pgpass, pg_service.conf and .my.cnf"
+                                >
+                                    <FileInput size={16} />
+                                </button>
+                                <button className="btn btn-ghost btn-icon" onClick={handleImport} title="Import from JSON or TOML">
+                                    <Upload size={16} />
+                                </button>
+                                <button
+                                    className="btn btn-ghost btn-icon"
+                                    onClick={handleExport}
+                                    title="Export without passwords"
+                                    disabled={savedConnections.length === 0}
+                                >
+                                    <Download size={16} />
+                                </button>
+                                {savedConnections.length > 0 && (
                                     <button className="btn btn-primary" onClick={onAddNew}>
                                         <Plus size={16} />
                                         Add Connection
                                     </button>
-                                </div>
-                            )}
+                                )}
+                            </div>
                         </div>
                     ) : (
                         /* Edit View */
@@ -587,7 +761,7 @@ export const ManageConnectionsModal: React.FC<ManageConnectionsModalProps> = ({
                                             type="password"
                                             name="password"
                                             className="form-input"
-                                            placeholder="Password"
+                                            placeholder={editConfig?.credential_id ? 'Saved - type to replace' : 'Password'}
                                             value={editConfig?.password || ''}
                                             onChange={handleConfigChange}
                                         />
@@ -595,6 +769,92 @@ export const ManageConnectionsModal: React.FC<ManageConnectionsModalProps> = ({
                                 </div>
                             </div>
 
+                            {/* Organisation */}
+                            <div className="form-section">
+                                <label className="form-label">
+                                    <Folder size={14} />
+                                    Organisation
+                                </label>
+                                <div className="form-row">
+                                    <div className="form-group flex-2">
+                                        <input
+                                            type="text"
+                                            className="form-input"
+                                            placeholder="Folder, e.g. Clients/Acme"
+                                            value={editDetails.folder || ''}
+                                            onChange={(e) => setEditDetails(prev => ({ ...prev, folder: e.target.value }))}
+                                        />
+                                    </div>
+                                    <div className="form-group flex-1">
+                                        <select
+                                            className="form-input"
+                                            value={editDetails.environment || ''}
+                                            onChange={(e) => setEditDetails(prev => ({
+                                                ...prev,
+                                                environment: (e.target.value || undefined) as ProfileEnvironment | undefined,
+                                            }))}
+                                        >
+                                            <option value="">No environment</option>
+                                            <option value="dev">Dev</option>
+                                            <option value="staging">Staging</option>
+                                            <option value="prod">Prod</option>
+                                        </select>
+                                    </div>
+                                </div>
+                                <div className="form-row">
+                                    <div className="form-group flex-2">
+                                        <input
+                                            type="text"
+                                            className="form-input"
+                                            placeholder="Tags, separated by commas"
+                                            value={editTags}
+                                            onChange={(e) => setEditTags(e.target.value)}
+                                        />
+                                    </div>
+                                    <div className="form-group flex-1" style={{ display: 'flex', gap: '8px', alignItems: 'center' }}>
+                                        <input
+                                            type="color"
+                                            className="form-input"
+                                            title="Label colour"
+                                            value={editDetails.color || '#5294e2'}
+                                            onChange={(e) => setEditDetails(prev => ({ ...prev, color: e.target.value }))}
+                                        />
+                                        {editDetails.color && (
+                                            <button
+                                                type="button"
+                                                className="btn btn-ghost btn-icon"
+                                                title="Remove colour"
+                                                onClick={() => setEditDetails(prev => ({ ...prev, color: undefined }))}
+                                            >
+                                                <X size={14} />
+                                            </button>
+                                        )}
+                                    </div>
+                                </div>
+                                <label className="form-label">
+                                    <input
+                                        type="checkbox"
+                                        checked={!!editConfig?.read_only}
+                                        onChange={(e) => setEditConfig(prev => prev ? { ...prev, read_only: e.target.checked || undefined } : prev)}
+                                    />
+                                    Read-only
+                                    <span className="form-hint optional">
+                                        Reject statements that change data or schema
+                                    </span>
+                                </label>
+                                <label className="form-label">
+                                    <input
+                                        type="checkbox"
+                                        checked={!!editConfig?.protected}
+                                        onChange={(e) => setEditConfig(prev => prev ? { ...prev, protected: e.target.checked || undefined } : prev)}
+                                    />
+                                    Protected
+                                    <span className="form-hint optional">
+                                        Confirm DROP, TRUNCATE and DELETE or UPDATE without WHERE before running them
+                                    </span>
+                                </label>
+                            </div>
+
                             {/* Test Result */}
                             {testResult && (
                                 <div className={`connection-test-result ${testResult.success ? 'success' : 'error'}`}>
@@ -0,0 +1,87 @@
+import { useState } from 'react';
+import { Lock, Loader2 } from 'lucide-react';
+import { Modal } from '../UI/Modal';
+import { SecretStoreStatus, unlockSecretStore } from '../../lib/tauri';
+
+interface UnlockSecretsModalProps {
+    status: SecretStoreStatus;
+    onUnlock: (status: SecretStoreStatus) => void;
+    onClose: () => void;
+}
+
+/** Master password prompt for the encrypted credential file used when no system keychain is available. */
+export const UnlockSecretsModal: React.FC<UnlockSecretsModalProps> = ({ status, onUnlock, onClose }) => {
+    const [password, setPassword] = useState('');
+    const [confirm, setConfirm] = useState('');
+    const [unlocking, setUnlocking] = useState(false);
+    const [error, setError] = useState<string | null>(null);
+
+    const creating = !status.initialized;
+
+    const handleUnlock = async () => {
+        if (creating && password !== confirm) {
+            setError('The passwords do not match');
+            return;
+        }
+        setUnlocking(true);
+        setError(null);
+        try {
+            onUnlock(await unlockSecretStore(password));
+        } catch (err) {
+            setError(String(err));
+        } finally {
+            setUnlocking(false);
+        }
+    };
+
+    return (
+        <Modal
+            isOpen={true}
+            onClose={onClose}
+            title={creating ? 'Create Master Password' : 'Unlock Saved Passwords'}
+            footer={
+                <>
+                    <button className="btn btn-ghost" onClick={onClose}>
+                        Not now
+                    </button>
+                    <button className="btn btn-primary" onClick={handleUnlock} disabled={unlocking || !password}>
+                        {unlocking ? <Loader2 size={16} className="animate-spin" /> : <Lock size={16} />}
+                        {creating ? 'Create' : 'Unlock'}
+                    </button>
+                </>
+            }
+        >
+            <p className="form-hint">
+                No system keychain is available, so saved passwords are kept in a file
+                encrypted with a master password.
+            </p>
+            {status.warning && <p className="form-hint">{status.warning}</p>}
+            <div className="form-group">
+                <input
+                    type="password"
+                    className="form-input"
+                    placeholder="Master password"
+                    value={password}
+                    autoFocus
+                    onChange={(e) => setPassword(e.target.value)}
+                    onKeyDown={(e) => e.key === 'Enter' && !creating && handleUnlock()}
+                />
+            </div>
+            {creating && (
+                <div className="form-group">
+                    <input
+                        type="password"
+                        className="form-input"
+                        placeholder="Repeat master password"
+                        value={confirm}
+                        onChange={(e) => setConfirm(e.target.value)}
+                        onKeyDown={(e) => e.key === 'Enter' && handleUnlock()}
+                    />
+                </div>
+            )}
+            {error && <span className="form-hint error">{error}</span>}
+        </Modal>
+    );
+};
+
+export default UnlockSecretsModal;
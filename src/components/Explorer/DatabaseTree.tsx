@@ -2,6 +2,7 @@ import React, { useState, useEffect } from 'react';
 import { ChevronRight, ChevronDown, Database, Grid, Layers, RefreshCw, Power, Trash2, Download, Copy, Unplug, Table as TableIcon, Hash, Key } from 'lucide-react';
 import { useConnectionStore } from '../../stores/connectionStore';
 import { getTables, disconnectDatabase, connectDatabase, listDatabases, executeQuery, getTableStructure } from '../../lib/tauri';
+import { describeImpacts } from '../Results/ConfirmDestructiveModal';
 import { SavedConnection } from '../../lib/storage';
 import { Modal } from '../UI/Modal';
 import { DatabaseIcon } from '../UI/DatabaseIcon';
@@ -503,28 +504,41 @@ export const DatabaseTree: React.FC<DatabaseTreeProps> = ({ onTableDataRequest,
         const tableName = contextMenu.table;
         const connectionId = activeConnectionId; // Capture for closure
 
-        setConfirmationModal({
-            isOpen: true,
-            title: 'Drop Table',
-            message: `Are you sure you want to DROP table "${tableName}"? This cannot be undone.`,
-            onConfirm: async () => {
-                try {
-                    await executeQuery(connectionId, `DROP TABLE ${tableName}`);
-                    const fetchedTables = await getTables(connectionId);
-                    setTables(fetchedTables);
-                    setAlertModal({
-                        isOpen: true,
-                        title: 'Success',
-                        message: `Table ${tableName} dropped.`
-                    });
-                } catch (err) {
-                    setAlertModal({
+        const dropTable = async (confirmationToken?: string) => {
+            try {
+                const result = await executeQuery(connectionId, `DROP TABLE ${tableName}`, confirmationToken);
+                // Protected connections ask once more, with the number of rows lost
+                if (result.confirmation_required) {
+                    const { token } = result.confirmation_required;
+                    setConfirmationModal({
                         isOpen: true,
-                        title: 'Error',
-                        message: 'Failed to drop table: ' + err
+                        title: 'Protected Connection',
+                        message: describeImpacts(result.confirmation_required),
+                        onConfirm: () => dropTable(token)
                     });
+                    return;
                 }
+                const fetchedTables = await getTables(connectionId);
+                setTables(fetchedTables);
+                setAlertModal({
+                    isOpen: true,
+                    title: 'Success',
+                    message: `Table ${tableName} dropped.`
+                });
+            } catch (err) {
+                setAlertModal({
+                    isOpen: true,
+                    title: 'Error',
+                    message: 'Failed to drop table: ' + err
+                });
             }
+        };
+
+        setConfirmationModal({
+            isOpen: true,
+            title: 'Drop Table',
+            message: `Are you sure you want to DROP table "${tableName}"? This cannot be undone.`,
+            onConfirm: () => dropTable()
         });
         closeContextMenu();
     };
@@ -919,7 +933,7 @@ export const DatabaseTree: React.FC<DatabaseTreeProps> = ({ onTableDataRequest,
                     </>
                 }
             >
-                <div>{confirmationModal.message}</div>
+                <div style={{ whiteSpace: 'pre-line' }}>{confirmationModal.message}</div>
             </Modal>
 
             {/* Alert Modal */}
@@ -0,0 +1,63 @@
+import { AlertTriangle } from 'lucide-react';
+import { Modal } from '../UI/Modal';
+import { ConfirmationRequired, StatementImpact } from '../../lib/tauri';
+
+interface ConfirmDestructiveModalProps {
+    confirmation: ConfirmationRequired;
+    connectionName?: string;
+    onConfirm: (token: string) => void;
+    onClose: () => void;
+}
+
+/** "1,234 rows" or "about 1,234 rows" when the planner estimated it. */
+export function describeRows(impact: StatementImpact): string | null {
+    if (impact.estimated_rows === undefined) return null;
+    const rows = `${impact.estimated_rows.toLocaleString()} row${impact.estimated_rows === 1 ? '' : 's'}`;
+    return impact.estimate_source === 'explain' ? `about ${rows}` : rows;
+}
+
+/** Plain-text summary of the impacts, for confirm dialogs that only take a message. */
+export function describeImpacts(confirmation: ConfirmationRequired): string {
+    return confirmation.impacts
+        .map(impact => {
+            const rows = describeRows(impact);
+            return rows ? `${impact.description} (${rows})` : impact.description;
+        })
+        .join('\n');
+}
+
+/** Shown when a protected connection holds back a destructive query until the user confirms it. */
+export const ConfirmDestructiveModal: React.FC<ConfirmDestructiveModalProps> = ({ confirmation, connectionName, onConfirm, onClose }) => (
+    <Modal
+        isOpen={true}
+        onClose={onClose}
+        title="Confirm Destructive Query"
+        footer={
+            <>
+                <button className="btn btn-ghost" onClick={onClose}>
+                    Cancel
+                </button>
+                <button className="btn btn-danger" onClick={() => onConfirm(confirmation.token)}>
+                    <AlertTriangle size={16} />
+                    Run anyway
+                </button>
+            </>
+        }
+    >
+        <p className="form-hint">
+            {connectionName ? `"${connectionName}"` : 'This connection'} is protected. The query will:
+        </p>
+        {confirmation.impacts.map((impact, i) => {
+            const rows = describeRows(impact);
+            return (
+                <div key={i} className="form-group">
+                    <div style={{ fontWeight: 600 }}>
+                        {impact.description}
+                        {rows && <span style={{ color: 'var(--error)' }}> — {rows}</span>}
+                    </div>
+                    <pre style={{ margin: '4px 0 0', whiteSpace: 'pre-wrap', fontSize: 12, opacity: 0.8 }}>{impact.statement}</pre>
+                </div>
+            );
+        })}
+    </Modal>
+);
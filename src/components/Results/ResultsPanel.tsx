@@ -1,5 +1,5 @@
 import React, { useState, useEffect } from 'react';
-import { QueryResult, executeQuery } from '../../lib/tauri';
+import { QueryResult, executeQuery, isBinaryPreview } from '../../lib/tauri';
 import { Table, Code, ExternalLink, Copy, Download, CheckSquare, Square, ChevronUp, ChevronDown, ChevronLeft, ChevronRight, Edit2, Check, X } from 'lucide-react';
 import { JsonViewer } from './JsonViewer';
 
@@ -227,8 +227,9 @@ export const ResultsPanel: React.FC<ResultsPanelProps> = ({
         );
     }
 
-    // Check for empty results (query executed but returned 0 rows)
-    if (result.row_count === 0) {
+    // Check for empty results (query executed but returned 0 rows and no columns).
+    // Empty results with column metadata still render the grid headers.
+    if (result.row_count === 0 && result.columns.length === 0) {
         return (
             <div className="empty-state">
                 <div style={{
@@ -828,6 +829,10 @@ export const ResultsPanel: React.FC<ResultsPanelProps> = ({
             );
         }
 
+        if (isBinaryPreview(value)) {
+            return `${value.$binary.preview}… (${value.$binary.length.toLocaleString()} bytes)`;
+        }
+
         if (typeof value === 'object') {
             return JSON.stringify(value);
         }
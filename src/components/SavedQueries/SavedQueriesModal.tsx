@@ -7,7 +7,7 @@ interface SavedQueriesModalProps {
     onClose: () => void;
     queries: SavedQuery[];
     onSelect: (sql: string) => void;
-    onDelete: (id: string) => void;
+    onDelete: (id: number) => void;
 }
 
 export const SavedQueriesModal: React.FC<SavedQueriesModalProps> = ({
@@ -61,7 +61,7 @@ export const SavedQueriesModal: React.FC<SavedQueriesModalProps> = ({
                                     }}
                                 >
                                     <div style={{ flex: 1, minWidth: 0, marginRight: '16px' }}>
-                                        <div style={{ fontWeight: 500, marginBottom: '4px' }}>{query.name}</div>
+                                        <div style={{ fontWeight: 500, marginBottom: '4px' }}>{query.title ?? query.sql}</div>
                                         <div style={{
                                             fontSize: '12px',
                                             color: 'var(--text-muted)',
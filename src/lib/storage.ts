@@ -1,141 +1,133 @@
-import { ConnectionConfig } from './tauri';
-
-const STORAGE_KEY = 'datablaze_saved_connections';
-
-export interface SavedConnection {
-    id: string;
-    config: ConnectionConfig;
-    savePassword: boolean;
-    createdAt: string;
-}
-
-export function getSavedConnections(): SavedConnection[] {
+import {
+    ConnectionConfig, ConnectionProfile, HistoryEntry, listProfiles, saveProfile, deleteProfile,
+    searchHistory, saveQuery as saveHistoryQuery, deleteHistoryEntry,
+} from './tauri';
+
+// Profiles kept in localStorage by earlier versions
+const LEGACY_STORAGE_KEY = 'datablaze_saved_connections';
+
+export type SavedConnection = ConnectionProfile;
+
+/** Folder, tags and labels of a profile, kept when only its connection changes. */
+export type ProfileDetails = Pick<ConnectionProfile, 'folder' | 'tags' | 'color' | 'environment'>;
+
+export async function getSavedConnections(): Promise<SavedConnection[]> {
+    return listProfiles();
+}
+
+// The backend sends passwords, SSH passphrases and keys to the secret store;
+// the profile only keeps its credential_id.
+export async function saveConnection(
+    id: string,
+    config: ConnectionConfig,
+    savePassword?: boolean,
+    details?: ProfileDetails,
+): Promise<SavedConnection> {
+    const connections = await getSavedConnections();
+
+    // A profile with the same name is updated instead of duplicated
+    const existing = connections.find(c => c.config.name === config.name && c.id !== id)
+        ?? connections.find(c => c.id === id);
+
+    return saveProfile({
+        folder: existing?.folder,
+        tags: existing?.tags,
+        color: existing?.color,
+        environment: existing?.environment,
+        ...details,
+        id: existing?.id ?? id,
+        config: { ...config, credential_id: config.credential_id ?? existing?.config.credential_id },
+        // Reconnecting keeps the choice made when the profile was saved
+        save_password: savePassword ?? existing?.save_password ?? true,
+        created_at: existing?.created_at ?? '',
+        updated_at: '',
+    });
+}
+
+export async function updateSavedConnection(
+    id: string,
+    config: ConnectionConfig,
+    savePassword?: boolean,
+    details?: ProfileDetails,
+): Promise<SavedConnection> {
+    return saveConnection(id, config, savePassword, details);
+}
+
+export async function removeSavedConnection(id: string): Promise<void> {
     try {
-        const data = localStorage.getItem(STORAGE_KEY);
-        if (data) {
-            return JSON.parse(data);
-        }
+        await deleteProfile(id);
     } catch (error) {
-        console.error('Failed to load saved connections:', error);
+        console.error('Failed to remove saved connection:', error);
     }
-    return [];
 }
 
-export function saveConnection(id: string, config: ConnectionConfig, savePassword: boolean = true): void {
-    try {
-        const connections = getSavedConnections();
-
-        // Check if connection with same name already exists
-        const existingByName = connections.findIndex(c =>
-            c.config.name === config.name && c.id !== id
-        );
-
-        // If exists by name, update that one instead
-        const existingIndex = existingByName >= 0 ? existingByName : connections.findIndex(c => c.id === id);
-
-        const savedConn: SavedConnection = {
-            id,
-            config: savePassword ? config : { ...config, password: '' },
-            savePassword,
-            createdAt: new Date().toISOString(),
-        };
-
-        if (existingIndex >= 0) {
-            connections[existingIndex] = savedConn;
-        } else {
-            connections.push(savedConn);
+/** Move profiles saved in localStorage by earlier versions to the backend. */
+export async function migrateLocalConnections(): Promise<void> {
+    const data = localStorage.getItem(LEGACY_STORAGE_KEY);
+    if (!data) return;
+
+    const legacy: { id: string; config: ConnectionConfig; savePassword?: boolean; createdAt?: string }[] = JSON.parse(data);
+    // Profiles that fail to save stay in localStorage for the next start
+    const failed: typeof legacy = [];
+    for (const conn of legacy) {
+        try {
+            await saveProfile({
+                id: conn.id,
+                config: conn.config,
+                save_password: conn.savePassword ?? true,
+                created_at: conn.createdAt ?? '',
+                updated_at: '',
+            });
+        } catch (error) {
+            console.error(`Failed to migrate saved connection ${conn.config.name}:`, error);
+            failed.push(conn);
         }
-
-        localStorage.setItem(STORAGE_KEY, JSON.stringify(connections));
-    } catch (error) {
-        console.error('Failed to save connection:', error);
     }
-}
-
-export function updateSavedConnection(id: string, config: ConnectionConfig, savePassword: boolean = true): void {
-    saveConnection(id, config, savePassword);
-}
 
-export function removeSavedConnection(id: string): void {
-    try {
-        const connections = getSavedConnections();
-        const filtered = connections.filter(c => c.id !== id);
-        localStorage.setItem(STORAGE_KEY, JSON.stringify(filtered));
-    } catch (error) {
-        console.error('Failed to remove saved connection:', error);
+    if (failed.length > 0) {
+        localStorage.setItem(LEGACY_STORAGE_KEY, JSON.stringify(failed));
+    } else {
+        localStorage.removeItem(LEGACY_STORAGE_KEY);
     }
 }
 
-export function clearAllConnections(): void {
-    localStorage.removeItem(STORAGE_KEY);
-}
+// Saved queries kept in localStorage by earlier versions
+const LEGACY_QUERY_STORAGE_KEY = 'datablaze_saved_queries';
 
-// Clear duplicates on load
-export function cleanupDuplicates(): void {
-    try {
-        const connections = getSavedConnections();
-        const seen = new Map<string, SavedConnection>();
-
-        // Keep only the most recent connection for each name
-        for (const conn of connections) {
-            const key = conn.config.name || conn.config.database;
-            if (!seen.has(key) || new Date(conn.createdAt) > new Date(seen.get(key)!.createdAt)) {
-                seen.set(key, conn);
-            }
-        }
+/** A saved query is a pinned history entry; `title` holds its name. */
+export type SavedQuery = HistoryEntry;
 
-        const cleaned = Array.from(seen.values());
-        if (cleaned.length !== connections.length) {
-            localStorage.setItem(STORAGE_KEY, JSON.stringify(cleaned));
-            console.log('Cleaned up duplicate connections:', connections.length - cleaned.length, 'removed');
-        }
-    } catch (error) {
-        console.error('Failed to cleanup duplicates:', error);
-    }
+export async function getSavedQueries(): Promise<SavedQuery[]> {
+    return searchHistory({ pinned_only: true, limit: 1000 });
 }
 
-const QUERY_STORAGE_KEY = 'datablaze_saved_queries';
-
-export interface SavedQuery {
-    id: string;
-    name: string;
-    sql: string;
-    createdAt: string;
+export async function saveQuery(connectionName: string, name: string, sql: string): Promise<SavedQuery> {
+    return saveHistoryQuery(connectionName, name, sql);
 }
 
-export function getSavedQueries(): SavedQuery[] {
+export async function removeSavedQuery(id: number): Promise<void> {
     try {
-        const data = localStorage.getItem(QUERY_STORAGE_KEY);
-        if (data) return JSON.parse(data);
+        await deleteHistoryEntry(id);
     } catch (error) {
-        console.error('Failed to load saved queries:', error);
+        console.error('Failed to remove saved query:', error);
     }
-    return [];
 }
 
-export function saveQuery(query: SavedQuery): void {
-    try {
-        const queries = getSavedQueries();
-        const existingIndex = queries.findIndex(q => q.id === query.id);
-        if (existingIndex >= 0) {
-            queries[existingIndex] = query;
-        } else {
-            queries.push(query);
-        }
-        localStorage.setItem(QUERY_STORAGE_KEY, JSON.stringify(queries));
-    } catch (error) {
-        console.error('Failed to save query:', error);
-    }
-}
+/** Move queries saved in localStorage by earlier versions to the history store. */
+export async function migrateLocalQueries(): Promise<void> {
+    const data = localStorage.getItem(LEGACY_QUERY_STORAGE_KEY);
+    if (!data) return;
 
-export function removeSavedQuery(id: string): void {
-    try {
-        const queries = getSavedQueries();
-        const filtered = queries.filter(q => q.id !== id);
-        localStorage.setItem(QUERY_STORAGE_KEY, JSON.stringify(filtered));
-    } catch (error) {
-        console.error('Failed to remove saved query:', error);
+    const legacy: { id: string; name: string; sql: string; createdAt?: string }[] = JSON.parse(data);
+    // Each query leaves localStorage as soon as it is saved, so a migration
+    // cut short resumes where it stopped instead of saving duplicates
+    while (legacy.length > 0) {
+        const query = legacy[0];
+        await saveHistoryQuery('', query.name, query.sql);
+        legacy.shift();
+        localStorage.setItem(LEGACY_QUERY_STORAGE_KEY, JSON.stringify(legacy));
     }
+    localStorage.removeItem(LEGACY_QUERY_STORAGE_KEY);
 }
 
 // ============================================
@@ -1,4 +1,5 @@
 import { invoke } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
 
 // Types
 export interface DatabaseType {
@@ -16,12 +17,152 @@ export interface ConnectionConfig {
   database: string;
   username: string;
   password: string;
+  /** `UTC`, `local`, an offset like `+05:30` or an IANA name; unset keeps the session timezone */
+  display_timezone?: string;
+  /** Reach the server through an SSH bastion */
+  ssh_tunnel?: SshTunnelConfig;
+  tls?: TlsConfig;
+  /** Connection URL or DSN; when set it decides host, port, credentials, TLS and parameters */
+  url?: string;
+  /** Unix socket path (PostgreSQL: the socket directory), used instead of host and port */
+  socket?: string;
+  /** Extra driver parameters such as `application_name` */
+  parameters?: Record<string, string>;
+  /** Open the session read-only and reject statements that write */
+  read_only?: boolean;
+  /** Ask before DROP, TRUNCATE, ALTER ... DROP and DELETE or UPDATE without WHERE */
+  protected?: boolean;
+  /** Pool limits; unset ones keep the engine's defaults */
+  pool?: PoolConfig;
+  /** SQL run on every new connection, e.g. `SET search_path TO app` */
+  init_sql?: string;
+  /** Secret store entry holding the password and SSH secrets; set by `saveCredentials` */
+  credential_id?: string;
+}
+
+export interface PoolConfig {
+  max_connections?: number;
+  /** Connections kept open even when unused */
+  min_connections?: number;
+  acquire_timeout_secs?: number;
+  /** 0 keeps unused connections open */
+  idle_timeout_secs?: number;
+  /** 0 never replaces a connection for its age */
+  max_lifetime_secs?: number;
+}
+
+export type TlsMode = 'disable' | 'prefer' | 'require' | 'verify_ca' | 'verify_full';
+
+export interface TlsConfig {
+  mode: TlsMode;
+  /** PEM bundle trusted in addition to the system roots */
+  ca_cert_path?: string;
+  client_cert_path?: string;
+  client_key_path?: string;
+  /** Name the SQL Server certificate is checked against instead of the host */
+  server_name?: string;
+}
+
+export interface SshTunnelConfig {
+  host: string;
+  port: number;
+  username: string;
+  auth: 'password' | 'private_key' | 'agent';
+  password?: string;
+  private_key_path?: string;
+  /** Private key in OpenSSH or PEM form, used instead of `private_key_path` */
+  private_key?: string;
+  /** Passphrase of an encrypted private key */
+  passphrase?: string;
+  /** `known_hosts` file checked for the bastion's key; the user's own when unset */
+  known_hosts_path?: string;
+  /** Trust and remember a host seen for the first time */
+  accept_new_host_key?: boolean;
+}
+
+export interface SecretStoreStatus {
+  /** `keychain`: the platform secret store; `encrypted_file`: a file protected by a master password */
+  backend: 'keychain' | 'encrypted_file';
+  initialized: boolean;
+  unlocked: boolean;
+  /** Why the encrypted file is used although a keychain was expected */
+  warning?: string;
+}
+
+export type ProfileEnvironment = 'dev' | 'staging' | 'prod';
+
+/** A saved connection; secrets live in the secret store, not in the profile. */
+export interface ConnectionProfile {
+  id: string;
+  config: ConnectionConfig;
+  /** Folder path such as `Clients/Acme`; top level when empty */
+  folder?: string;
+  tags?: string[];
+  /** CSS colour of the profile's label */
+  color?: string;
+  environment?: ProfileEnvironment;
+  save_password: boolean;
+  created_at: string;
+  updated_at: string;
+}
+
+export type ProfileFormat = 'json' | 'toml';
+
+export interface ProfileImportResult {
+  imported: ConnectionProfile[];
+  /** Entries left out or imported only in part, with the reason */
+  skipped: string[];
 }
 
 export interface Connection {
   id: string;
   config: ConnectionConfig;
   connected: boolean;
+  /** Latest state from `connection-status` events; connected until one arrives */
+  status?: ConnectionState;
+}
+
+export type ConnectionState = 'connected' | 'degraded' | 'reconnecting' | 'lost';
+
+/** Payload of the `connection-status` event, sent when a connection's state changes. */
+export interface ConnectionStatus {
+  connection_id: string;
+  state: ConnectionState;
+  message?: string;
+}
+
+export interface PoolStats {
+  /** Open connections, idle or in use */
+  size: number;
+  idle: number;
+  in_use: number;
+  max_connections: number;
+  min_connections: number;
+  /** How long getting a connection took for this report; unset when none was free within a second */
+  acquire_ms?: number;
+  /** Since the pool was opened; only reported for SQL Server */
+  waited?: number;
+  timed_out?: number;
+  total_wait_ms?: number;
+  connections_created?: number;
+}
+
+/** The server's view of the session that answered the stats request. */
+export interface SessionInfo {
+  backend_pid?: string;
+  user?: string;
+  database?: string;
+  schema?: string;
+  server_version?: string;
+  encoding?: string;
+  timezone?: string;
+}
+
+export interface ConnectionStats {
+  state: ConnectionState;
+  pool: PoolStats;
+  /** Unset when no connection was free to ask */
+  session?: SessionInfo;
 }
 
 export interface TableInfo {
@@ -42,6 +183,8 @@ export interface ColumnInfo {
   foreign_key_column?: string;
   is_auto_increment?: boolean;
   max_length?: number;
+  numeric_precision?: number;
+  numeric_scale?: number;
   check_constraint?: string;
 }
 
@@ -53,6 +196,10 @@ export interface TableStructure {
 export interface ResultColumn {
   name: string;
   type_name: string;
+  nullable?: boolean;
+  precision?: number;
+  scale?: number;
+  source_table?: string;
 }
 
 export interface QueryResult {
@@ -61,6 +208,367 @@ export interface QueryResult {
   row_count: number;
   execution_time_ms: number;
   truncated?: boolean;
+  /** Set instead of running the query on a protected connection; run it again with the token to go ahead */
+  confirmation_required?: ConfirmationRequired;
+}
+
+export type DestructiveKind = 'delete_without_where' | 'update_without_where' | 'drop' | 'truncate' | 'alter_drop';
+
+export interface StatementImpact {
+  kind: DestructiveKind;
+  statement: string;
+  /** e.g. "Deletes every row of orders" */
+  description: string;
+  tables: string[];
+  estimated_rows?: number;
+  /** `count` is exact, `explain` the planner's estimate */
+  estimate_source?: 'count' | 'explain';
+}
+
+export interface ConfirmationRequired {
+  token: string;
+  impacts: StatementImpact[];
+}
+
+/** Placeholder sent instead of binary cells larger than the preview threshold. */
+export interface BinaryPreview {
+  $binary: {
+    length: number;
+    preview: string;
+  };
+}
+
+export function isBinaryPreview(value: unknown): value is BinaryPreview {
+  return typeof value === 'object' && value !== null && '$binary' in value;
+}
+
+export interface PlanNode {
+  node_type: string;
+  relation?: string;
+  startup_cost?: number;
+  total_cost?: number;
+  estimated_rows?: number;
+  actual_rows?: number;
+  actual_time_ms?: number;
+  loops?: number;
+  details: Record<string, unknown>;
+  children: PlanNode[];
+}
+
+export interface QueryPlan {
+  root: PlanNode;
+  raw: string;
+  analyzed: boolean;
+  planning_time_ms?: number;
+  execution_time_ms?: number;
+}
+
+export interface HistoryEntry {
+  id: number;
+  connection_name: string;
+  database_name: string;
+  sql: string;
+  executed_at: string;
+  duration_ms: number | null;
+  row_count: number | null;
+  error: string | null;
+  pinned: boolean;
+  title: string | null;
+}
+
+export interface HistoryFilter {
+  search?: string;
+  connection_name?: string;
+  /** RFC 3339 timestamp or `YYYY-MM-DD` */
+  from?: string;
+  to?: string;
+  pinned_only?: boolean;
+  errors_only?: boolean;
+  limit?: number;
+  offset?: number;
+}
+
+export type ExportFormat =
+  | 'csv'
+  | 'tsv'
+  | 'ndjson'
+  | 'json_array'
+  | 'sql_insert'
+  | 'parquet'
+  | 'arrow_ipc'
+  | 'xlsx';
+
+export interface ExportOptions {
+  delimiter?: string;
+  quote?: string;
+  quote_style?: 'necessary' | 'always' | 'non_numeric' | 'never';
+  header?: boolean;
+  null_token?: string;
+  target_dialect?: ConnectionConfig['db_type'];
+  table_name?: string;
+  rows_per_statement?: number;
+  compression?: 'none' | 'snappy' | 'gzip' | 'lz4' | 'zstd' | 'brotli';
+  compression_level?: number;
+  row_group_size?: number;
+  sheet_name?: string;
+}
+
+export interface ExportSummary {
+  path: string;
+  rows: number;
+  bytes: number;
+  duration_ms: number;
+}
+
+export interface TaskProgress {
+  task_id: string;
+  processed: number;
+  total?: number;
+  /** Set by tasks that stream a file, such as `executeSqlFile` */
+  bytes_read?: number;
+  bytes_total?: number;
+}
+
+export type ColumnKind =
+  | 'boolean'
+  | 'integer'
+  | 'float'
+  | 'decimal'
+  | 'date'
+  | 'time'
+  | 'date_time'
+  | 'timestamp'
+  | 'binary'
+  | 'json'
+  | 'text';
+
+export interface CsvReadOptions {
+  delimiter?: string;
+  quote?: string;
+  encoding?: string;
+  has_header?: boolean;
+  null_token?: string;
+  preview_rows?: number;
+}
+
+export interface InferredColumn {
+  name: string;
+  kind: ColumnKind;
+  nullable: boolean;
+  max_length: number;
+  precision: number;
+  scale: number;
+}
+
+export interface CsvPreview {
+  encoding: string;
+  delimiter: string;
+  has_header: boolean;
+  columns: InferredColumn[];
+  rows: string[][];
+}
+
+export type JsonLayout = 'array' | 'ndjson';
+
+export interface JsonReadOptions {
+  layout?: JsonLayout;
+  nested?: 'flatten' | 'json';
+  separator?: string;
+  max_depth?: number;
+  preview_rows?: number;
+}
+
+export interface JsonPreview {
+  layout: JsonLayout;
+  columns: InferredColumn[];
+  rows: unknown[][];
+}
+
+export interface ColumnMapping {
+  source: string;
+  target: string;
+  sql_type?: string;
+}
+
+export interface ImportOptions {
+  csv?: CsvReadOptions;
+  json?: JsonReadOptions;
+  mapping?: ColumnMapping[];
+  create_table?: boolean;
+  batch_size?: number;
+  on_error?: 'abort' | 'skip';
+}
+
+export interface ImportRowError {
+  line: number;
+  message: string;
+}
+
+export interface ImportSummary {
+  table: string;
+  created_table?: string;
+  rows_read: number;
+  rows_imported: number;
+  rows_failed: number;
+  errors: ImportRowError[];
+  duration_ms: number;
+}
+
+export interface CopyTableOptions {
+  mapping?: ColumnMapping[];
+  create_table?: boolean;
+  truncate?: boolean;
+  /** `WHERE` condition in the source dialect */
+  filter?: string;
+  batch_size?: number;
+  on_error?: 'abort' | 'skip';
+}
+
+export interface ColumnMismatch {
+  column: string;
+  source_type?: string;
+  target_type?: string;
+  message: string;
+}
+
+export interface CopyTableSummary {
+  source_table: string;
+  target_table: string;
+  created_table?: string;
+  rows_read: number;
+  rows_copied: number;
+  rows_failed: number;
+  errors: ImportRowError[];
+  mismatches: ColumnMismatch[];
+  duration_ms: number;
+}
+
+export interface SchemaCompareOptions {
+  generate_script?: boolean;
+  /** Drop objects only in the target; otherwise those statements are commented out */
+  include_drops?: boolean;
+  /** Match objects by name alone, ignoring their schema */
+  ignore_schema?: boolean;
+  tables?: string[];
+  exclude_tables?: string[];
+}
+
+export type SchemaObjectType =
+  | 'table'
+  | 'column'
+  | 'primary_key'
+  | 'unique_constraint'
+  | 'foreign_key'
+  | 'check_constraint'
+  | 'index'
+  | 'view'
+  | 'sequence';
+
+export interface SchemaDifference {
+  object_type: SchemaObjectType;
+  /** `missing`: only in the source; `extra`: only in the target */
+  change: 'missing' | 'extra' | 'changed';
+  table?: string;
+  name: string;
+  /** Target value to source value, e.g. `type: integer -> bigint` */
+  details?: string[];
+}
+
+export interface SchemaComparison {
+  source_type: ConnectionConfig['db_type'];
+  target_type: ConnectionConfig['db_type'];
+  tables_compared: number;
+  views_compared: number;
+  sequences_compared: number;
+  differences: SchemaDifference[];
+  warnings: string[];
+  /** Migration script that brings the target in line with the source */
+  script?: string;
+}
+
+export interface DataCompareOptions {
+  /** Columns to compare besides the key; by default every column both tables have */
+  columns?: string[];
+  ignore_columns?: string[];
+  /** Rows per hash-compared chunk; 0 reads every row */
+  chunk_size?: number;
+  max_differences?: number;
+  generate_script?: boolean;
+}
+
+export interface ColumnValueDifference {
+  column: string;
+  source: unknown;
+  target: unknown;
+}
+
+export interface RowDifference {
+  change: 'missing' | 'extra' | 'changed';
+  key: Record<string, unknown>;
+  columns?: ColumnValueDifference[];
+}
+
+export interface DataComparison {
+  key_columns: string[];
+  columns: string[];
+  source_rows: number;
+  target_rows: number;
+  rows_missing: number;
+  rows_extra: number;
+  rows_changed: number;
+  rows_identical: number;
+  chunks: number;
+  chunks_skipped: number;
+  /** The first differing rows; the counts include all of them */
+  differences: RowDifference[];
+  warnings: string[];
+  /** Statements that make the target rows match the source */
+  script?: string;
+  duration_ms: number;
+}
+
+export interface DumpOptions {
+  content?: 'schema_and_data' | 'schema_only' | 'data_only';
+  /** `copy` writes PostgreSQL `COPY ... FROM stdin` blocks; other engines always use `INSERT` */
+  data_format?: 'insert' | 'copy';
+  tables?: string[];
+  exclude_tables?: string[];
+  drop_existing?: boolean;
+  rows_per_statement?: number;
+}
+
+export interface DumpSummary {
+  path: string;
+  tables: number;
+  views: number;
+  sequences: number;
+  rows: number;
+  bytes: number;
+  duration_ms: number;
+}
+
+export interface ScriptOptions {
+  /** Run the whole file in one transaction; DDL still commits implicitly on MySQL */
+  transaction?: boolean;
+  /** `continue` runs every statement but rolls the transaction back if any failed */
+  on_error?: 'stop' | 'skip' | 'continue';
+  encoding?: string;
+}
+
+export interface ScriptStatementError {
+  statement: number;
+  line: number;
+  sql: string;
+  message: string;
+}
+
+export interface ScriptSummary {
+  statements_executed: number;
+  statements_failed: number;
+  errors: ScriptStatementError[];
+  bytes_read: number;
+  committed: boolean;
+  duration_ms: number;
 }
 
 export interface TestConnectionResult {
@@ -74,6 +582,66 @@ export async function testConnection(config: ConnectionConfig): Promise<TestConn
   return invoke('test_connection', { config });
 }
 
+export async function parseConnectionUrl(url: string): Promise<ConnectionConfig> {
+  return invoke('parse_connection_url', { url });
+}
+
+export async function formatConnectionUrl(config: ConnectionConfig): Promise<string> {
+  return invoke('format_connection_url', { config });
+}
+
+/** Move the connection's secrets into the secret store; returns the config without them */
+export async function saveCredentials(config: ConnectionConfig): Promise<ConnectionConfig> {
+  return invoke('save_credentials', { config });
+}
+
+export async function deleteCredentials(credentialId: string): Promise<void> {
+  return invoke('delete_credentials', { credentialId });
+}
+
+export async function getSecretStoreStatus(): Promise<SecretStoreStatus> {
+  return invoke('get_secret_store_status');
+}
+
+export async function unlockSecretStore(masterPassword: string): Promise<SecretStoreStatus> {
+  return invoke('unlock_secret_store', { masterPassword });
+}
+
+export async function lockSecretStore(): Promise<SecretStoreStatus> {
+  return invoke('lock_secret_store');
+}
+
+export async function listProfiles(): Promise<ConnectionProfile[]> {
+  return invoke('list_profiles');
+}
+
+/** Why the saved profiles could not be loaded at startup, if they could not. */
+export async function profileLoadError(): Promise<string | null> {
+  return invoke('profile_load_error');
+}
+
+export async function saveProfile(profile: ConnectionProfile): Promise<ConnectionProfile> {
+  return invoke('save_profile', { profile });
+}
+
+export async function deleteProfile(id: string): Promise<boolean> {
+  return invoke('delete_profile', { id });
+}
+
+/** Write all profiles, or those in `ids`, to a file without their secrets. Returns how many. */
+export async function exportProfiles(path: string, format: ProfileFormat, ids?: string[]): Promise<number> {
+  return invoke('export_profiles', { ids: ids ?? null, format, path });
+}
+
+export async function importProfiles(path: string, format: ProfileFormat): Promise<ProfileImportResult> {
+  return invoke('import_profiles', { path, format });
+}
+
+/** Connections from `~/.pgpass`, `pg_service.conf` and `~/.my.cnf`. */
+export async function importClientProfiles(): Promise<ProfileImportResult> {
+  return invoke('import_client_profiles');
+}
+
 export async function connectDatabase(config: ConnectionConfig): Promise<Connection> {
   return invoke('connect_database', { config });
 }
@@ -82,8 +650,12 @@ export async function disconnectDatabase(id: string): Promise<boolean> {
   return invoke('disconnect_database', { id });
 }
 
-export async function executeQuery(id: string, sql: string): Promise<QueryResult> {
-  return invoke('execute_query', { id, sql });
+export async function executeQuery(id: string, sql: string, confirmationToken?: string): Promise<QueryResult> {
+  return invoke('execute_query', { id, sql, confirmationToken });
+}
+
+export async function explainQuery(id: string, sql: string, analyze: boolean): Promise<QueryPlan> {
+  return invoke('explain_query', { id, sql, analyze });
 }
 
 export async function getTables(id: string): Promise<TableInfo[]> {
@@ -98,6 +670,161 @@ export async function getTableData(id: string, table: string, limit: number): Pr
   return invoke('get_table_data', { id, table, limit });
 }
 
+export async function getConnectionStats(id: string): Promise<ConnectionStats> {
+  return invoke('get_connection_stats', { id });
+}
+
 export async function listDatabases(id: string): Promise<string[]> {
   return invoke('list_databases', { id });
 }
+
+export async function downloadCell(
+  id: string,
+  table: string,
+  pk: Record<string, unknown>,
+  column: string,
+  path: string
+): Promise<number> {
+  return invoke('download_cell', { id, table, pk, column, path });
+}
+
+export async function uploadCell(
+  id: string,
+  table: string,
+  pk: Record<string, unknown>,
+  column: string,
+  path: string
+): Promise<number> {
+  return invoke('upload_cell', { id, table, pk, column, path });
+}
+
+export async function searchHistory(filter: HistoryFilter): Promise<HistoryEntry[]> {
+  return invoke('search_history', { filter });
+}
+
+export async function saveQuery(connectionName: string, title: string, sql: string): Promise<HistoryEntry> {
+  return invoke('save_query', { connectionName, title, sql });
+}
+
+export async function setHistoryPinned(entryId: number, pinned: boolean, title?: string): Promise<HistoryEntry> {
+  return invoke('set_history_pinned', { entryId, pinned, title });
+}
+
+export async function deleteHistoryEntry(entryId: number): Promise<boolean> {
+  return invoke('delete_history_entry', { entryId });
+}
+
+export async function clearHistory(connectionName?: string): Promise<number> {
+  return invoke('clear_history', { connectionName });
+}
+
+export async function exportQuery(
+  id: string,
+  sql: string,
+  format: ExportFormat,
+  path: string,
+  taskId: string,
+  options?: ExportOptions
+): Promise<ExportSummary> {
+  return invoke('export_query', { id, sql, format, path, options, taskId });
+}
+
+export async function exportTable(
+  id: string,
+  table: string,
+  format: ExportFormat,
+  path: string,
+  taskId: string,
+  options?: ExportOptions
+): Promise<ExportSummary> {
+  return invoke('export_table', { id, table, format, path, options, taskId });
+}
+
+export async function dumpDatabase(
+  id: string,
+  path: string,
+  taskId: string,
+  options?: DumpOptions
+): Promise<DumpSummary> {
+  return invoke('dump_database', { id, path, options, taskId });
+}
+
+export async function previewCsv(path: string, options?: CsvReadOptions): Promise<CsvPreview> {
+  return invoke('preview_csv', { path, options });
+}
+
+export async function importCsv(
+  id: string,
+  path: string,
+  table: string,
+  taskId: string,
+  options?: ImportOptions
+): Promise<ImportSummary> {
+  return invoke('import_csv', { id, path, table, options, taskId });
+}
+
+export async function previewJson(path: string, options?: JsonReadOptions): Promise<JsonPreview> {
+  return invoke('preview_json', { path, options });
+}
+
+export async function importJson(
+  id: string,
+  path: string,
+  table: string,
+  taskId: string,
+  options?: ImportOptions
+): Promise<ImportSummary> {
+  return invoke('import_json', { id, path, table, options, taskId });
+}
+
+export async function executeSqlFile(
+  id: string,
+  path: string,
+  taskId: string,
+  options?: ScriptOptions
+): Promise<ScriptSummary> {
+  return invoke('execute_sql_file', { id, path, options, taskId });
+}
+
+export async function copyTable(
+  sourceId: string,
+  sourceTable: string,
+  targetId: string,
+  targetTable: string,
+  taskId: string,
+  options?: CopyTableOptions
+): Promise<CopyTableSummary> {
+  return invoke('copy_table', { sourceId, sourceTable, targetId, targetTable, options, taskId });
+}
+
+export async function compareSchemas(
+  sourceId: string,
+  targetId: string,
+  options?: SchemaCompareOptions
+): Promise<SchemaComparison> {
+  return invoke('compare_schemas', { sourceId, targetId, options });
+}
+
+export async function compareTableData(
+  sourceId: string,
+  sourceTable: string,
+  targetId: string,
+  targetTable: string,
+  keyColumns: string[],
+  taskId: string,
+  options?: DataCompareOptions
+): Promise<DataComparison> {
+  return invoke('compare_table_data', { sourceId, sourceTable, targetId, targetTable, keyColumns, options, taskId });
+}
+
+export async function cancelTask(taskId: string): Promise<boolean> {
+  return invoke('cancel_task', { taskId });
+}
+
+export function onTaskProgress(handler: (progress: TaskProgress) => void): Promise<UnlistenFn> {
+  return listen<TaskProgress>('task-progress', (event) => handler(event.payload));
+}
+
+export function onConnectionStatus(handler: (status: ConnectionStatus) => void): Promise<UnlistenFn> {
+  return listen<ConnectionStatus>('connection-status', (event) => handler(event.payload));
+}
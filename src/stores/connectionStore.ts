@@ -1,6 +1,6 @@
 import { create } from 'zustand';
-import { Connection, TableInfo, QueryResult, TableStructure } from '../lib/tauri';
-import { getSavedConnections, saveConnection, removeSavedConnection, SavedConnection, cleanupDuplicates, getSavedQueries, saveQuery, removeSavedQuery, SavedQuery, getAppState, saveAppState } from '../lib/storage';
+import { Connection, ConnectionState as ConnectionHealth, TableInfo, QueryResult, TableStructure } from '../lib/tauri';
+import { getSavedConnections, saveConnection, removeSavedConnection, SavedConnection, migrateLocalConnections, migrateLocalQueries, getSavedQueries, saveQuery, removeSavedQuery, SavedQuery, getAppState, saveAppState } from '../lib/storage';
 
 interface QueryTab {
     id: string;
@@ -28,12 +28,13 @@ interface ConnectionState {
     error: string | null;
 
     // Actions
-    loadSavedConnections: () => void;
-    loadSavedQueries: () => void;
+    loadSavedConnections: () => Promise<void>;
+    loadSavedQueries: () => Promise<void>;
     loadAppState: () => void;
     persistTabsState: () => void;
     addConnection: (connection: Connection, persist?: boolean, savePassword?: boolean) => void;
     removeConnection: (id: string) => void;
+    setConnectionStatus: (id: string, status: ConnectionHealth) => void;
     removeSavedConnectionById: (id: string) => void;
     setActiveConnection: (id: string | null) => void;
     setTables: (tables: TableInfo[]) => void;
@@ -47,7 +48,7 @@ interface ConnectionState {
     setConnecting: (connecting: boolean) => void;
     setError: (error: string | null) => void;
     addSavedQuery: (name: string, sql: string) => void;
-    deleteSavedQuery: (id: string) => void;
+    deleteSavedQuery: (id: number) => void;
     addStructureTab: (tableName: string) => void;
     updateTabResult: (id: string, result: QueryResult | null, isExecuting: boolean, error: string | null) => void;
 }
@@ -210,36 +211,46 @@ export const useConnectionStore = create<ConnectionState>((set, get) => ({
         saveTabsState(state.queryTabs, state.activeTabId, state.connections);
     },
 
-    loadSavedConnections: () => {
-        cleanupDuplicates(); // Clean duplicates on load
-        const saved = getSavedConnections();
-        set({ savedConnections: saved });
+    loadSavedConnections: async () => {
+        // Profiles saved in localStorage by older versions move to the backend
+        await migrateLocalConnections()
+            .catch((err) => console.error('Failed to move saved connections to the profile store:', err));
+        try {
+            set({ savedConnections: await getSavedConnections() });
+        } catch (err) {
+            console.error('Failed to load saved connections:', err);
+        }
     },
 
-    loadSavedQueries: () => {
-        set({ savedQueries: getSavedQueries() });
+    loadSavedQueries: async () => {
+        // Queries saved in localStorage by older versions move to the history store
+        await migrateLocalQueries()
+            .catch((err) => console.error('Failed to move saved queries to the history store:', err));
+        try {
+            set({ savedQueries: await getSavedQueries() });
+        } catch (err) {
+            console.error('Failed to load saved queries:', err);
+        }
     },
 
     addSavedQuery: (name, sql) => {
-        const newQuery: SavedQuery = {
-            id: crypto.randomUUID(),
-            name,
-            sql,
-            createdAt: new Date().toISOString()
-        };
-        saveQuery(newQuery);
-        set({ savedQueries: getSavedQueries() });
+        const state = get();
+        const connectionName = state.connections.find(c => c.id === state.activeConnectionId)?.config.name ?? '';
+        saveQuery(connectionName, name, sql)
+            .then(() => get().loadSavedQueries())
+            .catch((err) => set({ error: `Query not saved: ${err}` }));
     },
 
     deleteSavedQuery: (id) => {
-        removeSavedQuery(id);
-        set({ savedQueries: getSavedQueries() });
+        removeSavedQuery(id).then(() => get().loadSavedQueries());
     },
 
-    addConnection: (connection, persist = true, savePassword = true) => {
-        // Save to localStorage ONLY if persist is true
+    addConnection: (connection, persist = true, savePassword) => {
+        // Save the profile ONLY if persist is true; secrets go to the secret store
         if (persist) {
-            saveConnection(connection.id, connection.config, savePassword);
+            saveConnection(connection.id, connection.config, savePassword)
+                .then(() => get().loadSavedConnections())
+                .catch((err) => set({ error: `Connection not saved: ${err}` }));
         }
 
         set((state) => {
@@ -265,9 +276,6 @@ export const useConnectionStore = create<ConnectionState>((set, get) => ({
 
             return {
                 connections: newConnections,
-                // Only reload saved connections if we actually saved/persisted something or if we want to ensure sync
-                // However, reload only if persisted to avoid showing temporary connections as saved
-                savedConnections: persist ? getSavedConnections() : state.savedConnections,
                 activeConnectionId: connection.id,
             };
         });
@@ -281,9 +289,14 @@ export const useConnectionStore = create<ConnectionState>((set, get) => ({
         }));
     },
 
+    setConnectionStatus: (id, status) => {
+        set((state) => ({
+            connections: state.connections.map((c) => (c.id === id ? { ...c, status } : c)),
+        }));
+    },
+
     removeSavedConnectionById: (id) => {
-        removeSavedConnection(id);
-        set({ savedConnections: getSavedConnections() });
+        removeSavedConnection(id).then(() => get().loadSavedConnections());
     },
 
     setActiveConnection: (id) => set({ activeConnectionId: id }),